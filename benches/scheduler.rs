@@ -0,0 +1,113 @@
+use std::{cell::RefCell, hint::black_box, rc::Rc, time::Instant};
+
+use rem6502::{
+    cpu::CPU,
+    devices::{Cia, Device, Line, Via},
+};
+
+const CYCLES: u64 = 2_000_000;
+const CLOCK_HZ: u64 = 1_000_000;
+const T1_PERIOD: u16 = 998;
+const COUNT: u16 = 0x10;
+
+// How devices were run before the scheduler: each one ticked every cycle
+// and every interrupt output looked at every cycle. The wrapper keeps the
+// device on the bus while hiding its events and output from the CPU, so
+// the host loop does all of that itself.
+struct Polled<D: Device>(Rc<RefCell<D>>);
+
+impl<D: Device> Device for Polled<D> {
+    fn peek(&self, addr: u16) -> u8 {
+        self.0.borrow().peek(addr)
+    }
+
+    fn read(&mut self, addr: u16) -> u8 {
+        self.0.borrow_mut().read(addr)
+    }
+
+    fn write(&mut self, addr: u16, val: u8) {
+        self.0.borrow_mut().write(addr, val);
+    }
+
+    fn tick(&mut self, cycle: u64) {
+        self.0.borrow_mut().tick(cycle);
+    }
+}
+
+// A main loop that never touches the devices, a VIA raising a free-running
+// T1 interrupt, and a CIA with timer A running but masked, as a machine
+// would have something going on that the CPU doesn't hear about.
+fn program(cpu: &mut CPU) {
+    // CLI; loop: INX; JMP loop
+    for (addr, val) in [0x58, 0xE8, 0x4C, 0x01, 0x00].into_iter().enumerate() {
+        cpu.write_byte(addr as u16, val);
+    }
+    // LDA $8004; INC COUNT; BNE +2; INC COUNT + 1; RTI
+    let handler = [0xAD, 0x04, 0x80, 0xE6, 0x10, 0xD0, 0x02, 0xE6, 0x11, 0x40];
+    for (offset, val) in handler.into_iter().enumerate() {
+        cpu.write_byte(0x0300 + offset as u16, val);
+    }
+    cpu.write_byte(0xFFFE, 0x00);
+    cpu.write_byte(0xFFFF, 0x03);
+
+    let [lo, hi] = T1_PERIOD.to_le_bytes();
+    cpu.write_byte(0x800B, 0x40);
+    cpu.write_byte(0x800E, 0xC0);
+    cpu.write_byte(0x8004, lo);
+    cpu.write_byte(0x8005, hi);
+
+    cpu.write_byte(0xDC04, 0x00);
+    cpu.write_byte(0xDC05, 0x10);
+    cpu.write_byte(0xDC0E, 0x01);
+}
+
+fn get_count(cpu: &CPU) -> u64 {
+    u16::from_le_bytes([cpu.read_byte(COUNT), cpu.read_byte(COUNT + 1)]) as u64
+}
+
+fn polled() -> u64 {
+    let mut cpu = CPU::new();
+    let via = Rc::new(RefCell::new(Via::new()));
+    let cia = Rc::new(RefCell::new(Cia::new(CLOCK_HZ)));
+    cpu.map_device_with_line(
+        0x8000..=0x800F,
+        Rc::new(RefCell::new(Polled(via.clone()))),
+        Line::Unconnected,
+    );
+    cpu.map_device_with_line(
+        0xDC00..=0xDC0F,
+        Rc::new(RefCell::new(Polled(cia.clone()))),
+        Line::Unconnected,
+    );
+    program(&mut cpu);
+    while cpu.get_cycles() < CYCLES {
+        cpu.tick();
+        let cycle = cpu.get_cycles();
+        via.borrow_mut().tick(cycle);
+        cia.borrow_mut().tick(cycle);
+        cpu.set_irq(via.borrow().irq() || cia.borrow().irq());
+    }
+    get_count(&cpu)
+}
+
+fn scheduled() -> u64 {
+    let mut cpu = CPU::new();
+    cpu.map_device(0x8000..=0x800F, Rc::new(RefCell::new(Via::new())));
+    cpu.map_device(0xDC00..=0xDC0F, Rc::new(RefCell::new(Cia::new(CLOCK_HZ))));
+    program(&mut cpu);
+    cpu.run_until(CYCLES);
+    get_count(&cpu)
+}
+
+fn bench(name: &str, f: fn() -> u64) {
+    let start = Instant::now();
+    let interrupts = black_box(f());
+    let elapsed = start.elapsed();
+    let mhz = CYCLES as f64 / elapsed.as_secs_f64() / 1_000_000.0;
+    println!("{name:<10} {elapsed:>12.2?} {mhz:>8.2} MHz ({interrupts} interrupts)");
+}
+
+fn main() {
+    bench("polled", polled);
+    bench("scheduled", scheduled);
+}
@@ -0,0 +1,55 @@
+// Apple-1 on the console: the keyboard is stdin and the display stdout.
+//
+//     apple1 WOZMON.BIN [PROGRAM.BIN@ADDR]...
+//
+// Programs are loaded into RAM at the given hex address before the machine
+// starts, ready to run from Wozmon with e.g. `E000R`. Stdin is read a line
+// at a time unless the terminal is put in raw mode, e.g. with
+// `stty -icanon -echo` before starting.
+
+use std::{env, fs, process::ExitCode};
+
+use rem6502::{machines::Apple1, serial::StdioBackend};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let Some((wozmon, programs)) = args.split_first() else {
+        eprintln!("usage: apple1 WOZMON.BIN [PROGRAM.BIN@ADDR]...");
+        return ExitCode::FAILURE;
+    };
+
+    let rom = match fs::read(wozmon) {
+        Ok(rom) if rom.len() == Apple1::WOZMON_LENGTH => rom,
+        Ok(_) => {
+            eprintln!("{wozmon}: Wozmon ROM must be 256 bytes");
+            return ExitCode::FAILURE;
+        }
+        Err(err) => {
+            eprintln!("{wozmon}: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut apple1 = Apple1::new(&rom, Box::new(StdioBackend::new()));
+
+    for program in programs {
+        let Some((path, addr)) = program.rsplit_once('@') else {
+            eprintln!("{program}: expected PROGRAM.BIN@ADDR");
+            return ExitCode::FAILURE;
+        };
+        let Ok(addr) = u16::from_str_radix(addr.trim_start_matches('$'), 16) else {
+            eprintln!("{program}: bad address {addr}");
+            return ExitCode::FAILURE;
+        };
+        match fs::read(path) {
+            Ok(data) => apple1.load(addr, &data),
+            Err(err) => {
+                eprintln!("{path}: {err}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    apple1.get_cpu().set_throttled(true);
+    apple1.run_throttled(u64::MAX);
+    ExitCode::SUCCESS
+}
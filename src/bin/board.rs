@@ -0,0 +1,90 @@
+// Runs a machine from a description file, see `rem6502::board`.
+//
+//     board [--cycles N] BOARD.TOML
+//
+// ACIAs with `serial = "stdio"` talk to the console, and text displays are
+// shown on stderr whenever they change. With `--cycles` the machine runs
+// flat out for N cycles, then prints its text displays and whatever the
+// queue-backed ACIAs sent to stdout.
+
+use std::{env, process::ExitCode};
+
+use rem6502::board::{Board, BoardBuilder, Component};
+
+// Cycles between display checks.
+const SLICE: u64 = 1_000;
+
+const USAGE: &str = "usage: board [--cycles N] BOARD.TOML";
+
+fn get_screens(board: &Board) -> Vec<String> {
+    board
+        .get_components()
+        .iter()
+        .filter_map(|(_, component)| match component {
+            Component::TextVideo(video) => Some(video.borrow().get_text()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn main() -> ExitCode {
+    let mut cycles = None;
+    let mut path = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--cycles" => match args.next().and_then(|n| n.parse::<u64>().ok()) {
+                Some(n) => cycles = Some(n),
+                None => {
+                    eprintln!("{USAGE}");
+                    return ExitCode::FAILURE;
+                }
+            },
+            _ if path.is_none() => path = Some(arg),
+            _ => {
+                eprintln!("{USAGE}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let Some(path) = path else {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    };
+
+    let mut board = match BoardBuilder::load(&path).and_then(|builder| builder.build()) {
+        Ok(board) => board,
+        Err(err) => {
+            eprintln!("{err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Some(cycles) = cycles {
+        board.run_until(cycles);
+        for screen in get_screens(&board) {
+            println!("{screen}");
+        }
+        for (name, _) in board.get_components() {
+            if let Some(serial) = board.get_serial(name) {
+                print!("{}", String::from_utf8_lossy(&serial.take_output()));
+            }
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    board.get_cpu().set_throttled(true);
+    let mut shown = vec![];
+    loop {
+        let cycle = board.get_cpu().get_cycles();
+        board.run_throttled(cycle + SLICE);
+
+        let screens = get_screens(&board);
+        if screens != shown {
+            for screen in &screens {
+                eprintln!("{screen}\n");
+            }
+            shown = screens;
+        }
+    }
+}
@@ -0,0 +1,80 @@
+// The breadboard 65C02 computer on the console: the ACIA is stdin and
+// stdout, and the LCD is shown on stderr whenever it changes.
+//
+//     breadboard [--lcd4] [--cycles N] ROM.BIN
+//
+// `--lcd4` selects the 4-bit LCD wiring. With `--cycles` the machine runs
+// flat out for N cycles and then prints the LCD to stdout, which is handy
+// for checking a program from a script.
+
+use std::{env, fs, process::ExitCode};
+
+use rem6502::{
+    machines::{Breadboard, LcdWiring},
+    serial::StdioBackend,
+};
+
+// Cycles between LCD checks.
+const SLICE: u64 = 1_000;
+
+const USAGE: &str = "usage: breadboard [--lcd4] [--cycles N] ROM.BIN";
+
+fn main() -> ExitCode {
+    let mut wiring = LcdWiring::EightBit;
+    let mut cycles = None;
+    let mut path = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--lcd4" => wiring = LcdWiring::FourBit,
+            "--cycles" => match args.next().and_then(|n| n.parse::<u64>().ok()) {
+                Some(n) => cycles = Some(n),
+                None => {
+                    eprintln!("{USAGE}");
+                    return ExitCode::FAILURE;
+                }
+            },
+            _ if path.is_none() => path = Some(arg),
+            _ => {
+                eprintln!("{USAGE}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let Some(path) = path else {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    };
+
+    let rom = match fs::read(&path) {
+        Ok(rom) if rom.len() == Breadboard::ROM_LENGTH => rom,
+        Ok(_) => {
+            eprintln!("{path}: ROM image must be 32K");
+            return ExitCode::FAILURE;
+        }
+        Err(err) => {
+            eprintln!("{path}: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut board = Breadboard::new(&rom, Box::new(StdioBackend::new()), wiring);
+
+    if let Some(cycles) = cycles {
+        board.run_until(cycles);
+        println!("{}", board.get_lcd_text());
+        return ExitCode::SUCCESS;
+    }
+
+    board.get_cpu().set_throttled(true);
+    let mut shown = String::new();
+    loop {
+        let cycle = board.get_cpu().get_cycles();
+        board.run_throttled(cycle + SLICE);
+
+        let text = board.get_lcd_text();
+        if text != shown {
+            eprintln!("{text}\n");
+            shown = text;
+        }
+    }
+}
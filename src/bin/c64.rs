@@ -0,0 +1,100 @@
+// A headless C64: boots BASIC from the given ROMs, optionally loads a
+// program and types some text, then prints the text screen.
+//
+//     c64 [--type TEXT] [--cycles N] BASIC.BIN KERNAL.BIN CHARGEN.BIN [PROGRAM.PRG]
+//
+// The program is loaded once BASIC is up, so that e.g.
+// `--type 'RUN\n'` runs a BASIC one. `\n` in TEXT presses RETURN. After the
+// text has been typed the machine runs for N more cycles, by default one
+// million, before the screen is printed.
+
+use std::{env, fs, process::ExitCode};
+
+use rem6502::machines::C64;
+
+// Long enough for the real KERNAL's RAM test and BASIC's start-up.
+const BOOT_CYCLES: u64 = 3_000_000;
+const DEFAULT_CYCLES: u64 = 1_000_000;
+const SLICE: u64 = 10_000;
+
+const USAGE: &str =
+    "usage: c64 [--type TEXT] [--cycles N] BASIC.BIN KERNAL.BIN CHARGEN.BIN [PROGRAM.PRG]";
+
+fn read_file(path: &str, length: Option<usize>) -> Option<Vec<u8>> {
+    match fs::read(path) {
+        Ok(data) if length.is_none_or(|length| data.len() == length) => Some(data),
+        Ok(_) => {
+            eprintln!("{path}: expected {} bytes", length.unwrap_or(0));
+            None
+        }
+        Err(err) => {
+            eprintln!("{path}: {err}");
+            None
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let mut text = String::new();
+    let mut cycles = DEFAULT_CYCLES;
+    let mut paths = vec![];
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--type" => match args.next() {
+                Some(arg) => text = arg.replace("\\n", "\n"),
+                None => {
+                    eprintln!("{USAGE}");
+                    return ExitCode::FAILURE;
+                }
+            },
+            "--cycles" => match args.next().and_then(|n| n.parse::<u64>().ok()) {
+                Some(n) => cycles = n,
+                None => {
+                    eprintln!("{USAGE}");
+                    return ExitCode::FAILURE;
+                }
+            },
+            _ => paths.push(arg),
+        }
+    }
+    let [basic, kernal, chargen, program @ ..] = paths.as_slice() else {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    };
+    if program.len() > 1 {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    }
+    let (Some(basic), Some(kernal), Some(chargen)) = (
+        read_file(basic, Some(C64::BASIC_LENGTH)),
+        read_file(kernal, Some(C64::KERNAL_LENGTH)),
+        read_file(chargen, Some(C64::CHARGEN_LENGTH)),
+    ) else {
+        return ExitCode::FAILURE;
+    };
+
+    let mut c64 = C64::new(&basic, &kernal, &chargen);
+    c64.run_until(BOOT_CYCLES);
+
+    if let Some(path) = program.first() {
+        let Some(prg) = read_file(path, None) else {
+            return ExitCode::FAILURE;
+        };
+        if let Err(err) = c64.load_prg(&prg) {
+            eprintln!("{path}: {err}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    c64.type_text(&text);
+    while c64.is_typing() {
+        let cycle = c64.get_cpu().get_cycles();
+        c64.run_until(cycle + SLICE);
+    }
+    let cycle = c64.get_cpu().get_cycles();
+    c64.run_until(cycle + cycles);
+
+    println!("{}", c64.get_screen());
+    ExitCode::SUCCESS
+}
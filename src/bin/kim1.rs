@@ -0,0 +1,157 @@
+// KIM-1 on the console.
+//
+//     kim1 [--keypad] 6530-002.BIN 6530-003.BIN [PROGRAM.BIN@ADDR]...
+//
+// By default the TTY jumper is fitted and the monitor talks to stdin and
+// stdout at 1200 baud. With `--keypad` the LED display is shown on stdout
+// instead and stdin is the keypad: 0-9 and upper case A-F are the hex keys,
+// and `a` AD, `d` DA, `+` +, `g` GO, `p` PC, `s` ST and `r` RS. Keys are
+// read a line at a time unless the terminal is put in raw mode, e.g. with
+// `stty -icanon -echo` before starting.
+
+use std::{
+    env, fs,
+    io::{self, Write},
+    process::ExitCode,
+};
+
+use rem6502::{
+    devices::Rriot,
+    machines::Kim1,
+    serial::{SerialBackend, StdioBackend},
+};
+
+// Cycles between display and keypad checks, and how long a key is held
+// down.
+const SLICE: u64 = 1_000;
+const KEY_CYCLES: u64 = 50_000;
+
+enum Key {
+    Keypad(u8),
+    Stop,
+    Reset,
+}
+
+fn get_key(byte: u8) -> Option<Key> {
+    Some(match byte {
+        b'0'..=b'9' => Key::Keypad(byte - b'0'),
+        b'A'..=b'F' => Key::Keypad(byte - b'A' + 0xA),
+        b'a' => Key::Keypad(Kim1::KEY_AD),
+        b'd' => Key::Keypad(Kim1::KEY_DA),
+        b'+' => Key::Keypad(Kim1::KEY_PLUS),
+        b'g' => Key::Keypad(Kim1::KEY_GO),
+        b'p' => Key::Keypad(Kim1::KEY_PC),
+        b's' => Key::Stop,
+        b'r' => Key::Reset,
+        _ => return None,
+    })
+}
+
+fn read_rom(path: &str) -> Option<Vec<u8>> {
+    match fs::read(path) {
+        Ok(rom) if rom.len() == Rriot::ROM_LENGTH => Some(rom),
+        Ok(_) => {
+            eprintln!("{path}: 6530 ROM must be 1024 bytes");
+            None
+        }
+        Err(err) => {
+            eprintln!("{path}: {err}");
+            None
+        }
+    }
+}
+
+fn run_keypad(mut kim1: Kim1) -> ExitCode {
+    let mut keyboard = StdioBackend::new();
+    let mut shown = String::new();
+    let mut pressed = false;
+    let mut next_key = 0;
+    loop {
+        // Keys are held and then released for a while, so that the monitor
+        // sees each one come up again.
+        let cycle = kim1.get_cpu().get_cycles();
+        if cycle >= next_key && pressed {
+            kim1.release_key();
+            kim1.set_stop_key(false);
+            pressed = false;
+            next_key = cycle + KEY_CYCLES;
+        } else if cycle >= next_key {
+            if let Some(key) = keyboard.receive().and_then(get_key) {
+                match key {
+                    Key::Keypad(key) => kim1.press_key(key),
+                    Key::Stop => kim1.set_stop_key(true),
+                    Key::Reset => kim1.reset(),
+                }
+                pressed = true;
+                next_key = cycle + KEY_CYCLES;
+            }
+        }
+
+        kim1.run_throttled(cycle + SLICE);
+
+        let display = kim1.get_display();
+        if display != shown {
+            print!("\r{display}");
+            if io::stdout().flush().is_err() {
+                return ExitCode::FAILURE;
+            }
+            shown = display;
+        }
+    }
+}
+
+fn run_tty(mut kim1: Kim1) -> ExitCode {
+    kim1.run_throttled(u64::MAX);
+    ExitCode::SUCCESS
+}
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let keypad = args.first().is_some_and(|arg| arg == "--keypad");
+    if keypad {
+        args.remove(0);
+    }
+    let [rom_002, rom_003, programs @ ..] = args.as_slice() else {
+        eprintln!("usage: kim1 [--keypad] 6530-002.BIN 6530-003.BIN [PROGRAM.BIN@ADDR]...");
+        return ExitCode::FAILURE;
+    };
+    let (Some(rom_002), Some(rom_003)) = (read_rom(rom_002), read_rom(rom_003)) else {
+        return ExitCode::FAILURE;
+    };
+
+    let mut kim1 = if keypad {
+        Kim1::new(&rom_002, &rom_003)
+    } else {
+        Kim1::with_tty(
+            &rom_002,
+            &rom_003,
+            Box::new(StdioBackend::new()),
+            Kim1::DEFAULT_BAUD,
+        )
+    };
+
+    for program in programs {
+        let Some((path, addr)) = program.rsplit_once('@') else {
+            eprintln!("{program}: expected PROGRAM.BIN@ADDR");
+            return ExitCode::FAILURE;
+        };
+        let Ok(addr) = u16::from_str_radix(addr.trim_start_matches('$'), 16) else {
+            eprintln!("{program}: bad address {addr}");
+            return ExitCode::FAILURE;
+        };
+        match fs::read(path) {
+            Ok(data) => kim1.load(addr, &data),
+            Err(err) => {
+                eprintln!("{path}: {err}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    kim1.get_cpu().set_throttled(true);
+    if keypad {
+        run_keypad(kim1)
+    } else {
+        run_tty(kim1)
+    }
+}
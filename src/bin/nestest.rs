@@ -0,0 +1,68 @@
+// Runs nestest in its automated mode and prints the log, for diffing
+// against the reference nestest.log.
+//
+//     nestest [--lines N] NESTEST.NES
+//
+// The CPU starts at $C000 and one line is printed per instruction, for N
+// instructions or by default as many as the reference log has.
+
+use std::{
+    env, fs,
+    io::{self, BufWriter, Write},
+    process::ExitCode,
+};
+
+use rem6502::machines::{Cartridge, Nes};
+
+// Lines in the reference log.
+const REFERENCE_LINES: usize = 8991;
+
+const USAGE: &str = "usage: nestest [--lines N] NESTEST.NES";
+
+fn main() -> ExitCode {
+    let mut lines = REFERENCE_LINES;
+    let mut path = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--lines" => match args.next().and_then(|n| n.parse::<usize>().ok()) {
+                Some(n) => lines = n,
+                None => {
+                    eprintln!("{USAGE}");
+                    return ExitCode::FAILURE;
+                }
+            },
+            _ if path.is_none() => path = Some(arg),
+            _ => {
+                eprintln!("{USAGE}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    let Some(path) = path else {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    };
+
+    let cartridge = match fs::read(&path).and_then(|bytes| Cartridge::from_ines(&bytes)) {
+        Ok(cartridge) => cartridge,
+        Err(err) => {
+            eprintln!("{path}: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut nes = Nes::new(&cartridge);
+    nes.start_nestest();
+
+    let mut out = BufWriter::new(io::stdout().lock());
+    for _ in 0..lines {
+        if writeln!(out, "{}", nes.trace()).is_err() {
+            return ExitCode::FAILURE;
+        }
+        nes.step();
+    }
+    match out.flush() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(_) => ExitCode::FAILURE,
+    }
+}
@@ -0,0 +1,655 @@
+// Machines put together from a description instead of Rust glue. A
+// description is either built up with `BoardBuilder` or read from a TOML
+// file such as
+//
+//     name = "breadboard rev B"
+//
+//     [cpu]
+//     variant = "cmos"        # nmos, cmos or 2a03
+//     clock = 1_000_000
+//
+//     [[ram]]
+//     start = 0x0000
+//     end = 0x3FFF
+//
+//     [[rom]]
+//     start = 0x8000
+//     image = "rom.bin"       # relative to the description
+//
+//     [[device]]
+//     name = "via"
+//     type = "via"
+//     start = 0x6000
+//     end = 0x7FFF            # the VIA repeats through 8K
+//     irq = "irq"
+//
+//     [reset]
+//     s = 0xFD
+//
+// `[cpu]` may also set `address_bits`, for parts with fewer address lines,
+// and `throttled`, for `run_throttled` to keep to the clock rate. RAM is
+// mapped from `start` to `end`, or for `length` bytes, and repeats through
+// the range if `length` is smaller. It can be loaded from an `image` at
+// its start. ROM covers its image unless `end` is given, and repeats
+// through the range. RAM, ROM and devices may all be given a `name`, which
+// `Board` looks them up by. Later entries are mapped over earlier ones.
+//
+// Device types are via, pia, riot, cia, acia, text_video, rng and
+// interrupt_controller. Without `end` each covers its own registers, and a
+// riot 1K so that A9 reaches its ports and timer. `irq` is irq (the
+// default), nmi, none, or the name of an interrupt_controller to add the
+// device to as a source. An acia's `serial` is queue (the default), stdio
+// or tcp:ADDRESS, a text_video takes `columns` and `rows`, and an rng a
+// `seed`.
+//
+// The board is reset when built. If `[reset]` sets `pc`, `s` or `p`, the
+// reset sequence is run and then the registers are set, e.g. to start a
+// program that is not reached through the reset vector.
+
+use std::{cell::RefCell, fs, io, ops::RangeInclusive, path::Path, rc::Rc};
+
+use crate::{
+    clock,
+    cpu::{Variant, CPU},
+    devices::{
+        Acia, Cia, Device, InterruptController, Line, Pia, Ram, Riot, Rng, Rom, TextVideo, Via,
+    },
+    serial::{QueueBackend, SerialBackend, StdioBackend, TcpBackend},
+    Byte, Word,
+};
+
+mod toml;
+
+use toml::Table;
+
+const VIA_LENGTH: usize = 0x10;
+const PIA_LENGTH: usize = 0x4;
+const RIOT_LENGTH: usize = 0x400;
+const CIA_LENGTH: usize = 0x10;
+const ACIA_LENGTH: usize = 0x4;
+const RNG_LENGTH: usize = 0x1;
+const INTERRUPT_CONTROLLER_LENGTH: usize = 0x4;
+
+const TEXT_VIDEO_COLUMNS: usize = 40;
+const TEXT_VIDEO_ROWS: usize = 25;
+
+// Where an ACIA's serial line goes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Serial {
+    // In-memory queues, reached through `Board::get_serial`.
+    Queue,
+    Stdio,
+    // Listens on the address, e.g. 127.0.0.1:6551.
+    Tcp(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DeviceKind {
+    // RAM of `length` bytes, with `image` loaded at its start.
+    Ram { length: usize, image: Vec<Byte> },
+    Rom { image: Vec<Byte> },
+    Via,
+    Pia,
+    Riot,
+    Cia,
+    Acia(Serial),
+    TextVideo { columns: usize, rows: usize },
+    Rng { seed: u64 },
+    InterruptController,
+}
+
+// Where a device's interrupt output goes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Wiring {
+    Irq,
+    Nmi,
+    None,
+    // A source on the interrupt controller of this name.
+    Controller(String),
+}
+
+// One thing on the bus. Without `end` it covers its own registers or
+// memory from `start`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeviceConfig {
+    pub name: String,
+    pub kind: DeviceKind,
+    pub start: Word,
+    pub end: Option<Word>,
+    pub wiring: Wiring,
+}
+
+// A device as built, for reaching its own methods.
+#[derive(Clone)]
+pub enum Component {
+    Ram(Rc<RefCell<Ram>>),
+    Rom(Rc<RefCell<Rom>>),
+    Via(Rc<RefCell<Via>>),
+    Pia(Rc<RefCell<Pia>>),
+    Riot(Rc<RefCell<Riot>>),
+    Cia(Rc<RefCell<Cia>>),
+    Acia(Rc<RefCell<Acia>>),
+    TextVideo(Rc<RefCell<TextVideo>>),
+    Rng(Rc<RefCell<Rng>>),
+    InterruptController(Rc<RefCell<InterruptController>>),
+}
+
+impl Component {
+    pub fn get_device(&self) -> Rc<RefCell<dyn Device>> {
+        match self {
+            Self::Ram(device) => device.clone(),
+            Self::Rom(device) => device.clone(),
+            Self::Via(device) => device.clone(),
+            Self::Pia(device) => device.clone(),
+            Self::Riot(device) => device.clone(),
+            Self::Cia(device) => device.clone(),
+            Self::Acia(device) => device.clone(),
+            Self::TextVideo(device) => device.clone(),
+            Self::Rng(device) => device.clone(),
+            Self::InterruptController(device) => device.clone(),
+        }
+    }
+}
+
+// Registers set once the reset sequence has run. Left alone if `None`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ResetConfig {
+    pub pc: Option<Word>,
+    pub s: Option<Byte>,
+    pub p: Option<Byte>,
+}
+
+impl ResetConfig {
+    fn is_empty(&self) -> bool {
+        self.pc.is_none() && self.s.is_none() && self.p.is_none()
+    }
+}
+
+pub struct BoardBuilder {
+    name: String,
+    variant: Variant,
+    clock_hz: u64,
+    address_bits: u32,
+    throttled: bool,
+    devices: Vec<DeviceConfig>,
+    reset: ResetConfig,
+}
+
+impl Default for BoardBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BoardBuilder {
+    pub fn new() -> Self {
+        Self {
+            name: String::new(),
+            variant: Variant::Nmos,
+            clock_hz: clock::DEFAULT_HZ,
+            address_bits: 16,
+            throttled: false,
+            devices: vec![],
+            reset: ResetConfig::default(),
+        }
+    }
+
+    // Reads a description file. Image paths are taken relative to it.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|err| in_file(path, err))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        Self::from_toml(&text, dir).map_err(|err| in_file(path, err))
+    }
+
+    // Reads a description, with image paths relative to `dir`.
+    pub fn from_toml(text: &str, dir: &Path) -> io::Result<Self> {
+        let mut builder = Self::new();
+        let mut ram = 0;
+        let mut rom = 0;
+        for mut table in toml::parse(text)? {
+            match (table.get_name(), table.is_array()) {
+                ("", _) => {
+                    if let Some(name) = table.take_string("name")? {
+                        builder.set_name(&name);
+                    }
+                }
+                ("cpu", false) => builder.read_cpu(&mut table)?,
+                ("reset", false) => builder.read_reset(&mut table)?,
+                ("ram", true) => {
+                    let config = read_ram(&mut table, dir, format!("ram{ram}"))?;
+                    builder.add_device(config);
+                    ram += 1;
+                }
+                ("rom", true) => {
+                    let config = read_rom(&mut table, dir, format!("rom{rom}"))?;
+                    builder.add_device(config);
+                    rom += 1;
+                }
+                ("device", true) => {
+                    let config = read_device(&mut table)?;
+                    builder.add_device(config);
+                }
+                (name, _) => {
+                    return Err(toml::error(
+                        table.get_line(),
+                        format!("unknown table {name}"),
+                    ))
+                }
+            }
+            table.check_used()?;
+        }
+        Ok(builder)
+    }
+
+    fn read_cpu(&mut self, table: &mut Table) -> io::Result<()> {
+        if let Some(variant) = table.take_string("variant")? {
+            self.set_variant(match variant.as_str() {
+                "nmos" => Variant::Nmos,
+                "cmos" => Variant::Cmos,
+                "2a03" => Variant::Ricoh2A03,
+                _ => return Err(bad_value(table, "variant", &variant)),
+            });
+        }
+        if let Some(hz) = take_number(table, "clock", 1..=u32::MAX as i64)? {
+            self.set_clock_rate(hz as u64);
+        }
+        if let Some(bits) = take_number(table, "address_bits", 1..=16)? {
+            self.set_address_bits(bits as u32);
+        }
+        if let Some(throttled) = table.take_boolean("throttled")? {
+            self.set_throttled(throttled);
+        }
+        Ok(())
+    }
+
+    fn read_reset(&mut self, table: &mut Table) -> io::Result<()> {
+        self.reset = ResetConfig {
+            pc: take_number(table, "pc", 0..=0xFFFF)?.map(|pc| pc as Word),
+            s: take_number(table, "s", 0..=0xFF)?.map(|s| s as Byte),
+            p: take_number(table, "p", 0..=0xFF)?.map(|p| p as Byte),
+        };
+        Ok(())
+    }
+
+    pub fn set_name(&mut self, name: &str) {
+        self.name = name.to_string();
+    }
+
+    pub fn set_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+    }
+
+    pub fn set_clock_rate(&mut self, hz: u64) {
+        self.clock_hz = hz;
+    }
+
+    pub fn set_address_bits(&mut self, bits: u32) {
+        self.address_bits = bits;
+    }
+
+    pub fn set_throttled(&mut self, throttled: bool) {
+        self.throttled = throttled;
+    }
+
+    // Devices are mapped in the order they are added, so later ones take
+    // precedence where they overlap.
+    pub fn add_device(&mut self, config: DeviceConfig) {
+        self.devices.push(config);
+    }
+
+    pub fn set_reset(&mut self, reset: ResetConfig) {
+        self.reset = reset;
+    }
+
+    pub fn get_devices(&self) -> &[DeviceConfig] {
+        &self.devices
+    }
+
+    pub fn build(&self) -> io::Result<Board> {
+        let mut cpu = CPU::new();
+        cpu.set_variant(self.variant);
+        cpu.set_clock_rate(self.clock_hz);
+        if !(1..=16).contains(&self.address_bits) {
+            return Err(invalid("address bus must be 1 to 16 bits wide".to_string()));
+        }
+        cpu.set_address_bits(self.address_bits);
+
+        let mut components: Vec<(String, Component)> = vec![];
+        let mut serials = vec![];
+        for config in &self.devices {
+            if components.iter().any(|(name, _)| *name == config.name) {
+                return Err(invalid(format!("{} is defined twice", config.name)));
+            }
+            let (component, serial) = self.create(config)?;
+            if let Some(serial) = serial {
+                serials.push((config.name.clone(), serial));
+            }
+            components.push((config.name.clone(), component));
+        }
+
+        for (config, (_, component)) in self.devices.iter().zip(&components) {
+            let device = component.get_device();
+            let range = get_range(config, component)?;
+            let line = match &config.wiring {
+                Wiring::Irq => Line::Irq,
+                Wiring::Nmi => Line::Nmi,
+                Wiring::None => Line::Unconnected,
+                Wiring::Controller(controller) => {
+                    let Some((_, Component::InterruptController(controller))) =
+                        components.iter().find(|(name, _)| name == controller)
+                    else {
+                        return Err(invalid(format!(
+                            "{}: {controller} is not an interrupt controller",
+                            config.name
+                        )));
+                    };
+                    controller
+                        .borrow_mut()
+                        .add_source(&config.name, device.clone());
+                    Line::Unconnected
+                }
+            };
+            cpu.map_device_with_line(range, device, line);
+        }
+        cpu.set_throttled(self.throttled);
+
+        let mut board = Board {
+            name: self.name.clone(),
+            cpu,
+            components,
+            serials,
+            reset: self.reset,
+        };
+        board.reset();
+        Ok(board)
+    }
+
+    fn create(&self, config: &DeviceConfig) -> io::Result<(Component, Option<QueueBackend>)> {
+        let fits = |length: usize| config.start as usize + length <= 0x10000;
+        let mut serial = None;
+        let component = match &config.kind {
+            DeviceKind::Ram { length, image } => {
+                if *length == 0 || !fits(*length) || image.len() > *length {
+                    return Err(invalid(format!("{}: RAM does not fit", config.name)));
+                }
+                let mut ram = Ram::new(config.start, *length);
+                ram.get_mut_data()[..image.len()].copy_from_slice(image);
+                Component::Ram(Rc::new(RefCell::new(ram)))
+            }
+            DeviceKind::Rom { image } => {
+                if image.is_empty() || !fits(image.len()) {
+                    return Err(invalid(format!("{}: ROM image does not fit", config.name)));
+                }
+                Component::Rom(Rc::new(RefCell::new(Rom::new(config.start, image))))
+            }
+            DeviceKind::Via => Component::Via(Rc::new(RefCell::new(Via::new()))),
+            DeviceKind::Pia => Component::Pia(Rc::new(RefCell::new(Pia::new()))),
+            DeviceKind::Riot => Component::Riot(Rc::new(RefCell::new(Riot::new()))),
+            DeviceKind::Cia => Component::Cia(Rc::new(RefCell::new(Cia::new(self.clock_hz)))),
+            DeviceKind::Acia(kind) => {
+                let backend: Box<dyn SerialBackend> = match kind {
+                    Serial::Queue => {
+                        let queue = QueueBackend::new();
+                        serial = Some(queue.clone());
+                        Box::new(queue)
+                    }
+                    Serial::Stdio => Box::new(StdioBackend::new()),
+                    Serial::Tcp(addr) => Box::new(TcpBackend::bind(addr.as_str())?),
+                };
+                Component::Acia(Rc::new(RefCell::new(Acia::new(backend, self.clock_hz))))
+            }
+            DeviceKind::TextVideo { columns, rows } => {
+                if *columns == 0 || *rows == 0 || !fits(columns * rows + 3) {
+                    return Err(invalid(format!("{}: screen does not fit", config.name)));
+                }
+                let video = TextVideo::new(config.start, *columns, *rows);
+                Component::TextVideo(Rc::new(RefCell::new(video)))
+            }
+            DeviceKind::Rng { seed } => Component::Rng(Rc::new(RefCell::new(Rng::new(*seed)))),
+            DeviceKind::InterruptController => {
+                let controller = InterruptController::new();
+                Component::InterruptController(Rc::new(RefCell::new(controller)))
+            }
+        };
+        Ok((component, serial))
+    }
+}
+
+// A machine built from a description.
+pub struct Board {
+    name: String,
+    cpu: CPU,
+    components: Vec<(String, Component)>,
+    serials: Vec<(String, QueueBackend)>,
+    reset: ResetConfig,
+}
+
+impl Board {
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_cpu(&mut self) -> &mut CPU {
+        &mut self.cpu
+    }
+
+    pub fn get_component(&self, name: &str) -> Option<&Component> {
+        self.components
+            .iter()
+            .find(|(component, _)| component == name)
+            .map(|(_, component)| component)
+    }
+
+    pub fn get_components(&self) -> &[(String, Component)] {
+        &self.components
+    }
+
+    // The other end of a queue-backed ACIA's serial line.
+    pub fn get_serial(&self, name: &str) -> Option<QueueBackend> {
+        self.serials
+            .iter()
+            .find(|(serial, _)| serial == name)
+            .map(|(_, queue)| queue.clone())
+    }
+
+    // Resets the CPU as described, see `ResetConfig`.
+    pub fn reset(&mut self) {
+        self.cpu.reset();
+        if self.reset.is_empty() {
+            return;
+        }
+        self.cpu.run();
+        let registers = self.cpu.get_registers();
+        if let Some(pc) = self.reset.pc {
+            *registers.get_mut_pc() = pc;
+        }
+        if let Some(s) = self.reset.s {
+            *registers.get_mut_s() = s;
+        }
+        if let Some(p) = self.reset.p {
+            registers.set_p(p);
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.cpu.tick();
+    }
+
+    pub fn run_until(&mut self, cycle: u64) {
+        self.cpu.run_until(cycle);
+    }
+
+    pub fn run_throttled(&mut self, cycle: u64) {
+        self.cpu.run_throttled(cycle);
+    }
+}
+
+// The mapped range: from `start` to `end` if given, otherwise the device's
+// own size.
+fn get_range(config: &DeviceConfig, component: &Component) -> io::Result<RangeInclusive<Word>> {
+    let length = match component {
+        Component::Ram(ram) => return finish_range(config, ram.borrow().get_range()),
+        Component::Rom(rom) => return finish_range(config, rom.borrow().get_range()),
+        Component::TextVideo(video) => return finish_range(config, video.borrow().get_range()),
+        Component::Via(_) => VIA_LENGTH,
+        Component::Pia(_) => PIA_LENGTH,
+        Component::Riot(_) => RIOT_LENGTH,
+        Component::Cia(_) => CIA_LENGTH,
+        Component::Acia(_) => ACIA_LENGTH,
+        Component::Rng(_) => RNG_LENGTH,
+        Component::InterruptController(_) => INTERRUPT_CONTROLLER_LENGTH,
+    };
+    if config.start as usize + length > 0x10000 {
+        return Err(invalid(format!(
+            "{} runs past the end of memory",
+            config.name
+        )));
+    }
+    finish_range(config, config.start..=config.start + (length - 1) as Word)
+}
+
+fn finish_range(
+    config: &DeviceConfig,
+    range: RangeInclusive<Word>,
+) -> io::Result<RangeInclusive<Word>> {
+    match config.end {
+        Some(end) if end < config.start => {
+            Err(invalid(format!("{} ends before it starts", config.name)))
+        }
+        Some(end) => Ok(config.start..=end),
+        None => Ok(range),
+    }
+}
+
+fn read_ram(table: &mut Table, dir: &Path, default_name: String) -> io::Result<DeviceConfig> {
+    let name = table.take_string("name")?.unwrap_or(default_name);
+    let start = take_address(table, "start")?.ok_or_else(|| missing(table, "start"))?;
+    let end = take_address(table, "end")?;
+    let image = match table.take_string("image")? {
+        Some(image) => read_image(dir, &image)?,
+        None => vec![],
+    };
+    let length = match (take_number(table, "length", 1..=0x10000)?, end) {
+        (Some(length), _) => length as usize,
+        (None, Some(end)) if end >= start => (end - start) as usize + 1,
+        (None, Some(_)) => return Err(bad_value(table, "end", "before start")),
+        (None, None) => return Err(missing(table, "end or length")),
+    };
+    Ok(DeviceConfig {
+        name,
+        kind: DeviceKind::Ram { length, image },
+        start,
+        end,
+        wiring: Wiring::None,
+    })
+}
+
+fn read_rom(table: &mut Table, dir: &Path, default_name: String) -> io::Result<DeviceConfig> {
+    let name = table.take_string("name")?.unwrap_or(default_name);
+    let start = take_address(table, "start")?.ok_or_else(|| missing(table, "start"))?;
+    let end = take_address(table, "end")?;
+    let image = table
+        .take_string("image")?
+        .ok_or_else(|| missing(table, "image"))?;
+    Ok(DeviceConfig {
+        name,
+        kind: DeviceKind::Rom {
+            image: read_image(dir, &image)?,
+        },
+        start,
+        end,
+        wiring: Wiring::None,
+    })
+}
+
+fn read_device(table: &mut Table) -> io::Result<DeviceConfig> {
+    let name = table
+        .take_string("name")?
+        .ok_or_else(|| missing(table, "name"))?;
+    let kind = table
+        .take_string("type")?
+        .ok_or_else(|| missing(table, "type"))?;
+    let start = take_address(table, "start")?.ok_or_else(|| missing(table, "start"))?;
+    let end = take_address(table, "end")?;
+    let wiring = match table.take_string("irq")?.as_deref() {
+        None | Some("irq") => Wiring::Irq,
+        Some("nmi") => Wiring::Nmi,
+        Some("none") => Wiring::None,
+        Some(controller) => Wiring::Controller(controller.to_string()),
+    };
+    let kind = match kind.as_str() {
+        "via" => DeviceKind::Via,
+        "pia" => DeviceKind::Pia,
+        "riot" => DeviceKind::Riot,
+        "cia" => DeviceKind::Cia,
+        "acia" => DeviceKind::Acia(match table.take_string("serial")?.as_deref() {
+            None | Some("queue") => Serial::Queue,
+            Some("stdio") => Serial::Stdio,
+            Some(serial) => match serial.strip_prefix("tcp:") {
+                Some(addr) => Serial::Tcp(addr.to_string()),
+                None => return Err(bad_value(table, "serial", serial)),
+            },
+        }),
+        "text_video" => DeviceKind::TextVideo {
+            columns: take_number(table, "columns", 1..=0xFFFF)?
+                .map_or(TEXT_VIDEO_COLUMNS, |columns| columns as usize),
+            rows: take_number(table, "rows", 1..=0xFFFF)?
+                .map_or(TEXT_VIDEO_ROWS, |rows| rows as usize),
+        },
+        "rng" => DeviceKind::Rng {
+            seed: take_number(table, "seed", 0..=i64::MAX)?.unwrap_or(0) as u64,
+        },
+        "interrupt_controller" => DeviceKind::InterruptController,
+        _ => return Err(bad_value(table, "type", &kind)),
+    };
+    Ok(DeviceConfig {
+        name,
+        kind,
+        start,
+        end,
+        wiring,
+    })
+}
+
+fn read_image(dir: &Path, image: &str) -> io::Result<Vec<Byte>> {
+    let path = dir.join(image);
+    fs::read(&path).map_err(|err| in_file(&path, err))
+}
+
+fn take_number(
+    table: &mut Table,
+    key: &str,
+    range: RangeInclusive<i64>,
+) -> io::Result<Option<i64>> {
+    match table.take_integer(key)? {
+        Some(val) if !range.contains(&val) => Err(bad_value(table, key, &val.to_string())),
+        val => Ok(val),
+    }
+}
+
+fn take_address(table: &mut Table, key: &str) -> io::Result<Option<Word>> {
+    Ok(take_number(table, key, 0..=0xFFFF)?.map(|addr| addr as Word))
+}
+
+fn missing(table: &Table, key: &str) -> io::Error {
+    toml::error(
+        table.get_line(),
+        format!("[{}] needs {key}", table.get_name()),
+    )
+}
+
+fn bad_value(table: &Table, key: &str, val: &str) -> io::Error {
+    toml::error(
+        table.get_line(),
+        format!("[{}] has a bad {key}: {val}", table.get_name()),
+    )
+}
+
+fn in_file(path: &Path, err: io::Error) -> io::Error {
+    io::Error::new(err.kind(), format!("{}: {err}", path.display()))
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
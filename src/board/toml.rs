@@ -0,0 +1,247 @@
+use std::io;
+
+// The part of TOML board descriptions use: `[table]` and `[[array]]`
+// headers, and `key = value` lines whose value is an integer, a string or
+// a boolean. Integers may be hex, octal or binary with a 0x, 0o or 0b
+// prefix, and may contain underscores. Arrays, inline tables, floats,
+// dates and dotted keys are rejected rather than misread.
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) enum Value {
+    Integer(i64),
+    String(String),
+    Boolean(bool),
+}
+
+struct Entry {
+    key: String,
+    value: Value,
+    line: usize,
+}
+
+// A table's entries, in file order. The root table has an empty name.
+// Values are taken out as they are used, so that whatever is left over
+// can be reported as unknown.
+pub(super) struct Table {
+    name: String,
+    array: bool,
+    line: usize,
+    entries: Vec<Entry>,
+}
+
+impl Table {
+    pub(super) fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub(super) fn is_array(&self) -> bool {
+        self.array
+    }
+
+    pub(super) fn get_line(&self) -> usize {
+        self.line
+    }
+
+    fn take(&mut self, key: &str) -> Option<Entry> {
+        let index = self.entries.iter().position(|entry| entry.key == key)?;
+        Some(self.entries.remove(index))
+    }
+
+    pub(super) fn take_integer(&mut self, key: &str) -> io::Result<Option<i64>> {
+        match self.take(key) {
+            Some(Entry {
+                value: Value::Integer(val),
+                ..
+            }) => Ok(Some(val)),
+            Some(entry) => Err(error(entry.line, format!("{key} must be an integer"))),
+            None => Ok(None),
+        }
+    }
+
+    pub(super) fn take_string(&mut self, key: &str) -> io::Result<Option<String>> {
+        match self.take(key) {
+            Some(Entry {
+                value: Value::String(val),
+                ..
+            }) => Ok(Some(val)),
+            Some(entry) => Err(error(entry.line, format!("{key} must be a string"))),
+            None => Ok(None),
+        }
+    }
+
+    pub(super) fn take_boolean(&mut self, key: &str) -> io::Result<Option<bool>> {
+        match self.take(key) {
+            Some(Entry {
+                value: Value::Boolean(val),
+                ..
+            }) => Ok(Some(val)),
+            Some(entry) => Err(error(entry.line, format!("{key} must be true or false"))),
+            None => Ok(None),
+        }
+    }
+
+    // Fails on the first key nothing asked for, which is most likely a
+    // typo.
+    pub(super) fn check_used(&self) -> io::Result<()> {
+        match self.entries.first() {
+            Some(entry) => Err(error(entry.line, format!("unknown key {}", entry.key))),
+            None => Ok(()),
+        }
+    }
+}
+
+// The root table followed by the others in file order.
+pub(super) fn parse(text: &str) -> io::Result<Vec<Table>> {
+    let mut tables = vec![Table {
+        name: String::new(),
+        array: false,
+        line: 0,
+        entries: vec![],
+    }];
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let line = strip_comment(line).trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('[') {
+            let (name, array) = match header.strip_prefix('[') {
+                Some(header) => (header.strip_suffix("]]"), true),
+                None => (header.strip_suffix(']'), false),
+            };
+            let name = match name.map(str::trim) {
+                Some(name) if is_bare_key(name) => name.to_string(),
+                _ => return Err(error(number, format!("bad table header {line}"))),
+            };
+            let defined = tables.iter().find(|table| table.name == name);
+            if defined.is_some_and(|table| !array || !table.array) {
+                return Err(error(number, format!("table {name} is defined twice")));
+            }
+            tables.push(Table {
+                name,
+                array,
+                line: number,
+                entries: vec![],
+            });
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(error(number, format!("expected key = value, found {line}")));
+        };
+        let key = key.trim();
+        if !is_bare_key(key) {
+            return Err(error(number, format!("bad key {key}")));
+        }
+        let value = parse_value(value.trim()).map_err(|message| error(number, message))?;
+        let table = tables.last_mut().unwrap();
+        if table.entries.iter().any(|entry| entry.key == key) {
+            return Err(error(number, format!("{key} is set twice")));
+        }
+        table.entries.push(Entry {
+            key: key.to_string(),
+            value,
+            line: number,
+        });
+    }
+    Ok(tables)
+}
+
+pub(super) fn error(line: usize, message: String) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {line}: {message}"),
+    )
+}
+
+// Drops a comment, leaving any `#` inside a string alone.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        match quote {
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '#' => return &line[..index],
+            None => {}
+        }
+    }
+    line
+}
+
+fn is_bare_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+fn parse_value(value: &str) -> Result<Value, String> {
+    match value {
+        "true" => return Ok(Value::Boolean(true)),
+        "false" => return Ok(Value::Boolean(false)),
+        _ => {}
+    }
+    if let Some(literal) = value.strip_prefix('\'') {
+        return match literal.strip_suffix('\'') {
+            Some(literal) if !literal.contains('\'') => Ok(Value::String(literal.to_string())),
+            _ => Err(format!("bad string {value}")),
+        };
+    }
+    if let Some(basic) = value.strip_prefix('"') {
+        return parse_basic_string(basic).ok_or(format!("bad string {value}"));
+    }
+    parse_integer(value)
+        .map(Value::Integer)
+        .ok_or(format!("unsupported value {value}"))
+}
+
+// The rest of a double-quoted string after the opening quote.
+fn parse_basic_string(basic: &str) -> Option<Value> {
+    let mut string = String::new();
+    let mut chars = basic.chars();
+    loop {
+        match chars.next()? {
+            '"' => break,
+            '\\' => string.push(match chars.next()? {
+                'n' => '\n',
+                't' => '\t',
+                'r' => '\r',
+                '"' => '"',
+                '\\' => '\\',
+                _ => return None,
+            }),
+            c => string.push(c),
+        }
+    }
+    match chars.next() {
+        Some(_) => None,
+        None => Some(Value::String(string)),
+    }
+}
+
+fn parse_integer(value: &str) -> Option<i64> {
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let (radix, digits) = match digits.get(..2) {
+        Some("0x") => (16, &digits[2..]),
+        Some("0o") => (8, &digits[2..]),
+        Some("0b") => (2, &digits[2..]),
+        _ => (10, digits),
+    };
+    // Underscores only go between digits.
+    if digits.is_empty() || digits.starts_with('_') || digits.ends_with('_') {
+        return None;
+    }
+    if digits.contains("__") || !digits.chars().all(|c| c == '_' || c.is_digit(radix)) {
+        return None;
+    }
+    let val = i64::from_str_radix(&digits.replace('_', ""), radix).ok()?;
+    Some(if negative { -val } else { val })
+}
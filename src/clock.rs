@@ -0,0 +1,50 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+pub const DEFAULT_HZ: u64 = 1_000_000;
+pub const APPLE_II_HZ: u64 = 1_022_727;
+pub const NES_NTSC_HZ: u64 = 1_789_773;
+pub const NES_PAL_HZ: u64 = 1_662_607;
+pub const C64_PAL_HZ: u64 = 985_248;
+pub const C64_NTSC_HZ: u64 = 1_022_727;
+pub const ATARI_2600_NTSC_HZ: u64 = 1_193_182;
+
+const MIN_SLEEP: Duration = Duration::from_millis(1);
+const MAX_LAG: Duration = Duration::from_millis(100);
+
+pub fn cycles_to_duration(cycles: u64, hz: u64) -> Duration {
+    let nanos = cycles as u128 * 1_000_000_000 / hz as u128;
+    Duration::from_nanos(nanos as u64)
+}
+
+// Keeps emulated time in step with the wall clock. Sleeps are batched so
+// that we don't hit the scheduler once per instruction, and if the host
+// falls too far behind (e.g. it was suspended) the reference point is
+// moved instead of running flat out to catch up.
+pub struct Throttle {
+    start: Instant,
+    start_cycle: u64,
+}
+
+impl Throttle {
+    pub fn new(cycle: u64) -> Self {
+        Self {
+            start: Instant::now(),
+            start_cycle: cycle,
+        }
+    }
+
+    pub fn sync(&mut self, cycle: u64, hz: u64) {
+        let emulated = cycles_to_duration(cycle - self.start_cycle, hz);
+        let elapsed = self.start.elapsed();
+
+        if emulated > elapsed + MIN_SLEEP {
+            thread::sleep(emulated - elapsed);
+        } else if elapsed > emulated + MAX_LAG {
+            self.start = Instant::now();
+            self.start_cycle = cycle;
+        }
+    }
+}
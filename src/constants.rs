@@ -4,3 +4,7 @@ pub type Word = u16;
 pub const MEMORY_LENGTH: usize = 0x10000;
 
 pub const SP: Byte = 0x01;
+
+pub const NMI_VECTOR: Word = 0xFFFA;
+pub const RESET_VECTOR: Word = 0xFFFC;
+pub const IRQ_VECTOR: Word = 0xFFFE;
@@ -1,47 +1,343 @@
+use std::{
+    cell::RefCell, collections::VecDeque, ops::RangeInclusive, rc::Rc, time::Duration,
+};
+
 use crate::{
+    clock::{self, Throttle},
+    devices::{Device, Line},
+    dma::{Dma, DmaTransfer},
     instructions::{InstructionExecutor, Instructions},
     memory::Memory,
     registers::Registers,
+    scheduler::Scheduler,
     sequencer, Byte, Word,
 };
 
+// The processor being emulated. The CMOS parts add instructions and
+// addressing modes, give valid N and Z flags in decimal mode at the cost of
+// a cycle, and clear D on interrupts and reset. `Cmos` is the W65C02S,
+// with the Rockwell bit instructions and WAI and STP. The NES's 2A03 is an NMOS
+// core with decimal mode cut out: D can still be set, but ADC and SBC stay
+// binary.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Variant {
+    Nmos,
+    Cmos,
+    Ricoh2A03,
+}
+
+impl Variant {
+    pub fn has_decimal_mode(&self) -> bool {
+        *self != Variant::Ricoh2A03
+    }
+}
+
 pub struct CPU {
+    variant: Variant,
     mem: Memory,
     registers: Registers,
     addr_bus: Word,
     data_bus: Byte,
-    cycles: usize,
+    cycles: u64,
+    scheduler: Scheduler,
+    clock_hz: u64,
+    throttle: Option<Throttle>,
+    sequence: VecDeque<Instructions>,
+    rdy: bool,
+    so_edge: bool,
+    dma: VecDeque<DmaTransfer>,
+    irq: bool,
+    nmi: bool,
+    nmi_prev: bool,
+    nmi_edge: bool,
+    interrupt_pending: bool,
+    polling: bool,
+    waiting: bool,
+    stopped: bool,
+}
+
+impl Default for CPU {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CPU {
     pub fn new() -> Self {
         Self {
+            variant: Variant::Nmos,
             mem: Memory::new(),
             registers: Registers::new(),
             addr_bus: 0x0,
             data_bus: 0x0,
-            cycles: 0x1,
+            cycles: 0x0,
+            scheduler: Scheduler::new(),
+            clock_hz: clock::DEFAULT_HZ,
+            throttle: None,
+            sequence: VecDeque::new(),
+            rdy: true,
+            so_edge: false,
+            dma: VecDeque::new(),
+            irq: false,
+            nmi: false,
+            nmi_prev: false,
+            nmi_edge: false,
+            interrupt_pending: false,
+            polling: true,
+            waiting: false,
+            stopped: false,
         }
     }
 
+    pub fn get_variant(&self) -> Variant {
+        self.variant
+    }
+
+    pub fn set_variant(&mut self, variant: Variant) {
+        self.variant = variant;
+    }
+
+    // The number of address lines the package brings out, 16 unless set
+    // otherwise. With fewer, e.g. the 13 of the 6507, the upper bits of
+    // every address are dropped and memory repeats through the address
+    // space, while PC and the other registers stay 16 bits wide.
+    pub fn set_address_bits(&mut self, bits: u32) {
+        assert!(
+            (1..=16).contains(&bits),
+            "address bus must be 1 to 16 bits wide"
+        );
+        self.mem.set_address_mask(((1u32 << bits) - 1) as Word);
+    }
+
+    pub fn get_address_bits(&self) -> u32 {
+        self.mem.get_address_mask().count_ones()
+    }
+
     pub fn write_byte(&mut self, addr: Word, val: Byte) {
+        self.mem.set_cycle(self.cycles);
         self.mem.write_byte(addr, val);
+        self.update_touched();
     }
 
+    // Looks at memory from outside the CPU, so devices see no bus cycle.
     pub fn read_byte(&self, addr: Word) -> Byte {
-        self.mem.read_byte(addr)
+        self.mem.peek_byte(addr)
+    }
+
+    pub fn map_device(&mut self, range: RangeInclusive<Word>, device: Rc<RefCell<dyn Device>>) {
+        self.map_device_with_line(range, device, Line::Irq);
+    }
+
+    // Like `map_device`, with the device's interrupt output wired to `line`.
+    pub fn map_device_with_line(
+        &mut self,
+        range: RangeInclusive<Word>,
+        device: Rc<RefCell<dyn Device>>,
+        line: Line,
+    ) {
+        self.mem.map(range, device, line);
+    }
+
+    // Brings every mapped device up to the current cycle and takes in its
+    // interrupt output and next event. Devices are otherwise only looked at
+    // when accessed or when their event is due, so the host calls this
+    // after changing one from outside, e.g. an input pin, and before
+    // reading state off one. The `run` functions call it when they start
+    // and finish.
+    pub fn update_devices(&mut self) {
+        loop {
+            for index in 0..self.mem.get_device_count() {
+                self.update_device(index);
+            }
+            if !self.mem.take_changed() {
+                break;
+            }
+        }
+    }
+
+    // Callbacks are fired at the end of the cycle they are due in.
+    pub fn get_scheduler(&mut self) -> &mut Scheduler {
+        &mut self.scheduler
+    }
+
+    // Brings one device up to date, hands on any DMA it has started and
+    // moves its event to the cycle it now asks for.
+    fn update_device(&mut self, index: usize) {
+        let (dma, next) = self.mem.update_device(index, self.cycles);
+        if let Some(dma) = dma {
+            self.request_dma(dma);
+        }
+        let event = self.mem.get_event(index);
+        if event.map(|(cycle, _)| cycle) == next {
+            return;
+        }
+        if let Some((_, id)) = event {
+            self.scheduler.cancel(id);
+        }
+        let event = next.map(|cycle| {
+            let cycle = cycle.max(self.cycles + 1);
+            let id = self.scheduler.schedule(cycle, move |cpu: &mut CPU| {
+                cpu.mem.set_event(index, None);
+                cpu.update_device(index);
+            });
+            (cycle, id)
+        });
+        self.mem.set_event(index, event);
+    }
+
+    fn update_touched(&mut self) {
+        while let Some(index) = self.mem.pop_touched() {
+            self.update_device(index);
+        }
+        // Devices can follow each other's interrupt outputs, like an
+        // interrupt controller does its sources, so when one changes they
+        // are all brought up to date.
+        if self.mem.take_changed() {
+            self.update_devices();
+        }
+    }
+
+    fn service_devices(&mut self) {
+        while let Some(callback) = self.scheduler.pop_due(self.cycles) {
+            callback(self);
+        }
+        self.update_touched();
+    }
+
+    pub fn get_cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    pub fn get_clock_rate(&self) -> u64 {
+        self.clock_hz
+    }
+
+    pub fn set_clock_rate(&mut self, hz: u64) {
+        assert!(hz > 0, "clock rate must be non-zero");
+        self.clock_hz = hz;
+        if self.throttle.is_some() {
+            self.throttle = Some(Throttle::new(self.cycles));
+        }
+    }
+
+    pub fn get_elapsed(&self) -> Duration {
+        clock::cycles_to_duration(self.cycles, self.clock_hz)
+    }
+
+    // When throttled, `run_loop` and `run_throttled` sleep so that emulated
+    // time keeps pace with real time at the configured clock rate. Off by
+    // default.
+    pub fn set_throttled(&mut self, throttled: bool) {
+        self.throttle = throttled.then(|| Throttle::new(self.cycles));
+    }
+
+    pub fn is_throttled(&self) -> bool {
+        self.throttle.is_some()
     }
 
     pub fn get_registers(&mut self) -> &mut Registers {
         &mut self.registers
     }
 
+    // RDY is only sampled on read cycles: while it is low the CPU stalls on
+    // the next read and finishes any writes in between, like NMOS parts.
+    pub fn set_rdy(&mut self, rdy: bool) {
+        self.rdy = rdy;
+    }
+
+    pub fn get_rdy(&self) -> bool {
+        self.rdy
+    }
+
+    // Falling edge on SO. V is set at the end of the cycle the edge is
+    // detected in, i.e. during the next call to `tick`.
+    pub fn assert_so(&mut self) {
+        self.so_edge = true;
+    }
+
+    // IRQ is level sensitive and wired-OR with the IRQ outputs of mapped
+    // devices. NMI is latched on the edge where it becomes asserted, and is
+    // likewise wired-OR with devices mapped on `Line::Nmi`. Both are sampled
+    // once per cycle, with device outputs as of their last update.
+    pub fn set_irq(&mut self, irq: bool) {
+        self.irq = irq;
+    }
+
+    pub fn set_nmi(&mut self, nmi: bool) {
+        self.nmi = nmi;
+    }
+
+    // Pulls RESET: whatever was in progress is abandoned, and the next seven
+    // cycles load PC from the reset vector and set I. Registers other than
+    // S and P are left as they were, like on the real part.
+    pub fn reset(&mut self) {
+        self.sequence = sequencer::get_reset_sequence(self.variant).into();
+        self.polling = false;
+        self.dma.clear();
+        self.interrupt_pending = false;
+        self.nmi_edge = false;
+        self.waiting = false;
+        self.stopped = false;
+    }
+
+    // After WAI the CPU does nothing until IRQ or NMI is asserted. With I
+    // set an IRQ only wakes it, and it carries on with the next
+    // instruction.
+    pub fn is_waiting(&self) -> bool {
+        self.waiting
+    }
+
+    // After STP only a reset gets the CPU going again.
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    pub fn request_dma(&mut self, dma: Dma) {
+        self.dma.push_back(DmaTransfer::new(dma));
+    }
+
+    pub fn is_dma_active(&self) -> bool {
+        !self.dma.is_empty()
+    }
+
+    pub fn is_halted(&self) -> bool {
+        !self.rdy && self.is_read_cycle()
+    }
+
+    fn is_read_cycle(&self) -> bool {
+        match self.sequence.front() {
+            Some(instruction) => !instruction.is_write(),
+            None => true,
+        }
+    }
+
+    fn tick_dma(&mut self) -> bool {
+        let is_read_cycle = self.is_read_cycle();
+        let Some(transfer) = self.dma.front_mut() else {
+            return false;
+        };
+        if !transfer.is_started() && !is_read_cycle {
+            return false;
+        }
+        if transfer.tick(self.cycles, &mut self.mem) {
+            self.dma.pop_front();
+        }
+        true
+    }
+
     pub fn execute(&mut self, instruction: &Instructions) {
+        match instruction {
+            Instructions::Wait => self.waiting = true,
+            Instructions::Stop => self.stopped = true,
+            _ => {}
+        }
         let mut instruction_executor = InstructionExecutor::new(
             &mut self.mem,
             &mut self.registers,
             &mut self.addr_bus,
             &mut self.data_bus,
+            &mut self.nmi_edge,
+            self.variant.has_decimal_mode(),
         );
         instruction_executor.execute_instruction(instruction);
     }
@@ -49,30 +345,142 @@ impl CPU {
     fn get_instruction(&mut self) -> Vec<Instructions> {
         let instruction = self.mem.read_byte(self.registers.get_pc());
         self.registers.inc_pc();
-        sequencer::get_seqeunce(instruction, &self.registers, &self.mem)
+        sequencer::get_seqeunce(instruction, self.variant, &self.registers, &self.mem)
+    }
+
+    fn get_next_sequence(&mut self) -> Vec<Instructions> {
+        if self.interrupt_pending {
+            self.interrupt_pending = false;
+            return sequencer::get_interrupt_sequence(self.variant);
+        }
+        self.get_instruction()
+    }
+
+    // Interrupts are polled at the end of the second-to-last cycle of an
+    // instruction. Since the last micro-op overlaps the next opcode fetch,
+    // that is right before the second-to-last micro-op runs. A taken branch
+    // that stays on its page only polls before its operand fetch. BRK and
+    // interrupt entry don't poll at all, so the first instruction of a
+    // handler always runs.
+    fn get_poll_point(&self) -> usize {
+        match self.sequence.back() {
+            Some(Instructions::AddToPC) => 3,
+            _ => 2,
+        }
+    }
+
+    fn poll_interrupts(&mut self) {
+        let irq = self.irq || self.mem.irq();
+        self.interrupt_pending = self.nmi_edge || (irq && !self.registers.get_p().i);
+    }
+
+    fn detect_nmi_edge(&mut self) {
+        let nmi = self.nmi || self.mem.nmi();
+        if nmi && !self.nmi_prev {
+            self.nmi_edge = true;
+        }
+        self.nmi_prev = nmi;
+    }
+
+    pub fn tick(&mut self) {
+        self.mem.set_cycle(self.cycles);
+        self.cycles += 1;
+
+        if self.waiting && (self.nmi_edge || self.irq || self.mem.irq()) {
+            self.waiting = false;
+            self.poll_interrupts();
+        }
+
+        if !self.tick_dma() && !self.is_halted() && !self.waiting && !self.stopped {
+            if self.sequence.is_empty() {
+                self.sequence = self.get_next_sequence().into();
+                self.polling = !self
+                    .sequence
+                    .iter()
+                    .any(|instruction| matches!(instruction, Instructions::LoadVector));
+            }
+            if self.polling && self.sequence.len() == self.get_poll_point() {
+                self.poll_interrupts();
+            }
+            if let Some(instruction) = self.sequence.pop_front() {
+                self.execute(&instruction);
+            }
+        }
+
+        if self.mem.is_touched() || self.scheduler.is_due(self.cycles) {
+            self.service_devices();
+        }
+        self.detect_nmi_edge();
+
+        if self.so_edge {
+            self.registers.get_mut_p().v = true;
+            self.so_edge = false;
+        }
     }
 
     pub fn run(&mut self) {
-        let instructions = self.get_instruction();
-        for instruction in instructions {
-            self.cycles += 1;
-            self.execute(&instruction);
+        while self.sequence.is_empty() && self.is_dma_active() {
+            self.tick();
+        }
+        loop {
+            self.tick();
+            if self.sequence.is_empty() || self.is_halted() {
+                break;
+            }
+        }
+    }
+
+    pub fn run_until(&mut self, cycle: u64) {
+        self.update_devices();
+        while self.cycles < cycle {
+            self.tick();
+        }
+        self.update_devices();
+    }
+
+    // Runs until `cycle` like `run_until`, BRK and all, keeping to the
+    // clock rate when throttled. For machines that are left running rather
+    // than programs run to their end.
+    pub fn run_throttled(&mut self, cycle: u64) {
+        self.update_devices();
+        while self.cycles < cycle {
+            self.tick();
+            self.sync_throttle();
+        }
+        self.update_devices();
+    }
+
+    // For machines that tick the CPU themselves along with other hardware.
+    // Only syncs between instructions, so it can be called every cycle.
+    pub fn sync_throttle(&mut self) {
+        if !self.sequence.is_empty() {
+            return;
+        }
+        if let Some(throttle) = self.throttle.as_mut() {
+            throttle.sync(self.cycles, self.clock_hz);
         }
     }
 
     pub fn run_loop(&mut self) {
+        self.update_devices();
         loop {
-            if self.mem.read_byte(self.registers.get_pc()) == 0 {
+            if self.mem.peek_byte(self.registers.get_pc()) == 0 {
                 break;
             }
             self.run();
+            if self.is_halted() || self.stopped {
+                break;
+            }
+            self.sync_throttle();
         }
+        self.update_devices();
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::dma::Dma;
 
     #[test]
     fn lda_im() {
@@ -84,7 +492,7 @@ mod tests {
 
         assert_eq!(cpu.get_registers().get_a(), 0x34);
         assert_eq!(cpu.get_registers().get_pc(), 0x2);
-        assert_eq!(cpu.cycles, 3);
+        assert_eq!(cpu.cycles, 2);
     }
 
     #[test]
@@ -98,7 +506,7 @@ mod tests {
 
         assert_eq!(cpu.get_registers().get_a(), 0x68);
         assert_eq!(cpu.get_registers().get_pc(), 0x2);
-        assert_eq!(cpu.cycles, 4);
+        assert_eq!(cpu.cycles, 3);
     }
 
     #[test]
@@ -113,7 +521,7 @@ mod tests {
 
         assert_eq!(cpu.get_registers().get_a(), 0x24);
         assert_eq!(cpu.get_registers().get_pc(), 0x3);
-        assert_eq!(cpu.cycles, 5);
+        assert_eq!(cpu.cycles, 4);
     }
 
     #[test]
@@ -128,7 +536,7 @@ mod tests {
 
         assert_eq!(cpu.get_registers().get_a(), 0x32);
         assert_eq!(cpu.get_registers().get_pc(), 0x2);
-        assert_eq!(cpu.cycles, 5);
+        assert_eq!(cpu.cycles, 4);
     }
 
     #[test]
@@ -145,7 +553,7 @@ mod tests {
 
         assert_eq!(cpu.get_registers().get_a(), 0x31);
         assert_eq!(cpu.get_registers().get_pc(), 0x2);
-        assert_eq!(cpu.cycles, 7);
+        assert_eq!(cpu.cycles, 6);
     }
 
     #[test]
@@ -162,7 +570,7 @@ mod tests {
 
         assert_eq!(cpu.get_registers().get_a(), 0x28);
         assert_eq!(cpu.get_registers().get_pc(), 0x2);
-        //assert_eq!(cpu.cycles, 6);
+        assert_eq!(cpu.cycles, 5);
     }
 
     #[test]
@@ -179,7 +587,7 @@ mod tests {
 
         assert_eq!(cpu.get_registers().get_a(), 0x32);
         assert_eq!(cpu.get_registers().get_pc(), 0x3);
-        //assert_eq!(cpu.cycles, 5);
+        assert_eq!(cpu.cycles, 4);
     }
 
     #[test]
@@ -196,7 +604,93 @@ mod tests {
 
         assert_eq!(cpu.get_registers().get_a(), 0x32);
         assert_eq!(cpu.get_registers().get_pc(), 0x3);
-        //assert_eq!(cpu.cycles, 5);
+        assert_eq!(cpu.cycles, 4);
+    }
+
+    #[test]
+    fn lda_a_x_page_crossing() {
+        let mut cpu = CPU::new();
+        cpu.write_byte(0x0, 0xBD);
+        cpu.write_byte(0x1, 0xD4);
+        cpu.write_byte(0x2, 0x64);
+        cpu.write_byte(0x6524, 0x32);
+        *cpu.get_registers().get_mut_x() = 0x50;
+
+        cpu.run();
+
+        assert_eq!(cpu.get_registers().get_a(), 0x32);
+        assert_eq!(cpu.get_registers().get_pc(), 0x3);
+        assert_eq!(cpu.cycles, 5);
+    }
+
+    #[test]
+    fn lda_zp_y_ind_page_crossing() {
+        let mut cpu = CPU::new();
+        cpu.write_byte(0x0, 0xB1);
+        cpu.write_byte(0x1, 0x34);
+        cpu.write_byte(0x0034, 0xD2);
+        cpu.write_byte(0x0035, 0x33);
+        cpu.write_byte(0x3422, 0x28);
+        *cpu.get_registers().get_mut_y() = 0x50;
+
+        cpu.run();
+
+        assert_eq!(cpu.get_registers().get_a(), 0x28);
+        assert_eq!(cpu.get_registers().get_pc(), 0x2);
+        assert_eq!(cpu.cycles, 6);
+    }
+
+    #[test]
+    fn indexed_read_cycles() {
+        let absolute = [
+            0x7D, 0x79, 0x3D, 0x39, 0xDD, 0xD9, 0x5D, 0x59, 0xBD, 0xB9, 0xBE, 0xBC, 0x1D, 0x19,
+            0xFD, 0xF9,
+        ];
+        let indirect = [0x71, 0x31, 0xD1, 0x51, 0xB1, 0x11, 0xF1];
+        for (base, cycles) in [(0x6434, 0), (0x64D4, 1)] {
+            for opcode in absolute {
+                let mut cpu = CPU::new();
+                cpu.write_byte(0x0, opcode);
+                cpu.write_byte(0x1, base as Byte);
+                cpu.write_byte(0x2, (base >> 8) as Byte);
+                *cpu.get_registers().get_mut_x() = 0x50;
+                *cpu.get_registers().get_mut_y() = 0x50;
+
+                cpu.run();
+
+                assert_eq!(cpu.cycles, 4 + cycles, "{:02X} from {:04X}", opcode, base);
+            }
+            for opcode in indirect {
+                let mut cpu = CPU::new();
+                cpu.write_byte(0x0, opcode);
+                cpu.write_byte(0x1, 0x34);
+                cpu.write_byte(0x34, base as Byte);
+                cpu.write_byte(0x35, (base >> 8) as Byte);
+                *cpu.get_registers().get_mut_y() = 0x50;
+
+                cpu.run();
+
+                assert_eq!(cpu.cycles, 5 + cycles, "{:02X} from {:04X}", opcode, base);
+            }
+        }
+    }
+
+    #[test]
+    fn indexed_write_cycles() {
+        for (opcode, cycles) in [(0x9D, 5), (0x99, 5), (0x91, 6), (0xFE, 7), (0x1E, 7)] {
+            let mut cpu = CPU::new();
+            cpu.write_byte(0x0, opcode);
+            cpu.write_byte(0x1, 0x34);
+            cpu.write_byte(0x2, 0x64);
+            cpu.write_byte(0x34, 0x34);
+            cpu.write_byte(0x35, 0x64);
+            *cpu.get_registers().get_mut_x() = 0x50;
+            *cpu.get_registers().get_mut_y() = 0x50;
+
+            cpu.run();
+
+            assert_eq!(cpu.cycles, cycles, "{:02X}", opcode);
+        }
     }
 
     #[test]
@@ -289,6 +783,9 @@ mod tests {
         cpu.write_byte(0x1, 0x34);
         *cpu.get_registers().get_mut_a() = 0x50;
 
+        // Carry set, i.e. no borrow.
+        cpu.get_registers().get_mut_p().c = true;
+
         cpu.run();
 
         assert_eq!(cpu.get_registers().get_a(), 0x1C);
@@ -302,6 +799,9 @@ mod tests {
         cpu.write_byte(0x34, 0x14);
         *cpu.get_registers().get_mut_a() = 0x72;
 
+        // Carry set, i.e. no borrow.
+        cpu.get_registers().get_mut_p().c = true;
+
         cpu.run();
 
         assert_eq!(cpu.get_registers().get_a(), 0x5E);
@@ -316,6 +816,9 @@ mod tests {
         cpu.write_byte(0x2434, 0x23);
         *cpu.get_registers().get_mut_a() = 0x32;
 
+        // Carry set, i.e. no borrow.
+        cpu.get_registers().get_mut_p().c = true;
+
         cpu.run();
 
         assert_eq!(cpu.get_registers().get_a(), 0xF);
@@ -330,6 +833,9 @@ mod tests {
         *cpu.get_registers().get_mut_x() = 0x02;
         *cpu.get_registers().get_mut_a() = 0x32;
 
+        // Carry set, i.e. no borrow.
+        cpu.get_registers().get_mut_p().c = true;
+
         cpu.run();
 
         assert_eq!(cpu.get_registers().get_a(), 0xF);
@@ -345,6 +851,9 @@ mod tests {
         *cpu.get_registers().get_mut_x() = 0x50;
         *cpu.get_registers().get_mut_a() = 0x34;
 
+        // Carry set, i.e. no borrow.
+        cpu.get_registers().get_mut_p().c = true;
+
         cpu.run();
 
         assert_eq!(cpu.get_registers().get_a(), 0x2);
@@ -360,6 +869,9 @@ mod tests {
         *cpu.get_registers().get_mut_y() = 0x50;
         *cpu.get_registers().get_mut_a() = 0x33;
 
+        // Carry set, i.e. no borrow.
+        cpu.get_registers().get_mut_p().c = true;
+
         cpu.run();
 
         assert_eq!(cpu.get_registers().get_a(), 0x1);
@@ -820,7 +1332,7 @@ mod tests {
         assert_eq!(cpu.get_registers().get_p().z, true);
         assert_eq!(cpu.get_registers().get_p().c, true);
         assert_eq!(cpu.get_registers().get_pc(), 0x2);
-        assert_eq!(cpu.cycles, 3);
+        assert_eq!(cpu.cycles, 2);
     }
 
     #[test]
@@ -837,7 +1349,7 @@ mod tests {
         assert_eq!(cpu.get_registers().get_p().z, true);
         assert_eq!(cpu.get_registers().get_p().c, true);
         assert_eq!(cpu.get_registers().get_pc(), 0x2);
-        assert_eq!(cpu.cycles, 4);
+        assert_eq!(cpu.cycles, 3);
     }
 
     #[test]
@@ -855,7 +1367,7 @@ mod tests {
         assert_eq!(cpu.get_registers().get_p().z, true);
         assert_eq!(cpu.get_registers().get_p().c, true);
         assert_eq!(cpu.get_registers().get_pc(), 0x3);
-        assert_eq!(cpu.cycles, 5);
+        assert_eq!(cpu.cycles, 4);
     }
 
     #[test]
@@ -945,6 +1457,9 @@ mod tests {
         cpu.write_byte(0x2, 0x13);
         cpu.write_byte(0x1312, 0x85);
 
+        // The carry is rotated in.
+        cpu.get_registers().get_mut_p().c = true;
+
         cpu.run();
 
         assert_eq!(cpu.read_byte(0x1312), 0x0B);
@@ -972,6 +1487,9 @@ mod tests {
         cpu.write_byte(0x2, 0x13);
         cpu.write_byte(0x1312, 0x85);
 
+        // The carry is rotated in.
+        cpu.get_registers().get_mut_p().c = true;
+
         cpu.run();
 
         assert_eq!(cpu.read_byte(0x1312), 0xC2);
@@ -989,7 +1507,7 @@ mod tests {
 
         assert_eq!(cpu.read_byte(0x12), 0x14);
         assert_eq!(cpu.get_registers().get_pc(), 0x2);
-        assert_eq!(cpu.cycles, 4);
+        assert_eq!(cpu.cycles, 3);
     }
 
     #[test]
@@ -1004,7 +1522,7 @@ mod tests {
 
         assert_eq!(cpu.read_byte(0x34), 0x14);
         assert_eq!(cpu.get_registers().get_pc(), 0x2);
-        assert_eq!(cpu.cycles, 5);
+        assert_eq!(cpu.cycles, 4);
     }
 
     #[test]
@@ -1019,7 +1537,7 @@ mod tests {
 
         assert_eq!(cpu.read_byte(0x1312), 0x14);
         assert_eq!(cpu.get_registers().get_pc(), 0x3);
-        assert_eq!(cpu.cycles, 5);
+        assert_eq!(cpu.cycles, 4);
     }
 
     #[test]
@@ -1032,7 +1550,7 @@ mod tests {
         cpu.run();
 
         assert_eq!(cpu.read_byte(0x12), 0x14);
-        assert_eq!(cpu.cycles, 4);
+        assert_eq!(cpu.cycles, 3);
     }
 
     #[test]
@@ -1070,7 +1588,7 @@ mod tests {
         cpu.run();
 
         assert_eq!(cpu.get_registers().get_x(), 0x34);
-        assert_eq!(cpu.cycles, 3);
+        assert_eq!(cpu.cycles, 2);
     }
 
     #[test]
@@ -1247,7 +1765,7 @@ mod tests {
 
         assert_eq!(cpu.read_byte(0x32), 0x45);
         assert_eq!(cpu.get_registers().get_pc(), 0x2);
-        assert_eq!(cpu.cycles, 6);
+        assert_eq!(cpu.cycles, 5);
     }
 
     #[test]
@@ -1262,7 +1780,7 @@ mod tests {
 
         assert_eq!(cpu.read_byte(0x1832), 0x45);
         assert_eq!(cpu.get_registers().get_pc(), 0x3);
-        assert_eq!(cpu.cycles, 7);
+        assert_eq!(cpu.cycles, 6);
     }
 
     #[test]
@@ -1278,7 +1796,7 @@ mod tests {
 
         assert_eq!(cpu.read_byte(0x1832), 0x45);
         assert_eq!(cpu.get_registers().get_pc(), 0x3);
-        assert_eq!(cpu.cycles, 8);
+        assert_eq!(cpu.cycles, 7);
     }
 
     #[test]
@@ -1389,7 +1907,7 @@ mod tests {
         cpu.run();
 
         assert_eq!(cpu.get_registers().get_pc(), 0x34);
-        assert_eq!(cpu.cycles, 4);
+        assert_eq!(cpu.cycles, 3);
     }
 
     #[test]
@@ -1402,7 +1920,7 @@ mod tests {
         cpu.run();
 
         assert_eq!(cpu.get_registers().get_pc(), 0xFFFF);
-        assert_eq!(cpu.cycles, 5);
+        assert_eq!(cpu.cycles, 4);
     }
 
     #[test]
@@ -1415,7 +1933,7 @@ mod tests {
         cpu.run();
 
         assert_eq!(cpu.get_registers().get_pc(), 0x2);
-        assert_eq!(cpu.cycles, 3);
+        assert_eq!(cpu.cycles, 2);
     }
 
     #[test]
@@ -1580,6 +2098,7 @@ mod tests {
 
         assert_eq!(cpu.get_registers().get_p_byte(), 0xD4);
         assert_eq!(cpu.get_registers().get_pc(), 0x3022);
+        assert_eq!(cpu.cycles, 6);
     }
 
     #[test]
@@ -1630,4 +2149,1339 @@ mod tests {
         assert_eq!(cpu.get_registers().get_p().v, true);
         assert_eq!(cpu.get_registers().get_pc(), 0x3);
     }
+
+    #[test]
+    fn rdy_halts_on_read_cycle() {
+        let mut cpu = CPU::new();
+        cpu.write_byte(0x0, 0xA5);
+        cpu.write_byte(0x1, 0x34);
+        cpu.write_byte(0x34, 0x68);
+
+        cpu.tick();
+        cpu.set_rdy(false);
+        cpu.tick();
+        cpu.tick();
+
+        assert!(cpu.is_halted());
+        assert_eq!(cpu.get_registers().get_a(), 0x0);
+        assert_eq!(cpu.cycles, 3);
+
+        cpu.set_rdy(true);
+        cpu.run();
+
+        assert_eq!(cpu.get_registers().get_a(), 0x68);
+        assert_eq!(cpu.get_registers().get_pc(), 0x2);
+        assert_eq!(cpu.cycles, 5);
+    }
+
+    #[test]
+    fn rdy_holds_on_write_cycle() {
+        let mut cpu = CPU::new();
+        cpu.write_byte(0x0, 0x85);
+        cpu.write_byte(0x1, 0x34);
+        *cpu.get_registers().get_mut_a() = 0x42;
+
+        cpu.tick();
+        cpu.tick();
+        cpu.set_rdy(false);
+        cpu.tick();
+
+        assert_eq!(cpu.read_byte(0x34), 0x42);
+
+        cpu.tick();
+
+        assert!(cpu.is_halted());
+        assert_eq!(cpu.get_registers().get_pc(), 0x2);
+    }
+
+    #[test]
+    fn rdy_low_at_fetch() {
+        let mut cpu = CPU::new();
+        cpu.write_byte(0x0, 0xE8);
+        cpu.set_rdy(false);
+
+        cpu.run();
+        cpu.run();
+
+        assert_eq!(cpu.get_registers().get_pc(), 0x0);
+        assert_eq!(cpu.cycles, 2);
+
+        cpu.set_rdy(true);
+        cpu.run();
+
+        assert_eq!(cpu.get_registers().get_x(), 0x1);
+    }
+
+    #[test]
+    fn so_sets_overflow() {
+        let mut cpu = CPU::new();
+        cpu.write_byte(0x0, 0xEA);
+
+        cpu.assert_so();
+        assert!(!cpu.get_registers().get_p().v);
+
+        cpu.tick();
+        assert!(cpu.get_registers().get_p().v);
+    }
+
+    #[test]
+    fn so_overrides_clv() {
+        let mut cpu = CPU::new();
+        cpu.write_byte(0x0, 0xB8);
+        cpu.registers.p.v = true;
+
+        cpu.tick();
+        cpu.assert_so();
+        cpu.tick();
+
+        assert!(cpu.get_registers().get_p().v);
+    }
+
+    #[test]
+    fn dma_steals_513_cycles() {
+        let mut cpu = CPU::new();
+        for i in 0..0x100 {
+            cpu.write_byte(0x0200 + i, i as Byte);
+        }
+        cpu.write_byte(0x0, 0xE8);
+        cpu.write_byte(0x1, 0xE8);
+        cpu.run();
+
+        cpu.request_dma(Dma::new(0x0200, 0x0300, 0x100));
+        cpu.run();
+
+        assert_eq!(cpu.cycles, 2 + 513 + 2);
+        assert!(!cpu.is_dma_active());
+        assert_eq!(cpu.read_byte(0x0300), 0x00);
+        assert_eq!(cpu.read_byte(0x03FF), 0xFF);
+        assert_eq!(cpu.get_registers().get_x(), 0x2);
+    }
+
+    #[test]
+    fn dma_steals_514_cycles_when_unaligned() {
+        let mut cpu = CPU::new();
+        cpu.write_byte(0x0, 0xA5);
+        cpu.write_byte(0x1, 0x34);
+        cpu.write_byte(0x2, 0xE8);
+        cpu.run();
+
+        cpu.request_dma(Dma::new(0x0200, 0x0300, 0x100));
+        cpu.run();
+
+        assert_eq!(cpu.cycles, 3 + 514 + 2);
+        assert_eq!(cpu.get_registers().get_x(), 0x1);
+    }
+
+    #[test]
+    fn dma_waits_for_read_cycle() {
+        let mut cpu = CPU::new();
+        cpu.write_byte(0x0, 0x85);
+        cpu.write_byte(0x1, 0x34);
+        cpu.write_byte(0x34, 0x11);
+        *cpu.get_registers().get_mut_a() = 0x42;
+
+        cpu.tick();
+        cpu.tick();
+        cpu.request_dma(Dma::to_port(0x0034, 0x0040, 0x1));
+        cpu.tick();
+
+        assert_eq!(cpu.read_byte(0x34), 0x42);
+        assert!(cpu.is_dma_active());
+
+        for _ in 0..4 {
+            cpu.tick();
+        }
+
+        assert_eq!(cpu.read_byte(0x40), 0x42);
+        assert!(!cpu.is_dma_active());
+        assert_eq!(cpu.get_registers().get_pc(), 0x2);
+    }
+
+    fn interrupt_cpu() -> CPU {
+        let mut cpu = CPU::new();
+        cpu.write_byte(0xFFFA, 0x00);
+        cpu.write_byte(0xFFFB, 0x50);
+        cpu.write_byte(0xFFFE, 0x00);
+        cpu.write_byte(0xFFFF, 0x40);
+        cpu
+    }
+
+    #[test]
+    fn irq() {
+        let mut cpu = interrupt_cpu();
+        cpu.write_byte(0x0, 0xE8);
+        cpu.set_irq(true);
+
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_x(), 0x1);
+
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_pc(), 0x4000);
+        assert_eq!(cpu.read_byte(0x01FF), 0x00);
+        assert_eq!(cpu.read_byte(0x01FE), 0x01);
+        assert_eq!(cpu.read_byte(0x01FD), 0x20);
+        assert!(cpu.get_registers().get_p().i);
+        assert_eq!(cpu.cycles, 9);
+    }
+
+    #[test]
+    fn irq_masked() {
+        let mut cpu = interrupt_cpu();
+        cpu.write_byte(0x0, 0xE8);
+        cpu.write_byte(0x1, 0xE8);
+        cpu.registers.p.i = true;
+        cpu.set_irq(true);
+
+        cpu.run();
+        cpu.run();
+
+        assert_eq!(cpu.get_registers().get_x(), 0x2);
+        assert_eq!(cpu.get_registers().get_pc(), 0x2);
+    }
+
+    #[test]
+    fn nmi_is_edge_triggered() {
+        let mut cpu = interrupt_cpu();
+        cpu.write_byte(0x0, 0xE8);
+        cpu.write_byte(0x5000, 0xE8);
+        cpu.write_byte(0x5001, 0xE8);
+        cpu.registers.p.i = true;
+        cpu.set_nmi(true);
+
+        cpu.run();
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_pc(), 0x5000);
+
+        cpu.run();
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_pc(), 0x5002);
+    }
+
+    #[test]
+    fn brk() {
+        let mut cpu = interrupt_cpu();
+        cpu.write_byte(0x0, 0x00);
+
+        cpu.run();
+
+        assert_eq!(cpu.get_registers().get_pc(), 0x4000);
+        assert_eq!(cpu.read_byte(0x01FE), 0x02);
+        assert_eq!(cpu.read_byte(0x01FD), 0x30);
+        assert_eq!(cpu.cycles, 7);
+    }
+
+    #[test]
+    fn php_plp() {
+        let mut cpu = CPU::new();
+        cpu.write_byte(0x0, 0x08);
+        cpu.write_byte(0x1, 0x28);
+        cpu.registers.p.c = true;
+
+        cpu.run();
+        assert_eq!(cpu.read_byte(0x01FF), 0x31);
+        assert_eq!(cpu.cycles, 3);
+
+        cpu.registers.p.c = false;
+        cpu.run();
+        assert!(cpu.get_registers().get_p().c);
+        assert_eq!(cpu.get_registers().get_s(), 0xFF);
+        assert_eq!(cpu.cycles, 3 + 4);
+    }
+
+    #[test]
+    fn pha_pla() {
+        let mut cpu = CPU::new();
+        cpu.write_byte(0x0, 0x48);
+        cpu.write_byte(0x1, 0x68);
+        *cpu.registers.get_mut_a() = 0x42;
+
+        cpu.run();
+        assert_eq!(cpu.read_byte(0x01FF), 0x42);
+        assert_eq!(cpu.cycles, 3);
+
+        *cpu.registers.get_mut_a() = 0x0;
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_a(), 0x42);
+        assert_eq!(cpu.get_registers().get_s(), 0xFF);
+        assert_eq!(cpu.cycles, 3 + 4);
+    }
+
+    #[test]
+    fn cli_delays_irq() {
+        let mut cpu = interrupt_cpu();
+        cpu.write_byte(0x0, 0x58);
+        cpu.write_byte(0x1, 0xE8);
+        cpu.registers.p.i = true;
+        cpu.set_irq(true);
+
+        cpu.run();
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_x(), 0x1);
+
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_pc(), 0x4000);
+    }
+
+    #[test]
+    fn sei_delays_masking() {
+        let mut cpu = interrupt_cpu();
+        cpu.write_byte(0x0, 0x78);
+        cpu.set_irq(true);
+
+        cpu.run();
+        cpu.run();
+
+        assert_eq!(cpu.get_registers().get_pc(), 0x4000);
+        assert_eq!(cpu.read_byte(0x01FE), 0x01);
+        assert_eq!(cpu.read_byte(0x01FD) & 0x04, 0x04);
+    }
+
+    #[test]
+    fn plp_delays_irq() {
+        let mut cpu = interrupt_cpu();
+        cpu.write_byte(0x0, 0x28);
+        cpu.write_byte(0x1, 0xE8);
+        cpu.write_byte(0x01FF, 0x20);
+        *cpu.registers.get_mut_s() = 0xFE;
+        cpu.registers.p.i = true;
+        cpu.set_irq(true);
+
+        cpu.run();
+        assert!(!cpu.get_registers().get_p().i);
+        assert_eq!(cpu.cycles, 4);
+
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_x(), 0x1);
+
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_pc(), 0x4000);
+    }
+
+    #[test]
+    fn taken_branch_delays_irq() {
+        let mut cpu = interrupt_cpu();
+        cpu.write_byte(0x0, 0xD0);
+        cpu.write_byte(0x1, 0x00);
+        cpu.write_byte(0x2, 0xE8);
+
+        cpu.tick();
+        cpu.set_irq(true);
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_pc(), 0x2);
+
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_x(), 0x1);
+
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_pc(), 0x4000);
+    }
+
+    #[test]
+    fn page_crossing_branch_does_not_delay_irq() {
+        let mut cpu = interrupt_cpu();
+        cpu.write_byte(0x00F0, 0xD0);
+        cpu.write_byte(0x00F1, 0x10);
+        *cpu.registers.get_mut_pc() = 0x00F0;
+
+        cpu.tick();
+        cpu.set_irq(true);
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_pc(), 0x0102);
+
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_pc(), 0x4000);
+        assert_eq!(cpu.read_byte(0x01FF), 0x01);
+        assert_eq!(cpu.read_byte(0x01FE), 0x02);
+    }
+
+    #[test]
+    fn nmi_hijacks_brk() {
+        let mut cpu = interrupt_cpu();
+        cpu.write_byte(0x0, 0x00);
+
+        cpu.tick();
+        cpu.tick();
+        cpu.set_nmi(true);
+        cpu.run();
+
+        assert_eq!(cpu.get_registers().get_pc(), 0x5000);
+        assert_eq!(cpu.read_byte(0x01FD) & 0x10, 0x10);
+
+        cpu.write_byte(0x5000, 0xE8);
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_x(), 0x1);
+    }
+
+    #[test]
+    fn nmi_hijacks_irq() {
+        let mut cpu = interrupt_cpu();
+        cpu.write_byte(0x0, 0xE8);
+        cpu.set_irq(true);
+
+        cpu.run();
+        cpu.tick();
+        cpu.tick();
+        cpu.set_nmi(true);
+        cpu.run();
+
+        assert_eq!(cpu.get_registers().get_pc(), 0x5000);
+        assert_eq!(cpu.read_byte(0x01FD) & 0x10, 0x00);
+    }
+
+    #[test]
+    fn late_nmi_does_not_hijack_brk() {
+        let mut cpu = interrupt_cpu();
+        cpu.write_byte(0x0, 0x00);
+        cpu.write_byte(0x4000, 0xE8);
+
+        for _ in 0..5 {
+            cpu.tick();
+        }
+        cpu.set_nmi(true);
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_pc(), 0x4000);
+
+        cpu.run();
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_pc(), 0x5000);
+    }
+
+    #[test]
+    fn irq_entry_masks_irq() {
+        let mut cpu = interrupt_cpu();
+        cpu.write_byte(0x0, 0xE8);
+        cpu.write_byte(0x4000, 0xE8);
+        cpu.write_byte(0x4001, 0xE8);
+        cpu.set_irq(true);
+
+        cpu.run();
+        cpu.run();
+        cpu.run();
+        cpu.run();
+
+        assert_eq!(cpu.get_registers().get_x(), 0x3);
+        assert_eq!(cpu.get_registers().get_pc(), 0x4002);
+        assert_eq!(cpu.get_registers().get_s(), 0xFC);
+    }
+
+    #[test]
+    fn loads_set_flags() {
+        let mut cpu = CPU::new();
+        cpu.write_byte(0x0, 0xA9);
+        cpu.write_byte(0x1, 0x80);
+        cpu.write_byte(0x2, 0xAA);
+        cpu.write_byte(0x3, 0xE8);
+
+        cpu.run();
+        assert!(cpu.get_registers().get_p().n);
+        cpu.run();
+        assert!(cpu.get_registers().get_p().n);
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_x(), 0x81);
+        assert!(!cpu.get_registers().get_p().z);
+
+        cpu.write_byte(0x4, 0xA2);
+        cpu.write_byte(0x5, 0x00);
+        cpu.run();
+        assert!(cpu.get_registers().get_p().z);
+        assert!(!cpu.get_registers().get_p().n);
+    }
+
+    #[test]
+    fn txs_keeps_flags() {
+        let mut cpu = CPU::new();
+        cpu.write_byte(0x0, 0x9A);
+        cpu.registers.p.z = true;
+
+        cpu.run();
+
+        assert_eq!(cpu.get_registers().get_s(), 0x0);
+        assert!(cpu.get_registers().get_p().z);
+    }
+
+    #[test]
+    fn adc_carry_and_overflow() {
+        let mut cpu = CPU::new();
+        cpu.write_byte(0x0, 0x69);
+        cpu.write_byte(0x1, 0x50);
+        cpu.write_byte(0x2, 0x69);
+        cpu.write_byte(0x3, 0x70);
+        *cpu.get_registers().get_mut_a() = 0x50;
+        cpu.registers.p.c = true;
+
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_a(), 0xA1);
+        assert!(cpu.get_registers().get_p().v);
+        assert!(cpu.get_registers().get_p().n);
+        assert!(!cpu.get_registers().get_p().c);
+
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_a(), 0x11);
+        assert!(!cpu.get_registers().get_p().v);
+        assert!(cpu.get_registers().get_p().c);
+    }
+
+    #[test]
+    fn sbc_borrow_and_overflow() {
+        let mut cpu = CPU::new();
+        cpu.write_byte(0x0, 0xE9);
+        cpu.write_byte(0x1, 0x01);
+        *cpu.get_registers().get_mut_a() = 0x80;
+
+        cpu.run();
+
+        assert_eq!(cpu.get_registers().get_a(), 0x7E);
+        assert!(cpu.get_registers().get_p().v);
+        assert!(cpu.get_registers().get_p().c);
+    }
+
+    #[test]
+    fn adc_decimal() {
+        let mut cpu = CPU::new();
+        cpu.write_byte(0x0, 0x69);
+        cpu.write_byte(0x1, 0x48);
+        *cpu.get_registers().get_mut_a() = 0x58;
+        cpu.registers.p.d = true;
+        cpu.registers.p.c = true;
+
+        cpu.run();
+
+        assert_eq!(cpu.get_registers().get_a(), 0x07);
+        assert!(cpu.get_registers().get_p().c);
+    }
+
+    #[test]
+    fn sbc_decimal() {
+        let mut cpu = CPU::new();
+        cpu.write_byte(0x0, 0xE9);
+        cpu.write_byte(0x1, 0x29);
+        *cpu.get_registers().get_mut_a() = 0x12;
+        cpu.registers.p.d = true;
+        cpu.registers.p.c = true;
+
+        cpu.run();
+
+        assert_eq!(cpu.get_registers().get_a(), 0x83);
+        assert!(!cpu.get_registers().get_p().c);
+    }
+
+    #[test]
+    fn rotate_through_carry() {
+        let mut cpu = CPU::new();
+        cpu.write_byte(0x0, 0x2A);
+        cpu.write_byte(0x1, 0x6A);
+        *cpu.get_registers().get_mut_a() = 0x80;
+
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_a(), 0x00);
+        assert!(cpu.get_registers().get_p().c);
+        assert!(cpu.get_registers().get_p().z);
+
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_a(), 0x80);
+        assert!(!cpu.get_registers().get_p().c);
+        assert!(cpu.get_registers().get_p().n);
+    }
+
+    #[test]
+    fn cmp_negative_from_difference() {
+        let mut cpu = CPU::new();
+        cpu.write_byte(0x0, 0xC9);
+        cpu.write_byte(0x1, 0x10);
+        *cpu.get_registers().get_mut_a() = 0xF0;
+
+        cpu.run();
+
+        assert!(cpu.get_registers().get_p().n);
+        assert!(cpu.get_registers().get_p().c);
+    }
+
+    #[test]
+    fn bit_flags_from_memory() {
+        let mut cpu = CPU::new();
+        cpu.write_byte(0x0, 0x24);
+        cpu.write_byte(0x1, 0xD4);
+        cpu.write_byte(0xD4, 0xC0);
+        *cpu.registers.get_mut_a() = 0x01;
+
+        cpu.run();
+
+        assert!(cpu.get_registers().get_p().n);
+        assert!(cpu.get_registers().get_p().v);
+        assert!(cpu.get_registers().get_p().z);
+    }
+
+    #[test]
+    fn bit_zero_from_and() {
+        let mut cpu = CPU::new();
+        cpu.write_byte(0x0, 0x24);
+        cpu.write_byte(0x1, 0xD4);
+        cpu.write_byte(0xD4, 0x81);
+        *cpu.registers.get_mut_a() = 0x01;
+
+        cpu.run();
+
+        assert!(cpu.get_registers().get_p().n);
+        assert!(!cpu.get_registers().get_p().v);
+        assert!(!cpu.get_registers().get_p().z);
+    }
+
+    #[test]
+    fn adc_decimal_flags() {
+        // 99 + 01 gives 00 with the carry set, but N and Z go by the
+        // intermediate sums, $A0 and $9A.
+        let mut cpu = CPU::new();
+        cpu.write_byte(0x0, 0x69);
+        cpu.write_byte(0x1, 0x01);
+        *cpu.get_registers().get_mut_a() = 0x99;
+        cpu.registers.p.d = true;
+
+        cpu.run();
+
+        assert_eq!(cpu.get_registers().get_a(), 0x00);
+        assert!(cpu.get_registers().get_p().c);
+        assert!(cpu.get_registers().get_p().n);
+        assert!(!cpu.get_registers().get_p().z);
+        assert!(!cpu.get_registers().get_p().v);
+    }
+
+    #[test]
+    fn sbc_decimal_flags() {
+        // 00 - 01 gives 99 with a borrow, and the flags of $00 - $01.
+        let mut cpu = CPU::new();
+        cpu.write_byte(0x0, 0xE9);
+        cpu.write_byte(0x1, 0x01);
+        cpu.registers.p.d = true;
+        cpu.registers.p.c = true;
+
+        cpu.run();
+
+        assert_eq!(cpu.get_registers().get_a(), 0x99);
+        assert!(!cpu.get_registers().get_p().c);
+        assert!(cpu.get_registers().get_p().n);
+        assert!(!cpu.get_registers().get_p().z);
+    }
+
+    #[test]
+    fn rotate_memory_through_carry() {
+        // ROL $1312, ROR $1312, with the carry clear to begin with.
+        let mut cpu = CPU::new();
+        for (i, byte) in [0x2E, 0x12, 0x13, 0x6E, 0x12, 0x13].iter().enumerate() {
+            cpu.write_byte(i as Word, *byte);
+        }
+        cpu.write_byte(0x1312, 0x85);
+
+        cpu.run();
+        assert_eq!(cpu.read_byte(0x1312), 0x0A);
+        assert!(cpu.get_registers().get_p().c);
+
+        cpu.run();
+        assert_eq!(cpu.read_byte(0x1312), 0x85);
+        assert!(!cpu.get_registers().get_p().c);
+        assert!(cpu.get_registers().get_p().n);
+    }
+
+    #[test]
+    fn read_modify_write_sets_flags() {
+        // ASL $10, LSR $11, INC $12, DEC $13
+        let mut cpu = CPU::new();
+        for (i, byte) in [0x06, 0x10, 0x46, 0x11, 0xE6, 0x12, 0xC6, 0x13]
+            .iter()
+            .enumerate()
+        {
+            cpu.write_byte(i as Word, *byte);
+        }
+        for (i, byte) in [0x40, 0x01, 0xFF, 0x00].iter().enumerate() {
+            cpu.write_byte(0x10 + i as Word, *byte);
+        }
+
+        cpu.run();
+        assert!(cpu.get_registers().get_p().n);
+        assert!(!cpu.get_registers().get_p().z);
+
+        cpu.run();
+        assert!(cpu.get_registers().get_p().z);
+        assert!(cpu.get_registers().get_p().c);
+
+        cpu.run();
+        assert!(cpu.get_registers().get_p().z);
+        assert!(!cpu.get_registers().get_p().n);
+
+        cpu.run();
+        assert!(cpu.get_registers().get_p().n);
+        assert!(!cpu.get_registers().get_p().z);
+    }
+
+    #[test]
+    fn registers_set_flags() {
+        // DEX, INY, TAY, PLA
+        let mut cpu = CPU::new();
+        for (i, byte) in [0xCA, 0xC8, 0xA8, 0x68].iter().enumerate() {
+            cpu.write_byte(i as Word, *byte);
+        }
+        *cpu.get_registers().get_mut_s() = 0xFE;
+        cpu.write_byte(0x01FF, 0x00);
+
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_x(), 0xFF);
+        assert!(cpu.get_registers().get_p().n);
+
+        cpu.run();
+        assert!(!cpu.get_registers().get_p().n);
+        assert!(!cpu.get_registers().get_p().z);
+
+        cpu.run();
+        assert!(cpu.get_registers().get_p().z);
+
+        *cpu.get_registers().get_mut_a() = 0x80;
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_a(), 0x00);
+        assert!(cpu.get_registers().get_p().z);
+    }
+
+    #[test]
+    fn nop() {
+        let mut cpu = CPU::new();
+        cpu.write_byte(0x0, 0xEA);
+
+        cpu.run();
+
+        assert_eq!(cpu.read_byte(0x0), 0xEA);
+        assert_eq!(cpu.get_registers().get_pc(), 0x1);
+        assert_eq!(cpu.cycles, 2);
+    }
+
+    #[test]
+    fn reset() {
+        let mut cpu = CPU::new();
+        cpu.write_byte(0xFFFC, 0x00);
+        cpu.write_byte(0xFFFD, 0xFF);
+        *cpu.get_registers().get_mut_pc() = 0x1234;
+
+        cpu.reset();
+        cpu.run();
+
+        assert_eq!(cpu.get_registers().get_pc(), 0xFF00);
+        assert_eq!(cpu.get_registers().get_s(), 0xFC);
+        assert!(cpu.get_registers().get_p().i);
+        assert_eq!(cpu.cycles, 7);
+        assert_eq!(cpu.read_byte(0x01FF), 0x00);
+    }
+
+    #[test]
+    fn reset_keeps_registers() {
+        // LDA #$12, LDX #$34, then reset part way through LDY #$56.
+        let mut cpu = CPU::new();
+        for (i, byte) in [0xA9, 0x12, 0xA2, 0x34, 0xA0, 0x56].iter().enumerate() {
+            cpu.write_byte(i as Word, *byte);
+        }
+        cpu.write_byte(0xFFFC, 0x00);
+        cpu.write_byte(0xFFFD, 0xFF);
+        cpu.run();
+        cpu.run();
+        cpu.tick();
+
+        cpu.reset();
+        cpu.run();
+
+        assert_eq!(cpu.get_registers().get_pc(), 0xFF00);
+        assert_eq!(cpu.get_registers().get_a(), 0x12);
+        assert_eq!(cpu.get_registers().get_x(), 0x34);
+        assert_eq!(cpu.get_registers().get_y(), 0x00);
+        assert_eq!(cpu.cycles, 2 + 2 + 1 + 7);
+        for addr in 0x01FD..=0x01FF {
+            assert_eq!(cpu.read_byte(addr), 0x00);
+        }
+    }
+
+    fn cmos_cpu(program: &[Byte]) -> CPU {
+        let mut cpu = CPU::new();
+        cpu.set_variant(Variant::Cmos);
+        for (i, byte) in program.iter().enumerate() {
+            cpu.write_byte(i as Word, *byte);
+        }
+        cpu
+    }
+
+    #[test]
+    fn cmos_stz() {
+        let mut cpu = cmos_cpu(&[0x64, 0x10, 0x9C, 0x00, 0x20, 0x74, 0x10, 0x9E, 0x00, 0x20]);
+        for addr in [0x10, 0x11, 0x2000, 0x2001] {
+            cpu.write_byte(addr, 0xFF);
+        }
+        *cpu.get_registers().get_mut_x() = 0x1;
+
+        for _ in 0..4 {
+            cpu.run();
+        }
+
+        for addr in [0x10, 0x11, 0x2000, 0x2001] {
+            assert_eq!(cpu.read_byte(addr), 0x0);
+        }
+    }
+
+    #[test]
+    fn cmos_push_pull_index() {
+        // PHX, PHY, PLX, PLY
+        let mut cpu = cmos_cpu(&[0xDA, 0x5A, 0xFA, 0x7A]);
+        *cpu.get_registers().get_mut_x() = 0x12;
+        *cpu.get_registers().get_mut_y() = 0x80;
+
+        for _ in 0..4 {
+            cpu.run();
+        }
+
+        assert_eq!(cpu.get_registers().get_x(), 0x80);
+        assert_eq!(cpu.get_registers().get_y(), 0x12);
+        assert!(!cpu.get_registers().get_p().n);
+        assert_eq!(cpu.get_registers().get_s(), 0xFF);
+    }
+
+    #[test]
+    fn cmos_inc_dec_a() {
+        let mut cpu = cmos_cpu(&[0x1A, 0x3A, 0x3A]);
+        *cpu.get_registers().get_mut_a() = 0x7F;
+
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_a(), 0x80);
+        assert!(cpu.get_registers().get_p().n);
+
+        cpu.run();
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_a(), 0x7E);
+    }
+
+    #[test]
+    fn cmos_bra() {
+        let mut cpu = cmos_cpu(&[0x80, 0x10]);
+
+        cpu.run();
+
+        assert_eq!(cpu.get_registers().get_pc(), 0x12);
+        assert_eq!(cpu.cycles, 3);
+    }
+
+    #[test]
+    fn cmos_zp_indirect() {
+        // LDA ($10), STA ($12)
+        let mut cpu = cmos_cpu(&[0xB2, 0x10, 0x92, 0x12]);
+        cpu.write_byte(0x10, 0x00);
+        cpu.write_byte(0x11, 0x30);
+        cpu.write_byte(0x12, 0x00);
+        cpu.write_byte(0x13, 0x40);
+        cpu.write_byte(0x3000, 0x5A);
+        *cpu.get_registers().get_mut_y() = 0x5;
+
+        cpu.run();
+        assert_eq!(cpu.cycles, 5);
+        cpu.run();
+        assert_eq!(cpu.cycles, 5 + 5);
+
+        assert_eq!(cpu.get_registers().get_a(), 0x5A);
+        assert_eq!(cpu.read_byte(0x4000), 0x5A);
+    }
+
+    #[test]
+    fn cmos_tsb_trb() {
+        // TSB $10, TRB $2000
+        let mut cpu = cmos_cpu(&[0x04, 0x10, 0x1C, 0x00, 0x20]);
+        cpu.write_byte(0x10, 0x0F);
+        cpu.write_byte(0x2000, 0x0F);
+        *cpu.get_registers().get_mut_a() = 0x30;
+
+        cpu.run();
+        assert_eq!(cpu.read_byte(0x10), 0x3F);
+        assert!(cpu.get_registers().get_p().z);
+
+        *cpu.get_registers().get_mut_a() = 0x03;
+        cpu.run();
+        assert_eq!(cpu.read_byte(0x2000), 0x0C);
+        assert!(!cpu.get_registers().get_p().z);
+    }
+
+    #[test]
+    fn cmos_bit() {
+        // BIT #$C0, BIT $0F,X
+        let mut cpu = cmos_cpu(&[0x89, 0xC0, 0x34, 0x0F]);
+        cpu.write_byte(0x10, 0xC1);
+        *cpu.get_registers().get_mut_a() = 0x01;
+        *cpu.get_registers().get_mut_x() = 0x01;
+
+        cpu.run();
+        assert!(cpu.get_registers().get_p().z);
+        assert!(!cpu.get_registers().get_p().n);
+        assert!(!cpu.get_registers().get_p().v);
+
+        cpu.run();
+        assert!(!cpu.get_registers().get_p().z);
+        assert!(cpu.get_registers().get_p().n);
+        assert!(cpu.get_registers().get_p().v);
+    }
+
+    #[test]
+    fn cmos_jmp_indexed_indirect() {
+        let mut cpu = cmos_cpu(&[0x7C, 0x00, 0x20]);
+        cpu.write_byte(0x2004, 0x34);
+        cpu.write_byte(0x2005, 0x12);
+        *cpu.get_registers().get_mut_x() = 0x4;
+
+        cpu.run();
+
+        assert_eq!(cpu.get_registers().get_pc(), 0x1234);
+        assert_eq!(cpu.cycles, 6);
+    }
+
+    #[test]
+    fn cmos_decimal_flags() {
+        // $99 + $01 = $00 with carry: Z is set from the BCD result, and the
+        // extra cycle is taken.
+        let mut cpu = cmos_cpu(&[0x69, 0x01]);
+        *cpu.get_registers().get_mut_a() = 0x99;
+        cpu.registers.p.d = true;
+
+        cpu.run();
+
+        assert_eq!(cpu.get_registers().get_a(), 0x00);
+        assert!(cpu.get_registers().get_p().c);
+        assert!(cpu.get_registers().get_p().z);
+        assert_eq!(cpu.cycles, 3);
+    }
+
+    #[test]
+    fn cmos_nops() {
+        for (opcode, length, cycles) in [
+            (0x02, 2, 2),
+            (0xE2, 2, 2),
+            (0x44, 2, 3),
+            (0x54, 2, 4),
+            (0xF4, 2, 4),
+            (0xDC, 3, 4),
+            (0x5C, 3, 8),
+            (0x03, 1, 1),
+            (0xFB, 1, 1),
+        ] {
+            let mut cpu = cmos_cpu(&[opcode, 0xFF, 0xFF]);
+            let a = cpu.get_registers().get_a();
+
+            cpu.run();
+
+            assert_eq!(cpu.get_registers().get_pc(), length, "{:02X}", opcode);
+            assert_eq!(cpu.cycles, cycles, "{:02X}", opcode);
+            assert_eq!(cpu.get_registers().get_a(), a);
+        }
+    }
+
+    #[test]
+    fn cmos_rmb_smb() {
+        // RMB3 $10, SMB7 $10
+        let mut cpu = cmos_cpu(&[0x37, 0x10, 0xF7, 0x10]);
+        cpu.write_byte(0x10, 0x0F);
+
+        cpu.run();
+        assert_eq!(cpu.read_byte(0x10), 0x07);
+        assert_eq!(cpu.cycles, 5);
+
+        cpu.run();
+        assert_eq!(cpu.read_byte(0x10), 0x87);
+        assert_eq!(cpu.cycles, 5 + 5);
+    }
+
+    #[test]
+    fn cmos_bbr_bbs() {
+        // BBR0 $10,+$10, then at $13 BBS0 $10,+$10 and BBS1 $10,-$80
+        let mut cpu = cmos_cpu(&[0x0F, 0x10, 0x10]);
+        for (i, byte) in [0x8F, 0x10, 0x10, 0x9F, 0x10, 0x80].iter().enumerate() {
+            cpu.write_byte(0x13 + i as Word, *byte);
+        }
+        cpu.write_byte(0x10, 0xFE);
+
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_pc(), 0x13);
+        assert_eq!(cpu.cycles, 6);
+
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_pc(), 0x16);
+        assert_eq!(cpu.cycles, 6 + 5);
+
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_pc(), 0xFF99);
+        assert_eq!(cpu.cycles, 6 + 5 + 7);
+        assert_eq!(cpu.read_byte(0x10), 0xFE);
+    }
+
+    #[test]
+    fn cmos_wai() {
+        let mut cpu = cmos_cpu(&[0xCB, 0xE8, 0xCB]);
+        cpu.write_byte(0xFFFE, 0x00);
+        cpu.write_byte(0xFFFF, 0x40);
+        cpu.registers.p.i = true;
+
+        cpu.run();
+        assert_eq!(cpu.cycles, 3);
+        assert!(cpu.is_waiting());
+        cpu.run_until(10);
+        assert_eq!(cpu.get_registers().get_pc(), 0x1);
+
+        // With I set, IRQ only wakes it up.
+        cpu.set_irq(true);
+        cpu.run();
+        assert!(!cpu.is_waiting());
+        assert_eq!(cpu.get_registers().get_x(), 0x1);
+        assert_eq!(cpu.get_registers().get_pc(), 0x2);
+
+        cpu.set_irq(false);
+        cpu.registers.p.i = false;
+        cpu.run();
+        assert!(cpu.is_waiting());
+        cpu.set_irq(true);
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_pc(), 0x4000);
+        assert_eq!(cpu.read_byte(0x01FE), 0x03);
+    }
+
+    #[test]
+    fn cmos_stp() {
+        let mut cpu = cmos_cpu(&[0xDB, 0xE8]);
+        cpu.write_byte(0xFFFC, 0x01);
+        cpu.write_byte(0xFFFD, 0x00);
+
+        cpu.run();
+        assert!(cpu.is_stopped());
+        cpu.set_irq(true);
+        cpu.run_until(20);
+        assert_eq!(cpu.get_registers().get_pc(), 0x1);
+        assert_eq!(cpu.get_registers().get_x(), 0x0);
+
+        cpu.set_irq(false);
+        cpu.reset();
+        cpu.run();
+        cpu.run();
+        assert!(!cpu.is_stopped());
+        assert_eq!(cpu.get_registers().get_x(), 0x1);
+    }
+
+    #[test]
+    fn address_bits() {
+        let mut cpu = CPU::new();
+        cpu.set_address_bits(13);
+        assert_eq!(cpu.get_address_bits(), 13);
+
+        // The reset vector is read from $1FFC, and the program at $F000
+        // is the one written at $1000.
+        cpu.write_byte(0xFFFC, 0x00);
+        cpu.write_byte(0xFFFD, 0xF0);
+        cpu.write_byte(0x1000, 0xAD); // LDA $E080
+        cpu.write_byte(0x1001, 0x80);
+        cpu.write_byte(0x1002, 0xE0);
+        cpu.write_byte(0x0080, 0x42);
+        assert_eq!(cpu.read_byte(0x1FFD), 0xF0);
+
+        cpu.reset();
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_pc(), 0xF000);
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_a(), 0x42);
+        assert_eq!(cpu.get_registers().get_pc(), 0xF003);
+    }
+
+    #[test]
+    fn ricoh_2a03_ignores_decimal() {
+        let mut cpu = CPU::new();
+        cpu.set_variant(Variant::Ricoh2A03);
+        // ADC #$01, SBC #$01
+        for (i, byte) in [0x69, 0x01, 0xE9, 0x01].iter().enumerate() {
+            cpu.write_byte(i as Word, *byte);
+        }
+        *cpu.get_registers().get_mut_a() = 0x09;
+        cpu.registers.p.d = true;
+
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_a(), 0x0A);
+
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_a(), 0x08);
+        assert!(cpu.get_registers().get_p().d);
+    }
+
+    #[test]
+    fn cmos_interrupt_clears_decimal() {
+        let mut cpu = cmos_cpu(&[0x00, 0x00]);
+        cpu.registers.p.d = true;
+
+        cpu.run();
+
+        assert!(!cpu.get_registers().get_p().d);
+        assert!(cpu.get_registers().get_p().i);
+        assert_eq!(cpu.read_byte(0x01FD) & 0x08, 0x08);
+    }
+
+    #[test]
+    fn jmp_abs_page_wrap() {
+        // JMP ($03FF)
+        let program = [0x6C, 0xFF, 0x03];
+        let mut cpu = CPU::new();
+        for (i, byte) in program.iter().enumerate() {
+            cpu.write_byte(i as Word, *byte);
+        }
+        cpu.write_byte(0x03FF, 0x34);
+        cpu.write_byte(0x0300, 0x56);
+        cpu.write_byte(0x0400, 0x12);
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_pc(), 0x5634);
+        assert_eq!(cpu.cycles, 5);
+
+        let mut cpu = cmos_cpu(&program);
+        cpu.write_byte(0x03FF, 0x34);
+        cpu.write_byte(0x0300, 0x56);
+        cpu.write_byte(0x0400, 0x12);
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_pc(), 0x1234);
+        assert_eq!(cpu.cycles, 6);
+    }
+
+    #[test]
+    fn zp_page_wrap() {
+        // LDX #$20, LDA $F0,X
+        let mut cpu = CPU::new();
+        for (i, byte) in [0xA2, 0x20, 0xB5, 0xF0].iter().enumerate() {
+            cpu.write_byte(i as Word, *byte);
+        }
+        cpu.write_byte(0x0010, 0x42);
+        cpu.write_byte(0x0110, 0x99);
+        cpu.run();
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_a(), 0x42);
+
+        // LDA ($FF,X), LDA ($FF),Y, with the pointer's high byte at $00
+        let mut cpu = CPU::new();
+        for (i, byte) in [0xA1, 0xFF, 0xB1, 0xFF].iter().enumerate() {
+            cpu.write_byte(0x0200 + i as Word, *byte);
+        }
+        *cpu.get_registers().get_mut_pc() = 0x0200;
+        cpu.write_byte(0x00FF, 0x80);
+        cpu.write_byte(0x0000, 0x30);
+        cpu.write_byte(0x0100, 0x40);
+        cpu.write_byte(0x3080, 0x42);
+        cpu.write_byte(0x4080, 0x99);
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_a(), 0x42);
+        *cpu.get_registers().get_mut_a() = 0x0;
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_a(), 0x42);
+    }
+
+    #[test]
+    fn unofficial_lengths_and_cycles() {
+        let mut opcodes = vec![
+            (0x1A, 1, 2),
+            (0xFA, 1, 2),
+            (0x80, 2, 2),
+            (0x89, 2, 2),
+            (0x04, 2, 3),
+            (0x14, 2, 4),
+            (0xF4, 2, 4),
+            (0x0C, 3, 4),
+            (0x1C, 3, 4),
+            (0xFC, 3, 4),
+            (0x0B, 2, 2),
+            (0x2B, 2, 2),
+            (0x4B, 2, 2),
+            (0x6B, 2, 2),
+            (0x8B, 2, 2),
+            (0xAB, 2, 2),
+            (0xCB, 2, 2),
+            (0xEB, 2, 2),
+            (0x83, 2, 6),
+            (0x87, 2, 3),
+            (0x8F, 3, 4),
+            (0x97, 2, 4),
+            (0xA3, 2, 6),
+            (0xA7, 2, 3),
+            (0xAF, 3, 4),
+            (0xB3, 2, 5),
+            (0xB7, 2, 4),
+            (0xBF, 3, 4),
+            (0x93, 2, 6),
+            (0x9F, 3, 5),
+            (0x9B, 3, 5),
+            (0x9C, 3, 5),
+            (0x9E, 3, 5),
+            (0xBB, 3, 4),
+        ];
+        // SLO, RLA, SRE, RRA, DCP and ISB
+        for row in [0x00, 0x20, 0x40, 0x60, 0xC0, 0xE0] {
+            for (column, length, cycles) in [
+                (0x03, 2, 8),
+                (0x07, 2, 5),
+                (0x0F, 3, 6),
+                (0x13, 2, 8),
+                (0x17, 2, 6),
+                (0x1B, 3, 7),
+                (0x1F, 3, 7),
+            ] {
+                opcodes.push((row | column, length, cycles));
+            }
+        }
+        for (opcode, length, cycles) in opcodes {
+            let mut cpu = CPU::new();
+            for (i, byte) in [opcode, 0x10, 0x00].iter().enumerate() {
+                cpu.write_byte(i as Word, *byte);
+            }
+
+            cpu.run();
+
+            assert_eq!(cpu.get_registers().get_pc(), length, "{:02X}", opcode);
+            assert_eq!(cpu.cycles, cycles, "{:02X}", opcode);
+        }
+    }
+
+    #[test]
+    fn lax_sax() {
+        // LAX $10, LDA #$F0, LDX #$3C, SAX $11
+        let mut cpu = CPU::new();
+        for (i, byte) in [0xA7, 0x10, 0xA9, 0xF0, 0xA2, 0x3C, 0x87, 0x11]
+            .iter()
+            .enumerate()
+        {
+            cpu.write_byte(i as Word, *byte);
+        }
+        cpu.write_byte(0x10, 0x8F);
+
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_a(), 0x8F);
+        assert_eq!(cpu.get_registers().get_x(), 0x8F);
+        assert!(cpu.get_registers().get_p().n);
+
+        cpu.run();
+        cpu.run();
+        cpu.run();
+        assert_eq!(cpu.read_byte(0x11), 0x30);
+    }
+
+    #[test]
+    fn unofficial_read_modify_write() {
+        // LDA #$10, SLO $10, RLA $11, SRE $12, RRA $13, DCP $14, ISB $15
+        let mut cpu = CPU::new();
+        for (i, byte) in [
+            0xA9, 0x10, 0x07, 0x10, 0x27, 0x11, 0x47, 0x12, 0x67, 0x13, 0xC7, 0x14, 0xE7, 0x15,
+        ]
+        .iter()
+        .enumerate()
+        {
+            cpu.write_byte(i as Word, *byte);
+        }
+        for (i, byte) in [0x81, 0x80, 0x03, 0x02, 0x83, 0x01].iter().enumerate() {
+            cpu.write_byte(0x10 + i as Word, *byte);
+        }
+        cpu.run();
+
+        cpu.run();
+        assert_eq!(cpu.read_byte(0x10), 0x02);
+        assert_eq!(cpu.get_registers().get_a(), 0x12);
+        assert_eq!(cpu.get_registers().get_p().c, true);
+
+        cpu.run();
+        assert_eq!(cpu.read_byte(0x11), 0x01);
+        assert_eq!(cpu.get_registers().get_a(), 0x00);
+        assert_eq!(cpu.get_registers().get_p().z, true);
+
+        cpu.run();
+        assert_eq!(cpu.read_byte(0x12), 0x01);
+        assert_eq!(cpu.get_registers().get_a(), 0x01);
+        assert_eq!(cpu.get_registers().get_p().c, true);
+
+        cpu.run();
+        assert_eq!(cpu.read_byte(0x13), 0x81);
+        assert_eq!(cpu.get_registers().get_a(), 0x82);
+        assert_eq!(cpu.get_registers().get_p().c, false);
+        assert_eq!(cpu.get_registers().get_p().n, true);
+
+        cpu.run();
+        assert_eq!(cpu.read_byte(0x14), 0x82);
+        assert_eq!(cpu.get_registers().get_p().z, true);
+        assert_eq!(cpu.get_registers().get_p().c, true);
+
+        cpu.run();
+        assert_eq!(cpu.read_byte(0x15), 0x02);
+        assert_eq!(cpu.get_registers().get_a(), 0x80);
+        assert_eq!(cpu.get_registers().get_p().c, true);
+        assert_eq!(cpu.get_registers().get_p().v, false);
+    }
+
+    #[test]
+    fn unofficial_immediates() {
+        // LDA #$FF, ANC #$80, LDA #$FF, ALR #$03, LDA #$FF, ARR #$40,
+        // LDX #$0F, AXS #$01, SBC #$0F
+        let mut cpu = CPU::new();
+        for (i, byte) in [
+            0xA9, 0xFF, 0x0B, 0x80, 0xA9, 0xFF, 0x4B, 0x03, 0xA9, 0xFF, 0x6B, 0x40, 0xA2, 0x0F,
+            0xCB, 0x01, 0xEB, 0x0F,
+        ]
+        .iter()
+        .enumerate()
+        {
+            cpu.write_byte(i as Word, *byte);
+        }
+
+        cpu.run();
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_a(), 0x80);
+        assert_eq!(cpu.get_registers().get_p().c, true);
+
+        cpu.run();
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_a(), 0x01);
+        assert_eq!(cpu.get_registers().get_p().c, true);
+
+        cpu.run();
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_a(), 0xA0);
+        assert_eq!(cpu.get_registers().get_p().c, false);
+        assert_eq!(cpu.get_registers().get_p().v, true);
+
+        cpu.run();
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_x(), 0xFF);
+        assert_eq!(cpu.get_registers().get_p().c, false);
+        assert_eq!(cpu.get_registers().get_p().n, true);
+
+        cpu.run();
+        assert_eq!(cpu.get_registers().get_a(), 0x90);
+        assert_eq!(cpu.get_registers().get_p().c, true);
+    }
+
+    #[test]
+    fn shx_page_crossing() {
+        // LDX #$07, LDY #$01, SHX $1000,Y, SHX $10FF,Y
+        let mut cpu = CPU::new();
+        for (i, byte) in [0xA2, 0x07, 0xA0, 0x01, 0x9E, 0x00, 0x10, 0x9E, 0xFF, 0x10]
+            .iter()
+            .enumerate()
+        {
+            cpu.write_byte(i as Word, *byte);
+        }
+        cpu.run();
+        cpu.run();
+
+        cpu.run();
+        assert_eq!(cpu.read_byte(0x1001), 0x01);
+
+        // X AND $11 lands in $0100 rather than $1100.
+        cpu.run();
+        assert_eq!(cpu.read_byte(0x0100), 0x01);
+        assert_eq!(cpu.read_byte(0x1100), 0x00);
+    }
+
+    #[test]
+    fn kil() {
+        let mut cpu = CPU::new();
+        cpu.write_byte(0x0, 0x02);
+        cpu.write_byte(0x1, 0xE8);
+
+        cpu.run();
+        assert!(cpu.is_stopped());
+        cpu.run_until(20);
+        assert_eq!(cpu.get_registers().get_pc(), 0x1);
+        assert_eq!(cpu.get_registers().get_x(), 0x0);
+    }
 }
@@ -0,0 +1,108 @@
+use crate::{dma::Dma, Byte, Word};
+
+mod acia;
+mod ay38910;
+mod block;
+mod cassette;
+mod cia;
+mod ds1307;
+mod eeprom24;
+mod font;
+mod hd44780;
+mod i2c;
+mod interrupt_controller;
+mod pia;
+mod ram;
+mod riot;
+mod rriot;
+mod rng;
+mod rom;
+mod rtc;
+mod sd_card;
+mod sn76489;
+mod sound;
+mod speaker;
+mod spi;
+mod text_video;
+mod via;
+
+pub use acia::Acia;
+pub use ay38910::Ay38910;
+pub use block::BlockDevice;
+pub use cassette::Cassette;
+pub use cia::Cia;
+pub use ds1307::Ds1307;
+pub use eeprom24::Eeprom24;
+pub use hd44780::Hd44780;
+pub use i2c::{I2c, I2cDevice, I2cPins};
+pub use interrupt_controller::{InterruptController, Trigger};
+pub use pia::{Pia, PiaPeripheral};
+pub use ram::Ram;
+pub use riot::Riot;
+pub use rriot::Rriot;
+pub use rng::Rng;
+pub use rom::Rom;
+pub use rtc::{Rtc, TimeSource};
+pub use sd_card::SdCard;
+pub use sn76489::Sn76489;
+pub use speaker::Speaker;
+pub use spi::{Spi, SpiDevice, SpiPins};
+pub use text_video::TextVideo;
+pub use via::Via;
+
+// The CPU input a device's interrupt output is wired to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Line {
+    Irq,
+    Nmi,
+    // Not wired to the CPU, e.g. when the output goes to an interrupt
+    // controller instead.
+    Unconnected,
+}
+
+// Anything that can sit on the address bus. `read` and `write` get the full
+// address so devices decode (and mirror) their own registers. A device that
+// moves data by DMA hands a transfer to the CPU from `take_dma`.
+//
+// Devices are not ticked every cycle. `tick` brings a device up to the given
+// cycle, working out whatever happened since it was last called, and is
+// called before each access and when the cycle from `get_next_event` comes
+// round. That is the next cycle at which the device would change anything
+// outside itself without being accessed: its interrupt output, a transfer
+// for `take_dma`, or something the host watches, such as a serial frame
+// going out. It is asked again after every tick. Ticking twice for the same
+// cycle does nothing.
+//
+// `peek` returns what `read` would without its side effects, such as
+// clearing interrupt flags or advancing a FIFO, for anything that looks at
+// memory without being a CPU bus cycle. Devices whose reads have no side
+// effects only implement `peek`.
+pub trait Device {
+    fn peek(&self, addr: Word) -> Byte;
+
+    fn read(&mut self, addr: Word) -> Byte {
+        self.peek(addr)
+    }
+
+    fn write(&mut self, addr: Word, val: Byte);
+
+    fn tick(&mut self, _cycle: u64) {}
+
+    fn get_next_event(&self) -> Option<u64> {
+        None
+    }
+
+    // Whether the device answers at `addr`. One that doesn't leaves the
+    // access to whatever is mapped under it.
+    fn is_selected(&self, _addr: Word) -> bool {
+        true
+    }
+
+    fn irq(&self) -> bool {
+        false
+    }
+
+    fn take_dma(&mut self) -> Option<Dma> {
+        None
+    }
+}
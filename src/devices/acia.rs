@@ -0,0 +1,267 @@
+use crate::{serial::SerialBackend, Byte, Word};
+
+use super::Device;
+
+const DATA: Word = 0x0;
+const STATUS: Word = 0x1;
+const COMMAND: Word = 0x2;
+const CONTROL: Word = 0x3;
+
+const REG_MASK: Word = 0x03;
+
+const STATUS_PARITY: Byte = 0x01;
+const STATUS_FRAMING: Byte = 0x02;
+const STATUS_OVERRUN: Byte = 0x04;
+const STATUS_RDRF: Byte = 0x08;
+const STATUS_TDRE: Byte = 0x10;
+const STATUS_IRQ: Byte = 0x80;
+
+const COMMAND_DTR: Byte = 0x01;
+const COMMAND_RX_IRQ_DISABLE: Byte = 0x02;
+const COMMAND_TIC_MASK: Byte = 0x0C;
+const COMMAND_TIC_TX_IRQ: Byte = 0x04;
+const COMMAND_ECHO: Byte = 0x10;
+const COMMAND_PARITY_ENABLE: Byte = 0x20;
+
+const CONTROL_STOP_BITS: Byte = 0x80;
+
+const CRYSTAL_HZ: u64 = 1_843_200;
+
+// Baud rates selected by the low nibble of the control register, in tenths
+// of a baud. Selection 0 uses the 16x external receiver clock, which on most
+// boards is the crystal itself.
+const BAUD_RATES: [u64; 16] = [
+    CRYSTAL_HZ * 10 / 16,
+    500,
+    750,
+    1_099,
+    1_346,
+    1_500,
+    3_000,
+    6_000,
+    12_000,
+    18_000,
+    24_000,
+    36_000,
+    48_000,
+    72_000,
+    96_000,
+    192_000,
+];
+
+// MOS 6551 Asynchronous Communications Interface Adapter. Characters move
+// between the data registers and the host backend one frame time apart, as
+// set by the baud rate, word length, parity and stop bits in the control
+// and command registers.
+//
+// The device is brought up to date a frame at a time, and asks to be
+// looked at when a frame is due to go out or come in.
+pub struct Acia {
+    cycle: u64,
+    backend: Box<dyn SerialBackend>,
+    clock_hz: u64,
+    rx_data: Byte,
+    tx_data: Byte,
+    status: Byte,
+    command: Byte,
+    control: Byte,
+    tx_shift: Option<Byte>,
+    tx_countdown: u64,
+    rx_countdown: u64,
+}
+
+impl Acia {
+    pub fn new(backend: Box<dyn SerialBackend>, clock_hz: u64) -> Self {
+        let mut acia = Self {
+            cycle: 0x0,
+            backend,
+            clock_hz,
+            rx_data: 0x0,
+            tx_data: 0x0,
+            status: STATUS_TDRE,
+            command: 0x0,
+            control: 0x0,
+            tx_shift: None,
+            tx_countdown: 0,
+            rx_countdown: 0,
+        };
+        acia.rx_countdown = acia.get_frame_cycles();
+        acia
+    }
+
+    pub fn get_backend(&mut self) -> &mut dyn SerialBackend {
+        self.backend.as_mut()
+    }
+
+    fn get_word_length(&self) -> u64 {
+        8 - ((self.control >> 5) & 0b11) as u64
+    }
+
+    fn get_data_mask(&self) -> Byte {
+        (0xFF_u16 >> (8 - self.get_word_length())) as Byte
+    }
+
+    fn get_frame_bits(&self) -> u64 {
+        let parity = (self.command & COMMAND_PARITY_ENABLE != 0) as u64;
+        let stop = if self.control & CONTROL_STOP_BITS != 0 {
+            2
+        } else {
+            1
+        };
+        1 + self.get_word_length() + parity + stop
+    }
+
+    pub fn get_baud_rate(&self) -> f64 {
+        BAUD_RATES[(self.control & 0x0F) as usize] as f64 / 10.0
+    }
+
+    pub fn get_frame_cycles(&self) -> u64 {
+        let tenths = BAUD_RATES[(self.control & 0x0F) as usize];
+        (self.clock_hz * self.get_frame_bits() * 10 / tenths).max(1)
+    }
+
+    fn is_receiver_enabled(&self) -> bool {
+        self.command & COMMAND_DTR != 0
+    }
+
+    fn raise_irq(&mut self) {
+        self.status |= STATUS_IRQ;
+    }
+
+    fn start_transmit(&mut self) {
+        if self.tx_shift.is_some() || self.status & STATUS_TDRE != 0 {
+            return;
+        }
+        self.tx_shift = Some(self.tx_data & self.get_data_mask());
+        self.tx_countdown = self.get_frame_cycles();
+        self.status |= STATUS_TDRE;
+        if self.command & COMMAND_TIC_MASK == COMMAND_TIC_TX_IRQ {
+            self.raise_irq();
+        }
+    }
+
+    // Cycles that only count the frame times down.
+    fn get_quiet_cycles(&self) -> u64 {
+        let rx = self.rx_countdown - 1;
+        match self.tx_shift {
+            Some(_) => rx.min(self.tx_countdown - 1),
+            None => rx,
+        }
+    }
+
+    fn tick_transmitter(&mut self) {
+        let Some(val) = self.tx_shift else {
+            return;
+        };
+        self.tx_countdown -= 1;
+        if self.tx_countdown != 0 {
+            return;
+        }
+        self.backend.transmit(val);
+        self.tx_shift = None;
+        self.start_transmit();
+    }
+
+    fn tick_receiver(&mut self) {
+        self.rx_countdown -= 1;
+        if self.rx_countdown != 0 {
+            return;
+        }
+        self.rx_countdown = self.get_frame_cycles();
+        if !self.is_receiver_enabled() {
+            return;
+        }
+        let Some(val) = self.backend.receive() else {
+            return;
+        };
+        if self.status & STATUS_RDRF != 0 {
+            self.status |= STATUS_OVERRUN;
+            return;
+        }
+        self.rx_data = val & self.get_data_mask();
+        self.status |= STATUS_RDRF;
+        if self.command & COMMAND_ECHO != 0 {
+            self.backend.transmit(self.rx_data);
+        }
+        if self.command & COMMAND_RX_IRQ_DISABLE == 0 {
+            self.raise_irq();
+        }
+    }
+}
+
+impl Device for Acia {
+    fn peek(&self, addr: Word) -> Byte {
+        match addr & REG_MASK {
+            DATA => self.rx_data,
+            STATUS => self.status,
+            COMMAND => self.command,
+            CONTROL => self.control,
+            _ => unreachable!(),
+        }
+    }
+
+    fn read(&mut self, addr: Word) -> Byte {
+        match addr & REG_MASK {
+            DATA => {
+                self.status &= !(STATUS_RDRF | STATUS_OVERRUN | STATUS_FRAMING | STATUS_PARITY);
+                self.rx_data
+            }
+            STATUS => {
+                let val = self.status;
+                self.status &= !STATUS_IRQ;
+                val
+            }
+            COMMAND => self.command,
+            CONTROL => self.control,
+            _ => unreachable!(),
+        }
+    }
+
+    fn write(&mut self, addr: Word, val: Byte) {
+        match addr & REG_MASK {
+            DATA => {
+                self.tx_data = val;
+                self.status &= !STATUS_TDRE;
+                self.start_transmit();
+            }
+            STATUS => {
+                // Programmed reset: clears command bits 0-4 and the overrun
+                // flag, leaves the control register alone.
+                self.command &= 0xE0;
+                self.status &= !STATUS_OVERRUN;
+            }
+            COMMAND => self.command = val,
+            CONTROL => self.control = val,
+            _ => unreachable!(),
+        }
+    }
+
+    fn tick(&mut self, cycle: u64) {
+        while self.cycle < cycle {
+            let cycles = self.get_quiet_cycles().min(cycle - self.cycle);
+            if cycles > 0 {
+                if self.tx_shift.is_some() {
+                    self.tx_countdown -= cycles;
+                }
+                self.rx_countdown -= cycles;
+                self.cycle += cycles;
+            } else {
+                self.tick_transmitter();
+                self.tick_receiver();
+                self.cycle += 1;
+            }
+        }
+    }
+
+    fn get_next_event(&self) -> Option<u64> {
+        let tx = self.tx_shift.map(|_| self.cycle + self.tx_countdown);
+        let rx = self
+            .is_receiver_enabled()
+            .then_some(self.cycle + self.rx_countdown);
+        tx.into_iter().chain(rx).min()
+    }
+
+    fn irq(&self) -> bool {
+        self.status & STATUS_IRQ != 0
+    }
+}
@@ -0,0 +1,345 @@
+use crate::{wav::encode_wav, Byte, Word};
+
+use super::{
+    sound::{self, Synth},
+    Device,
+};
+
+const REGISTERS_LENGTH: usize = 16;
+
+const TONE_A: usize = 0x0;
+const NOISE_PERIOD: usize = 0x6;
+const MIXER: usize = 0x7;
+const AMPLITUDE_A: usize = 0x8;
+const ENVELOPE_PERIOD: usize = 0xB;
+const ENVELOPE_SHAPE: usize = 0xD;
+const PORT_A: usize = 0xE;
+const PORT_B: usize = 0xF;
+
+// Bits implemented in each register.
+const REGISTER_MASKS: [Byte; REGISTERS_LENGTH] = [
+    0xFF, 0x0F, 0xFF, 0x0F, 0xFF, 0x0F, 0x1F, 0xFF, 0x1F, 0x1F, 0x1F, 0xFF, 0xFF, 0x0F, 0xFF, 0xFF,
+];
+
+const MIXER_PORT_A_OUTPUT: Byte = 0x40;
+const MIXER_PORT_B_OUTPUT: Byte = 0x80;
+const AMPLITUDE_ENVELOPE: Byte = 0x10;
+
+const SHAPE_HOLD: Byte = 0x1;
+const SHAPE_ALTERNATE: Byte = 0x2;
+const SHAPE_ATTACK: Byte = 0x4;
+const SHAPE_CONTINUE: Byte = 0x8;
+
+// Output of each of the 16 amplitude levels, roughly 3 dB apart.
+const LEVELS: [f32; 16] = [
+    0.0, 0.0137, 0.0205, 0.0291, 0.0423, 0.0618, 0.0847, 0.1369, 0.1691, 0.2647, 0.3527, 0.4499,
+    0.5704, 0.6873, 0.8482, 1.0,
+];
+
+// The tone counters run at the chip clock divided by 8.
+const CLOCK_DIVIDER: u64 = 8;
+
+// Function of the bus control pins BDIR and BC1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BusMode {
+    Inactive,
+    Read,
+    Write,
+    Latch,
+}
+
+// General Instrument AY-3-8910 programmable sound generator: three square
+// wave channels, a noise generator and an envelope generator, plus two I/O
+// ports. Memory mapped, even addresses latch a register number and odd
+// ones write it; both read the latched register. It can also be wired to a
+// port through `set_pins`, driving BDIR, BC1 and DA0-DA7.
+//
+// Register writes are logged with the cycle passed to the last `tick`, and
+// `render` replays them into mono samples offline.
+pub struct Ay38910 {
+    clock_hz: u64,
+    cpu_hz: u64,
+    cycle: u64,
+    registers: [Byte; REGISTERS_LENGTH],
+    address: usize,
+    port_a_input: Byte,
+    port_b_input: Byte,
+    writes: Vec<(u64, (Byte, Byte))>,
+    output: Option<Byte>,
+}
+
+impl Ay38910 {
+    pub fn new(clock_hz: u64, cpu_hz: u64) -> Self {
+        Self {
+            clock_hz,
+            cpu_hz,
+            cycle: 0,
+            registers: [0x0; REGISTERS_LENGTH],
+            address: 0,
+            port_a_input: 0xFF,
+            port_b_input: 0xFF,
+            writes: vec![],
+            output: None,
+        }
+    }
+
+    pub fn get_registers(&self) -> &[Byte] {
+        &self.registers
+    }
+
+    // Register writes as (cycle, (register, value)).
+    pub fn get_writes(&self) -> &[(u64, (Byte, Byte))] {
+        &self.writes
+    }
+
+    pub fn get_port_a(&self) -> Byte {
+        self.port(PORT_A, MIXER_PORT_A_OUTPUT, self.port_a_input)
+    }
+
+    pub fn get_port_b(&self) -> Byte {
+        self.port(PORT_B, MIXER_PORT_B_OUTPUT, self.port_b_input)
+    }
+
+    pub fn set_port_a_input(&mut self, val: Byte) {
+        self.port_a_input = val;
+    }
+
+    pub fn set_port_b_input(&mut self, val: Byte) {
+        self.port_b_input = val;
+    }
+
+    // Drives BDIR, BC1 and the data bus. BC2 is assumed tied high.
+    pub fn set_pins(&mut self, bdir: bool, bc1: bool, data: Byte) {
+        let mode = match (bdir, bc1) {
+            (false, false) => BusMode::Inactive,
+            (false, true) => BusMode::Read,
+            (true, false) => BusMode::Write,
+            (true, true) => BusMode::Latch,
+        };
+        self.output = None;
+        match mode {
+            BusMode::Inactive => {}
+            BusMode::Read => self.output = self.read_register(),
+            BusMode::Write => self.write_register(data),
+            BusMode::Latch => self.latch_address(data),
+        }
+    }
+
+    // Level driven on DA0-DA7 in read mode, if a register is selected.
+    pub fn get_data(&self) -> Option<Byte> {
+        self.output
+    }
+
+    // Everything written up to the last tick, as samples at `sample_rate`.
+    pub fn render(&self, sample_rate: u32) -> Vec<i16> {
+        sound::render(
+            AySynth::new(),
+            &self.writes,
+            self.cycle,
+            self.cpu_hz,
+            self.clock_hz / CLOCK_DIVIDER,
+            sample_rate,
+        )
+    }
+
+    pub fn render_wav(&self, sample_rate: u32) -> Vec<u8> {
+        encode_wav(sample_rate, &self.render(sample_rate))
+    }
+
+    fn port(&self, reg: usize, output: Byte, input: Byte) -> Byte {
+        match self.registers[MIXER] & output {
+            0 => input,
+            _ => self.registers[reg],
+        }
+    }
+
+    // Addresses 16 and up deselect the chip.
+    fn latch_address(&mut self, val: Byte) {
+        self.address = val as usize;
+    }
+
+    fn read_register(&self) -> Option<Byte> {
+        match self.address {
+            PORT_A => Some(self.get_port_a()),
+            PORT_B => Some(self.get_port_b()),
+            reg => self.registers.get(reg).copied(),
+        }
+    }
+
+    fn write_register(&mut self, val: Byte) {
+        let Some(reg) = self.registers.get_mut(self.address) else {
+            return;
+        };
+        *reg = val & REGISTER_MASKS[self.address];
+        self.writes.push((self.cycle, (self.address as Byte, *reg)));
+    }
+}
+
+impl Device for Ay38910 {
+    fn peek(&self, _addr: Word) -> Byte {
+        self.read_register().unwrap_or(0xFF)
+    }
+
+    fn write(&mut self, addr: Word, val: Byte) {
+        match addr & 0x1 {
+            0 => self.latch_address(val),
+            _ => self.write_register(val),
+        }
+    }
+
+    fn tick(&mut self, cycle: u64) {
+        self.cycle = cycle;
+    }
+}
+
+struct Envelope {
+    step: u8,
+    attack: bool,
+    holding: bool,
+    shape: Byte,
+}
+
+impl Envelope {
+    fn restart(&mut self, shape: Byte) {
+        self.shape = shape;
+        self.step = 0;
+        self.attack = shape & SHAPE_ATTACK != 0;
+        self.holding = false;
+    }
+
+    fn level(&self) -> usize {
+        match self.attack {
+            true => self.step as usize,
+            false => 15 - self.step as usize,
+        }
+    }
+
+    fn advance(&mut self) {
+        if self.holding {
+            return;
+        }
+        if self.step < 15 {
+            self.step += 1;
+            return;
+        }
+        if self.shape & SHAPE_CONTINUE == 0 {
+            // Shapes 0-7 always end low.
+            self.attack = false;
+            self.holding = true;
+        } else if self.shape & SHAPE_HOLD != 0 {
+            if self.shape & SHAPE_ALTERNATE != 0 {
+                self.attack = !self.attack;
+            }
+            self.holding = true;
+        } else {
+            self.step = 0;
+            if self.shape & SHAPE_ALTERNATE != 0 {
+                self.attack = !self.attack;
+            }
+        }
+    }
+}
+
+struct AySynth {
+    registers: [Byte; REGISTERS_LENGTH],
+    tone_counters: [u16; 3],
+    tones: [bool; 3],
+    noise_counter: u8,
+    lfsr: u32,
+    envelope_counter: u16,
+    envelope: Envelope,
+    prescaler: bool,
+}
+
+impl AySynth {
+    fn new() -> Self {
+        Self {
+            registers: [0x0; REGISTERS_LENGTH],
+            tone_counters: [0; 3],
+            tones: [false; 3],
+            noise_counter: 0,
+            lfsr: 0x1,
+            envelope_counter: 0,
+            envelope: Envelope {
+                step: 15,
+                attack: false,
+                holding: true,
+                shape: 0x0,
+            },
+            prescaler: false,
+        }
+    }
+
+    fn tone_period(&self, channel: usize) -> u16 {
+        let reg = TONE_A + channel * 2;
+        let period = u16::from_le_bytes([self.registers[reg], self.registers[reg + 1]]);
+        period.max(1)
+    }
+
+    fn envelope_period(&self) -> u16 {
+        let period = u16::from_le_bytes([
+            self.registers[ENVELOPE_PERIOD],
+            self.registers[ENVELOPE_PERIOD + 1],
+        ]);
+        period.max(1)
+    }
+
+    // Noise and envelope run at half the tone rate.
+    fn step_noise_and_envelope(&mut self) {
+        self.noise_counter += 1;
+        if self.noise_counter >= self.registers[NOISE_PERIOD].max(1) {
+            self.noise_counter = 0;
+            let bit = (self.lfsr ^ (self.lfsr >> 3)) & 0x1;
+            self.lfsr = (self.lfsr >> 1) | (bit << 16);
+        }
+
+        self.envelope_counter += 1;
+        if self.envelope_counter >= self.envelope_period() {
+            self.envelope_counter = 0;
+            self.envelope.advance();
+        }
+    }
+}
+
+impl Synth for AySynth {
+    type Write = (Byte, Byte);
+
+    fn write(&mut self, (reg, val): (Byte, Byte)) {
+        self.registers[reg as usize] = val;
+        if reg as usize == ENVELOPE_SHAPE {
+            self.envelope.restart(val);
+            self.envelope_counter = 0;
+        }
+    }
+
+    fn step(&mut self) -> f32 {
+        for channel in 0..3 {
+            self.tone_counters[channel] += 1;
+            if self.tone_counters[channel] >= self.tone_period(channel) {
+                self.tone_counters[channel] = 0;
+                self.tones[channel] = !self.tones[channel];
+            }
+        }
+        self.prescaler = !self.prescaler;
+        if self.prescaler {
+            self.step_noise_and_envelope();
+        }
+
+        let noise = self.lfsr & 0x1 != 0;
+        let mixer = self.registers[MIXER];
+        let mut level = 0.0;
+        for channel in 0..3 {
+            let tone_off = mixer & (0x01 << channel) != 0;
+            let noise_off = mixer & (0x08 << channel) != 0;
+            if !((self.tones[channel] || tone_off) && (noise || noise_off)) {
+                continue;
+            }
+            let amplitude = self.registers[AMPLITUDE_A + channel];
+            level += match amplitude & AMPLITUDE_ENVELOPE {
+                0 => LEVELS[amplitude as usize & 0x0F],
+                _ => LEVELS[self.envelope.level()],
+            };
+        }
+        level / 3.0
+    }
+}
@@ -0,0 +1,244 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use crate::{dma::Dma, Byte, Word};
+
+use super::Device;
+
+const STATUS: Word = 0x0;
+const CONTROL: Word = 0x1;
+const SECTOR_0: Word = 0x2;
+const SECTOR_1: Word = 0x3;
+const SECTOR_2: Word = 0x4;
+const ADDR_L: Word = 0x5;
+const ADDR_H: Word = 0x6;
+const DATA: Word = 0x7;
+
+const REG_MASK: Word = 0x07;
+
+const COMMAND_READ: Byte = 0x01;
+const COMMAND_WRITE: Byte = 0x02;
+
+const STATUS_ERROR: Byte = 0x01;
+const STATUS_DONE: Byte = 0x40;
+const STATUS_BUSY: Byte = 0x80;
+
+const CONTROL_IRQ: Byte = 0x01;
+
+const SECTOR_LENGTH: usize = 512;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Transfer {
+    Idle,
+    Read,
+    Write,
+}
+
+// Memory-mapped block device that moves 512-byte sectors between a host
+// disk image and memory by DMA, so a transfer steals the same cycles as any
+// other DMA. Eight registers, mirrored through the mapped range:
+//
+//   0  status (read) / command (write): 1 reads, 2 writes a sector
+//   1  control: bit 0 enables the completion IRQ
+//   2  sector number, bits 0-7
+//   3  sector number, bits 8-15
+//   4  sector number, bits 16-23
+//   5  DMA address, low byte
+//   6  DMA address, high byte
+//   7  data port the DMA controller transfers through
+//
+// Status bit 7 is set while a transfer is in progress, bit 6 once it has
+// finished and bit 0 if the command failed, e.g. for a sector beyond the
+// end of the image. Reading the status clears bit 6 and the IRQ.
+pub struct BlockDevice<T> {
+    storage: T,
+    sectors: u32,
+
+    status: Byte,
+    control: Byte,
+    sector: u32,
+    addr: Word,
+    transfer: Transfer,
+    buffer: Vec<Byte>,
+    index: usize,
+    dma: Option<Dma>,
+}
+
+impl BlockDevice<File> {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        Self::new(file)
+    }
+}
+
+impl<T: Read + Write + Seek> BlockDevice<T> {
+    pub fn new(mut storage: T) -> io::Result<Self> {
+        let size = storage.seek(SeekFrom::End(0))?;
+        Ok(Self {
+            storage,
+            sectors: (size / SECTOR_LENGTH as u64) as u32,
+            status: 0x0,
+            control: 0x0,
+            sector: 0,
+            addr: 0x0,
+            transfer: Transfer::Idle,
+            buffer: vec![0x0; SECTOR_LENGTH],
+            index: 0,
+            dma: None,
+        })
+    }
+
+    pub fn get_storage(&self) -> &T {
+        &self.storage
+    }
+
+    pub fn get_sectors(&self) -> u32 {
+        self.sectors
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.status & STATUS_BUSY != 0
+    }
+
+    fn seek(&mut self) -> io::Result<()> {
+        if self.sector >= self.sectors {
+            return Err(io::ErrorKind::InvalidInput.into());
+        }
+        let offset = self.sector as u64 * SECTOR_LENGTH as u64;
+        self.storage.seek(SeekFrom::Start(offset))?;
+        Ok(())
+    }
+
+    fn read_sector(&mut self) -> io::Result<()> {
+        self.seek()?;
+        self.storage.read_exact(&mut self.buffer)
+    }
+
+    fn write_sector(&mut self) -> io::Result<()> {
+        self.seek()?;
+        self.storage.write_all(&self.buffer)?;
+        self.storage.flush()
+    }
+
+    fn command(&mut self, addr: Word, val: Byte) {
+        if self.is_busy() {
+            return;
+        }
+        let port = (addr & !REG_MASK) | DATA;
+        self.status = 0x0;
+        self.index = 0;
+        match val {
+            COMMAND_READ => match self.read_sector() {
+                Ok(()) => {
+                    self.transfer = Transfer::Read;
+                    self.dma = Some(Dma::from_port(port, self.addr, SECTOR_LENGTH));
+                }
+                Err(_) => self.finish(STATUS_ERROR),
+            },
+            COMMAND_WRITE if self.sector < self.sectors => {
+                self.transfer = Transfer::Write;
+                self.dma = Some(Dma::to_port(self.addr, port, SECTOR_LENGTH));
+            }
+            _ => self.finish(STATUS_ERROR),
+        }
+        if self.transfer != Transfer::Idle {
+            self.status = STATUS_BUSY;
+        }
+    }
+
+    fn finish(&mut self, flags: Byte) {
+        self.transfer = Transfer::Idle;
+        self.status = STATUS_DONE | flags;
+    }
+
+    fn peek_data(&self) -> Byte {
+        match self.transfer {
+            Transfer::Read => self.buffer[self.index],
+            _ => 0xFF,
+        }
+    }
+
+    fn read_data(&mut self) -> Byte {
+        if self.transfer != Transfer::Read {
+            return 0xFF;
+        }
+        let val = self.peek_data();
+        self.index += 1;
+        if self.index == SECTOR_LENGTH {
+            self.finish(0x0);
+        }
+        val
+    }
+
+    fn write_data(&mut self, val: Byte) {
+        if self.transfer != Transfer::Write {
+            return;
+        }
+        self.buffer[self.index] = val;
+        self.index += 1;
+        if self.index == SECTOR_LENGTH {
+            let flags = match self.write_sector() {
+                Ok(()) => 0x0,
+                Err(_) => STATUS_ERROR,
+            };
+            self.finish(flags);
+        }
+    }
+}
+
+impl<T: Read + Write + Seek> Device for BlockDevice<T> {
+    fn peek(&self, addr: Word) -> Byte {
+        match addr & REG_MASK {
+            STATUS => self.status,
+            CONTROL => self.control,
+            SECTOR_0 => self.sector.to_le_bytes()[0],
+            SECTOR_1 => self.sector.to_le_bytes()[1],
+            SECTOR_2 => self.sector.to_le_bytes()[2],
+            ADDR_L => self.addr.to_le_bytes()[0],
+            ADDR_H => self.addr.to_le_bytes()[1],
+            DATA => self.peek_data(),
+            _ => unreachable!(),
+        }
+    }
+
+    fn read(&mut self, addr: Word) -> Byte {
+        match addr & REG_MASK {
+            STATUS => {
+                let val = self.status;
+                self.status &= !STATUS_DONE;
+                val
+            }
+            DATA => self.read_data(),
+            _ => self.peek(addr),
+        }
+    }
+
+    fn write(&mut self, addr: Word, val: Byte) {
+        let mut sector = self.sector.to_le_bytes();
+        let mut dma_addr = self.addr.to_le_bytes();
+        match addr & REG_MASK {
+            STATUS => self.command(addr, val),
+            CONTROL => self.control = val,
+            SECTOR_0 => sector[0] = val,
+            SECTOR_1 => sector[1] = val,
+            SECTOR_2 => sector[2] = val,
+            ADDR_L => dma_addr[0] = val,
+            ADDR_H => dma_addr[1] = val,
+            DATA => self.write_data(val),
+            _ => unreachable!(),
+        }
+        self.sector = u32::from_le_bytes(sector);
+        self.addr = Word::from_le_bytes(dma_addr);
+    }
+
+    fn irq(&self) -> bool {
+        self.status & STATUS_DONE != 0 && self.control & CONTROL_IRQ != 0
+    }
+
+    fn take_dma(&mut self) -> Option<Dma> {
+        self.dma.take()
+    }
+}
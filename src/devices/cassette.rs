@@ -0,0 +1,104 @@
+use std::{fs, io, path::Path};
+
+use crate::{wav::decode_wav, Byte, Word};
+
+use super::Device;
+
+const LEVEL_HIGH: Byte = 0x80;
+
+// Cassette input in the style of the Apple II port at $C060: a recording
+// played back through a zero-crossing detector, read as bit 7 of the mapped
+// address. Playback starts at the cycle `play` is called and advances with
+// the CPU clock, so a loader timing edges sees the tape at its real speed.
+// Reads return 0 before playback starts and after the tape runs out.
+pub struct Cassette {
+    cpu_hz: u64,
+    cycle: u64,
+    sample_rate: u32,
+    samples: Vec<i16>,
+    start: Option<u64>,
+    level: bool,
+}
+
+impl Cassette {
+    pub fn new(sample_rate: u32, samples: Vec<i16>, cpu_hz: u64) -> Self {
+        Self {
+            cpu_hz,
+            cycle: 0,
+            sample_rate,
+            samples,
+            start: None,
+            level: false,
+        }
+    }
+
+    pub fn from_wav(wav: &[u8], cpu_hz: u64) -> io::Result<Self> {
+        let (sample_rate, samples) = decode_wav(wav)?;
+        Ok(Self::new(sample_rate, samples, cpu_hz))
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P, cpu_hz: u64) -> io::Result<Self> {
+        Self::from_wav(&fs::read(path)?, cpu_hz)
+    }
+
+    // Starts the tape from the beginning at the current cycle.
+    pub fn play(&mut self) {
+        self.start = Some(self.cycle);
+        self.level = false;
+    }
+
+    pub fn stop(&mut self) {
+        self.start = None;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.position().is_some()
+    }
+
+    // Sample under the tape head, if playing.
+    pub fn position(&self) -> Option<usize> {
+        let start = self.start?;
+        let elapsed = (self.cycle - start) as u128;
+        let position = (elapsed * self.sample_rate as u128 / self.cpu_hz as u128) as usize;
+        (position < self.samples.len()).then_some(position)
+    }
+
+    // Output of the zero-crossing detector. Silence keeps the last level.
+    pub fn get_level(&mut self) -> bool {
+        self.level = self.peek_level();
+        self.level
+    }
+
+    fn peek_level(&self) -> bool {
+        let Some(position) = self.position() else {
+            return false;
+        };
+        match self.samples[position] {
+            sample if sample > 0 => true,
+            sample if sample < 0 => false,
+            _ => self.level,
+        }
+    }
+}
+
+impl Device for Cassette {
+    fn peek(&self, _addr: Word) -> Byte {
+        match self.peek_level() {
+            true => LEVEL_HIGH,
+            false => 0x0,
+        }
+    }
+
+    fn read(&mut self, _addr: Word) -> Byte {
+        match self.get_level() {
+            true => LEVEL_HIGH,
+            false => 0x0,
+        }
+    }
+
+    fn write(&mut self, _addr: Word, _val: Byte) {}
+
+    fn tick(&mut self, cycle: u64) {
+        self.cycle = cycle;
+    }
+}
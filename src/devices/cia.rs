@@ -0,0 +1,685 @@
+use crate::{Byte, Word};
+
+use super::Device;
+
+const PRA: Word = 0x0;
+const PRB: Word = 0x1;
+const DDRA: Word = 0x2;
+const DDRB: Word = 0x3;
+const TA_LO: Word = 0x4;
+const TA_HI: Word = 0x5;
+const TB_LO: Word = 0x6;
+const TB_HI: Word = 0x7;
+const TOD_10THS: Word = 0x8;
+const TOD_SEC: Word = 0x9;
+const TOD_MIN: Word = 0xA;
+const TOD_HR: Word = 0xB;
+const SDR: Word = 0xC;
+const ICR: Word = 0xD;
+const CRA: Word = 0xE;
+const CRB: Word = 0xF;
+
+const REG_MASK: Word = 0x0F;
+
+const ICR_TA: Byte = 0x01;
+const ICR_TB: Byte = 0x02;
+const ICR_ALARM: Byte = 0x04;
+const ICR_SP: Byte = 0x08;
+const ICR_FLAG: Byte = 0x10;
+const ICR_IR: Byte = 0x80;
+const ICR_SET: Byte = 0x80;
+const ICR_SOURCES: Byte = 0x1F;
+
+const CR_START: Byte = 0x01;
+const CR_PBON: Byte = 0x02;
+const CR_TOGGLE: Byte = 0x04;
+const CR_ONE_SHOT: Byte = 0x08;
+const CR_LOAD: Byte = 0x10;
+const CRA_CNT: Byte = 0x20;
+const CRA_SP_OUTPUT: Byte = 0x40;
+const CRA_TOD_50HZ: Byte = 0x80;
+const CRB_INMODE_MASK: Byte = 0x60;
+const CRB_ALARM: Byte = 0x80;
+
+const PB6: Byte = 0x40;
+const PB7: Byte = 0x80;
+
+const TOD_MASKS: [Byte; 4] = [0x0F, 0x7F, 0x7F, 0x9F];
+const TOD_PM: Byte = 0x80;
+
+const DEFAULT_TOD_HZ: u64 = 60;
+
+// One of the two interval timers. The counter is loaded from the latch when
+// it underflows, when the high latch byte is written while the timer is
+// stopped and on a force load. A started timer first counts on the cycle
+// after the write to its control register and underflows on the count
+// after reaching zero, so in continuous mode it fires every latch + 1
+// counts.
+struct Timer {
+    counter: Word,
+    latch: Word,
+    control: Byte,
+    start_delay: bool,
+    toggle: bool,
+    pulse: bool,
+}
+
+impl Timer {
+    fn new() -> Self {
+        Self {
+            counter: 0xFFFF,
+            latch: 0xFFFF,
+            control: 0x0,
+            start_delay: false,
+            toggle: false,
+            pulse: false,
+        }
+    }
+
+    fn is_started(&self) -> bool {
+        self.control & CR_START != 0
+    }
+
+    // Level the timer drives on PB6 or PB7 when enabled in its control
+    // register.
+    fn output(&self) -> bool {
+        if self.control & CR_TOGGLE != 0 {
+            self.toggle
+        } else {
+            self.pulse
+        }
+    }
+
+    fn write_latch_lo(&mut self, val: Byte) {
+        self.latch = (self.latch & 0xFF00) | val as Word;
+    }
+
+    fn write_latch_hi(&mut self, val: Byte) {
+        self.latch = (self.latch & 0x00FF) | ((val as Word) << 8);
+        if !self.is_started() {
+            self.counter = self.latch;
+        }
+    }
+
+    fn write_control(&mut self, val: Byte) {
+        if val & CR_LOAD != 0 {
+            self.counter = self.latch;
+        }
+        if val & CR_START != 0 && !self.is_started() {
+            self.start_delay = true;
+            self.toggle = true;
+        }
+        self.control = val & !CR_LOAD;
+    }
+
+    // How many of the coming cycles only count the timer down without an
+    // underflow, when it counts every cycle.
+    fn get_quiet_cycles(&self, count: bool) -> u64 {
+        if !self.is_started() {
+            return u64::MAX;
+        }
+        if self.start_delay {
+            return 0;
+        }
+        if !count {
+            return u64::MAX;
+        }
+        self.counter as u64
+    }
+
+    fn skip(&mut self, cycles: u64, count: bool) {
+        self.pulse = false;
+        if self.is_started() && count {
+            self.counter -= cycles as Word;
+        }
+    }
+
+    // The cycle after `cycle` on which the timer next underflows, when it
+    // counts every cycle.
+    fn get_underflow(&self, cycle: u64, count: bool) -> Option<u64> {
+        if !self.is_started() || !count {
+            return None;
+        }
+        Some(cycle + self.start_delay as u64 + self.counter as u64 + 1)
+    }
+
+    // Returns whether the timer underflowed.
+    fn tick(&mut self, count: bool) -> bool {
+        self.pulse = false;
+        if !self.is_started() {
+            return false;
+        }
+        if self.start_delay {
+            self.start_delay = false;
+            return false;
+        }
+        if !count {
+            return false;
+        }
+        if self.counter != 0 {
+            self.counter -= 1;
+            return false;
+        }
+        self.counter = self.latch;
+        if self.control & CR_ONE_SHOT != 0 {
+            self.control &= !CR_START;
+        }
+        self.toggle = !self.toggle;
+        self.pulse = true;
+        true
+    }
+}
+
+// MOS 6526 Complex Interface Adapter. Registers are decoded from the low
+// four address bits.
+//
+// The time-of-day clock counts pulses on the TOD pin, which is driven at
+// `set_tod_frequency` Hz (60 by default) derived from the CPU clock; CRA
+// bit 7 must match the mains frequency for it to keep time. Reading the
+// hours register latches the clock until tenths are read, and writing the
+// hours register stops it until tenths are written. With CRB bit 7 set,
+// writes go to the alarm instead.
+//
+// The interrupt output is a single line: map the device with
+// `Line::Nmi` to wire it to NMI as on the second CIA of a C64.
+//
+// When brought up to date, stretches where the timers only count down are
+// taken in one go, and the time-of-day clock is worked out from the pulses
+// that arrived in between.
+pub struct Cia {
+    cycle: u64,
+    ora: Byte,
+    orb: Byte,
+    ddra: Byte,
+    ddrb: Byte,
+    input_a: Byte,
+    input_b: Byte,
+
+    timer_a: Timer,
+    timer_b: Timer,
+
+    tod: [Byte; 4],
+    alarm: [Byte; 4],
+    tod_latch: Option<[Byte; 4]>,
+    tod_stopped: bool,
+    tod_pulses: u8,
+    tod_phase: u64,
+    tod_hz: u64,
+    clock_hz: u64,
+
+    sdr: Byte,
+    sr: Byte,
+    sr_count: u8,
+    sr_running: bool,
+    sr_pending: bool,
+    serial_output: Vec<Byte>,
+
+    icr: Byte,
+    mask: Byte,
+
+    cnt: bool,
+    cnt_edge: bool,
+    cnt_out: bool,
+    sp: bool,
+    sp_out: bool,
+    flag: bool,
+}
+
+impl Cia {
+    pub fn new(clock_hz: u64) -> Self {
+        Self {
+            cycle: 0x0,
+            ora: 0x0,
+            orb: 0x0,
+            ddra: 0x0,
+            ddrb: 0x0,
+            input_a: 0xFF,
+            input_b: 0xFF,
+            timer_a: Timer::new(),
+            timer_b: Timer::new(),
+            tod: [0x0, 0x0, 0x0, 0x01],
+            alarm: [0x0; 4],
+            tod_latch: None,
+            tod_stopped: false,
+            tod_pulses: 0,
+            tod_phase: 0,
+            tod_hz: DEFAULT_TOD_HZ,
+            clock_hz,
+            sdr: 0x0,
+            sr: 0x0,
+            sr_count: 0,
+            sr_running: false,
+            sr_pending: false,
+            serial_output: vec![],
+            icr: 0x0,
+            mask: 0x0,
+            cnt: true,
+            cnt_edge: false,
+            cnt_out: true,
+            sp: true,
+            sp_out: true,
+            flag: true,
+        }
+    }
+
+    pub fn set_tod_frequency(&mut self, hz: u64) {
+        self.tod_hz = hz;
+    }
+
+    pub fn get_port_a(&self) -> Byte {
+        (self.ora & self.ddra) | (self.input_a & !self.ddra)
+    }
+
+    pub fn get_port_b(&self) -> Byte {
+        let mut val = (self.orb & self.ddrb) | (self.input_b & !self.ddrb);
+        if self.timer_a.control & CR_PBON != 0 {
+            val = (val & !PB6) | if self.timer_a.output() { PB6 } else { 0 };
+        }
+        if self.timer_b.control & CR_PBON != 0 {
+            val = (val & !PB7) | if self.timer_b.output() { PB7 } else { 0 };
+        }
+        val
+    }
+
+    pub fn get_ddra(&self) -> Byte {
+        self.ddra
+    }
+
+    pub fn get_ddrb(&self) -> Byte {
+        self.ddrb
+    }
+
+    pub fn set_port_a_input(&mut self, val: Byte) {
+        self.input_a = val;
+    }
+
+    pub fn set_port_b_input(&mut self, val: Byte) {
+        self.input_b = val;
+    }
+
+    pub fn get_timer_a(&self) -> Word {
+        self.timer_a.counter
+    }
+
+    pub fn get_timer_b(&self) -> Word {
+        self.timer_b.counter
+    }
+
+    // Tenths, seconds, minutes and hours in BCD, hours with bit 7 as PM.
+    pub fn get_tod(&self) -> [Byte; 4] {
+        self.tod
+    }
+
+    // CNT is an input unless the serial port is in output mode. A rising
+    // edge clocks timers counting CNT and shifts in the level on SP.
+    pub fn set_cnt(&mut self, level: bool) {
+        let rising = !self.cnt && level;
+        self.cnt = level;
+        if !rising {
+            return;
+        }
+        self.cnt_edge = true;
+        if self.timer_a.control & CRA_SP_OUTPUT == 0 {
+            self.shift_in();
+        }
+    }
+
+    pub fn set_sp(&mut self, level: bool) {
+        self.sp = level;
+    }
+
+    pub fn get_cnt(&self) -> bool {
+        if self.timer_a.control & CRA_SP_OUTPUT != 0 {
+            return self.cnt_out;
+        }
+        self.cnt
+    }
+
+    pub fn get_sp(&self) -> bool {
+        if self.timer_a.control & CRA_SP_OUTPUT != 0 {
+            return self.sp_out;
+        }
+        self.sp
+    }
+
+    // A falling edge on FLAG sets its interrupt flag.
+    pub fn set_flag(&mut self, level: bool) {
+        if self.flag && !level {
+            self.icr |= ICR_FLAG;
+        }
+        self.flag = level;
+    }
+
+    // Bytes that have been shifted out of the serial port since the last
+    // call.
+    pub fn take_serial_output(&mut self) -> Vec<Byte> {
+        std::mem::take(&mut self.serial_output)
+    }
+
+    fn peek_icr(&self) -> Byte {
+        let mut val = self.icr;
+        if self.icr & self.mask != 0 {
+            val |= ICR_IR;
+        }
+        val
+    }
+
+    fn read_icr(&mut self) -> Byte {
+        let val = self.peek_icr();
+        self.icr = 0x0;
+        val
+    }
+
+    fn write_icr(&mut self, val: Byte) {
+        if val & ICR_SET != 0 {
+            self.mask |= val & ICR_SOURCES;
+        } else {
+            self.mask &= !(val & ICR_SOURCES);
+        }
+    }
+
+    fn peek_tod(&self, index: usize) -> Byte {
+        self.tod_latch.unwrap_or(self.tod)[index]
+    }
+
+    fn read_tod(&mut self, index: usize) -> Byte {
+        let val = self.peek_tod(index);
+        match index {
+            0 => self.tod_latch = None,
+            3 => self.tod_latch = Some(self.tod),
+            _ => {}
+        }
+        val
+    }
+
+    fn write_tod(&mut self, index: usize, val: Byte) {
+        let val = val & TOD_MASKS[index];
+        if self.timer_b.control & CRB_ALARM != 0 {
+            self.alarm[index] = val;
+        } else {
+            self.tod[index] = val;
+            match index {
+                0 => {
+                    self.tod_stopped = false;
+                    self.tod_pulses = 0;
+                }
+                3 => self.tod_stopped = true,
+                _ => {}
+            }
+        }
+        self.check_alarm();
+    }
+
+    fn check_alarm(&mut self) {
+        if self.tod == self.alarm {
+            self.icr |= ICR_ALARM;
+        }
+    }
+
+    fn advance_tod(&mut self) {
+        self.tod[0] = (self.tod[0] + 1) % 10;
+        if self.tod[0] != 0 {
+            return;
+        }
+        self.tod[1] = bcd_increment(self.tod[1], 0x60);
+        if self.tod[1] != 0 {
+            return;
+        }
+        self.tod[2] = bcd_increment(self.tod[2], 0x60);
+        if self.tod[2] != 0 {
+            return;
+        }
+        let pm = self.tod[3] & TOD_PM;
+        self.tod[3] = match self.tod[3] & !TOD_PM {
+            0x11 => 0x12 | (pm ^ TOD_PM),
+            0x12 => 0x01 | pm,
+            hr => bcd_increment(hr, 0x13) | pm,
+        };
+    }
+
+    fn get_tod_divider(&self) -> u64 {
+        if self.timer_a.control & CRA_TOD_50HZ != 0 {
+            5
+        } else {
+            6
+        }
+    }
+
+    fn tick_tod(&mut self, cycles: u64) {
+        let phase = self.tod_phase + self.tod_hz * cycles;
+        self.tod_phase = phase % self.clock_hz;
+        if self.tod_stopped {
+            return;
+        }
+        let pulses = self.tod_pulses as u64 + phase / self.clock_hz;
+        let divider = self.get_tod_divider();
+        self.tod_pulses = (pulses % divider) as u8;
+        for _ in 0..pulses / divider {
+            self.advance_tod();
+            self.check_alarm();
+        }
+    }
+
+    // The cycle the clock next moves on a tenth of a second.
+    fn get_tod_advance(&self) -> Option<u64> {
+        if self.tod_stopped || self.tod_hz == 0 {
+            return None;
+        }
+        let pulses = self
+            .get_tod_divider()
+            .saturating_sub(self.tod_pulses as u64)
+            .max(1);
+        let phase = pulses * self.clock_hz - self.tod_phase;
+        Some(self.cycle + phase.div_ceil(self.tod_hz))
+    }
+
+    fn write_sdr(&mut self, val: Byte) {
+        self.sdr = val;
+        if self.timer_a.control & CRA_SP_OUTPUT != 0 {
+            self.sr_pending = true;
+        }
+    }
+
+    fn shift_in(&mut self) {
+        self.sr = (self.sr << 1) | self.sp as Byte;
+        self.sr_count += 1;
+        if self.sr_count == 8 {
+            self.sr_count = 0;
+            self.sdr = self.sr;
+            self.icr |= ICR_SP;
+        }
+    }
+
+    // In output mode every timer A underflow toggles CNT, so a byte takes
+    // sixteen underflows. Data changes on the falling edge of CNT, MSB
+    // first.
+    fn shift_out(&mut self) {
+        if !self.sr_running {
+            if !self.sr_pending {
+                return;
+            }
+            self.sr = self.sdr;
+            self.sr_pending = false;
+            self.sr_running = true;
+            self.sr_count = 0;
+        }
+        self.sr_count += 1;
+        if self.sr_count % 2 == 1 {
+            self.sp_out = self.sr & 0x80 != 0;
+            self.sr = self.sr.rotate_left(1);
+            self.cnt_out = false;
+            return;
+        }
+        self.cnt_out = true;
+        if self.sr_count == 16 {
+            self.sr_running = false;
+            self.icr |= ICR_SP;
+            self.serial_output.push(self.sr);
+        }
+    }
+
+    fn counts_phi2_a(&self) -> bool {
+        self.timer_a.control & CRA_CNT == 0
+    }
+
+    fn counts_phi2_b(&self) -> bool {
+        self.timer_b.control & CRB_INMODE_MASK == 0
+    }
+
+    fn counts_underflows_b(&self) -> bool {
+        (self.timer_b.control & CRB_INMODE_MASK) >> 5 >= 0b10
+    }
+
+    fn is_shifting_out(&self) -> bool {
+        self.timer_a.control & CRA_SP_OUTPUT != 0 && (self.sr_running || self.sr_pending)
+    }
+
+    // How many of the coming cycles leave everything but the counters and
+    // the time-of-day clock alone.
+    fn get_quiet_cycles(&self) -> u64 {
+        if self.cnt_edge {
+            return 0;
+        }
+        let a = self.timer_a.get_quiet_cycles(self.counts_phi2_a());
+        let b = self.timer_b.get_quiet_cycles(self.counts_phi2_b());
+        a.min(b)
+    }
+
+    fn skip(&mut self, cycles: u64) {
+        self.timer_a.skip(cycles, self.counts_phi2_a());
+        self.timer_b.skip(cycles, self.counts_phi2_b());
+        self.tick_tod(cycles);
+    }
+
+    fn step(&mut self) {
+        let cnt_edge = self.cnt_edge;
+        self.cnt_edge = false;
+
+        let count_a = self.timer_a.control & CRA_CNT == 0 || cnt_edge;
+        let underflow_a = self.timer_a.tick(count_a);
+        if underflow_a {
+            self.icr |= ICR_TA;
+            if self.timer_a.control & CRA_SP_OUTPUT != 0 {
+                self.shift_out();
+            }
+        }
+
+        let count_b = match (self.timer_b.control & CRB_INMODE_MASK) >> 5 {
+            0b00 => true,
+            0b01 => cnt_edge,
+            0b10 => underflow_a,
+            _ => underflow_a && self.cnt,
+        };
+        if self.timer_b.tick(count_b) {
+            self.icr |= ICR_TB;
+        }
+
+        self.tick_tod(1);
+    }
+}
+
+fn bcd_increment(val: Byte, wrap: Byte) -> Byte {
+    let val = if val & 0x0F >= 0x09 {
+        (val & 0xF0) + 0x10
+    } else {
+        val + 1
+    };
+    if val >= wrap {
+        0x0
+    } else {
+        val
+    }
+}
+
+impl Device for Cia {
+    fn peek(&self, addr: Word) -> Byte {
+        match addr & REG_MASK {
+            PRA => self.get_port_a(),
+            PRB => self.get_port_b(),
+            DDRA => self.ddra,
+            DDRB => self.ddrb,
+            TA_LO => self.timer_a.counter as Byte,
+            TA_HI => (self.timer_a.counter >> 8) as Byte,
+            TB_LO => self.timer_b.counter as Byte,
+            TB_HI => (self.timer_b.counter >> 8) as Byte,
+            TOD_10THS => self.peek_tod(0),
+            TOD_SEC => self.peek_tod(1),
+            TOD_MIN => self.peek_tod(2),
+            TOD_HR => self.peek_tod(3),
+            SDR => self.sdr,
+            ICR => self.peek_icr(),
+            CRA => self.timer_a.control,
+            CRB => self.timer_b.control,
+            _ => unreachable!(),
+        }
+    }
+
+    fn read(&mut self, addr: Word) -> Byte {
+        match addr & REG_MASK {
+            TOD_10THS => self.read_tod(0),
+            TOD_HR => self.read_tod(3),
+            ICR => self.read_icr(),
+            _ => self.peek(addr),
+        }
+    }
+
+    fn write(&mut self, addr: Word, val: Byte) {
+        match addr & REG_MASK {
+            PRA => self.ora = val,
+            PRB => self.orb = val,
+            DDRA => self.ddra = val,
+            DDRB => self.ddrb = val,
+            TA_LO => self.timer_a.write_latch_lo(val),
+            TA_HI => self.timer_a.write_latch_hi(val),
+            TB_LO => self.timer_b.write_latch_lo(val),
+            TB_HI => self.timer_b.write_latch_hi(val),
+            TOD_10THS => self.write_tod(0, val),
+            TOD_SEC => self.write_tod(1, val),
+            TOD_MIN => self.write_tod(2, val),
+            TOD_HR => self.write_tod(3, val),
+            SDR => self.write_sdr(val),
+            ICR => self.write_icr(val),
+            CRA => self.timer_a.write_control(val),
+            CRB => self.timer_b.write_control(val),
+            _ => unreachable!(),
+        }
+    }
+
+    fn tick(&mut self, cycle: u64) {
+        while self.cycle < cycle {
+            let cycles = self.get_quiet_cycles().min(cycle - self.cycle);
+            if cycles > 0 {
+                self.skip(cycles);
+                self.cycle += cycles;
+            } else {
+                self.step();
+                self.cycle += 1;
+            }
+        }
+    }
+
+    // Interrupt sources only matter while the output is clear, as nothing
+    // but a read of the ICR clears it again. Bytes going out of the serial
+    // port are handed to the host as they finish either way.
+    fn get_next_event(&self) -> Option<u64> {
+        let idle = !self.irq();
+        let masked = |source: Byte| idle && self.mask & source != 0;
+        let underflow_b = self.counts_underflows_b() && self.timer_b.is_started();
+        let a = masked(ICR_TA) || masked(ICR_TB) && underflow_b || self.is_shifting_out();
+        [
+            a.then(|| self.timer_a.get_underflow(self.cycle, self.counts_phi2_a()))
+                .flatten(),
+            masked(ICR_TB)
+                .then(|| self.timer_b.get_underflow(self.cycle, self.counts_phi2_b()))
+                .flatten(),
+            masked(ICR_ALARM).then(|| self.get_tod_advance()).flatten(),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+    }
+
+    fn irq(&self) -> bool {
+        self.icr & self.mask != 0
+    }
+}
@@ -0,0 +1,182 @@
+use crate::Byte;
+
+use super::I2cDevice;
+
+const REGISTERS_LENGTH: usize = 0x40;
+
+const SECONDS: usize = 0x0;
+const MINUTES: usize = 0x1;
+const HOURS: usize = 0x2;
+const DAY: usize = 0x3;
+const DATE: usize = 0x4;
+const MONTH: usize = 0x5;
+const YEAR: usize = 0x6;
+const TIME_LENGTH: usize = 0x7;
+
+const CLOCK_HALT: Byte = 0x80;
+const HOURS_12: Byte = 0x40;
+const HOURS_PM: Byte = 0x20;
+
+// Maxim DS1307 real-time clock on I2C address 0x68: seven BCD timekeeping
+// registers, the control register and 56 bytes of RAM behind an
+// auto-incrementing register pointer. The time is copied to a read buffer
+// when the device is addressed, so a multi-byte read is consistent, and
+// writing the seconds register restarts the one-second countdown.
+//
+// Time advances with the CPU cycles passed to `tick`. Like the real part,
+// the oscillator starts halted until the CH bit is cleared.
+pub struct Ds1307 {
+    registers: [Byte; REGISTERS_LENGTH],
+    latched: [Byte; TIME_LENGTH],
+    pointer: usize,
+    pointer_pending: bool,
+    clock_hz: u64,
+    countdown: u64,
+}
+
+impl Ds1307 {
+    pub const ADDRESS: Byte = 0x68;
+
+    pub fn new(clock_hz: u64) -> Self {
+        let mut registers = [0x0; REGISTERS_LENGTH];
+        registers[SECONDS] = CLOCK_HALT;
+        registers[DAY] = 0x01;
+        registers[DATE] = 0x01;
+        registers[MONTH] = 0x01;
+        Self {
+            registers,
+            latched: [0x0; TIME_LENGTH],
+            pointer: 0,
+            pointer_pending: false,
+            clock_hz,
+            countdown: clock_hz,
+        }
+    }
+
+    pub fn get_registers(&self) -> &[Byte] {
+        &self.registers
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.registers[SECONDS] & CLOCK_HALT != 0
+    }
+
+    fn advance_second(&mut self) {
+        let r = &mut self.registers;
+        r[SECONDS] = bcd_increment(r[SECONDS], 0x60);
+        if r[SECONDS] != 0 {
+            return;
+        }
+        r[MINUTES] = bcd_increment(r[MINUTES], 0x60);
+        if r[MINUTES] != 0 {
+            return;
+        }
+        if !self.advance_hour() {
+            return;
+        }
+        let r = &mut self.registers;
+        r[DAY] = r[DAY] % 7 + 1;
+        let year = from_bcd(r[YEAR]);
+        let month = from_bcd(r[MONTH]);
+        r[DATE] = bcd_increment(r[DATE], to_bcd(days_in_month(month, year) + 1));
+        if r[DATE] != 0 {
+            return;
+        }
+        r[DATE] = 0x01;
+        r[MONTH] = bcd_increment(r[MONTH], 0x13);
+        if r[MONTH] != 0 {
+            return;
+        }
+        r[MONTH] = 0x01;
+        r[YEAR] = bcd_increment(r[YEAR], 0xA0);
+    }
+
+    // Returns whether the day rolled over.
+    fn advance_hour(&mut self) -> bool {
+        let hours = self.registers[HOURS];
+        if hours & HOURS_12 == 0 {
+            self.registers[HOURS] = bcd_increment(hours & 0x3F, 0x24);
+            return self.registers[HOURS] == 0;
+        }
+        let pm = hours & HOURS_PM;
+        self.registers[HOURS] = HOURS_12
+            | match hours & 0x1F {
+                0x11 => 0x12 | (pm ^ HOURS_PM),
+                0x12 => 0x01 | pm,
+                hour => bcd_increment(hour, 0x13) | pm,
+            };
+        hours & 0x1F == 0x11 && pm != 0
+    }
+}
+
+fn bcd_increment(val: Byte, wrap: Byte) -> Byte {
+    let val = if val & 0x0F >= 0x09 {
+        (val & 0xF0) + 0x10
+    } else {
+        val + 1
+    };
+    if val >= wrap {
+        0x0
+    } else {
+        val
+    }
+}
+
+fn from_bcd(val: Byte) -> u8 {
+    (val >> 4) * 10 + (val & 0x0F)
+}
+
+fn to_bcd(val: u8) -> Byte {
+    ((val / 10) << 4) | (val % 10)
+}
+
+// The year is 00-99 in 2000-2099, where every fourth year is a leap year.
+fn days_in_month(month: u8, year: u8) -> u8 {
+    match month {
+        2 if year.is_multiple_of(4) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+impl I2cDevice for Ds1307 {
+    fn start(&mut self, read: bool) {
+        self.latched.copy_from_slice(&self.registers[..TIME_LENGTH]);
+        self.pointer_pending = !read;
+    }
+
+    fn write(&mut self, val: Byte) -> bool {
+        if self.pointer_pending {
+            self.pointer_pending = false;
+            self.pointer = val as usize % REGISTERS_LENGTH;
+            return true;
+        }
+        self.registers[self.pointer] = val;
+        if self.pointer == SECONDS {
+            self.countdown = self.clock_hz;
+        }
+        self.pointer = (self.pointer + 1) % REGISTERS_LENGTH;
+        true
+    }
+
+    fn read(&mut self) -> Byte {
+        let val = match self.latched.get(self.pointer) {
+            Some(val) => *val,
+            None => self.registers[self.pointer],
+        };
+        self.pointer = (self.pointer + 1) % REGISTERS_LENGTH;
+        val
+    }
+
+    fn tick(&mut self, _cycle: u64) {
+        if self.is_halted() {
+            return;
+        }
+        self.countdown -= 1;
+        if self.countdown == 0 {
+            self.countdown = self.clock_hz;
+            self.advance_second();
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use crate::Byte;
+
+use super::I2cDevice;
+
+// Microchip 24LC-series serial EEPROM, e.g. `Eeprom24::new(0x8000, 64)` for
+// a 24LC256. Parts up to 256 bytes take a one-byte word address, larger
+// ones two bytes; the 24LC04-24LC16 block select bits are not modelled.
+//
+// Writes wrap around within the current page and reads run on
+// sequentially, wrapping around at the end of memory. Writes complete
+// immediately, so acknowledge polling always succeeds.
+pub struct Eeprom24 {
+    data: Vec<Byte>,
+    page_size: usize,
+    addr: usize,
+    addr_bytes: usize,
+    pending_addr: usize,
+}
+
+impl Eeprom24 {
+    pub fn new(size: usize, page_size: usize) -> Self {
+        assert!(size.is_power_of_two() && page_size.is_power_of_two());
+        Self {
+            data: vec![0xFF; size],
+            page_size,
+            addr: 0,
+            addr_bytes: if size > 0x100 { 2 } else { 1 },
+            pending_addr: 0,
+        }
+    }
+
+    pub fn get_data(&self) -> &[Byte] {
+        &self.data
+    }
+
+    pub fn load(&mut self, data: &[Byte]) {
+        let len = data.len().min(self.data.len());
+        self.data[..len].copy_from_slice(&data[..len]);
+    }
+
+    pub fn get_address(&self) -> usize {
+        self.addr
+    }
+}
+
+impl I2cDevice for Eeprom24 {
+    fn start(&mut self, read: bool) {
+        if !read {
+            self.pending_addr = self.addr_bytes;
+        }
+    }
+
+    fn write(&mut self, val: Byte) -> bool {
+        if self.pending_addr > 0 {
+            self.pending_addr -= 1;
+            self.addr = ((self.addr << 8) | val as usize) & (self.data.len() - 1);
+            return true;
+        }
+        self.data[self.addr] = val;
+        let page = self.addr & !(self.page_size - 1);
+        self.addr = page | ((self.addr + 1) & (self.page_size - 1));
+        true
+    }
+
+    fn read(&mut self) -> Byte {
+        let val = self.data[self.addr];
+        self.addr = (self.addr + 1) & (self.data.len() - 1);
+        val
+    }
+}
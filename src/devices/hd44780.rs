@@ -0,0 +1,371 @@
+use crate::Byte;
+
+const DDRAM_LENGTH: usize = 0x80;
+const CGRAM_LENGTH: usize = 0x40;
+const LINE_LENGTH: usize = 0x28;
+const SECOND_LINE: usize = 0x40;
+
+const CMD_CLEAR: Byte = 0x01;
+const CMD_HOME: Byte = 0x02;
+const CMD_ENTRY_MODE: Byte = 0x04;
+const CMD_DISPLAY: Byte = 0x08;
+const CMD_SHIFT: Byte = 0x10;
+const CMD_FUNCTION: Byte = 0x20;
+const CMD_CGRAM_ADDR: Byte = 0x40;
+const CMD_DDRAM_ADDR: Byte = 0x80;
+
+const ENTRY_INCREMENT: Byte = 0x02;
+const ENTRY_SHIFT: Byte = 0x01;
+const DISPLAY_ON: Byte = 0x04;
+const DISPLAY_CURSOR: Byte = 0x02;
+const DISPLAY_BLINK: Byte = 0x01;
+const SHIFT_DISPLAY: Byte = 0x08;
+const SHIFT_RIGHT: Byte = 0x04;
+const FUNCTION_8BIT: Byte = 0x10;
+const FUNCTION_2LINE: Byte = 0x08;
+
+const BUSY: Byte = 0x80;
+
+const LONG_EXECUTION_US: u64 = 1_520;
+const EXECUTION_US: u64 = 37;
+const WRITE_EXECUTION_US: u64 = 41;
+
+// Hitachi HD44780 character LCD controller with the A00 character ROM,
+// driven through its RS, R/W, E and DB0-DB7 pins. Writes are latched on the
+// falling edge of E. For reads the controller drives the data pins while E
+// is high. In 4-bit mode only DB4-DB7 are used, high nibble first.
+//
+// Instructions take their datasheet execution time, measured in CPU cycles
+// from the cycles passed to `tick`. Anything written while the busy flag is
+// set is ignored.
+pub struct Hd44780 {
+    columns: usize,
+    rows: usize,
+    clock_hz: u64,
+    cycle: u64,
+    busy_until: u64,
+
+    ddram: [Byte; DDRAM_LENGTH],
+    cgram: [Byte; CGRAM_LENGTH],
+    ac: usize,
+    cgram_selected: bool,
+    shift: usize,
+
+    entry_mode: Byte,
+    display: Byte,
+    function: Byte,
+
+    rs: bool,
+    rw: bool,
+    e: bool,
+    nibble: Option<Byte>,
+    read_low: bool,
+    output: Option<Byte>,
+}
+
+impl Hd44780 {
+    pub fn new(columns: usize, rows: usize, clock_hz: u64) -> Self {
+        Self {
+            columns,
+            rows,
+            clock_hz,
+            cycle: 0,
+            busy_until: 0,
+            ddram: [b' '; DDRAM_LENGTH],
+            cgram: [0x0; CGRAM_LENGTH],
+            ac: 0,
+            cgram_selected: false,
+            shift: 0,
+            entry_mode: ENTRY_INCREMENT,
+            display: 0x0,
+            function: FUNCTION_8BIT,
+            rs: false,
+            rw: false,
+            e: false,
+            nibble: None,
+            read_low: false,
+            output: None,
+        }
+    }
+
+    pub fn tick(&mut self, cycle: u64) {
+        self.cycle = cycle;
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.cycle < self.busy_until
+    }
+
+    // Drives the control and data pins. Only DB4-DB7 matter in 4-bit mode.
+    pub fn set_pins(&mut self, rs: bool, rw: bool, e: bool, data: Byte) {
+        let rising = !self.e && e;
+        let falling = self.e && !e;
+        self.rs = rs;
+        self.rw = rw;
+        self.e = e;
+
+        if rising && rw {
+            self.output = Some(self.read_output());
+        }
+        if !falling {
+            return;
+        }
+        self.output = None;
+        if rw {
+            self.finish_read();
+        } else {
+            self.latch_write(data);
+        }
+    }
+
+    // Level driven on DB0-DB7, if any. The controller only drives the bus
+    // while R/W and E are high.
+    pub fn get_data(&self) -> Option<Byte> {
+        self.output
+    }
+
+    pub fn is_display_on(&self) -> bool {
+        self.display & DISPLAY_ON != 0
+    }
+
+    pub fn is_four_bit(&self) -> bool {
+        self.function & FUNCTION_8BIT == 0
+    }
+
+    pub fn get_address(&self) -> usize {
+        self.ac
+    }
+
+    pub fn get_ddram(&self) -> &[Byte] {
+        &self.ddram
+    }
+
+    pub fn get_cgram(&self) -> &[Byte] {
+        &self.cgram
+    }
+
+    // Column and row of the cursor on the visible display, if it is shown
+    // and within view.
+    pub fn get_cursor(&self) -> Option<(usize, usize)> {
+        if !self.is_display_on() || self.display & (DISPLAY_CURSOR | DISPLAY_BLINK) == 0 {
+            return None;
+        }
+        if self.cgram_selected {
+            return None;
+        }
+        (0..self.rows)
+            .flat_map(|row| (0..self.columns).map(move |column| (column, row)))
+            .find(|&(column, row)| self.ddram_index(column, row) == self.ac)
+    }
+
+    // The characters in view on one row. Blank while the display is off.
+    pub fn get_line(&self, row: usize) -> String {
+        (0..self.columns)
+            .map(|column| match self.is_display_on() {
+                true => to_char(self.ddram[self.ddram_index(column, row)]),
+                false => ' ',
+            })
+            .collect()
+    }
+
+    // All rows, one per line. Characters outside printable ASCII, including
+    // the custom CGRAM characters, are shown as '?'.
+    pub fn get_text(&self) -> String {
+        (0..self.rows)
+            .map(|row| self.get_line(row))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn is_two_line(&self) -> bool {
+        self.function & FUNCTION_2LINE != 0
+    }
+
+    fn line_length(&self) -> usize {
+        if self.is_two_line() {
+            LINE_LENGTH
+        } else {
+            LINE_LENGTH * 2
+        }
+    }
+
+    // DDRAM address shown at a position, following the display shift. Rows
+    // beyond the controller's lines continue them, so on a 20x4 module the
+    // third row follows on from the first.
+    fn ddram_index(&self, column: usize, row: usize) -> usize {
+        let length = self.line_length();
+        if !self.is_two_line() {
+            return (row * self.columns + column + self.shift) % length;
+        }
+        let base = match row % 2 {
+            0 => 0x0,
+            _ => SECOND_LINE,
+        };
+        base + ((row / 2) * self.columns + column + self.shift) % length
+    }
+
+    fn next_ddram_address(&self, increment: bool) -> usize {
+        if !self.is_two_line() {
+            let length = LINE_LENGTH * 2;
+            return match increment {
+                true => (self.ac + 1) % length,
+                false => (self.ac + length - 1) % length,
+            };
+        }
+        match (self.ac, increment) {
+            (0x27, true) => SECOND_LINE,
+            (0x67, true) => 0x0,
+            (0x0, false) => 0x67,
+            (0x40, false) => 0x27,
+            (ac, true) => ac + 1,
+            (ac, false) => ac - 1,
+        }
+    }
+
+    fn step_address(&mut self) {
+        let increment = self.entry_mode & ENTRY_INCREMENT != 0;
+        if self.cgram_selected {
+            self.ac = match increment {
+                true => (self.ac + 1) % CGRAM_LENGTH,
+                false => (self.ac + CGRAM_LENGTH - 1) % CGRAM_LENGTH,
+            };
+            return;
+        }
+        self.ac = self.next_ddram_address(increment);
+    }
+
+    fn shift_display(&mut self, right: bool) {
+        let length = self.line_length();
+        self.shift = match right {
+            true => (self.shift + length - 1) % length,
+            false => (self.shift + 1) % length,
+        };
+    }
+
+    fn set_busy(&mut self, us: u64) {
+        self.busy_until = self.cycle + us * self.clock_hz / 1_000_000;
+    }
+
+    fn read_byte(&self) -> Byte {
+        if self.rs {
+            return match self.cgram_selected {
+                true => self.cgram[self.ac],
+                false => self.ddram[self.ac],
+            };
+        }
+        let busy = if self.is_busy() { BUSY } else { 0x0 };
+        busy | self.ac as Byte
+    }
+
+    fn read_output(&self) -> Byte {
+        let val = self.read_byte();
+        match (self.is_four_bit(), self.read_low) {
+            (false, _) => val,
+            (true, false) => val & 0xF0,
+            (true, true) => val << 4,
+        }
+    }
+
+    fn finish_read(&mut self) {
+        if self.is_four_bit() {
+            self.read_low = !self.read_low;
+            if self.read_low {
+                return;
+            }
+        }
+        if self.rs {
+            self.step_address();
+        }
+    }
+
+    fn latch_write(&mut self, data: Byte) {
+        self.read_low = false;
+        if !self.is_four_bit() {
+            self.write(data);
+            return;
+        }
+        match self.nibble.take() {
+            None => self.nibble = Some(data & 0xF0),
+            Some(high) => self.write(high | (data >> 4)),
+        }
+    }
+
+    fn write(&mut self, val: Byte) {
+        if self.is_busy() {
+            return;
+        }
+        if self.rs {
+            self.write_data(val);
+        } else {
+            self.write_instruction(val);
+        }
+    }
+
+    fn write_data(&mut self, val: Byte) {
+        if self.cgram_selected {
+            self.cgram[self.ac] = val & 0x1F;
+        } else {
+            self.ddram[self.ac] = val;
+        }
+        self.step_address();
+        if !self.cgram_selected && self.entry_mode & ENTRY_SHIFT != 0 {
+            self.shift_display(self.entry_mode & ENTRY_INCREMENT == 0);
+        }
+        self.set_busy(WRITE_EXECUTION_US);
+    }
+
+    fn write_instruction(&mut self, val: Byte) {
+        if val == 0x0 {
+            return;
+        }
+        let command = 0x80 >> val.leading_zeros();
+        let mut us = EXECUTION_US;
+        match command {
+            CMD_DDRAM_ADDR => {
+                self.ac = (val & 0x7F) as usize;
+                self.cgram_selected = false;
+            }
+            CMD_CGRAM_ADDR => {
+                self.ac = (val & 0x3F) as usize;
+                self.cgram_selected = true;
+            }
+            CMD_FUNCTION => {
+                self.function = val;
+                self.nibble = None;
+            }
+            CMD_SHIFT => {
+                let right = val & SHIFT_RIGHT != 0;
+                if val & SHIFT_DISPLAY != 0 {
+                    self.shift_display(right);
+                } else {
+                    self.ac = self.next_ddram_address(right);
+                }
+            }
+            CMD_DISPLAY => self.display = val,
+            CMD_ENTRY_MODE => self.entry_mode = val,
+            CMD_HOME => {
+                self.ac = 0;
+                self.cgram_selected = false;
+                self.shift = 0;
+                us = LONG_EXECUTION_US;
+            }
+            CMD_CLEAR => {
+                self.ddram = [b' '; DDRAM_LENGTH];
+                self.ac = 0;
+                self.cgram_selected = false;
+                self.shift = 0;
+                self.entry_mode |= ENTRY_INCREMENT;
+                us = LONG_EXECUTION_US;
+            }
+            _ => unreachable!(),
+        }
+        self.set_busy(us);
+    }
+}
+
+fn to_char(val: Byte) -> char {
+    match val {
+        0x20..=0x7D => val as char,
+        _ => '?',
+    }
+}
@@ -0,0 +1,233 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::Byte;
+
+// A byte-oriented I2C target.
+pub trait I2cDevice {
+    // Called when the device has been addressed after a (repeated) start.
+    fn start(&mut self, _read: bool) {}
+
+    // A byte written by the controller. Returns whether to acknowledge it.
+    fn write(&mut self, val: Byte) -> bool;
+
+    // The next byte to send to the controller.
+    fn read(&mut self) -> Byte;
+
+    fn stop(&mut self) {}
+
+    fn tick(&mut self, _cycle: u64) {}
+}
+
+// Which bits of a GPIO port the bus is wired to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct I2cPins {
+    pub scl: Byte,
+    pub sda: Byte,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Idle,
+    Address,
+    Write,
+    Read,
+}
+
+// Decodes a bit-banged I2C bus from the levels the controller drives on
+// SCL and SDA, with true meaning released. Both lines are open drain, so
+// the level on SDA is low if either side pulls it low. Targets are
+// addressed with 7-bit addresses; clock stretching is not used.
+pub struct I2c {
+    devices: Vec<(Byte, Rc<RefCell<dyn I2cDevice>>)>,
+    pins: I2cPins,
+    active: Option<usize>,
+    reading: bool,
+    state: State,
+
+    scl: bool,
+    sda: bool,
+    bits: u8,
+    shift: Byte,
+    pull_low: bool,
+    acked: bool,
+}
+
+impl I2c {
+    pub fn new(pins: I2cPins) -> Self {
+        Self {
+            devices: vec![],
+            pins,
+            active: None,
+            reading: false,
+            state: State::Idle,
+            scl: true,
+            sda: true,
+            bits: 0,
+            shift: 0x0,
+            pull_low: false,
+            acked: false,
+        }
+    }
+
+    pub fn attach(&mut self, addr: Byte, device: Rc<RefCell<dyn I2cDevice>>) {
+        self.devices.push((addr, device));
+    }
+
+    pub fn tick(&mut self, cycle: u64) {
+        for (_, device) in &self.devices {
+            device.borrow_mut().tick(cycle);
+        }
+    }
+
+    pub fn set_pins(&mut self, scl: bool, sda: bool) {
+        let old_scl = self.scl;
+        let old_sda = self.sda;
+        self.scl = scl;
+        self.sda = sda;
+
+        if scl && old_scl {
+            if old_sda && !sda {
+                self.start();
+            } else if !old_sda && sda {
+                self.stop();
+            }
+            return;
+        }
+        if scl && !old_scl {
+            self.clock_rising();
+        } else if !scl && old_scl {
+            self.clock_falling();
+        }
+    }
+
+    pub fn get_scl(&self) -> bool {
+        self.scl
+    }
+
+    pub fn get_sda(&self) -> bool {
+        self.sda && !self.pull_low
+    }
+
+    // Takes a GPIO port wired open drain: a line is released unless its
+    // direction bit is an output driving low.
+    pub fn set_port(&mut self, output: Byte, ddr: Byte) {
+        let released = output | !ddr;
+        self.set_pins(released & self.pins.scl != 0, released & self.pins.sda != 0);
+    }
+
+    // Bus levels on the input side of a GPIO port.
+    pub fn drive_port(&self, input: Byte) -> Byte {
+        let mut val = input & !(self.pins.scl | self.pins.sda);
+        if self.get_scl() {
+            val |= self.pins.scl;
+        }
+        if self.get_sda() {
+            val |= self.pins.sda;
+        }
+        val
+    }
+
+    fn device(&self) -> Option<&Rc<RefCell<dyn I2cDevice>>> {
+        self.active.map(|index| &self.devices[index].1)
+    }
+
+    fn start(&mut self) {
+        self.state = State::Address;
+        self.active = None;
+        self.bits = 0;
+        self.pull_low = false;
+    }
+
+    fn stop(&mut self) {
+        if let Some(device) = self.device() {
+            device.borrow_mut().stop();
+        }
+        self.state = State::Idle;
+        self.active = None;
+        self.pull_low = false;
+    }
+
+    fn clock_rising(&mut self) {
+        match self.state {
+            State::Idle => return,
+            State::Address | State::Write => {
+                if self.bits < 8 {
+                    self.shift = (self.shift << 1) | self.sda as Byte;
+                }
+            }
+            State::Read => {
+                if self.bits == 8 {
+                    self.acked = !self.sda;
+                }
+            }
+        }
+        self.bits += 1;
+    }
+
+    fn clock_falling(&mut self) {
+        match self.state {
+            State::Idle => {}
+            State::Address | State::Write => match self.bits {
+                8 => self.pull_low = self.receive(),
+                9 => {
+                    self.pull_low = false;
+                    self.bits = 0;
+                    if self.state == State::Address {
+                        self.begin_transfer();
+                    }
+                }
+                _ => {}
+            },
+            State::Read => match self.bits {
+                0..=7 => self.pull_low = (self.shift << self.bits) & 0x80 == 0,
+                8 => self.pull_low = false,
+                _ => {
+                    self.bits = 0;
+                    if self.acked {
+                        self.load();
+                    } else {
+                        self.state = State::Idle;
+                    }
+                }
+            },
+        }
+    }
+
+    // Handles a complete byte from the controller and returns whether it is
+    // acknowledged.
+    fn receive(&mut self) -> bool {
+        if self.state == State::Write {
+            return match self.device() {
+                Some(device) => device.borrow_mut().write(self.shift),
+                None => false,
+            };
+        }
+        let addr = self.shift >> 1;
+        self.reading = self.shift & 0x1 != 0;
+        self.active = self.devices.iter().position(|(a, _)| *a == addr);
+        self.active.is_some()
+    }
+
+    // After the address has been acknowledged.
+    fn begin_transfer(&mut self) {
+        let Some(device) = self.device() else {
+            self.state = State::Idle;
+            return;
+        };
+        device.borrow_mut().start(self.reading);
+        if self.reading {
+            self.state = State::Read;
+            self.load();
+        } else {
+            self.state = State::Write;
+        }
+    }
+
+    fn load(&mut self) {
+        self.shift = match self.device() {
+            Some(device) => device.borrow_mut().read(),
+            None => 0xFF,
+        };
+        self.pull_low = self.shift & 0x80 == 0;
+    }
+}
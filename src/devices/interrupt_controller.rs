@@ -0,0 +1,268 @@
+use std::{cell::RefCell, collections::VecDeque, fmt, rc::Rc};
+
+use crate::{Byte, Word};
+
+use super::Device;
+
+const STATUS: Word = 0x0;
+const MASK: Word = 0x1;
+const ACK: Word = 0x2;
+const CONTROL: Word = 0x3;
+
+const REG_MASK: Word = 0x03;
+
+const CONTROL_ENABLE: Byte = 0x01;
+
+const MAX_SOURCES: usize = 8;
+const NO_SOURCE: Byte = 0xFF;
+
+// Assertions kept for `get_events`; older ones are dropped.
+const EVENTS_LENGTH: usize = 256;
+
+// How a source's request is held.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    // Pending while the source asserts its output.
+    Level,
+    // Latched on the rising edge until acknowledged.
+    Edge,
+}
+
+struct Source {
+    name: String,
+    device: Option<Rc<RefCell<dyn Device>>>,
+    input: bool,
+    trigger: Trigger,
+    priority: Byte,
+    previous: bool,
+    latched: bool,
+    count: u64,
+}
+
+impl Source {
+    fn level(&self) -> bool {
+        self.input
+            || self
+                .device
+                .as_ref()
+                .is_some_and(|device| device.borrow().irq())
+    }
+
+    fn pending(&self) -> bool {
+        match self.trigger {
+            Trigger::Level => self.level(),
+            Trigger::Edge => self.latched,
+        }
+    }
+}
+
+// Interrupt controller combining up to eight IRQ sources into one output,
+// mapped on the CPU's IRQ line in place of the wired-OR. Sources are either
+// devices, whose own output is then mapped on `Line::Unconnected`, or inputs
+// driven by the host with `set_input`. Four registers, mirrored through the
+// mapped range:
+//
+//   0  status (read): bit n set while source n is pending, mask aside
+//      (write): 1 bits clear latched edge requests
+//   1  mask: bit n enables source n, all enabled after reset
+//   2  acknowledge (read): number of the highest priority enabled pending
+//      source, or $FF, and clears its latched edge request
+//      (write): clears the latched edge request of the source numbered
+//   3  control: bit 0 enables the output, set after reset
+//
+// Sources are numbered in the order they are added. The highest priority
+// wins, and the lowest number between equal priorities. Each assertion is
+// counted and logged with its cycle, and `Display` shows which sources are
+// holding the line, to track down interrupt storms.
+pub struct InterruptController {
+    sources: Vec<Source>,
+    mask: Byte,
+    control: Byte,
+    cycle: u64,
+    events: VecDeque<(u64, usize)>,
+}
+
+impl Default for InterruptController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InterruptController {
+    pub fn new() -> Self {
+        Self {
+            sources: vec![],
+            mask: 0xFF,
+            control: CONTROL_ENABLE,
+            cycle: 0,
+            events: VecDeque::new(),
+        }
+    }
+
+    // Adds a device's IRQ output as the next source and returns its number.
+    pub fn add_source(&mut self, name: &str, device: Rc<RefCell<dyn Device>>) -> usize {
+        self.push_source(name, Some(device))
+    }
+
+    // Adds a source driven by `set_input` and returns its number.
+    pub fn add_input(&mut self, name: &str) -> usize {
+        self.push_source(name, None)
+    }
+
+    pub fn set_input(&mut self, source: usize, level: bool) {
+        self.sources[source].input = level;
+    }
+
+    pub fn set_trigger(&mut self, source: usize, trigger: Trigger) {
+        self.sources[source].trigger = trigger;
+    }
+
+    pub fn set_priority(&mut self, source: usize, priority: Byte) {
+        self.sources[source].priority = priority;
+    }
+
+    pub fn get_name(&self, source: usize) -> &str {
+        &self.sources[source].name
+    }
+
+    // Bit n set while source n is pending.
+    pub fn get_pending(&self) -> Byte {
+        self.sources
+            .iter()
+            .enumerate()
+            .filter(|(_, source)| source.pending())
+            .fold(0x0, |bits, (n, _)| bits | (0x1 << n))
+    }
+
+    // Highest priority source that is pending and enabled.
+    pub fn get_active(&self) -> Option<usize> {
+        let enabled = self.get_pending() & self.mask;
+        (0..self.sources.len())
+            .filter(|n| enabled & (0x1 << n) != 0)
+            .min_by_key(|n| (Byte::MAX - self.sources[*n].priority, *n))
+    }
+
+    // Name of the source that would be acknowledged next.
+    pub fn get_cause(&self) -> Option<&str> {
+        self.get_active().map(|source| self.get_name(source))
+    }
+
+    // Times each source has asserted its output.
+    pub fn get_counts(&self) -> Vec<u64> {
+        self.sources.iter().map(|source| source.count).collect()
+    }
+
+    // Recent assertions as (cycle, source name), oldest first.
+    pub fn get_events(&self) -> Vec<(u64, &str)> {
+        self.events
+            .iter()
+            .map(|(cycle, source)| (*cycle, self.get_name(*source)))
+            .collect()
+    }
+
+    fn push_source(&mut self, name: &str, device: Option<Rc<RefCell<dyn Device>>>) -> usize {
+        assert!(
+            self.sources.len() < MAX_SOURCES,
+            "at most {MAX_SOURCES} interrupt sources"
+        );
+        self.sources.push(Source {
+            name: name.to_string(),
+            device,
+            input: false,
+            trigger: Trigger::Level,
+            priority: 0,
+            previous: false,
+            latched: false,
+            count: 0,
+        });
+        self.sources.len() - 1
+    }
+
+    fn clear(&mut self, bits: Byte) {
+        for (n, source) in self.sources.iter_mut().enumerate() {
+            if bits & (0x1 << n) != 0 {
+                source.latched = false;
+            }
+        }
+    }
+
+    fn acknowledge(&mut self) -> Byte {
+        let Some(source) = self.get_active() else {
+            return NO_SOURCE;
+        };
+        self.sources[source].latched = false;
+        source as Byte
+    }
+}
+
+impl Device for InterruptController {
+    fn peek(&self, addr: Word) -> Byte {
+        match addr & REG_MASK {
+            STATUS => self.get_pending(),
+            MASK => self.mask,
+            ACK => self.get_active().map_or(NO_SOURCE, |source| source as Byte),
+            CONTROL => self.control,
+            _ => unreachable!(),
+        }
+    }
+
+    fn read(&mut self, addr: Word) -> Byte {
+        match addr & REG_MASK {
+            ACK => self.acknowledge(),
+            _ => self.peek(addr),
+        }
+    }
+
+    fn write(&mut self, addr: Word, val: Byte) {
+        match addr & REG_MASK {
+            STATUS => self.clear(val),
+            MASK => self.mask = val,
+            ACK => self.clear(0x1_u8.checked_shl(val as u32).unwrap_or(0x0)),
+            CONTROL => self.control = val,
+            _ => unreachable!(),
+        }
+    }
+
+    fn tick(&mut self, cycle: u64) {
+        self.cycle = cycle;
+        for (n, source) in self.sources.iter_mut().enumerate() {
+            let level = source.level();
+            if level && !source.previous {
+                source.count += 1;
+                source.latched |= source.trigger == Trigger::Edge;
+                if self.events.len() == EVENTS_LENGTH {
+                    self.events.pop_front();
+                }
+                self.events.push_back((cycle, n));
+            }
+            source.previous = level;
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.control & CONTROL_ENABLE != 0 && self.get_active().is_some()
+    }
+}
+
+impl fmt::Display for InterruptController {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.get_cause() {
+            Some(cause) if self.irq() => writeln!(f, "IRQ asserted by {cause}")?,
+            Some(cause) => writeln!(f, "IRQ disabled, {cause} pending")?,
+            None => writeln!(f, "IRQ idle")?,
+        }
+        for (n, source) in self.sources.iter().enumerate() {
+            let state = match (source.pending(), self.mask & (0x1 << n) != 0) {
+                (true, true) => "pending",
+                (true, false) => "pending, masked",
+                (false, _) => "idle",
+            };
+            writeln!(
+                f,
+                "  {n}: {} ({:?}, priority {}) {state}, asserted {} times",
+                source.name, source.trigger, source.priority, source.count
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,373 @@
+use crate::{Byte, Word};
+
+use super::Device;
+
+const PORT_A: Word = 0x0;
+const CRA: Word = 0x1;
+const PORT_B: Word = 0x2;
+const CRB: Word = 0x3;
+
+const REG_MASK: Word = 0x03;
+
+const CR_C1_IRQ: Byte = 0x01;
+const CR_C1_POSITIVE: Byte = 0x02;
+const CR_OR_SELECT: Byte = 0x04;
+const CR_C2_IRQ: Byte = 0x08;
+const CR_C2_POSITIVE: Byte = 0x10;
+const CR_C2_OUTPUT: Byte = 0x20;
+const CR_IRQ2: Byte = 0x40;
+const CR_IRQ1: Byte = 0x80;
+const CR_WRITABLE: Byte = 0x3F;
+
+// Something attached to one side of a PIA, such as a keyboard or a display.
+pub trait PiaPeripheral {
+    // Polled when the PIA is brought up to date while the last byte has
+    // been taken, i.e. the C1 flag is clear: before each access, and every
+    // cycle if C1 is to interrupt the CPU. A byte returned is put on the
+    // port's input pins and strobed in with an active C1 edge.
+    fn strobe(&mut self) -> Option<Byte> {
+        None
+    }
+
+    // Called with the port's pins whenever the CPU writes its output
+    // register.
+    fn output(&mut self, _val: Byte) {}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum C2Mode {
+    Input,
+    Handshake,
+    Pulse,
+    Low,
+    High,
+}
+
+// One half of the PIA: a port, its data direction and control registers,
+// and the C1/C2 control lines.
+struct Side {
+    or: Byte,
+    ddr: Byte,
+    cr: Byte,
+    input: Byte,
+    irq1: bool,
+    irq2: bool,
+    c1: bool,
+    c2: bool,
+    c2_out: bool,
+    c2_pulse: bool,
+    peripheral: Option<Box<dyn PiaPeripheral>>,
+}
+
+impl Side {
+    fn new() -> Self {
+        Self {
+            or: 0x0,
+            ddr: 0x0,
+            cr: 0x0,
+            input: 0xFF,
+            irq1: false,
+            irq2: false,
+            c1: true,
+            c2: true,
+            c2_out: true,
+            c2_pulse: false,
+            peripheral: None,
+        }
+    }
+
+    fn c2_mode(&self) -> C2Mode {
+        if self.cr & CR_C2_OUTPUT == 0 {
+            return C2Mode::Input;
+        }
+        match (self.cr & (CR_C2_POSITIVE | CR_C2_IRQ)) >> 3 {
+            0b00 => C2Mode::Handshake,
+            0b01 => C2Mode::Pulse,
+            0b10 => C2Mode::Low,
+            _ => C2Mode::High,
+        }
+    }
+
+    fn pins(&self) -> Byte {
+        (self.or & self.ddr) | (self.input & !self.ddr)
+    }
+
+    fn read_cr(&self) -> Byte {
+        let mut val = self.cr;
+        if self.irq1 {
+            val |= CR_IRQ1;
+        }
+        if self.irq2 {
+            val |= CR_IRQ2;
+        }
+        val
+    }
+
+    fn write_cr(&mut self, val: Byte) {
+        self.cr = val & CR_WRITABLE;
+        match self.c2_mode() {
+            C2Mode::Input => {}
+            C2Mode::Handshake | C2Mode::Pulse => {
+                self.c2_out = true;
+                self.irq2 = false;
+            }
+            C2Mode::Low => {
+                self.c2_out = false;
+                self.irq2 = false;
+            }
+            C2Mode::High => {
+                self.c2_out = true;
+                self.irq2 = false;
+            }
+        }
+    }
+
+    fn write_data(&mut self, val: Byte) {
+        if self.cr & CR_OR_SELECT == 0 {
+            self.ddr = val;
+            return;
+        }
+        self.or = val;
+        let pins = self.pins();
+        if let Some(peripheral) = self.peripheral.as_mut() {
+            peripheral.output(pins);
+        }
+    }
+
+    // Reading the output register clears both interrupt flags.
+    fn peek_data(&self) -> Option<Byte> {
+        (self.cr & CR_OR_SELECT != 0).then(|| self.pins())
+    }
+
+    fn read_data(&mut self) -> Option<Byte> {
+        let val = self.peek_data()?;
+        self.irq1 = false;
+        self.irq2 = false;
+        Some(val)
+    }
+
+    // Handshake or pulse output on C2 after a read of port A or a write of
+    // port B.
+    fn strobe_c2(&mut self) {
+        match self.c2_mode() {
+            C2Mode::Handshake => self.c2_out = false,
+            C2Mode::Pulse => {
+                self.c2_out = false;
+                self.c2_pulse = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn set_c1(&mut self, level: bool) {
+        let old = self.c1;
+        self.c1 = level;
+        if old == level || level != (self.cr & CR_C1_POSITIVE != 0) {
+            return;
+        }
+        self.irq1 = true;
+        if self.c2_mode() == C2Mode::Handshake {
+            self.c2_out = true;
+        }
+    }
+
+    fn set_c2(&mut self, level: bool) {
+        let old = self.c2;
+        self.c2 = level;
+        if self.c2_mode() != C2Mode::Input {
+            return;
+        }
+        if old != level && level == (self.cr & CR_C2_POSITIVE != 0) {
+            self.irq2 = true;
+        }
+    }
+
+    fn get_c2(&self) -> bool {
+        match self.c2_mode() {
+            C2Mode::Input => self.c2,
+            _ => self.c2_out,
+        }
+    }
+
+    fn irq(&self) -> bool {
+        let c2_irq = self.c2_mode() == C2Mode::Input && self.cr & CR_C2_IRQ != 0;
+        (self.irq1 && self.cr & CR_C1_IRQ != 0) || (self.irq2 && c2_irq)
+    }
+
+    // Whether a byte from the peripheral would interrupt the CPU, so it has
+    // to be polled without waiting for an access.
+    fn is_polling(&self) -> bool {
+        self.peripheral.is_some() && !self.irq1 && self.cr & CR_C1_IRQ != 0
+    }
+
+    fn tick(&mut self) {
+        if self.c2_pulse {
+            self.c2_pulse = false;
+            self.c2_out = true;
+        }
+        if self.irq1 {
+            return;
+        }
+        let Some(val) = self.peripheral.as_mut().and_then(|p| p.strobe()) else {
+            return;
+        };
+        self.input = val;
+        let active = self.cr & CR_C1_POSITIVE != 0;
+        self.set_c1(!active);
+        self.set_c1(active);
+    }
+}
+
+// Motorola 6821 Peripheral Interface Adapter. RS0 and RS1 are the low two
+// address bits, and bit 2 of each control register selects between the
+// data direction and output registers.
+//
+// IRQA and IRQB are separate outputs; `irq` reports either, as on boards
+// that wire both to the CPU.
+pub struct Pia {
+    cycle: u64,
+    a: Side,
+    b: Side,
+}
+
+impl Default for Pia {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Pia {
+    pub fn new() -> Self {
+        Self {
+            cycle: 0x0,
+            a: Side::new(),
+            b: Side::new(),
+        }
+    }
+
+    pub fn set_peripheral_a(&mut self, peripheral: Box<dyn PiaPeripheral>) {
+        self.a.peripheral = Some(peripheral);
+    }
+
+    pub fn set_peripheral_b(&mut self, peripheral: Box<dyn PiaPeripheral>) {
+        self.b.peripheral = Some(peripheral);
+    }
+
+    pub fn get_port_a(&self) -> Byte {
+        self.a.pins()
+    }
+
+    pub fn get_port_b(&self) -> Byte {
+        self.b.pins()
+    }
+
+    pub fn get_ddra(&self) -> Byte {
+        self.a.ddr
+    }
+
+    pub fn get_ddrb(&self) -> Byte {
+        self.b.ddr
+    }
+
+    pub fn set_port_a_input(&mut self, val: Byte) {
+        self.a.input = val;
+    }
+
+    pub fn set_port_b_input(&mut self, val: Byte) {
+        self.b.input = val;
+    }
+
+    pub fn set_ca1(&mut self, level: bool) {
+        self.a.set_c1(level);
+    }
+
+    pub fn set_ca2(&mut self, level: bool) {
+        self.a.set_c2(level);
+    }
+
+    pub fn set_cb1(&mut self, level: bool) {
+        self.b.set_c1(level);
+    }
+
+    pub fn set_cb2(&mut self, level: bool) {
+        self.b.set_c2(level);
+    }
+
+    pub fn get_ca2(&self) -> bool {
+        self.a.get_c2()
+    }
+
+    pub fn get_cb2(&self) -> bool {
+        self.b.get_c2()
+    }
+
+    pub fn get_irqa(&self) -> bool {
+        self.a.irq()
+    }
+
+    pub fn get_irqb(&self) -> bool {
+        self.b.irq()
+    }
+}
+
+impl Device for Pia {
+    fn peek(&self, addr: Word) -> Byte {
+        match addr & REG_MASK {
+            PORT_A => self.a.peek_data().unwrap_or(self.a.ddr),
+            CRA => self.a.read_cr(),
+            PORT_B => self.b.peek_data().unwrap_or(self.b.ddr),
+            CRB => self.b.read_cr(),
+            _ => unreachable!(),
+        }
+    }
+
+    fn read(&mut self, addr: Word) -> Byte {
+        match addr & REG_MASK {
+            PORT_A => match self.a.read_data() {
+                Some(val) => {
+                    self.a.strobe_c2();
+                    val
+                }
+                None => self.a.ddr,
+            },
+            CRA => self.a.read_cr(),
+            PORT_B => self.b.read_data().unwrap_or(self.b.ddr),
+            CRB => self.b.read_cr(),
+            _ => unreachable!(),
+        }
+    }
+
+    fn write(&mut self, addr: Word, val: Byte) {
+        match addr & REG_MASK {
+            PORT_A => self.a.write_data(val),
+            CRA => self.a.write_cr(val),
+            PORT_B => {
+                self.b.write_data(val);
+                if self.b.cr & CR_OR_SELECT != 0 {
+                    self.b.strobe_c2();
+                }
+            }
+            CRB => self.b.write_cr(val),
+            _ => unreachable!(),
+        }
+    }
+
+    // A C2 pulse lasts a cycle, and the peripherals only need polling
+    // once however long it has been, so the cycles in between don't matter.
+    fn tick(&mut self, cycle: u64) {
+        if cycle <= self.cycle {
+            return;
+        }
+        self.cycle = cycle;
+        self.a.tick();
+        self.b.tick();
+    }
+
+    fn get_next_event(&self) -> Option<u64> {
+        (self.a.is_polling() || self.b.is_polling()).then_some(self.cycle + 1)
+    }
+
+    fn irq(&self) -> bool {
+        self.a.irq() || self.b.irq()
+    }
+}
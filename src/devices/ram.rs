@@ -0,0 +1,51 @@
+use std::ops::RangeInclusive;
+
+use crate::{Byte, Word};
+
+use super::Device;
+
+// Read/write memory starting at `base`. Like `Rom`, it repeats through a
+// mapped range larger than itself, as RAM with incompletely decoded
+// address lines does.
+pub struct Ram {
+    base: Word,
+    data: Vec<Byte>,
+}
+
+impl Ram {
+    pub fn new(base: Word, length: usize) -> Self {
+        assert!(length > 0, "RAM is empty");
+        assert!(
+            base as usize + length <= 0x10000,
+            "RAM runs past the end of memory"
+        );
+        Self {
+            base,
+            data: vec![0x0; length],
+        }
+    }
+
+    // The addresses covered by the RAM itself.
+    pub fn get_range(&self) -> RangeInclusive<Word> {
+        self.base..=self.base + (self.data.len() - 1) as Word
+    }
+
+    pub fn get_data(&self) -> &[Byte] {
+        &self.data
+    }
+
+    pub fn get_mut_data(&mut self) -> &mut [Byte] {
+        &mut self.data
+    }
+}
+
+impl Device for Ram {
+    fn peek(&self, addr: Word) -> Byte {
+        self.data[addr.wrapping_sub(self.base) as usize % self.data.len()]
+    }
+
+    fn write(&mut self, addr: Word, val: Byte) {
+        let len = self.data.len();
+        self.data[addr.wrapping_sub(self.base) as usize % len] = val;
+    }
+}
@@ -0,0 +1,265 @@
+use crate::{Byte, Word};
+
+use super::Device;
+
+const RAM_LENGTH: usize = 0x80;
+const RAM_MASK: Word = 0x7F;
+
+const DRA: Word = 0x0;
+const DDRA: Word = 0x1;
+const DRB: Word = 0x2;
+const DDRB: Word = 0x3;
+
+const A0: Word = 0x01;
+const A1: Word = 0x02;
+const A2: Word = 0x04;
+const A3: Word = 0x08;
+const A4: Word = 0x10;
+
+const DEFAULT_RS_MASK: Word = 0x0200;
+
+const FLAG_TIMER: Byte = 0x80;
+const FLAG_PA7: Byte = 0x40;
+
+const PA7: Byte = 0x80;
+
+const PRESCALERS: [u16; 4] = [1, 8, 64, 1024];
+
+// MOS 6532 RAM-I/O-Timer. RS is taken from the address bus: with it low
+// the 128 bytes of RAM are selected, with it high the ports and timer. By
+// default RS is A9 as on the Atari 2600 and KIM-style boards.
+//
+// Writing the timer loads it and it first decrements on the following
+// cycle, then once per prescaler interval. When it passes zero the timer
+// flag is set and the count continues once per cycle until the timer is
+// written again.
+pub struct Riot {
+    ram: [Byte; RAM_LENGTH],
+    rs_mask: Word,
+
+    ora: Byte,
+    orb: Byte,
+    ddra: Byte,
+    ddrb: Byte,
+    input_a: Byte,
+    input_b: Byte,
+
+    cycle: u64,
+    timer: Byte,
+    interval: u16,
+    prescaler: u16,
+    timer_load: bool,
+    timer_irq: bool,
+
+    pa7_positive: bool,
+    pa7_irq: bool,
+    flags: Byte,
+}
+
+impl Default for Riot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Riot {
+    pub fn new() -> Self {
+        Self::with_rs_mask(DEFAULT_RS_MASK)
+    }
+
+    pub fn with_rs_mask(rs_mask: Word) -> Self {
+        Self {
+            ram: [0x0; RAM_LENGTH],
+            rs_mask,
+            ora: 0x0,
+            orb: 0x0,
+            ddra: 0x0,
+            ddrb: 0x0,
+            input_a: 0xFF,
+            input_b: 0xFF,
+            cycle: 0x0,
+            timer: 0x0,
+            interval: 1024,
+            prescaler: 1024,
+            timer_load: false,
+            timer_irq: false,
+            pa7_positive: false,
+            pa7_irq: false,
+            flags: 0x0,
+        }
+    }
+
+    pub fn get_port_a(&self) -> Byte {
+        (self.ora & self.ddra) | (self.input_a & !self.ddra)
+    }
+
+    pub fn get_port_b(&self) -> Byte {
+        (self.orb & self.ddrb) | (self.input_b & !self.ddrb)
+    }
+
+    pub fn get_ddra(&self) -> Byte {
+        self.ddra
+    }
+
+    pub fn get_ddrb(&self) -> Byte {
+        self.ddrb
+    }
+
+    pub fn set_port_a_input(&mut self, val: Byte) {
+        let old = self.get_port_a() & PA7 != 0;
+        self.input_a = val;
+        self.detect_pa7_edge(old);
+    }
+
+    pub fn set_port_b_input(&mut self, val: Byte) {
+        self.input_b = val;
+    }
+
+    pub fn get_timer(&self) -> Byte {
+        self.timer
+    }
+
+    pub fn get_ram(&self) -> &[Byte] {
+        &self.ram
+    }
+
+    fn detect_pa7_edge(&mut self, old: bool) {
+        let new = self.get_port_a() & PA7 != 0;
+        if old != new && new == self.pa7_positive {
+            self.flags |= FLAG_PA7;
+        }
+    }
+
+    fn get_cycles_to_underflow(&self) -> u64 {
+        self.prescaler as u64 + self.timer as u64 * self.interval as u64
+    }
+
+    // Counts `cycles` cycles off the timer at once, the same as ticking it
+    // that many times.
+    fn count(&mut self, cycles: u64) {
+        let prescaler = self.prescaler as u64;
+        let interval = self.interval as u64;
+        let underflow = self.get_cycles_to_underflow();
+        if cycles < prescaler {
+            self.prescaler -= cycles as u16;
+        } else if cycles < underflow {
+            let rest = cycles - prescaler;
+            self.timer -= (1 + rest / interval) as Byte;
+            self.prescaler = (interval - rest % interval) as u16;
+        } else {
+            self.timer = 0xFF_u8.wrapping_sub((cycles - underflow) as Byte);
+            self.flags |= FLAG_TIMER;
+            self.interval = 1;
+            self.prescaler = 1;
+        }
+    }
+
+    fn write_timer(&mut self, addr: Word, val: Byte) {
+        self.timer = val;
+        self.interval = PRESCALERS[(addr & (A1 | A0)) as usize];
+        self.prescaler = 1;
+        self.timer_load = true;
+        self.timer_irq = addr & A3 != 0;
+        self.flags &= !FLAG_TIMER;
+    }
+
+    fn peek_io(&self, addr: Word) -> Byte {
+        if addr & A2 == 0 {
+            return match addr & (A1 | A0) {
+                DRA => self.get_port_a(),
+                DDRA => self.ddra,
+                DRB => self.get_port_b(),
+                DDRB => self.ddrb,
+                _ => unreachable!(),
+            };
+        }
+        if addr & A0 == 0 {
+            return self.timer;
+        }
+        self.flags
+    }
+
+    fn read_io(&mut self, addr: Word) -> Byte {
+        let val = self.peek_io(addr);
+        if addr & A2 == 0 {
+            return val;
+        }
+        if addr & A0 == 0 {
+            self.timer_irq = addr & A3 != 0;
+            self.flags &= !FLAG_TIMER;
+        } else {
+            self.flags &= !FLAG_PA7;
+        }
+        val
+    }
+
+    fn write_io(&mut self, addr: Word, val: Byte) {
+        if addr & A2 == 0 {
+            let old = self.get_port_a() & PA7 != 0;
+            match addr & (A1 | A0) {
+                DRA => self.ora = val,
+                DDRA => self.ddra = val,
+                DRB => self.orb = val,
+                DDRB => self.ddrb = val,
+                _ => unreachable!(),
+            }
+            self.detect_pa7_edge(old);
+            return;
+        }
+        if addr & A4 != 0 {
+            self.write_timer(addr, val);
+            return;
+        }
+        self.pa7_positive = addr & A0 != 0;
+        self.pa7_irq = addr & A1 != 0;
+    }
+}
+
+impl Device for Riot {
+    fn peek(&self, addr: Word) -> Byte {
+        if addr & self.rs_mask == 0 {
+            return self.ram[(addr & RAM_MASK) as usize];
+        }
+        self.peek_io(addr)
+    }
+
+    fn read(&mut self, addr: Word) -> Byte {
+        if addr & self.rs_mask == 0 {
+            return self.ram[(addr & RAM_MASK) as usize];
+        }
+        self.read_io(addr)
+    }
+
+    fn write(&mut self, addr: Word, val: Byte) {
+        if addr & self.rs_mask == 0 {
+            self.ram[(addr & RAM_MASK) as usize] = val;
+            return;
+        }
+        self.write_io(addr, val);
+    }
+
+    fn tick(&mut self, cycle: u64) {
+        if cycle <= self.cycle {
+            return;
+        }
+        let mut cycles = cycle - self.cycle;
+        self.cycle = cycle;
+        if self.timer_load {
+            self.timer_load = false;
+            cycles -= 1;
+        }
+        self.count(cycles);
+    }
+
+    fn get_next_event(&self) -> Option<u64> {
+        if !self.timer_irq || self.flags & FLAG_TIMER != 0 {
+            return None;
+        }
+        Some(self.cycle + self.timer_load as u64 + self.get_cycles_to_underflow())
+    }
+
+    fn irq(&self) -> bool {
+        (self.timer_irq && self.flags & FLAG_TIMER != 0)
+            || (self.pa7_irq && self.flags & FLAG_PA7 != 0)
+    }
+}
@@ -0,0 +1,76 @@
+use crate::{Byte, Word};
+
+use super::Device;
+
+const GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+// Seeded pseudo-random number generator for paravirtual entropy. Every read,
+// at any address in the mapped range, returns the next byte of a SplitMix64
+// stream, so the same seed always gives the same bytes. Each write shifts a
+// byte into the seed, low byte last, and restarts the stream from it.
+pub struct Rng {
+    seed: u64,
+    state: u64,
+    buffer: u64,
+    remaining: u8,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            state: seed,
+            buffer: 0,
+            remaining: 0,
+        }
+    }
+
+    pub fn get_seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        *self = Self::new(seed);
+    }
+
+    pub fn next_byte(&mut self) -> Byte {
+        if self.remaining == 0 {
+            self.state = self.state.wrapping_add(GAMMA);
+            self.buffer = mix(self.state);
+            self.remaining = 8;
+        }
+        let byte = self.buffer as Byte;
+        self.buffer >>= 8;
+        self.remaining -= 1;
+        byte
+    }
+
+    // The byte `next_byte` would return, leaving the stream where it is.
+    pub fn peek_byte(&self) -> Byte {
+        match self.remaining {
+            0 => mix(self.state.wrapping_add(GAMMA)) as Byte,
+            _ => self.buffer as Byte,
+        }
+    }
+}
+
+fn mix(state: u64) -> u64 {
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+impl Device for Rng {
+    fn peek(&self, _addr: Word) -> Byte {
+        self.peek_byte()
+    }
+
+    fn read(&mut self, _addr: Word) -> Byte {
+        self.next_byte()
+    }
+
+    fn write(&mut self, _addr: Word, val: Byte) {
+        self.set_seed((self.seed << 8) | val as u64);
+    }
+}
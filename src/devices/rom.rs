@@ -0,0 +1,44 @@
+use std::ops::RangeInclusive;
+
+use crate::{Byte, Word};
+
+use super::Device;
+
+// Read-only memory starting at `base`. Writes are ignored, and an image
+// smaller than the mapped range is mirrored through it, as with partially
+// decoded ROM sockets.
+pub struct Rom {
+    base: Word,
+    data: Vec<Byte>,
+}
+
+impl Rom {
+    pub fn new(base: Word, data: &[Byte]) -> Self {
+        assert!(!data.is_empty(), "ROM image is empty");
+        assert!(
+            base as usize + data.len() <= 0x10000,
+            "ROM image runs past the end of memory"
+        );
+        Self {
+            base,
+            data: data.to_vec(),
+        }
+    }
+
+    // The addresses covered by the image itself.
+    pub fn get_range(&self) -> RangeInclusive<Word> {
+        self.base..=self.base + (self.data.len() - 1) as Word
+    }
+
+    pub fn get_data(&self) -> &[Byte] {
+        &self.data
+    }
+}
+
+impl Device for Rom {
+    fn peek(&self, addr: Word) -> Byte {
+        self.data[addr.wrapping_sub(self.base) as usize % self.data.len()]
+    }
+
+    fn write(&mut self, _addr: Word, _val: Byte) {}
+}
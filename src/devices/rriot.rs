@@ -0,0 +1,257 @@
+use std::ops::RangeInclusive;
+
+use crate::{Byte, Word};
+
+use super::Device;
+
+const ROM_LENGTH: usize = 0x400;
+const RAM_LENGTH: usize = 0x40;
+const IO_LENGTH: Word = 0x40;
+const IO_MASK: Word = 0x0F;
+
+const DRA: Word = 0x0;
+const DDRA: Word = 0x1;
+const DRB: Word = 0x2;
+const DDRB: Word = 0x3;
+
+const A0: Word = 0x01;
+const A1: Word = 0x02;
+const A2: Word = 0x04;
+const A3: Word = 0x08;
+
+const FLAG_TIMER: Byte = 0x80;
+
+const PRESCALERS: [u16; 4] = [1, 8, 64, 1024];
+
+// MOS 6530 ROM-RAM-I/O-Timer. Each part has 1K of mask ROM, 64 bytes of
+// RAM, two 8-bit ports and an interval timer. The chip selects are decoded
+// by the board, so the three blocks are placed at their own base addresses
+// and the device is mapped once per block.
+//
+// In the I/O block A2 low selects the ports. With A2 high a write loads the
+// timer with A0-A1 picking the prescaler and A3 enabling the interrupt; a
+// read with A0 low returns the count, A3 again setting the interrupt enable,
+// and with A0 high the flags, where bit 7 is the timer flag. The timer
+// counts like the 6532's: once per prescaler interval and, after passing
+// zero, once per cycle.
+pub struct Rriot {
+    rom: [Byte; ROM_LENGTH],
+    ram: [Byte; RAM_LENGTH],
+    rom_base: Word,
+    ram_base: Word,
+    io_base: Word,
+
+    ora: Byte,
+    orb: Byte,
+    ddra: Byte,
+    ddrb: Byte,
+    input_a: Byte,
+    input_b: Byte,
+
+    cycle: u64,
+    timer: Byte,
+    interval: u16,
+    prescaler: u16,
+    timer_load: bool,
+    timer_irq: bool,
+    flags: Byte,
+}
+
+impl Rriot {
+    pub const ROM_LENGTH: usize = ROM_LENGTH;
+
+    pub fn new(rom: &[Byte], rom_base: Word, ram_base: Word, io_base: Word) -> Self {
+        assert!(rom.len() <= ROM_LENGTH, "6530 ROM image is larger than 1K");
+        let mut image = [0x0; ROM_LENGTH];
+        image[..rom.len()].copy_from_slice(rom);
+        Self {
+            rom: image,
+            ram: [0x0; RAM_LENGTH],
+            rom_base,
+            ram_base,
+            io_base,
+            ora: 0x0,
+            orb: 0x0,
+            ddra: 0x0,
+            ddrb: 0x0,
+            input_a: 0xFF,
+            input_b: 0xFF,
+            cycle: 0x0,
+            timer: 0x0,
+            interval: 1024,
+            prescaler: 1024,
+            timer_load: false,
+            timer_irq: false,
+            flags: 0x0,
+        }
+    }
+
+    pub fn get_rom_range(&self) -> RangeInclusive<Word> {
+        self.rom_base..=self.rom_base + (ROM_LENGTH - 1) as Word
+    }
+
+    pub fn get_ram_range(&self) -> RangeInclusive<Word> {
+        self.ram_base..=self.ram_base + (RAM_LENGTH - 1) as Word
+    }
+
+    pub fn get_io_range(&self) -> RangeInclusive<Word> {
+        self.io_base..=self.io_base + IO_LENGTH - 1
+    }
+
+    pub fn get_port_a(&self) -> Byte {
+        (self.ora & self.ddra) | (self.input_a & !self.ddra)
+    }
+
+    pub fn get_port_b(&self) -> Byte {
+        (self.orb & self.ddrb) | (self.input_b & !self.ddrb)
+    }
+
+    pub fn get_ddra(&self) -> Byte {
+        self.ddra
+    }
+
+    pub fn get_ddrb(&self) -> Byte {
+        self.ddrb
+    }
+
+    pub fn set_port_a_input(&mut self, val: Byte) {
+        self.input_a = val;
+    }
+
+    pub fn set_port_b_input(&mut self, val: Byte) {
+        self.input_b = val;
+    }
+
+    pub fn get_timer(&self) -> Byte {
+        self.timer
+    }
+
+    pub fn get_ram(&self) -> &[Byte] {
+        &self.ram
+    }
+
+    fn get_cycles_to_underflow(&self) -> u64 {
+        self.prescaler as u64 + self.timer as u64 * self.interval as u64
+    }
+
+    // Counts `cycles` cycles off the timer at once, the same as ticking it
+    // that many times.
+    fn count(&mut self, cycles: u64) {
+        let prescaler = self.prescaler as u64;
+        let interval = self.interval as u64;
+        let underflow = self.get_cycles_to_underflow();
+        if cycles < prescaler {
+            self.prescaler -= cycles as u16;
+        } else if cycles < underflow {
+            let rest = cycles - prescaler;
+            self.timer -= (1 + rest / interval) as Byte;
+            self.prescaler = (interval - rest % interval) as u16;
+        } else {
+            self.timer = 0xFF_u8.wrapping_sub((cycles - underflow) as Byte);
+            self.flags |= FLAG_TIMER;
+            self.interval = 1;
+            self.prescaler = 1;
+        }
+    }
+
+    fn write_timer(&mut self, addr: Word, val: Byte) {
+        self.timer = val;
+        self.interval = PRESCALERS[(addr & (A1 | A0)) as usize];
+        self.prescaler = 1;
+        self.timer_load = true;
+        self.timer_irq = addr & A3 != 0;
+        self.flags &= !FLAG_TIMER;
+    }
+
+    fn peek_io(&self, addr: Word) -> Byte {
+        if addr & A2 == 0 {
+            return match addr & (A1 | A0) {
+                DRA => self.get_port_a(),
+                DDRA => self.ddra,
+                DRB => self.get_port_b(),
+                DDRB => self.ddrb,
+                _ => unreachable!(),
+            };
+        }
+        if addr & A0 == 0 {
+            return self.timer;
+        }
+        self.flags
+    }
+
+    fn read_io(&mut self, addr: Word) -> Byte {
+        if addr & A2 != 0 && addr & A0 == 0 {
+            self.timer_irq = addr & A3 != 0;
+            self.flags &= !FLAG_TIMER;
+        }
+        self.peek_io(addr)
+    }
+
+    fn write_io(&mut self, addr: Word, val: Byte) {
+        if addr & A2 != 0 {
+            self.write_timer(addr, val);
+            return;
+        }
+        match addr & (A1 | A0) {
+            DRA => self.ora = val,
+            DDRA => self.ddra = val,
+            DRB => self.orb = val,
+            DDRB => self.ddrb = val,
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Device for Rriot {
+    fn peek(&self, addr: Word) -> Byte {
+        if self.get_rom_range().contains(&addr) {
+            return self.rom[(addr - self.rom_base) as usize];
+        }
+        if self.get_ram_range().contains(&addr) {
+            return self.ram[(addr - self.ram_base) as usize];
+        }
+        self.peek_io(addr.wrapping_sub(self.io_base) & IO_MASK)
+    }
+
+    fn read(&mut self, addr: Word) -> Byte {
+        if self.get_rom_range().contains(&addr) || self.get_ram_range().contains(&addr) {
+            return self.peek(addr);
+        }
+        self.read_io(addr.wrapping_sub(self.io_base) & IO_MASK)
+    }
+
+    fn write(&mut self, addr: Word, val: Byte) {
+        if self.get_rom_range().contains(&addr) {
+            return;
+        }
+        if self.get_ram_range().contains(&addr) {
+            self.ram[(addr - self.ram_base) as usize] = val;
+            return;
+        }
+        self.write_io(addr.wrapping_sub(self.io_base) & IO_MASK, val);
+    }
+
+    fn tick(&mut self, cycle: u64) {
+        if cycle <= self.cycle {
+            return;
+        }
+        let mut cycles = cycle - self.cycle;
+        self.cycle = cycle;
+        if self.timer_load {
+            self.timer_load = false;
+            cycles -= 1;
+        }
+        self.count(cycles);
+    }
+
+    fn get_next_event(&self) -> Option<u64> {
+        if !self.timer_irq || self.flags & FLAG_TIMER != 0 {
+            return None;
+        }
+        Some(self.cycle + self.timer_load as u64 + self.get_cycles_to_underflow())
+    }
+
+    fn irq(&self) -> bool {
+        self.timer_irq && self.flags & FLAG_TIMER != 0
+    }
+}
@@ -0,0 +1,242 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{Byte, Word};
+
+use super::Device;
+
+const SECONDS: Word = 0x0;
+const MINUTES: Word = 0x1;
+const HOURS: Word = 0x2;
+const WEEKDAY: Word = 0x3;
+const DATE: Word = 0x4;
+const MONTH: Word = 0x5;
+const YEAR: Word = 0x6;
+const CENTURY: Word = 0x7;
+const CONTROL: Word = 0x8;
+const UNIX_0: Word = 0x9;
+const UNIX_3: Word = 0xC;
+
+const REG_MASK: Word = 0x0F;
+
+const CONTROL_BINARY: Byte = 0x01;
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+// Where the clock gets the time from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeSource {
+    // The host's wall clock, in UTC.
+    Host,
+    // Starts at the given Unix time and advances with the CPU cycles passed
+    // to `tick`, so runs are reproducible.
+    Fixed(u64),
+}
+
+// Paravirtual real-time clock. Sixteen registers, mirrored through the
+// mapped range:
+//
+//   0  seconds         5  month (1-12)
+//   1  minutes         6  year within the century
+//   2  hours (0-23)    7  century
+//   3  weekday (0-6, Sunday is 0)
+//   4  date (1-31)     8  control: bit 0 selects binary instead of BCD
+//   9-C  Unix time, little endian, always binary
+//
+// Reading register 0 or 9 takes a snapshot of the time that the other
+// registers read from, so read the seconds (or the low Unix byte) first.
+// Writing a time register sets the clock; the weekday is derived.
+pub struct Rtc {
+    source: TimeSource,
+    cpu_hz: u64,
+    cycle: u64,
+    offset: i64,
+    control: Byte,
+    latched: u64,
+}
+
+impl Rtc {
+    pub fn new(source: TimeSource, cpu_hz: u64) -> Self {
+        let mut rtc = Self {
+            source,
+            cpu_hz,
+            cycle: 0,
+            offset: 0,
+            control: 0x0,
+            latched: 0,
+        };
+        rtc.latched = rtc.get_time();
+        rtc
+    }
+
+    // Current time as Unix seconds.
+    pub fn get_time(&self) -> u64 {
+        let base = match self.source {
+            TimeSource::Host => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            TimeSource::Fixed(start) => start + self.cycle / self.cpu_hz,
+        };
+        (base as i64 + self.offset).max(0) as u64
+    }
+
+    pub fn set_time(&mut self, time: u64) {
+        self.offset += time as i64 - self.get_time() as i64;
+        self.latched = time;
+    }
+
+    pub fn is_binary(&self) -> bool {
+        self.control & CONTROL_BINARY != 0
+    }
+
+    fn encode(&self, val: u8) -> Byte {
+        match self.is_binary() {
+            true => val,
+            false => to_bcd(val),
+        }
+    }
+
+    fn decode(&self, val: Byte) -> u8 {
+        match self.is_binary() {
+            true => val,
+            false => from_bcd(val),
+        }
+    }
+
+    fn read_register(&self, reg: Word) -> Byte {
+        let time = DateTime::from_unix(self.latched);
+        match reg {
+            SECONDS => self.encode(time.second),
+            MINUTES => self.encode(time.minute),
+            HOURS => self.encode(time.hour),
+            WEEKDAY => self.encode(time.weekday),
+            DATE => self.encode(time.date),
+            MONTH => self.encode(time.month),
+            YEAR => self.encode((time.year % 100) as u8),
+            CENTURY => self.encode((time.year / 100) as u8),
+            CONTROL => self.control,
+            UNIX_0..=UNIX_3 => (self.latched >> ((reg - UNIX_0) * 8)) as Byte,
+            _ => 0x0,
+        }
+    }
+
+    fn write_register(&mut self, reg: Word, val: Byte) {
+        let now = self.get_time();
+        let mut time = DateTime::from_unix(now);
+        let field = self.decode(val);
+        match reg {
+            SECONDS => time.second = field,
+            MINUTES => time.minute = field,
+            HOURS => time.hour = field,
+            DATE => time.date = field,
+            MONTH => time.month = field,
+            YEAR => time.year = time.year / 100 * 100 + field as u16,
+            CENTURY => time.year = field as u16 * 100 + time.year % 100,
+            CONTROL => {
+                self.control = val;
+                return;
+            }
+            UNIX_0..=UNIX_3 => {
+                let shift = (reg - UNIX_0) * 8;
+                let time = (now & !(0xFF << shift)) | ((val as u64) << shift);
+                self.set_time(time);
+                return;
+            }
+            _ => return,
+        }
+        self.set_time(time.to_unix());
+    }
+}
+
+impl Device for Rtc {
+    fn peek(&self, addr: Word) -> Byte {
+        self.read_register(addr & REG_MASK)
+    }
+
+    fn read(&mut self, addr: Word) -> Byte {
+        let reg = addr & REG_MASK;
+        if reg == SECONDS || reg == UNIX_0 {
+            self.latched = self.get_time();
+        }
+        self.read_register(reg)
+    }
+
+    fn write(&mut self, addr: Word, val: Byte) {
+        self.write_register(addr & REG_MASK, val);
+    }
+
+    fn tick(&mut self, cycle: u64) {
+        self.cycle = cycle;
+    }
+}
+
+// Calendar fields of a Unix time, in UTC.
+struct DateTime {
+    year: u16,
+    month: u8,
+    date: u8,
+    weekday: u8,
+    hour: u8,
+    minute: u8,
+    second: u8,
+}
+
+impl DateTime {
+    fn from_unix(time: u64) -> Self {
+        let days = time as i64 / SECONDS_PER_DAY;
+        let seconds = time as i64 % SECONDS_PER_DAY;
+        let (year, month, date) = civil_from_days(days);
+        Self {
+            year: year as u16,
+            month,
+            date,
+            // 1970-01-01 was a Thursday.
+            weekday: ((days + 4) % 7) as u8,
+            hour: (seconds / 3600) as u8,
+            minute: (seconds / 60 % 60) as u8,
+            second: (seconds % 60) as u8,
+        }
+    }
+
+    // Out of range fields carry over, e.g. month 13 is January next year.
+    fn to_unix(&self) -> u64 {
+        let year = self.year as i64 + (self.month.max(1) as i64 - 1) / 12;
+        let month = (self.month.max(1) - 1) % 12 + 1;
+        let days = days_from_civil(year, month) + self.date as i64 - 1;
+        let seconds = self.hour as i64 * 3600 + self.minute as i64 * 60 + self.second as i64;
+        (days * SECONDS_PER_DAY + seconds).max(0) as u64
+    }
+}
+
+// Days since 1970-01-01 of the first of a month in the proleptic Gregorian
+// calendar, with years counted from March so the leap day comes last.
+fn days_from_civil(year: i64, month: u8) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, u8, u8) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let date = (day_of_year - (153 * month + 2) / 5 + 1) as u8;
+    let month = if month < 10 { month + 3 } else { month - 9 } as u8;
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    (year, month, date)
+}
+
+fn from_bcd(val: Byte) -> u8 {
+    (val >> 4) * 10 + (val & 0x0F)
+}
+
+fn to_bcd(val: u8) -> Byte {
+    ((val / 10) << 4) | (val % 10)
+}
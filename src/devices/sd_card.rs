@@ -0,0 +1,336 @@
+use std::{
+    collections::VecDeque,
+    fs::{File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use crate::Byte;
+
+use super::SpiDevice;
+
+const BLOCK_LENGTH: usize = 512;
+const COMMAND_LENGTH: usize = 6;
+const HIGH_CAPACITY_SIZE: u64 = 0x8000_0000;
+
+const GO_IDLE_STATE: Byte = 0;
+const SEND_IF_COND: Byte = 8;
+const STOP_TRANSMISSION: Byte = 12;
+const SET_BLOCKLEN: Byte = 16;
+const READ_SINGLE_BLOCK: Byte = 17;
+const READ_MULTIPLE_BLOCK: Byte = 18;
+const WRITE_BLOCK: Byte = 24;
+const WRITE_MULTIPLE_BLOCK: Byte = 25;
+const SD_SEND_OP_COND: Byte = 41;
+const APP_CMD: Byte = 55;
+const READ_OCR: Byte = 58;
+
+const R1_IDLE: Byte = 0x01;
+const R1_ILLEGAL_COMMAND: Byte = 0x04;
+const R1_ADDRESS_ERROR: Byte = 0x20;
+const R1_PARAMETER_ERROR: Byte = 0x40;
+
+const START_BLOCK: Byte = 0xFE;
+const START_MULTIPLE_WRITE: Byte = 0xFC;
+const STOP_TRAN: Byte = 0xFD;
+const DATA_ACCEPTED: Byte = 0x05;
+const DATA_WRITE_ERROR: Byte = 0x0D;
+const ERROR_TOKEN: Byte = 0x01;
+
+const BUSY_BYTES: usize = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum State {
+    Command,
+    ReadMultiple(u64),
+    WriteWait { block: u64, multiple: bool },
+    WriteData { block: u64, multiple: bool },
+}
+
+// SD card in SPI mode, backed by a host image. The card ignores everything
+// until CMD0 puts it into SPI mode, then supports the usual initialisation
+// (CMD8, ACMD41, CMD58), CMD16 with a 512-byte block length, and single
+// and multiple block reads and writes. Images over 2 GiB are presented as
+// SDHC with block addressing, smaller ones as SDSC with byte addresses.
+// CRCs are not checked; data blocks are sent with a valid CRC16.
+pub struct SdCard<T> {
+    storage: T,
+    blocks: u64,
+    high_capacity: bool,
+
+    spi_mode: bool,
+    idle: bool,
+    app_cmd: bool,
+    state: State,
+    command: Vec<Byte>,
+    data: Vec<Byte>,
+    response: VecDeque<Byte>,
+}
+
+impl SdCard<File> {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        Self::new(file)
+    }
+}
+
+impl<T: Read + Write + Seek> SdCard<T> {
+    pub fn new(mut storage: T) -> io::Result<Self> {
+        let size = storage.seek(SeekFrom::End(0))?;
+        Ok(Self {
+            storage,
+            blocks: size / BLOCK_LENGTH as u64,
+            high_capacity: size > HIGH_CAPACITY_SIZE,
+            spi_mode: false,
+            idle: true,
+            app_cmd: false,
+            state: State::Command,
+            command: vec![],
+            data: vec![],
+            response: VecDeque::new(),
+        })
+    }
+
+    pub fn get_storage(&self) -> &T {
+        &self.storage
+    }
+
+    pub fn get_blocks(&self) -> u64 {
+        self.blocks
+    }
+
+    pub fn is_high_capacity(&self) -> bool {
+        self.high_capacity
+    }
+
+    fn r1(&self, flags: Byte) -> Byte {
+        flags | if self.idle { R1_IDLE } else { 0x0 }
+    }
+
+    // Responses follow the command after one byte of 0xFF.
+    fn respond(&mut self, bytes: &[Byte]) {
+        self.response.push_back(0xFF);
+        self.response.extend(bytes);
+    }
+
+    fn get_block(&self, arg: u32) -> Result<u64, Byte> {
+        let block = match self.high_capacity {
+            true => arg as u64,
+            false if !(arg as usize).is_multiple_of(BLOCK_LENGTH) => return Err(R1_ADDRESS_ERROR),
+            false => arg as u64 / BLOCK_LENGTH as u64,
+        };
+        if block >= self.blocks {
+            return Err(R1_PARAMETER_ERROR);
+        }
+        Ok(block)
+    }
+
+    fn read_block(&mut self, block: u64) -> io::Result<Vec<Byte>> {
+        let mut data = vec![0x0; BLOCK_LENGTH];
+        self.storage
+            .seek(SeekFrom::Start(block * BLOCK_LENGTH as u64))?;
+        self.storage.read_exact(&mut data)?;
+        Ok(data)
+    }
+
+    fn write_block(&mut self, block: u64) -> io::Result<()> {
+        self.storage
+            .seek(SeekFrom::Start(block * BLOCK_LENGTH as u64))?;
+        self.storage.write_all(&self.data[..BLOCK_LENGTH])?;
+        self.storage.flush()
+    }
+
+    fn queue_block(&mut self, block: u64) {
+        self.response.push_back(0xFF);
+        match self.read_block(block) {
+            Ok(data) => {
+                let crc = crc16(&data);
+                self.response.push_back(START_BLOCK);
+                self.response.extend(data);
+                self.response.extend(crc.to_be_bytes());
+            }
+            Err(_) => {
+                self.response.push_back(ERROR_TOKEN);
+                self.state = State::Command;
+            }
+        }
+    }
+
+    fn execute(&mut self, cmd: Byte, arg: u32) {
+        if !self.spi_mode {
+            if cmd == GO_IDLE_STATE {
+                self.spi_mode = true;
+                self.respond(&[R1_IDLE]);
+            }
+            return;
+        }
+        let app_cmd = std::mem::take(&mut self.app_cmd);
+        match cmd {
+            GO_IDLE_STATE => {
+                self.idle = true;
+                self.state = State::Command;
+                self.respond(&[R1_IDLE]);
+            }
+            SEND_IF_COND => {
+                let r1 = self.r1(0x0);
+                self.respond(&[r1, 0x00, 0x00, (arg >> 8) as Byte & 0x0F, arg as Byte]);
+            }
+            APP_CMD => {
+                self.app_cmd = true;
+                let r1 = self.r1(0x0);
+                self.respond(&[r1]);
+            }
+            SD_SEND_OP_COND if app_cmd => {
+                self.idle = false;
+                self.respond(&[0x0]);
+            }
+            READ_OCR => {
+                let ccs = if self.high_capacity { 0x40 } else { 0x0 };
+                let r1 = self.r1(0x0);
+                self.respond(&[r1, 0x80 | ccs, 0xFF, 0x80, 0x00]);
+            }
+            SET_BLOCKLEN => {
+                let flags = match arg as usize {
+                    BLOCK_LENGTH => 0x0,
+                    _ => R1_PARAMETER_ERROR,
+                };
+                let r1 = self.r1(flags);
+                self.respond(&[r1]);
+            }
+            STOP_TRANSMISSION => {
+                self.response.clear();
+                self.state = State::Command;
+                // A stuff byte, then R1 and a short busy period.
+                self.response.push_back(0xFF);
+                self.respond(&[0x0]);
+                self.response.extend([0x0; BUSY_BYTES]);
+            }
+            READ_SINGLE_BLOCK | READ_MULTIPLE_BLOCK | WRITE_BLOCK | WRITE_MULTIPLE_BLOCK
+                if !self.idle =>
+            {
+                let block = match self.get_block(arg) {
+                    Ok(block) => block,
+                    Err(flags) => {
+                        self.respond(&[flags]);
+                        return;
+                    }
+                };
+                self.respond(&[0x0]);
+                match cmd {
+                    READ_SINGLE_BLOCK => self.queue_block(block),
+                    READ_MULTIPLE_BLOCK => {
+                        self.state = State::ReadMultiple(block + 1);
+                        self.queue_block(block);
+                    }
+                    _ => {
+                        self.state = State::WriteWait {
+                            block,
+                            multiple: cmd == WRITE_MULTIPLE_BLOCK,
+                        };
+                    }
+                }
+            }
+            _ => {
+                let r1 = self.r1(R1_ILLEGAL_COMMAND);
+                self.respond(&[r1]);
+            }
+        }
+    }
+
+    fn receive_command(&mut self, val: Byte) {
+        if self.command.is_empty() && val & 0xC0 != 0x40 {
+            return;
+        }
+        self.command.push(val);
+        if self.command.len() < COMMAND_LENGTH {
+            return;
+        }
+        let cmd = self.command[0] & 0x3F;
+        let arg = u32::from_be_bytes([
+            self.command[1],
+            self.command[2],
+            self.command[3],
+            self.command[4],
+        ]);
+        self.command.clear();
+        self.execute(cmd, arg);
+    }
+
+    fn receive_data(&mut self, block: u64, multiple: bool, val: Byte) {
+        self.data.push(val);
+        // Data followed by two CRC bytes.
+        if self.data.len() < BLOCK_LENGTH + 2 {
+            return;
+        }
+        let token = match self.write_block(block) {
+            Ok(()) => DATA_ACCEPTED,
+            Err(_) => DATA_WRITE_ERROR,
+        };
+        self.data.clear();
+        self.response.push_back(token);
+        self.response.extend([0x0; BUSY_BYTES]);
+        self.state = match (multiple, token) {
+            (true, DATA_ACCEPTED) if block + 1 < self.blocks => State::WriteWait {
+                block: block + 1,
+                multiple,
+            },
+            _ => State::Command,
+        };
+    }
+}
+
+// CRC16-CCITT as used for SD data blocks.
+fn crc16(data: &[Byte]) -> u16 {
+    let mut crc: u16 = 0x0;
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+impl<T: Read + Write + Seek> SpiDevice for SdCard<T> {
+    fn deselect(&mut self) {
+        self.command.clear();
+        self.response.clear();
+        if let State::ReadMultiple(_) = self.state {
+            self.state = State::Command;
+        }
+    }
+
+    fn transfer(&mut self, mosi: Byte) -> Byte {
+        match self.state.clone() {
+            State::Command | State::ReadMultiple(_) => self.receive_command(mosi),
+            State::WriteWait { block, multiple } => match (mosi, multiple) {
+                (START_BLOCK, false) | (START_MULTIPLE_WRITE, true) => {
+                    self.state = State::WriteData { block, multiple };
+                }
+                (STOP_TRAN, true) => {
+                    self.state = State::Command;
+                    self.response.push_back(0xFF);
+                    self.response.extend([0x0; BUSY_BYTES]);
+                }
+                _ => {}
+            },
+            State::WriteData { block, multiple } => self.receive_data(block, multiple, mosi),
+        }
+        if let State::ReadMultiple(block) = self.state {
+            if self.response.is_empty() {
+                match block < self.blocks {
+                    true => {
+                        self.state = State::ReadMultiple(block + 1);
+                        self.queue_block(block);
+                    }
+                    false => self.state = State::Command,
+                }
+            }
+        }
+        self.response.pop_front().unwrap_or(0xFF)
+    }
+}
@@ -0,0 +1,224 @@
+use crate::{wav::encode_wav, Byte, Word};
+
+use super::{
+    sound::{self, Synth},
+    Device,
+};
+
+const REGISTERS_LENGTH: usize = 8;
+
+const NOISE: usize = 0x6;
+
+const LATCH: Byte = 0x80;
+
+const NOISE_WHITE: u16 = 0x4;
+const NOISE_RATE: u16 = 0x3;
+const LFSR_RESET: u16 = 0x4000;
+
+// The counters run at the chip clock divided by 16.
+const CLOCK_DIVIDER: u64 = 16;
+
+// Texas Instruments SN76489 sound generator: three square wave channels and
+// a noise channel, each with a 4-bit attenuator in 2 dB steps. Write only:
+// a byte with bit 7 set latches a register and sets its low bits, a byte
+// with bit 7 clear sets the upper six bits of a tone period. Memory mapped
+// at any address, or wired to a port through `set_pins`.
+//
+// Writes are logged with the cycle passed to the last `tick`, and `render`
+// replays them into mono samples offline.
+pub struct Sn76489 {
+    clock_hz: u64,
+    cpu_hz: u64,
+    cycle: u64,
+    registers: [u16; REGISTERS_LENGTH],
+    latched: usize,
+    writes: Vec<(u64, Byte)>,
+    we: bool,
+}
+
+impl Sn76489 {
+    pub fn new(clock_hz: u64, cpu_hz: u64) -> Self {
+        Self {
+            clock_hz,
+            cpu_hz,
+            cycle: 0,
+            registers: initial_registers(),
+            latched: 0,
+            writes: vec![],
+            we: true,
+        }
+    }
+
+    // Tone 0, attenuation 0, tone 1, attenuation 1, tone 2, attenuation 2,
+    // noise control and noise attenuation.
+    pub fn get_registers(&self) -> &[u16] {
+        &self.registers
+    }
+
+    // Bytes written as (cycle, value).
+    pub fn get_writes(&self) -> &[(u64, Byte)] {
+        &self.writes
+    }
+
+    // Drives /WE and D0-D7. The byte is taken on the falling edge of /WE.
+    pub fn set_pins(&mut self, we: bool, data: Byte) {
+        if self.we && !we {
+            self.write_data(data);
+        }
+        self.we = we;
+    }
+
+    // Everything written up to the last tick, as samples at `sample_rate`.
+    pub fn render(&self, sample_rate: u32) -> Vec<i16> {
+        sound::render(
+            SnSynth::new(),
+            &self.writes,
+            self.cycle,
+            self.cpu_hz,
+            self.clock_hz / CLOCK_DIVIDER,
+            sample_rate,
+        )
+    }
+
+    pub fn render_wav(&self, sample_rate: u32) -> Vec<u8> {
+        encode_wav(sample_rate, &self.render(sample_rate))
+    }
+
+    fn write_data(&mut self, val: Byte) {
+        write_register(&mut self.registers, &mut self.latched, val);
+        self.writes.push((self.cycle, val));
+    }
+}
+
+impl Device for Sn76489 {
+    fn peek(&self, _addr: Word) -> Byte {
+        0xFF
+    }
+
+    fn write(&mut self, _addr: Word, val: Byte) {
+        self.write_data(val);
+    }
+
+    fn tick(&mut self, cycle: u64) {
+        self.cycle = cycle;
+    }
+}
+
+// All channels silent.
+fn initial_registers() -> [u16; REGISTERS_LENGTH] {
+    let mut registers = [0x0; REGISTERS_LENGTH];
+    for attenuation in registers.iter_mut().skip(1).step_by(2) {
+        *attenuation = 0xF;
+    }
+    registers
+}
+
+fn is_tone(reg: usize) -> bool {
+    reg.is_multiple_of(2) && reg != NOISE
+}
+
+// Returns whether the noise register was written.
+fn write_register(registers: &mut [u16; REGISTERS_LENGTH], latched: &mut usize, val: Byte) -> bool {
+    let data = val as u16;
+    if val & LATCH != 0 {
+        *latched = ((val >> 4) & 0x7) as usize;
+        let reg = &mut registers[*latched];
+        *reg = match is_tone(*latched) {
+            true => (*reg & 0x3F0) | (data & 0x0F),
+            false => data & 0x0F,
+        };
+    } else if is_tone(*latched) {
+        let reg = &mut registers[*latched];
+        *reg = (*reg & 0x00F) | ((data & 0x3F) << 4);
+    } else {
+        registers[*latched] = data & 0x0F;
+    }
+    *latched == NOISE
+}
+
+struct SnSynth {
+    registers: [u16; REGISTERS_LENGTH],
+    latched: usize,
+    counters: [u16; 4],
+    outputs: [bool; 4],
+    lfsr: u16,
+}
+
+impl SnSynth {
+    fn new() -> Self {
+        Self {
+            registers: initial_registers(),
+            latched: 0,
+            counters: [0; 4],
+            outputs: [false; 4],
+            lfsr: LFSR_RESET,
+        }
+    }
+
+    fn period(&self, channel: usize) -> u16 {
+        let period = match channel {
+            3 => match self.registers[NOISE] & NOISE_RATE {
+                3 => self.registers[4],
+                rate => 0x10 << rate,
+            },
+            _ => self.registers[channel * 2],
+        };
+        match period {
+            0 => 0x400,
+            _ => period,
+        }
+    }
+
+    fn shift_noise(&mut self) {
+        let feedback = match self.registers[NOISE] & NOISE_WHITE {
+            0 => self.lfsr & 0x1,
+            _ => (self.lfsr ^ (self.lfsr >> 1)) & 0x1,
+        };
+        self.lfsr = (self.lfsr >> 1) | (feedback << 14);
+    }
+}
+
+impl Synth for SnSynth {
+    type Write = Byte;
+
+    fn write(&mut self, val: Byte) {
+        if write_register(&mut self.registers, &mut self.latched, val) {
+            self.lfsr = LFSR_RESET;
+        }
+    }
+
+    fn step(&mut self) -> f32 {
+        for channel in 0..4 {
+            if self.counters[channel] > 1 {
+                self.counters[channel] -= 1;
+                continue;
+            }
+            self.counters[channel] = self.period(channel);
+            self.outputs[channel] = !self.outputs[channel];
+            if channel == 3 && self.outputs[channel] {
+                self.shift_noise();
+            }
+        }
+
+        let mut level = 0.0;
+        for channel in 0..4 {
+            let high = match channel {
+                3 => self.lfsr & 0x1 != 0,
+                _ => self.outputs[channel],
+            };
+            if high {
+                let attenuation = self.registers[channel * 2 + 1];
+                level += attenuation_level(attenuation);
+            }
+        }
+        level / 4.0
+    }
+}
+
+// 2 dB per step, with 15 switching the channel off.
+fn attenuation_level(attenuation: u16) -> f32 {
+    match attenuation {
+        0xF => 0.0,
+        _ => 10_f32.powf(-(attenuation as f32) * 2.0 / 20.0),
+    }
+}
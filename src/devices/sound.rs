@@ -0,0 +1,49 @@
+// Offline synthesis shared by the sound devices. A device records what the
+// CPU wrote and when, and rendering replays that log into a fresh
+// synthesizer, so the output only depends on the writes and their cycles.
+
+// A sound generator stepped at its own internal rate.
+pub trait Synth {
+    type Write: Copy;
+
+    fn write(&mut self, write: Self::Write);
+
+    // Advances one step and returns the output level, from 0.0 to 1.0.
+    fn step(&mut self) -> f32;
+}
+
+// Runs `synth` from cycle 0 to `cycles` at `step_hz` steps per second of a
+// `cpu_hz` CPU clock, applying each write at its cycle. Each sample is the
+// average level over its period.
+pub fn render<S: Synth>(
+    mut synth: S,
+    writes: &[(u64, S::Write)],
+    cycles: u64,
+    cpu_hz: u64,
+    step_hz: u64,
+    sample_rate: u32,
+) -> Vec<i16> {
+    let to_step = |cycle: u64| (cycle as u128 * step_hz as u128 / cpu_hz as u128) as u64;
+    let to_sample = |step: u64| (step as u128 * sample_rate as u128 / step_hz as u128) as usize;
+
+    let steps = to_step(cycles);
+    let mut samples = Vec::with_capacity(to_sample(steps));
+    let mut writes = writes.iter().peekable();
+    let mut sum = 0.0;
+    let mut count = 0;
+    for step in 0..steps {
+        while let Some((_, write)) = writes.next_if(|(cycle, _)| to_step(*cycle) <= step) {
+            synth.write(*write);
+        }
+        sum += synth.step();
+        count += 1;
+        let end = to_sample(step + 1);
+        if end > samples.len() {
+            let level = sum / count as f32;
+            samples.resize(end, (level * i16::MAX as f32) as i16);
+            sum = 0.0;
+            count = 0;
+        }
+    }
+    samples
+}
@@ -0,0 +1,100 @@
+use crate::{wav::encode_wav, Byte, Word};
+
+use super::{
+    sound::{self, Synth},
+    Device,
+};
+
+// One-bit speaker in the style of the Apple II soft switch at $C030: any
+// access to the mapped address, read or write, flips the speaker cone. The
+// data bus is not driven, so reads return 0.
+//
+// Toggles are logged with the cycle passed to the last `tick`, and `render`
+// replays them into mono samples offline. The same device records a
+// cassette output port.
+pub struct Speaker {
+    cpu_hz: u64,
+    cycle: u64,
+    level: bool,
+    toggles: Vec<u64>,
+}
+
+impl Speaker {
+    pub fn new(cpu_hz: u64) -> Self {
+        Self {
+            cpu_hz,
+            cycle: 0,
+            level: false,
+            toggles: vec![],
+        }
+    }
+
+    pub fn get_level(&self) -> bool {
+        self.level
+    }
+
+    // Cycles at which the speaker was toggled.
+    pub fn get_toggles(&self) -> &[u64] {
+        &self.toggles
+    }
+
+    // Everything up to the last tick, as samples at `sample_rate`.
+    pub fn render(&self, sample_rate: u32) -> Vec<i16> {
+        let toggles: Vec<(u64, ())> = self.toggles.iter().map(|cycle| (*cycle, ())).collect();
+        sound::render(
+            SpeakerSynth { level: false },
+            &toggles,
+            self.cycle,
+            self.cpu_hz,
+            self.cpu_hz,
+            sample_rate,
+        )
+    }
+
+    pub fn render_wav(&self, sample_rate: u32) -> Vec<u8> {
+        encode_wav(sample_rate, &self.render(sample_rate))
+    }
+
+    fn toggle(&mut self) {
+        self.level = !self.level;
+        self.toggles.push(self.cycle);
+    }
+}
+
+impl Device for Speaker {
+    fn peek(&self, _addr: Word) -> Byte {
+        0x0
+    }
+
+    fn read(&mut self, _addr: Word) -> Byte {
+        self.toggle();
+        0x0
+    }
+
+    fn write(&mut self, _addr: Word, _val: Byte) {
+        self.toggle();
+    }
+
+    fn tick(&mut self, cycle: u64) {
+        self.cycle = cycle;
+    }
+}
+
+struct SpeakerSynth {
+    level: bool,
+}
+
+impl Synth for SpeakerSynth {
+    type Write = ();
+
+    fn write(&mut self, _write: ()) {
+        self.level = !self.level;
+    }
+
+    fn step(&mut self) -> f32 {
+        match self.level {
+            true => 1.0,
+            false => 0.0,
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::Byte;
+
+// A byte-oriented SPI peripheral.
+pub trait SpiDevice {
+    fn select(&mut self) {}
+
+    fn deselect(&mut self) {}
+
+    // Called with each byte from the master once its last bit has been
+    // clocked in. Returns the byte to shift out during the next one.
+    fn transfer(&mut self, mosi: Byte) -> Byte;
+}
+
+// Which bits of a GPIO port the bus is wired to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpiPins {
+    pub cs: Byte,
+    pub sclk: Byte,
+    pub mosi: Byte,
+    pub miso: Byte,
+}
+
+// Decodes a bit-banged SPI bus from pin levels and clocks bytes in and out
+// of one device, MSB first. The mode (0-3) sets clock polarity and phase as
+// usual: in mode 0 MOSI is sampled on the rising edge of SCLK and MISO
+// changes on the falling edge. CS is active low, and MISO is released
+// (reads high) while it is deasserted.
+pub struct Spi {
+    device: Rc<RefCell<dyn SpiDevice>>,
+    pins: SpiPins,
+    cpol: bool,
+    cpha: bool,
+
+    selected: bool,
+    sclk: bool,
+    bits: u8,
+    shift_in: Byte,
+    out: Byte,
+    pending: Byte,
+    miso: bool,
+}
+
+impl Spi {
+    pub fn new(mode: u8, pins: SpiPins, device: Rc<RefCell<dyn SpiDevice>>) -> Self {
+        assert!(mode < 4, "SPI mode must be 0-3");
+        let cpol = mode & 0x2 != 0;
+        Self {
+            device,
+            pins,
+            cpol,
+            cpha: mode & 0x1 != 0,
+            selected: false,
+            sclk: cpol,
+            bits: 0,
+            shift_in: 0x0,
+            out: 0xFF,
+            pending: 0xFF,
+            miso: true,
+        }
+    }
+
+    pub fn get_device(&self) -> Rc<RefCell<dyn SpiDevice>> {
+        self.device.clone()
+    }
+
+    pub fn set_pins(&mut self, cs: bool, sclk: bool, mosi: bool) {
+        if cs && self.selected {
+            self.selected = false;
+            self.miso = true;
+            self.device.borrow_mut().deselect();
+        } else if !cs && !self.selected {
+            self.select();
+        }
+
+        let edge = sclk != self.sclk;
+        self.sclk = sclk;
+        if !edge || !self.selected {
+            return;
+        }
+        let leading = sclk != self.cpol;
+        if leading != self.cpha {
+            self.sample(mosi);
+        } else {
+            self.shift();
+        }
+    }
+
+    pub fn get_miso(&self) -> bool {
+        self.miso
+    }
+
+    // Takes the levels on a GPIO port.
+    pub fn set_port(&mut self, port: Byte) {
+        self.set_pins(
+            port & self.pins.cs != 0,
+            port & self.pins.sclk != 0,
+            port & self.pins.mosi != 0,
+        );
+    }
+
+    // Drives MISO onto the input levels of a GPIO port.
+    pub fn drive_port(&self, input: Byte) -> Byte {
+        match self.miso {
+            true => input | self.pins.miso,
+            false => input & !self.pins.miso,
+        }
+    }
+
+    fn select(&mut self) {
+        self.selected = true;
+        self.bits = 0;
+        self.out = 0xFF;
+        self.pending = 0xFF;
+        self.device.borrow_mut().select();
+        if !self.cpha {
+            self.miso = self.out & 0x80 != 0;
+        }
+    }
+
+    fn sample(&mut self, mosi: bool) {
+        self.shift_in = (self.shift_in << 1) | mosi as Byte;
+        self.bits += 1;
+        if self.bits == 8 {
+            self.bits = 0;
+            self.pending = self.device.borrow_mut().transfer(self.shift_in);
+        }
+    }
+
+    fn shift(&mut self) {
+        if self.bits == 0 {
+            self.out = self.pending;
+        }
+        self.miso = (self.out << self.bits) & 0x80 != 0;
+    }
+}
@@ -0,0 +1,236 @@
+use std::ops::RangeInclusive;
+
+use crate::{
+    image::{encode_png, encode_ppm},
+    Byte, Word,
+};
+
+use super::{
+    font::{FIRST_CHAR, FONT},
+    Device,
+};
+
+const CURSOR_X: usize = 0x0;
+const CURSOR_Y: usize = 0x1;
+const CONTROL: usize = 0x2;
+const REGISTERS_LENGTH: usize = 0x3;
+
+const CONTROL_CURSOR: Byte = 0x01;
+
+const CELL_WIDTH: usize = 8;
+const CELL_HEIGHT: usize = 8;
+const GLYPH_LENGTH: usize = CELL_HEIGHT;
+const CHAR_ROM_LENGTH: usize = 0x100 * GLYPH_LENGTH;
+
+const FOREGROUND: [Byte; 3] = [0xFF, 0xFF, 0xFF];
+const BACKGROUND: [Byte; 3] = [0x00, 0x00, 0x00];
+
+// Character-mode display: `columns` x `rows` bytes of screen RAM mapped at
+// `base`, one byte per character, followed by three registers:
+//
+//   0  cursor column
+//   1  cursor row
+//   2  control: bit 0 shows the cursor
+//
+// Pixels come from a character ROM of 8x8 glyphs, eight bytes each with
+// the leftmost pixel in bit 7. The default ROM has printable ASCII, with
+// codes $80-$FF as inverse video copies of $00-$7F. Text output maps codes
+// through a separate character set, so it can follow a custom ROM.
+pub struct TextVideo {
+    base: Word,
+    columns: usize,
+    rows: usize,
+    screen: Vec<Byte>,
+    registers: [Byte; REGISTERS_LENGTH],
+    char_rom: Vec<Byte>,
+    charset: [char; 0x100],
+    foreground: [Byte; 3],
+    background: [Byte; 3],
+}
+
+impl TextVideo {
+    pub fn new(base: Word, columns: usize, rows: usize) -> Self {
+        assert!(base as usize + columns * rows + REGISTERS_LENGTH <= 0x10000);
+        Self {
+            base,
+            columns,
+            rows,
+            screen: vec![b' '; columns * rows],
+            registers: [0x0; REGISTERS_LENGTH],
+            char_rom: default_char_rom(),
+            charset: default_charset(),
+            foreground: FOREGROUND,
+            background: BACKGROUND,
+        }
+    }
+
+    // Screen RAM and the registers, for mapping the device.
+    pub fn get_range(&self) -> RangeInclusive<Word> {
+        let len = self.screen.len() + REGISTERS_LENGTH;
+        self.base..=self.base + (len - 1) as Word
+    }
+
+    pub fn get_columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn get_rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn get_screen(&self) -> &[Byte] {
+        &self.screen
+    }
+
+    // Takes up to 256 glyphs; codes past the end of the ROM are blank.
+    pub fn set_char_rom(&mut self, rom: &[Byte]) {
+        assert!(rom.len().is_multiple_of(GLYPH_LENGTH) && rom.len() <= CHAR_ROM_LENGTH);
+        self.char_rom = vec![0x0; CHAR_ROM_LENGTH];
+        self.char_rom[..rom.len()].copy_from_slice(rom);
+    }
+
+    pub fn set_charset(&mut self, charset: [char; 0x100]) {
+        self.charset = charset;
+    }
+
+    pub fn set_colors(&mut self, foreground: [Byte; 3], background: [Byte; 3]) {
+        self.foreground = foreground;
+        self.background = background;
+    }
+
+    // Column and row of the cursor, if it is shown and on screen.
+    pub fn get_cursor(&self) -> Option<(usize, usize)> {
+        if self.registers[CONTROL] & CONTROL_CURSOR == 0 {
+            return None;
+        }
+        let column = self.registers[CURSOR_X] as usize;
+        let row = self.registers[CURSOR_Y] as usize;
+        (column < self.columns && row < self.rows).then_some((column, row))
+    }
+
+    pub fn get_line(&self, row: usize) -> String {
+        self.row(row)
+            .iter()
+            .map(|val| self.charset[*val as usize])
+            .collect()
+    }
+
+    // All rows, one per line.
+    pub fn get_text(&self) -> String {
+        (0..self.rows)
+            .map(|row| self.get_line(row))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // Redraws the screen from the top left of a terminal, with the cursor
+    // in reverse video.
+    pub fn render_ansi(&self) -> String {
+        let cursor = self.get_cursor();
+        let mut out = String::from("\x1b[H");
+        for row in 0..self.rows {
+            for (column, val) in self.row(row).iter().enumerate() {
+                let c = self.charset[*val as usize];
+                if cursor == Some((column, row)) {
+                    out.push_str(&format!("\x1b[7m{}\x1b[27m", c));
+                } else {
+                    out.push(c);
+                }
+            }
+            out.push_str("\x1b[K");
+            if row + 1 < self.rows {
+                out.push_str("\r\n");
+            }
+        }
+        out
+    }
+
+    pub fn get_pixel_size(&self) -> (usize, usize) {
+        (self.columns * CELL_WIDTH, self.rows * CELL_HEIGHT)
+    }
+
+    // The screen as 24-bit RGB pixels, row by row. The cursor inverts its
+    // cell.
+    pub fn render_rgb(&self) -> Vec<Byte> {
+        let (width, height) = self.get_pixel_size();
+        let cursor = self.get_cursor();
+        let mut rgb = Vec::with_capacity(width * height * 3);
+        for y in 0..height {
+            let (row, line) = (y / CELL_HEIGHT, y % CELL_HEIGHT);
+            for (column, val) in self.row(row).iter().enumerate() {
+                let mut bits = self.char_rom[*val as usize * GLYPH_LENGTH + line];
+                if cursor == Some((column, row)) {
+                    bits = !bits;
+                }
+                for x in 0..CELL_WIDTH {
+                    let color = match bits & (0x80 >> x) {
+                        0 => self.background,
+                        _ => self.foreground,
+                    };
+                    rgb.extend(color);
+                }
+            }
+        }
+        rgb
+    }
+
+    pub fn render_ppm(&self) -> Vec<u8> {
+        let (width, height) = self.get_pixel_size();
+        encode_ppm(width, height, &self.render_rgb())
+    }
+
+    pub fn render_png(&self) -> Vec<u8> {
+        let (width, height) = self.get_pixel_size();
+        encode_png(width, height, &self.render_rgb())
+    }
+
+    fn row(&self, row: usize) -> &[Byte] {
+        &self.screen[row * self.columns..(row + 1) * self.columns]
+    }
+}
+
+fn default_char_rom() -> Vec<Byte> {
+    let mut rom = vec![0x0; CHAR_ROM_LENGTH];
+    for (i, glyph) in FONT.iter().enumerate() {
+        let start = (FIRST_CHAR as usize + i) * GLYPH_LENGTH;
+        rom[start..start + GLYPH_LENGTH].copy_from_slice(glyph);
+    }
+    let (normal, inverse) = rom.split_at_mut(CHAR_ROM_LENGTH / 2);
+    for (bits, inverted) in normal.iter().zip(inverse) {
+        *inverted = !bits;
+    }
+    rom
+}
+
+// Printable ASCII, ignoring the inverse video bit. Everything else is '?'.
+fn default_charset() -> [char; 0x100] {
+    let mut charset = ['?'; 0x100];
+    for (code, c) in charset.iter_mut().enumerate() {
+        if let 0x20..=0x7E = code & 0x7F {
+            *c = (code & 0x7F) as u8 as char;
+        }
+    }
+    charset
+}
+
+impl Device for TextVideo {
+    fn peek(&self, addr: Word) -> Byte {
+        let offset = addr.wrapping_sub(self.base) as usize;
+        match offset.checked_sub(self.screen.len()) {
+            None => self.screen[offset],
+            Some(register) => self.registers.get(register).copied().unwrap_or(0x0),
+        }
+    }
+
+    fn write(&mut self, addr: Word, val: Byte) {
+        let offset = addr.wrapping_sub(self.base) as usize;
+        match offset.checked_sub(self.screen.len()) {
+            None => self.screen[offset] = val,
+            Some(register) => {
+                if let Some(reg) = self.registers.get_mut(register) {
+                    *reg = val;
+                }
+            }
+        }
+    }
+}
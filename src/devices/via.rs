@@ -0,0 +1,747 @@
+use crate::{Byte, Word};
+
+use super::Device;
+
+const ORB: Word = 0x0;
+const ORA: Word = 0x1;
+const DDRB: Word = 0x2;
+const DDRA: Word = 0x3;
+const T1C_L: Word = 0x4;
+const T1C_H: Word = 0x5;
+const T1L_L: Word = 0x6;
+const T1L_H: Word = 0x7;
+const T2C_L: Word = 0x8;
+const T2C_H: Word = 0x9;
+const SR: Word = 0xA;
+const ACR: Word = 0xB;
+const PCR: Word = 0xC;
+const IFR: Word = 0xD;
+const IER: Word = 0xE;
+const ORA_NH: Word = 0xF;
+
+const REG_MASK: Word = 0x0F;
+
+const IRQ_CA2: Byte = 0x01;
+const IRQ_CA1: Byte = 0x02;
+const IRQ_SR: Byte = 0x04;
+const IRQ_CB2: Byte = 0x08;
+const IRQ_CB1: Byte = 0x10;
+const IRQ_T2: Byte = 0x20;
+const IRQ_T1: Byte = 0x40;
+const IRQ_ANY: Byte = 0x80;
+
+const ACR_PA_LATCH: Byte = 0x01;
+const ACR_PB_LATCH: Byte = 0x02;
+const ACR_SR_MASK: Byte = 0x1C;
+const ACR_T2_PULSE: Byte = 0x20;
+const ACR_T1_FREE_RUN: Byte = 0x40;
+const ACR_T1_PB7: Byte = 0x80;
+
+const PB6: Byte = 0x40;
+const PB7: Byte = 0x80;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ControlMode {
+    InputNegative,
+    IndependentNegative,
+    InputPositive,
+    IndependentPositive,
+    Handshake,
+    Pulse,
+    Low,
+    High,
+}
+
+impl ControlMode {
+    fn from_bits(bits: Byte) -> Self {
+        match bits & 0b111 {
+            0b000 => ControlMode::InputNegative,
+            0b001 => ControlMode::IndependentNegative,
+            0b010 => ControlMode::InputPositive,
+            0b011 => ControlMode::IndependentPositive,
+            0b100 => ControlMode::Handshake,
+            0b101 => ControlMode::Pulse,
+            0b110 => ControlMode::Low,
+            _ => ControlMode::High,
+        }
+    }
+
+    fn is_independent(&self) -> bool {
+        matches!(
+            self,
+            ControlMode::IndependentNegative | ControlMode::IndependentPositive
+        )
+    }
+
+    fn is_active_edge(&self, old: bool, new: bool) -> bool {
+        match self {
+            ControlMode::InputNegative | ControlMode::IndependentNegative => old && !new,
+            ControlMode::InputPositive | ControlMode::IndependentPositive => !old && new,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ShiftMode {
+    Disabled,
+    InT2,
+    InPhi2,
+    InExternal,
+    OutFreeRun,
+    OutT2,
+    OutPhi2,
+    OutExternal,
+}
+
+impl ShiftMode {
+    fn from_acr(acr: Byte) -> Self {
+        match (acr & ACR_SR_MASK) >> 2 {
+            0b000 => ShiftMode::Disabled,
+            0b001 => ShiftMode::InT2,
+            0b010 => ShiftMode::InPhi2,
+            0b011 => ShiftMode::InExternal,
+            0b100 => ShiftMode::OutFreeRun,
+            0b101 => ShiftMode::OutT2,
+            0b110 => ShiftMode::OutPhi2,
+            _ => ShiftMode::OutExternal,
+        }
+    }
+
+    fn is_output(&self) -> bool {
+        matches!(
+            self,
+            ShiftMode::OutFreeRun | ShiftMode::OutT2 | ShiftMode::OutPhi2 | ShiftMode::OutExternal
+        )
+    }
+
+    fn uses_t2(&self) -> bool {
+        matches!(
+            self,
+            ShiftMode::InT2 | ShiftMode::OutFreeRun | ShiftMode::OutT2
+        )
+    }
+}
+
+// MOS 6522 Versatile Interface Adapter. Registers are decoded from the low
+// four address bits, so the device can be mapped anywhere and mirrors
+// itself across larger ranges.
+//
+// Timers are loaded on the cycle of the write to their high byte and start
+// counting on the following cycle. T1 raises its flag on the cycle it rolls
+// from $0000 to $FFFF; in free-run mode it reloads from the latches on the
+// next cycle, giving a period of N + 2 cycles.
+//
+// When brought up to date the cycles in between are stepped through one at
+// a time only around loads, underflows and shifts. Stretches where the
+// timers just count down are taken in one go.
+pub struct Via {
+    cycle: u64,
+
+    ora: Byte,
+    orb: Byte,
+    ddra: Byte,
+    ddrb: Byte,
+    input_a: Byte,
+    input_b: Byte,
+    latch_a: Byte,
+    latch_b: Byte,
+
+    t1_counter: Word,
+    t1_latch: Word,
+    t1_armed: bool,
+    t1_load: bool,
+    t1_reload: bool,
+    pb7: bool,
+
+    t2_counter: Word,
+    t2_latch_l: Byte,
+    t2_armed: bool,
+    t2_load: bool,
+
+    sr: Byte,
+    sr_count: u8,
+    sr_running: bool,
+
+    acr: Byte,
+    pcr: Byte,
+    ifr: Byte,
+    ier: Byte,
+
+    ca1: bool,
+    ca2: bool,
+    cb1: bool,
+    cb2: bool,
+    ca2_out: bool,
+    cb2_out: bool,
+    ca2_pulse: bool,
+    cb2_pulse: bool,
+}
+
+impl Default for Via {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Via {
+    pub fn new() -> Self {
+        Self {
+            cycle: 0x0,
+            ora: 0x0,
+            orb: 0x0,
+            ddra: 0x0,
+            ddrb: 0x0,
+            input_a: 0xFF,
+            input_b: 0xFF,
+            latch_a: 0x0,
+            latch_b: 0x0,
+            t1_counter: 0xFFFF,
+            t1_latch: 0xFFFF,
+            t1_armed: false,
+            t1_load: false,
+            t1_reload: false,
+            pb7: true,
+            t2_counter: 0xFFFF,
+            t2_latch_l: 0xFF,
+            t2_armed: false,
+            t2_load: false,
+            sr: 0x0,
+            sr_count: 0,
+            sr_running: false,
+            acr: 0x0,
+            pcr: 0x0,
+            ifr: 0x0,
+            ier: 0x0,
+            ca1: true,
+            ca2: true,
+            cb1: true,
+            cb2: true,
+            ca2_out: true,
+            cb2_out: true,
+            ca2_pulse: false,
+            cb2_pulse: false,
+        }
+    }
+
+    fn ca2_mode(&self) -> ControlMode {
+        ControlMode::from_bits(self.pcr >> 1)
+    }
+
+    fn cb2_mode(&self) -> ControlMode {
+        ControlMode::from_bits(self.pcr >> 5)
+    }
+
+    fn ca1_positive(&self) -> bool {
+        self.pcr & 0x01 != 0
+    }
+
+    fn cb1_positive(&self) -> bool {
+        self.pcr & 0x10 != 0
+    }
+
+    fn shift_mode(&self) -> ShiftMode {
+        ShiftMode::from_acr(self.acr)
+    }
+
+    fn set_flag(&mut self, flag: Byte) {
+        self.ifr |= flag;
+    }
+
+    fn clear_flag(&mut self, flag: Byte) {
+        self.ifr &= !flag;
+    }
+
+    fn get_ifr(&self) -> Byte {
+        let mut ifr = self.ifr & 0x7F;
+        if ifr & self.ier & 0x7F != 0 {
+            ifr |= IRQ_ANY;
+        }
+        ifr
+    }
+
+    // Output pins of port A. Bits configured as inputs read as the level
+    // driven by the host.
+    pub fn get_port_a(&self) -> Byte {
+        (self.ora & self.ddra) | (self.input_a & !self.ddra)
+    }
+
+    pub fn get_port_b(&self) -> Byte {
+        let mut val = (self.orb & self.ddrb) | (self.input_b & !self.ddrb);
+        if self.acr & ACR_T1_PB7 != 0 {
+            val = (val & !PB7) | if self.pb7 { PB7 } else { 0 };
+        }
+        val
+    }
+
+    pub fn get_ddra(&self) -> Byte {
+        self.ddra
+    }
+
+    pub fn get_ddrb(&self) -> Byte {
+        self.ddrb
+    }
+
+    pub fn set_port_a_input(&mut self, val: Byte) {
+        self.input_a = val;
+    }
+
+    pub fn set_port_b_input(&mut self, val: Byte) {
+        let falling_pb6 = self.input_b & PB6 != 0 && val & PB6 == 0;
+        self.input_b = val;
+        if falling_pb6 && self.acr & ACR_T2_PULSE != 0 {
+            self.count_t2();
+        }
+    }
+
+    pub fn set_ca1(&mut self, level: bool) {
+        let old = self.ca1;
+        self.ca1 = level;
+        if old == level || level != self.ca1_positive() {
+            return;
+        }
+        self.set_flag(IRQ_CA1);
+        if self.acr & ACR_PA_LATCH != 0 {
+            self.latch_a = self.get_port_a();
+        }
+        if self.ca2_mode() == ControlMode::Handshake {
+            self.ca2_out = true;
+        }
+    }
+
+    pub fn set_ca2(&mut self, level: bool) {
+        let old = self.ca2;
+        self.ca2 = level;
+        if self.ca2_mode().is_active_edge(old, level) {
+            self.set_flag(IRQ_CA2);
+        }
+    }
+
+    pub fn set_cb1(&mut self, level: bool) {
+        let old = self.cb1;
+        self.cb1 = level;
+        if old == level {
+            return;
+        }
+        if self.shift_mode() == ShiftMode::InExternal || self.shift_mode() == ShiftMode::OutExternal
+        {
+            // Data is shifted out on the falling edge and in on the rising
+            // edge of the external clock.
+            if level != self.shift_mode().is_output() {
+                self.shift();
+            }
+        }
+        if level != self.cb1_positive() {
+            return;
+        }
+        self.set_flag(IRQ_CB1);
+        if self.acr & ACR_PB_LATCH != 0 {
+            self.latch_b = self.get_port_b();
+        }
+        if self.cb2_mode() == ControlMode::Handshake {
+            self.cb2_out = true;
+        }
+    }
+
+    pub fn set_cb2(&mut self, level: bool) {
+        let old = self.cb2;
+        self.cb2 = level;
+        if self.cb2_mode().is_active_edge(old, level) {
+            self.set_flag(IRQ_CB2);
+        }
+    }
+
+    pub fn get_ca2(&self) -> bool {
+        match self.ca2_mode() {
+            ControlMode::Low => false,
+            ControlMode::High => true,
+            ControlMode::Handshake | ControlMode::Pulse => self.ca2_out,
+            _ => self.ca2,
+        }
+    }
+
+    pub fn get_cb2(&self) -> bool {
+        if self.shift_mode().is_output() {
+            return self.cb2_out;
+        }
+        match self.cb2_mode() {
+            ControlMode::Low => false,
+            ControlMode::High => true,
+            ControlMode::Handshake | ControlMode::Pulse => self.cb2_out,
+            _ => self.cb2,
+        }
+    }
+
+    pub fn get_t1(&self) -> Word {
+        self.t1_counter
+    }
+
+    pub fn get_t2(&self) -> Word {
+        self.t2_counter
+    }
+
+    fn port_a_access(&mut self, handshake: bool) {
+        let mode = self.ca2_mode();
+        if !mode.is_independent() {
+            self.clear_flag(IRQ_CA2);
+        }
+        self.clear_flag(IRQ_CA1);
+        if !handshake {
+            return;
+        }
+        match mode {
+            ControlMode::Handshake => self.ca2_out = false,
+            ControlMode::Pulse => {
+                self.ca2_out = false;
+                self.ca2_pulse = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn port_b_access(&mut self, write: bool) {
+        let mode = self.cb2_mode();
+        if !mode.is_independent() {
+            self.clear_flag(IRQ_CB2);
+        }
+        self.clear_flag(IRQ_CB1);
+        if !write {
+            return;
+        }
+        match mode {
+            ControlMode::Handshake => self.cb2_out = false,
+            ControlMode::Pulse => {
+                self.cb2_out = false;
+                self.cb2_pulse = true;
+            }
+            _ => {}
+        }
+    }
+
+    fn read_port_a(&self) -> Byte {
+        if self.acr & ACR_PA_LATCH != 0 {
+            return self.latch_a;
+        }
+        self.get_port_a()
+    }
+
+    fn read_port_b(&self) -> Byte {
+        let pins = if self.acr & ACR_PB_LATCH != 0 {
+            self.latch_b
+        } else {
+            self.get_port_b()
+        };
+        // Output bits always read back the output register.
+        let mut val = (self.orb & self.ddrb) | (pins & !self.ddrb);
+        if self.acr & ACR_T1_PB7 != 0 {
+            val = (val & !PB7) | (pins & PB7);
+        }
+        val
+    }
+
+    fn start_shift(&mut self) {
+        self.clear_flag(IRQ_SR);
+        self.sr_count = 0;
+        self.sr_running = self.shift_mode() != ShiftMode::Disabled;
+    }
+
+    fn shift(&mut self) {
+        if !self.sr_running {
+            return;
+        }
+        let mode = self.shift_mode();
+        if mode.is_output() {
+            let bit = self.sr & 0x80 != 0;
+            self.sr = self.sr.rotate_left(1);
+            self.cb2_out = bit;
+        } else {
+            self.sr = (self.sr << 1) | self.cb2 as Byte;
+        }
+        self.sr_count += 1;
+        if self.sr_count == 8 {
+            self.sr_count = 0;
+            if mode != ShiftMode::OutFreeRun {
+                self.sr_running = false;
+                self.set_flag(IRQ_SR);
+            }
+        }
+    }
+
+    fn count_t2(&mut self) {
+        self.t2_counter = self.t2_counter.wrapping_sub(1);
+        if self.t2_counter == 0xFFFF && self.t2_armed {
+            self.t2_armed = false;
+            self.set_flag(IRQ_T2);
+        }
+    }
+
+    fn tick_t1(&mut self) {
+        if self.t1_load {
+            self.t1_load = false;
+            return;
+        }
+        if self.t1_reload {
+            self.t1_reload = false;
+            self.t1_counter = self.t1_latch;
+            return;
+        }
+        self.t1_counter = self.t1_counter.wrapping_sub(1);
+        if self.t1_counter != 0xFFFF {
+            return;
+        }
+        if self.acr & ACR_T1_FREE_RUN != 0 {
+            self.set_flag(IRQ_T1);
+            self.pb7 = !self.pb7;
+            self.t1_reload = true;
+        } else if self.t1_armed {
+            self.t1_armed = false;
+            self.set_flag(IRQ_T1);
+            self.pb7 = true;
+        }
+    }
+
+    fn is_shifting_t2(&self) -> bool {
+        self.shift_mode().uses_t2() && self.sr_running
+    }
+
+    fn is_shifting_phi2(&self) -> bool {
+        matches!(self.shift_mode(), ShiftMode::InPhi2 | ShiftMode::OutPhi2) && self.sr_running
+    }
+
+    // How many of the coming cycles only count the timers down, without
+    // any of them reaching the end of its count.
+    fn get_quiet_cycles(&self) -> u64 {
+        if self.ca2_pulse || self.cb2_pulse || self.is_shifting_phi2() {
+            return 0;
+        }
+        if self.t1_load || self.t1_reload || self.t2_load {
+            return 0;
+        }
+        let t2 = if self.is_shifting_t2() {
+            self.t2_counter & 0xFF
+        } else if self.acr & ACR_T2_PULSE == 0 {
+            self.t2_counter
+        } else {
+            Word::MAX
+        };
+        self.t1_counter.min(t2) as u64
+    }
+
+    fn skip(&mut self, cycles: u64) {
+        if self.ca2_mode() == ControlMode::Pulse {
+            self.ca2_out = true;
+        }
+        if self.cb2_mode() == ControlMode::Pulse {
+            self.cb2_out = true;
+        }
+        let cycles = cycles as Word;
+        self.t1_counter -= cycles;
+        if self.is_shifting_t2() || self.acr & ACR_T2_PULSE == 0 {
+            self.t2_counter -= cycles;
+        }
+    }
+
+    fn step(&mut self) {
+        if self.ca2_pulse {
+            self.ca2_pulse = false;
+        } else if self.ca2_mode() == ControlMode::Pulse {
+            self.ca2_out = true;
+        }
+        if self.cb2_pulse {
+            self.cb2_pulse = false;
+        } else if self.cb2_mode() == ControlMode::Pulse {
+            self.cb2_out = true;
+        }
+
+        self.tick_t1();
+        self.tick_t2();
+
+        if matches!(self.shift_mode(), ShiftMode::InPhi2 | ShiftMode::OutPhi2) {
+            self.shift();
+        }
+    }
+
+    // The cycle T1 next rolls over to $FFFF.
+    fn get_t1_underflow(&self) -> u64 {
+        if self.t1_reload {
+            return self.cycle + 1 + self.t1_latch as u64 + 1;
+        }
+        self.cycle + self.t1_load as u64 + self.t1_counter as u64 + 1
+    }
+
+    fn get_t2_underflow(&self) -> Option<u64> {
+        if !self.t2_armed || self.is_shifting_t2() || self.acr & ACR_T2_PULSE != 0 {
+            return None;
+        }
+        Some(self.cycle + self.t2_load as u64 + self.t2_counter as u64 + 1)
+    }
+
+    // The last shift of a byte. Clocked by T2 only the next shift is worked
+    // out, and the event after it from there.
+    fn get_shift_done(&self) -> Option<u64> {
+        if self.is_shifting_phi2() {
+            return Some(self.cycle + (8 - self.sr_count) as u64);
+        }
+        if self.is_shifting_t2() && self.shift_mode() != ShiftMode::OutFreeRun {
+            let low = self.t2_counter & 0xFF;
+            return Some(self.cycle + self.t2_load as u64 + low as u64 + 1);
+        }
+        None
+    }
+
+    fn tick_t2(&mut self) {
+        if self.t2_load {
+            self.t2_load = false;
+            return;
+        }
+        let mode = self.shift_mode();
+        if mode.uses_t2() && self.sr_running {
+            // Only the low byte counts while clocking the shift register.
+            let low = (self.t2_counter & 0xFF) as Byte;
+            if low == 0 {
+                self.t2_counter = (self.t2_counter & 0xFF00) | self.t2_latch_l as Word;
+                self.shift();
+            } else {
+                self.t2_counter -= 1;
+            }
+            return;
+        }
+        if self.acr & ACR_T2_PULSE == 0 {
+            self.count_t2();
+        }
+    }
+}
+
+impl Device for Via {
+    fn peek(&self, addr: Word) -> Byte {
+        match addr & REG_MASK {
+            ORB => self.read_port_b(),
+            ORA | ORA_NH => self.read_port_a(),
+            DDRB => self.ddrb,
+            DDRA => self.ddra,
+            T1C_L => self.t1_counter.to_le_bytes()[0],
+            T1C_H => self.t1_counter.to_le_bytes()[1],
+            T1L_L => self.t1_latch.to_le_bytes()[0],
+            T1L_H => self.t1_latch.to_le_bytes()[1],
+            T2C_L => self.t2_counter.to_le_bytes()[0],
+            T2C_H => self.t2_counter.to_le_bytes()[1],
+            SR => self.sr,
+            ACR => self.acr,
+            PCR => self.pcr,
+            IFR => self.get_ifr(),
+            IER => self.ier | 0x80,
+            _ => unreachable!(),
+        }
+    }
+
+    fn read(&mut self, addr: Word) -> Byte {
+        let val = self.peek(addr);
+        match addr & REG_MASK {
+            ORB => self.port_b_access(false),
+            ORA => self.port_a_access(true),
+            T1C_L => self.clear_flag(IRQ_T1),
+            T2C_L => self.clear_flag(IRQ_T2),
+            SR => self.start_shift(),
+            ORA_NH => self.port_a_access(false),
+            _ => {}
+        }
+        val
+    }
+
+    fn write(&mut self, addr: Word, val: Byte) {
+        match addr & REG_MASK {
+            ORB => {
+                self.orb = val;
+                self.port_b_access(true);
+            }
+            ORA => {
+                self.ora = val;
+                self.port_a_access(true);
+            }
+            DDRB => self.ddrb = val,
+            DDRA => self.ddra = val,
+            T1C_L | T1L_L => self.t1_latch = (self.t1_latch & 0xFF00) | val as Word,
+            T1C_H => {
+                self.t1_latch = (self.t1_latch & 0x00FF) | ((val as Word) << 8);
+                self.t1_counter = self.t1_latch;
+                self.t1_armed = true;
+                self.t1_load = true;
+                self.t1_reload = false;
+                self.clear_flag(IRQ_T1);
+                if self.acr & ACR_T1_PB7 != 0 {
+                    self.pb7 = false;
+                }
+            }
+            T1L_H => {
+                self.t1_latch = (self.t1_latch & 0x00FF) | ((val as Word) << 8);
+                self.clear_flag(IRQ_T1);
+            }
+            T2C_L => self.t2_latch_l = val,
+            T2C_H => {
+                self.t2_counter = u16::from_le_bytes([self.t2_latch_l, val]);
+                self.t2_armed = true;
+                self.t2_load = true;
+                self.clear_flag(IRQ_T2);
+            }
+            SR => {
+                self.sr = val;
+                self.start_shift();
+            }
+            ACR => {
+                self.acr = val;
+                if self.shift_mode() == ShiftMode::Disabled {
+                    self.sr_running = false;
+                }
+            }
+            PCR => {
+                self.pcr = val;
+                self.ca2_out = self.ca2_mode() != ControlMode::Low;
+                self.cb2_out = self.cb2_mode() != ControlMode::Low;
+            }
+            IFR => self.ifr &= !(val & 0x7F),
+            IER => {
+                if val & 0x80 != 0 {
+                    self.ier |= val & 0x7F;
+                } else {
+                    self.ier &= !(val & 0x7F);
+                }
+            }
+            ORA_NH => self.ora = val,
+            _ => unreachable!(),
+        }
+    }
+
+    fn tick(&mut self, cycle: u64) {
+        while self.cycle < cycle {
+            let cycles = self.get_quiet_cycles().min(cycle - self.cycle);
+            if cycles > 0 {
+                self.skip(cycles);
+                self.cycle += cycles;
+            } else {
+                self.step();
+                self.cycle += 1;
+            }
+        }
+    }
+
+    // Flags are only ever set by counting, so once the interrupt output is
+    // asserted nothing changes it until the CPU clears a flag.
+    fn get_next_event(&self) -> Option<u64> {
+        if self.irq() {
+            return None;
+        }
+        let enabled = |flag: Byte| self.ier & flag != 0;
+        [
+            enabled(IRQ_T1).then(|| self.get_t1_underflow()),
+            self.get_t2_underflow().filter(|_| enabled(IRQ_T2)),
+            self.get_shift_done().filter(|_| enabled(IRQ_SR)),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+    }
+
+    fn irq(&self) -> bool {
+        self.ifr & self.ier & 0x7F != 0
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{memory::Memory, Byte, Word};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DmaAddr {
+    Incrementing(Word),
+    Fixed(Word),
+}
+
+impl DmaAddr {
+    fn at(&self, index: usize) -> Word {
+        match self {
+            DmaAddr::Incrementing(addr) => addr.wrapping_add(index as Word),
+            DmaAddr::Fixed(addr) => *addr,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dma {
+    pub src: DmaAddr,
+    pub dst: DmaAddr,
+    pub len: usize,
+}
+
+impl Dma {
+    pub fn new(src: Word, dst: Word, len: usize) -> Self {
+        Self {
+            src: DmaAddr::Incrementing(src),
+            dst: DmaAddr::Incrementing(dst),
+            len,
+        }
+    }
+
+    pub fn to_port(src: Word, port: Word, len: usize) -> Self {
+        Self {
+            src: DmaAddr::Incrementing(src),
+            dst: DmaAddr::Fixed(port),
+            len,
+        }
+    }
+
+    pub fn from_port(port: Word, dst: Word, len: usize) -> Self {
+        Self {
+            src: DmaAddr::Fixed(port),
+            dst: DmaAddr::Incrementing(dst),
+            len,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Stage {
+    Halt,
+    Align,
+    Get,
+    Put,
+}
+
+// The controller halts the CPU on a read cycle, spends one cycle doing so,
+// and then alternates get/put cycles. Gets only happen on even cycles, so a
+// transfer that would start on an odd one burns an extra alignment cycle.
+#[derive(Debug)]
+pub struct DmaTransfer {
+    dma: Dma,
+    stage: Stage,
+    index: usize,
+    latch: Byte,
+}
+
+impl DmaTransfer {
+    pub fn new(dma: Dma) -> Self {
+        Self {
+            dma,
+            stage: Stage::Halt,
+            index: 0x0,
+            latch: 0x0,
+        }
+    }
+
+    pub fn is_started(&self) -> bool {
+        !matches!(self.stage, Stage::Halt)
+    }
+
+    pub fn tick(&mut self, cycle: u64, mem: &mut Memory) -> bool {
+        match self.stage {
+            Stage::Halt => {
+                self.stage = if (cycle + 1).is_multiple_of(2) {
+                    Stage::Get
+                } else {
+                    Stage::Align
+                };
+                self.index = 0x0;
+                return self.dma.len == 0x0;
+            }
+            Stage::Align => self.stage = Stage::Get,
+            Stage::Get => {
+                self.latch = mem.read_byte(self.dma.src.at(self.index));
+                self.stage = Stage::Put;
+            }
+            Stage::Put => {
+                mem.write_byte(self.dma.dst.at(self.index), self.latch);
+                self.index += 1;
+                self.stage = Stage::Get;
+                return self.index == self.dma.len;
+            }
+        }
+        false
+    }
+}
@@ -1,7 +1,7 @@
 use crate::{
     memory::Memory,
     registers::{Flag, IndexedReg, Registers},
-    Byte, Word, SP,
+    Byte, Word, IRQ_VECTOR, NMI_VECTOR, RESET_VECTOR, SP,
 };
 
 #[derive(Debug)]
@@ -14,7 +14,9 @@ pub enum Instructions {
     CompareWithReg(IndexedReg),
     LoadZPAddr,
     LoadAddr(AddrSource),
+    LoadIndirectAddr,
     AddToAddrBus(IndexedReg),
+    AddToZPAddr(IndexedReg),
     AddToReg(IndexedReg),
     SubFromReg(IndexedReg),
     ORWithReg(IndexedReg),
@@ -34,11 +36,51 @@ pub enum Instructions {
     MoveAddrToPc,
     LoadStackPointer,
     PushPC,
+    PushPCHigh,
+    PushPCLow,
+    PushStatus(bool),
+    LoadVector,
+    LoadResetVector,
+    DecStackPointer,
     PullPC,
     PullToStatus,
     SetBitTestFlags,
+    SetBitTestZeroFlag,
+    TestAndSetBits,
+    TestAndResetBits,
+    SetDataBusBit(u8),
+    ResetDataBusBit(u8),
+    ClearDataBus,
+    UpdateFlags(IndexedReg),
+    MaskInterrupts,
     Shift(Direction, DataSource),
     Rotate(Direction, DataSource),
+    Wait,
+    Stop,
+    AXToDataBus,
+    DataBusToAX,
+    ANDSetCarry,
+    ANDShiftRight,
+    ANDRotateRight,
+    ANDSubFromX,
+    ANDXToA,
+    ANDWithStack,
+    StoreHighAnd(IndexedReg),
+}
+
+impl Instructions {
+    pub fn is_write(&self) -> bool {
+        matches!(
+            self,
+            Instructions::DataBusToMem(_)
+                | Instructions::PushFromReg(_)
+                | Instructions::PushPC
+                | Instructions::PushPCHigh
+                | Instructions::PushPCLow
+                | Instructions::PushStatus(_)
+                | Instructions::StoreHighAnd(_)
+        )
+    }
 }
 
 #[derive(Debug)]
@@ -64,6 +106,8 @@ pub struct InstructionExecutor<'a> {
     reg: &'a mut Registers,
     addr_bus: &'a mut Word,
     data_bus: &'a mut Byte,
+    nmi: &'a mut bool,
+    decimal: bool,
 }
 
 impl<'a> InstructionExecutor<'a> {
@@ -72,12 +116,16 @@ impl<'a> InstructionExecutor<'a> {
         reg: &'a mut Registers,
         addr_bus: &'a mut Word,
         data_bus: &'a mut Byte,
+        nmi: &'a mut bool,
+        decimal: bool,
     ) -> Self {
         Self {
             mem,
             reg,
             addr_bus,
             data_bus,
+            nmi,
+            decimal,
         }
     }
 
@@ -91,7 +139,9 @@ impl<'a> InstructionExecutor<'a> {
             Instructions::CompareWithReg(ind_reg) => self.compare_with_reg(ind_reg),
             Instructions::LoadZPAddr => self.load_zp_addr(),
             Instructions::LoadAddr(source) => self.load_addr(source),
+            Instructions::LoadIndirectAddr => self.load_indirect_addr(),
             Instructions::AddToAddrBus(ind_reg) => self.add_to_addr_bus(ind_reg),
+            Instructions::AddToZPAddr(ind_reg) => self.add_to_zp_addr(ind_reg),
             Instructions::AddToReg(ind_reg) => self.add_to_reg(ind_reg),
             Instructions::SubFromReg(ind_reg) => self.sub_from_reg(ind_reg),
             Instructions::ORWithReg(ind_reg) => self.or_with_reg(ind_reg),
@@ -111,11 +161,36 @@ impl<'a> InstructionExecutor<'a> {
             Instructions::MoveAddrToPc => self.move_addr_to_pc(),
             Instructions::LoadStackPointer => self.load_stack_pointer(),
             Instructions::PushPC => self.push_pc(),
+            Instructions::PushPCHigh => self.push_pc_high(),
+            Instructions::PushPCLow => self.push_pc_low(),
+            Instructions::PushStatus(brk) => self.push_status(*brk),
+            Instructions::LoadVector => self.load_vector(),
+            Instructions::LoadResetVector => self.load_reset_vector(),
+            Instructions::DecStackPointer => self.dec_stack_pointer(),
             Instructions::PullPC => self.pull_pc(),
             Instructions::PullToStatus => self.pull_to_status(),
             Instructions::SetBitTestFlags => self.set_bit_test_flags(),
+            Instructions::SetBitTestZeroFlag => self.set_bit_test_zero_flag(),
+            Instructions::TestAndSetBits => self.test_and_set_bits(),
+            Instructions::TestAndResetBits => self.test_and_reset_bits(),
+            Instructions::SetDataBusBit(bit) => *self.data_bus |= 0x1 << bit,
+            Instructions::ResetDataBusBit(bit) => *self.data_bus &= !(0x1 << bit),
+            Instructions::ClearDataBus => self.clear_data_bus(),
+            Instructions::UpdateFlags(ind_reg) => self.update_flags(ind_reg),
+            Instructions::MaskInterrupts => self.mask_interrupts(),
             Instructions::Shift(dir, source) => self.shift(dir, source),
             Instructions::Rotate(dir, source) => self.rotate(dir, source),
+            // Handled by the CPU, which owns the clock.
+            Instructions::Wait | Instructions::Stop => {}
+            Instructions::AXToDataBus => self.ax_to_data_bus(),
+            Instructions::DataBusToAX => self.data_bus_to_ax(),
+            Instructions::ANDSetCarry => self.and_set_carry(),
+            Instructions::ANDShiftRight => self.and_shift_right(),
+            Instructions::ANDRotateRight => self.and_rotate_right(),
+            Instructions::ANDSubFromX => self.and_sub_from_x(),
+            Instructions::ANDXToA => self.and_x_to_a(),
+            Instructions::ANDWithStack => self.and_with_stack(),
+            Instructions::StoreHighAnd(ind_reg) => self.store_high_and(ind_reg),
         }
     }
 
@@ -174,8 +249,12 @@ impl<'a> InstructionExecutor<'a> {
         *self.data_bus = self.get_reg(ind_reg);
     }
 
+    // Like TXS, TAS sets the stack pointer without touching the flags.
     fn data_bus_to_reg(&mut self, ind_reg: &IndexedReg) {
         *self.get_mut_reg(ind_reg) = *self.data_bus;
+        if !matches!(ind_reg, IndexedReg::S) {
+            self.reg.set_flags(*self.data_bus);
+        }
     }
 
     fn data_bus_to_mem(&mut self, source: &AddrSource) {
@@ -183,15 +262,20 @@ impl<'a> InstructionExecutor<'a> {
             .write_byte(self.get_addr(source), *self.data_bus);
     }
 
+    // TXS is the only transfer that leaves the flags alone.
     fn transfer_reg(&mut self, from: &IndexedReg, to: &IndexedReg) {
         let val = self.get_reg(from);
         let reg = self.get_mut_reg(to);
         *reg = val;
+        if !matches!(to, IndexedReg::S) {
+            self.reg.set_flags(val);
+        }
     }
 
     fn shift_left_data_bus(&mut self) {
         self.reg.get_mut_p().c = *self.data_bus & 0x80 != 0x0;
         *self.data_bus = *self.data_bus << 1;
+        self.reg.set_flags(*self.data_bus);
     }
 
     fn shift(&mut self, dir: &Direction, source: &DataSource) {
@@ -216,51 +300,113 @@ impl<'a> InstructionExecutor<'a> {
         let val = self.get_reg(&IndexedReg::A);
         *self.get_mut_reg(&IndexedReg::A) = val << 1;
         self.reg.get_mut_p().c = val & 0x80 != 0x0;
+        self.reg.set_flags(val << 1);
     }
 
     fn shift_right_data_bus(&mut self) {
         self.reg.get_mut_p().c = *self.data_bus & 0x01 != 0x0;
         *self.data_bus = *self.data_bus >> 1;
+        self.reg.set_flags(*self.data_bus);
     }
 
     fn shift_right_reg(&mut self) {
         let val = self.get_reg(&IndexedReg::A);
         *self.get_mut_reg(&IndexedReg::A) = val >> 1;
         self.reg.get_mut_p().c = val & 0x01 != 0x0;
+        self.reg.set_flags(val >> 1);
+    }
+
+    // Rotates go through the carry, nine bits in all.
+    fn rotate_left_val(&mut self, val: Byte) -> Byte {
+        let res = (val << 1) | self.reg.get_p().c as Byte;
+        self.reg.get_mut_p().c = val & 0x80 != 0x0;
+        self.reg.set_flags(res);
+        res
+    }
+
+    fn rotate_right_val(&mut self, val: Byte) -> Byte {
+        let res = (val >> 1) | ((self.reg.get_p().c as Byte) << 7);
+        self.reg.get_mut_p().c = val & 0x01 != 0x0;
+        self.reg.set_flags(res);
+        res
     }
 
     fn rotate_left_data_bus(&mut self) {
-        self.reg.get_mut_p().c = *self.data_bus & 0x80 != 0x0;
-        *self.data_bus = self.data_bus.rotate_left(1);
+        *self.data_bus = self.rotate_left_val(*self.data_bus);
     }
 
     fn rotate_left_reg(&mut self) {
         let val = self.get_reg(&IndexedReg::A);
-        *self.get_mut_reg(&IndexedReg::A) = val.rotate_left(1);
-        self.reg.get_mut_p().c = val & 0x80 != 0x0;
+        *self.get_mut_reg(&IndexedReg::A) = self.rotate_left_val(val);
     }
 
     fn rotate_right_data_bus(&mut self) {
-        self.reg.get_mut_p().c = *self.data_bus & 0x01 != 0x0;
-        *self.data_bus = self.data_bus.rotate_right(1);
+        *self.data_bus = self.rotate_right_val(*self.data_bus);
     }
 
     fn rotate_right_reg(&mut self) {
         let val = self.get_reg(&IndexedReg::A);
-        *self.get_mut_reg(&IndexedReg::A) = val.rotate_right(1);
-        self.reg.get_mut_p().c = val & 0x01 != 0x0;
+        *self.get_mut_reg(&IndexedReg::A) = self.rotate_right_val(val);
     }
 
+    // ADC, with the NMOS decimal mode quirks: N, V and Z come from the
+    // binary sum (N and V after the low nibble is adjusted). Parts without
+    // decimal mode ignore D.
     fn add_to_reg(&mut self, ind_reg: &IndexedReg) {
-        let val = self.get_reg(ind_reg).wrapping_add(*self.data_bus);
+        let lhs = self.get_reg(ind_reg);
+        let rhs = *self.data_bus;
+        let carry = self.reg.get_p().c as u16;
+        let binary = lhs as u16 + rhs as u16 + carry;
+        let val = match self.reg.get_p().d && self.decimal {
+            false => {
+                self.reg.get_mut_p().c = binary > 0xFF;
+                self.reg.get_mut_p().v = (!(lhs ^ rhs) & (lhs ^ binary as Byte)) & 0x80 != 0;
+                self.reg.set_flags(binary as Byte);
+                binary as Byte
+            }
+            true => {
+                let mut low = (lhs & 0x0F) as u16 + (rhs & 0x0F) as u16 + carry;
+                if low >= 0x0A {
+                    low = ((low + 0x06) & 0x0F) + 0x10;
+                }
+                let mut sum = (lhs & 0xF0) as u16 + (rhs & 0xF0) as u16 + low;
+                self.reg.get_mut_p().n = sum & 0x80 != 0;
+                self.reg.get_mut_p().v = (!(lhs ^ rhs) & (lhs ^ sum as Byte)) & 0x80 != 0;
+                self.reg.get_mut_p().z = binary as Byte == 0;
+                if sum >= 0xA0 {
+                    sum += 0x60;
+                }
+                self.reg.get_mut_p().c = sum > 0xFF;
+                sum as Byte
+            }
+        };
         *self.get_mut_reg(ind_reg) = val;
-        self.reg.set_flags(val);
     }
 
+    // SBC. In decimal mode the flags are those of the binary subtraction.
     fn sub_from_reg(&mut self, ind_reg: &IndexedReg) {
-        let val = self.get_reg(ind_reg).wrapping_sub(*self.data_bus);
+        let lhs = self.get_reg(ind_reg);
+        let rhs = *self.data_bus;
+        let borrow = !self.reg.get_p().c as i16;
+        let binary = lhs as i16 - rhs as i16 - borrow;
+        self.reg.get_mut_p().c = binary >= 0;
+        self.reg.get_mut_p().v = ((lhs ^ rhs) & (lhs ^ binary as Byte)) & 0x80 != 0;
+        self.reg.set_flags(binary as Byte);
+        let val = match self.reg.get_p().d && self.decimal {
+            false => binary as Byte,
+            true => {
+                let mut low = (lhs & 0x0F) as i16 - (rhs & 0x0F) as i16 - borrow;
+                if low < 0 {
+                    low = ((low - 0x06) & 0x0F) - 0x10;
+                }
+                let mut diff = (lhs & 0xF0) as i16 - (rhs & 0xF0) as i16 + low;
+                if diff < 0 {
+                    diff -= 0x60;
+                }
+                diff as Byte
+            }
+        };
         *self.get_mut_reg(ind_reg) = val;
-        self.reg.set_flags(val);
     }
 
     fn or_with_reg(&mut self, ind_reg: &IndexedReg) {
@@ -286,7 +432,7 @@ impl<'a> InstructionExecutor<'a> {
         let rhs = *self.data_bus;
         self.reg.get_mut_p().c = lhs >= rhs;
         self.reg.get_mut_p().z = lhs == rhs;
-        self.reg.get_mut_p().n = lhs < rhs;
+        self.reg.get_mut_p().n = lhs.wrapping_sub(rhs) & 0x80 != 0;
     }
 
     fn load_zp_addr(&mut self) {
@@ -301,31 +447,49 @@ impl<'a> InstructionExecutor<'a> {
         let h_byte = self.mem.read_byte(self.get_addr(source));
         *self.get_mut_addr(source) = self.get_addr(source).wrapping_add(1);
         *self.addr_bus = u16::from_le_bytes([l_byte, h_byte]);
-        println!("ADDR {}", *self.addr_bus);
+    }
+
+    // The high byte of a pointer is read from the same page as its low
+    // byte: the zero page for (zp,X) and (zp),Y, and on NMOS parts the page
+    // of JMP ($xxFF) too.
+    fn load_indirect_addr(&mut self) {
+        let ptr = *self.addr_bus;
+        let l_byte = self.mem.read_byte(ptr);
+        let high = (ptr & 0xFF00) | (ptr.wrapping_add(1) & 0x00FF);
+        let h_byte = self.mem.read_byte(high);
+        *self.addr_bus = u16::from_le_bytes([l_byte, h_byte]);
     }
 
     fn add_to_addr_bus(&mut self, ind_reg: &IndexedReg) {
         *self.addr_bus = self.addr_bus.wrapping_add(u16::from(self.get_reg(ind_reg)));
     }
 
+    // zp,X and zp,Y wrap around within the zero page.
+    fn add_to_zp_addr(&mut self, ind_reg: &IndexedReg) {
+        let l_byte = (*self.addr_bus as Byte).wrapping_add(self.get_reg(ind_reg));
+        *self.addr_bus = u16::from_le_bytes([l_byte, 0]);
+    }
+
     fn inc_reg(&mut self, ind_reg: &IndexedReg) {
-        let reg = self.get_mut_reg(ind_reg);
-        *reg = reg.wrapping_add(1);
+        let val = self.get_reg(ind_reg).wrapping_add(1);
+        *self.get_mut_reg(ind_reg) = val;
+        self.reg.set_flags(val);
     }
 
     fn dec_reg(&mut self, ind_reg: &IndexedReg) {
-        let reg = self.get_mut_reg(ind_reg);
-        *reg = reg.wrapping_sub(1);
+        let val = self.get_reg(ind_reg).wrapping_sub(1);
+        *self.get_mut_reg(ind_reg) = val;
+        self.reg.set_flags(val);
     }
 
     fn push(&mut self, val: Byte) {
         let addr = self.reg.get_s();
         self.mem.write_byte(u16::from_le_bytes([addr, SP]), val);
-        *self.reg.get_mut_s() -= 0b1;
+        *self.reg.get_mut_s() = addr.wrapping_sub(0b1);
     }
 
     fn pull(&mut self) -> Byte {
-        *self.reg.get_mut_s() += 0b1;
+        *self.reg.get_mut_s() = self.reg.get_s().wrapping_add(0b1);
         let addr = self.reg.get_s();
         self.mem.read_byte(u16::from_le_bytes([addr, SP]))
     }
@@ -339,6 +503,7 @@ impl<'a> InstructionExecutor<'a> {
         let val = self.pull();
         let reg = self.get_mut_reg(ind_reg);
         *reg = val;
+        self.reg.set_flags(val);
     }
 
     fn set_flags(&mut self, flag: &Flag) {
@@ -353,10 +518,12 @@ impl<'a> InstructionExecutor<'a> {
 
     fn inc_data_bus(&mut self) {
         *self.data_bus = self.data_bus.wrapping_add(1);
+        self.reg.set_flags(*self.data_bus);
     }
 
     fn dec_data_bus(&mut self) {
         *self.data_bus = self.data_bus.wrapping_sub(1);
+        self.reg.set_flags(*self.data_bus);
     }
 
     fn inc_pc(&mut self) {
@@ -386,6 +553,49 @@ impl<'a> InstructionExecutor<'a> {
         self.push(val[0]);
     }
 
+    fn push_pc_high(&mut self) {
+        let val = self.reg.get_pc().to_le_bytes();
+        self.push(val[1]);
+    }
+
+    fn push_pc_low(&mut self) {
+        let val = self.reg.get_pc().to_le_bytes();
+        self.push(val[0]);
+    }
+
+    fn push_status(&mut self, brk: bool) {
+        let mut val = (self.reg.get_p_byte() & !0x10) | 0x20;
+        if brk {
+            val |= 0x10;
+        }
+        self.push(val);
+    }
+
+    // A pending NMI takes over the vector fetch of BRK and IRQ sequences
+    // that are already under way.
+    fn load_vector(&mut self) {
+        let vector = if *self.nmi {
+            *self.nmi = false;
+            NMI_VECTOR
+        } else {
+            IRQ_VECTOR
+        };
+        let l_byte = self.mem.read_byte(vector);
+        let h_byte = self.mem.read_byte(vector.wrapping_add(1));
+        *self.addr_bus = u16::from_le_bytes([l_byte, h_byte]);
+    }
+
+    fn load_reset_vector(&mut self) {
+        let l_byte = self.mem.read_byte(RESET_VECTOR);
+        let h_byte = self.mem.read_byte(RESET_VECTOR.wrapping_add(1));
+        *self.addr_bus = u16::from_le_bytes([l_byte, h_byte]);
+    }
+
+    // The pushes of an interrupt entry with writes suppressed, as in reset.
+    fn dec_stack_pointer(&mut self) {
+        *self.reg.get_mut_s() = self.reg.get_s().wrapping_sub(0b1);
+    }
+
     fn pull_pc(&mut self) {
         let l_byte = self.pull();
         let h_byte = self.pull();
@@ -397,10 +607,139 @@ impl<'a> InstructionExecutor<'a> {
         self.reg.set_p(p);
     }
 
+    // Z from A AND M, N and V straight from bits 7 and 6 of M.
     fn set_bit_test_flags(&mut self) {
         let val = *self.data_bus & self.get_reg(&IndexedReg::A);
         self.reg.get_mut_p().z = val == 0;
-        self.reg.get_mut_p().n = (val & 0x80) >> 7 == 1;
-        self.reg.get_mut_p().v = (val & 0x40) >> 6 == 1;
+        self.reg.get_mut_p().n = (*self.data_bus & 0x80) >> 7 == 1;
+        self.reg.get_mut_p().v = (*self.data_bus & 0x40) >> 6 == 1;
+    }
+
+    // BIT #imm only sets Z, there being no memory operand to take N and V
+    // from.
+    fn set_bit_test_zero_flag(&mut self) {
+        self.reg.get_mut_p().z = *self.data_bus & self.get_reg(&IndexedReg::A) == 0;
+    }
+
+    // TSB and TRB: Z as for BIT, then the bits set in A are set or cleared
+    // in M.
+    fn test_and_set_bits(&mut self) {
+        let a = self.get_reg(&IndexedReg::A);
+        self.reg.get_mut_p().z = *self.data_bus & a == 0;
+        *self.data_bus |= a;
+    }
+
+    fn test_and_reset_bits(&mut self) {
+        let a = self.get_reg(&IndexedReg::A);
+        self.reg.get_mut_p().z = *self.data_bus & a == 0;
+        *self.data_bus &= !a;
+    }
+
+    fn clear_data_bus(&mut self) {
+        *self.data_bus = 0x0;
+    }
+
+    fn update_flags(&mut self, ind_reg: &IndexedReg) {
+        let val = self.get_reg(ind_reg);
+        self.reg.set_flags(val);
+    }
+
+    fn mask_interrupts(&mut self) {
+        self.reg.get_mut_p().i = true;
+        self.reg.get_mut_p().d = false;
+    }
+
+    // For the unofficial NMOS opcodes, most of which do two things at once.
+
+    fn ax_to_data_bus(&mut self) {
+        *self.data_bus = self.get_reg(&IndexedReg::A) & self.get_reg(&IndexedReg::X);
+    }
+
+    fn data_bus_to_ax(&mut self) {
+        *self.get_mut_reg(&IndexedReg::A) = *self.data_bus;
+        *self.get_mut_reg(&IndexedReg::X) = *self.data_bus;
+        self.reg.set_flags(*self.data_bus);
+    }
+
+    // ANC: AND, with N copied to C.
+    fn and_set_carry(&mut self) {
+        self.and_with_reg(&IndexedReg::A);
+        self.reg.get_mut_p().c = self.reg.get_p().n;
+    }
+
+    // ALR: AND, then LSR A.
+    fn and_shift_right(&mut self) {
+        self.and_with_reg(&IndexedReg::A);
+        self.shift_right_reg();
+    }
+
+    // ARR: AND, then ROR A, with C and V taken from bits 6 and 5 of the
+    // result. In decimal mode N, Z and V come from the binary result, which
+    // is then adjusted like ADC's.
+    fn and_rotate_right(&mut self) {
+        let val = self.get_reg(&IndexedReg::A) & *self.data_bus;
+        let carry = self.reg.get_p().c;
+        let mut res = (val >> 1) | ((carry as Byte) << 7);
+        self.reg.set_flags(res);
+        match self.reg.get_p().d && self.decimal {
+            false => {
+                self.reg.get_mut_p().c = res & 0x40 != 0;
+                self.reg.get_mut_p().v = ((res >> 6) ^ (res >> 5)) & 0x1 != 0;
+            }
+            true => {
+                self.reg.get_mut_p().n = carry;
+                self.reg.get_mut_p().v = (val ^ res) & 0x40 != 0;
+                if (val & 0x0F) + (val & 0x01) > 0x05 {
+                    res = (res & 0xF0) | (res.wrapping_add(0x06) & 0x0F);
+                }
+                self.reg.get_mut_p().c = (val & 0xF0) as u16 + (val & 0x10) as u16 > 0x50;
+                if self.reg.get_p().c {
+                    res = res.wrapping_add(0x60);
+                }
+            }
+        }
+        *self.get_mut_reg(&IndexedReg::A) = res;
+    }
+
+    // AXS: X = (A AND X) - M, with the flags set as for CMP.
+    fn and_sub_from_x(&mut self) {
+        let lhs = self.get_reg(&IndexedReg::A) & self.get_reg(&IndexedReg::X);
+        let rhs = *self.data_bus;
+        let val = lhs.wrapping_sub(rhs);
+        *self.get_mut_reg(&IndexedReg::X) = val;
+        self.reg.get_mut_p().c = lhs >= rhs;
+        self.reg.set_flags(val);
+    }
+
+    // XAA: A = (A OR magic) AND X AND M. The magic constant differs from
+    // chip to chip; this takes it as $FF, so A = X AND M.
+    fn and_x_to_a(&mut self) {
+        let val = self.get_reg(&IndexedReg::X) & *self.data_bus;
+        *self.get_mut_reg(&IndexedReg::A) = val;
+        self.reg.set_flags(val);
+    }
+
+    // LAS: A, X and S all get M AND S.
+    fn and_with_stack(&mut self) {
+        let val = *self.data_bus & self.get_reg(&IndexedReg::S);
+        *self.get_mut_reg(&IndexedReg::A) = val;
+        *self.get_mut_reg(&IndexedReg::X) = val;
+        *self.get_mut_reg(&IndexedReg::S) = val;
+        self.reg.set_flags(val);
+    }
+
+    // SHX, SHY, AHX and TAS store the data bus ANDed with one more than the
+    // high byte of the base address on the address bus. When adding the
+    // index crosses a page, the stored value replaces the high byte of the
+    // address as well.
+    fn store_high_and(&mut self, ind_reg: &IndexedReg) {
+        let base = *self.addr_bus;
+        let val = *self.data_bus & ((base >> 8) as Byte).wrapping_add(1);
+        let mut addr = base.wrapping_add(u16::from(self.get_reg(ind_reg)));
+        if addr & 0xFF00 != base & 0xFF00 {
+            addr = u16::from_le_bytes([addr as Byte, val]);
+        }
+        *self.addr_bus = addr;
+        self.mem.write_byte(addr, val);
     }
 }
@@ -1,8 +1,17 @@
+pub mod board;
+pub mod clock;
 mod constants;
 pub mod cpu;
+pub mod devices;
+pub mod dma;
+pub mod image;
 mod instructions;
+pub mod machines;
 mod memory;
 mod registers;
+pub mod scheduler;
 mod sequencer;
+pub mod serial;
+pub mod wav;
 
 use constants::*;
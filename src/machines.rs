@@ -0,0 +1,16 @@
+// Ready-made configurations of real machines, built from the CPU and the
+// devices.
+
+mod apple1;
+mod atari2600;
+mod breadboard;
+mod c64;
+mod kim1;
+mod nes;
+
+pub use apple1::Apple1;
+pub use atari2600::{Atari2600, BankSwitching, Tia, TiaWrite};
+pub use breadboard::{Breadboard, LcdWiring};
+pub use c64::{Key, Pla, Vic, C64};
+pub use kim1::Kim1;
+pub use nes::{Cartridge, Mirroring, Nes, Ppu};
@@ -0,0 +1,148 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    clock,
+    cpu::CPU,
+    devices::{Line, Pia, PiaPeripheral, Rom},
+    serial::SerialBackend,
+    Byte, Word,
+};
+
+const KEY_RETURN: Byte = 0x0D;
+const KEY_ESCAPE: Byte = 0x1B;
+const KEY_RUBOUT: Byte = b'_';
+
+const DISPLAY_COLUMNS: usize = 40;
+
+type Terminal = Rc<RefCell<Box<dyn SerialBackend>>>;
+
+// ASCII keyboard on PIA port A. The Apple-1 keyboard only has upper case
+// and sets bit 7 of every key; Return is CR and the rubout key, which
+// Wozmon treats as backspace, is `_`.
+struct Keyboard {
+    terminal: Terminal,
+    last_cr: bool,
+}
+
+impl PiaPeripheral for Keyboard {
+    fn strobe(&mut self) -> Option<Byte> {
+        loop {
+            let byte = self.terminal.borrow_mut().receive()?;
+            let last_cr = std::mem::replace(&mut self.last_cr, byte == b'\r');
+            let key = match byte {
+                b'\n' if last_cr => continue,
+                b'\r' | b'\n' => KEY_RETURN,
+                0x08 | 0x7F => KEY_RUBOUT,
+                b'a'..=b'z' => byte.to_ascii_uppercase(),
+                KEY_ESCAPE | 0x20..=0x5F => byte,
+                _ => continue,
+            };
+            return Some(key | 0x80);
+        }
+    }
+}
+
+// Terminal display on PIA port B, PB0-PB6. It shows the 64 upper case
+// characters, folding lower case onto them, starts a new line on CR or
+// after 40 columns and ignores other control characters. It is always
+// ready, so PB7 reads low.
+struct Display {
+    terminal: Terminal,
+    column: usize,
+}
+
+impl Display {
+    fn newline(&mut self) {
+        self.terminal.borrow_mut().transmit(b'\n');
+        self.column = 0;
+    }
+}
+
+impl PiaPeripheral for Display {
+    fn output(&mut self, val: Byte) {
+        let val = val & 0x7F;
+        match val {
+            KEY_RETURN => self.newline(),
+            0x20..=0x7F => {
+                let val = if val >= 0x60 { val - 0x20 } else { val };
+                self.terminal.borrow_mut().transmit(val);
+                self.column += 1;
+                if self.column == DISPLAY_COLUMNS {
+                    self.newline();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// Apple-1: RAM from $0000, a 6821 PIA at $D010-$D013 with the keyboard on
+// port A and the display on port B, and the 256-byte Wozmon ROM at $FF00.
+// Keyboard and display share one serial backend, so `StdioBackend` gives
+// a console and `QueueBackend` a scripted session. The CPU is reset on
+// creation and comes up in Wozmon.
+pub struct Apple1 {
+    cpu: CPU,
+    pia: Rc<RefCell<Pia>>,
+}
+
+impl Apple1 {
+    pub const PIA: Word = 0xD010;
+    pub const WOZMON: Word = 0xFF00;
+    pub const WOZMON_LENGTH: usize = 0x100;
+
+    pub fn new(wozmon: &[Byte], terminal: Box<dyn SerialBackend>) -> Self {
+        assert_eq!(
+            wozmon.len(),
+            Self::WOZMON_LENGTH,
+            "Wozmon ROM must be 256 bytes"
+        );
+        let terminal: Terminal = Rc::new(RefCell::new(terminal));
+
+        let mut pia = Pia::new();
+        pia.set_peripheral_a(Box::new(Keyboard {
+            terminal: terminal.clone(),
+            last_cr: false,
+        }));
+        pia.set_peripheral_b(Box::new(Display {
+            terminal,
+            column: 0,
+        }));
+        pia.set_port_b_input(0x7F);
+        let pia = Rc::new(RefCell::new(pia));
+
+        // The PIA's IRQ outputs are not connected. The clock is the same
+        // 1.022727 MHz as the Apple II's.
+        let mut cpu = CPU::new();
+        cpu.set_clock_rate(clock::APPLE_II_HZ);
+        cpu.map_device_with_line(Self::PIA..=Self::PIA + 0x3, pia.clone(), Line::Unconnected);
+        let rom = Rom::new(Self::WOZMON, wozmon);
+        cpu.map_device(rom.get_range(), Rc::new(RefCell::new(rom)));
+        cpu.reset();
+
+        Self { cpu, pia }
+    }
+
+    pub fn get_cpu(&mut self) -> &mut CPU {
+        &mut self.cpu
+    }
+
+    pub fn get_pia(&self) -> Rc<RefCell<Pia>> {
+        self.pia.clone()
+    }
+
+    // Copies a program into RAM, as if typed into Wozmon.
+    pub fn load(&mut self, addr: Word, data: &[Byte]) {
+        for (i, byte) in data.iter().enumerate() {
+            self.cpu.write_byte(addr.wrapping_add(i as Word), *byte);
+        }
+    }
+
+    pub fn run_until(&mut self, cycle: u64) {
+        self.cpu.run_until(cycle);
+    }
+
+    pub fn run_throttled(&mut self, cycle: u64) {
+        self.cpu.run_throttled(cycle);
+    }
+}
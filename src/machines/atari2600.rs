@@ -0,0 +1,174 @@
+use std::{cell::RefCell, io, rc::Rc};
+
+use crate::{
+    clock,
+    cpu::CPU,
+    devices::{Line, Riot},
+    Byte, Word,
+};
+
+mod cartridge;
+mod tia;
+
+use cartridge::Cartridge;
+
+pub use cartridge::BankSwitching;
+pub use tia::{Tia, TiaWrite};
+
+// The 6507 brings out A0-A12 only.
+const ADDRESS_BITS: u32 = 13;
+
+// The TIA and RIOT are selected by A7 within each page below $1000.
+const PAGES: Word = 0x10;
+const RIOT_OFFSET: Word = 0x80;
+
+// Player 0's joystick is on the high nibble of SWCHA, player 1's on the
+// low one.
+const JOYSTICK_SHIFT: [u32; 2] = [4, 0];
+const JOYSTICK_MASK: Byte = 0x0F;
+const FIRE_INPUT: usize = 4;
+
+// The CPU side of an NTSC Atari 2600: a 6507, which is a 6502 with 13
+// address lines and no interrupt inputs, so everything repeats every 8K.
+// The TIA sits in the lower half of each page below $1000 and the 6532
+// RIOT, with its 128 bytes of RAM, in the upper half; both repeat through
+// $0000-$0FFF. The cartridge is at $1000-$1FFF. Writing WSYNC halts the
+// CPU on its next read until the TIA reaches the start of a line. The CPU
+// is reset on creation.
+pub struct Atari2600 {
+    cpu: CPU,
+    tia: Rc<RefCell<Tia>>,
+    riot: Rc<RefCell<Riot>>,
+    cartridge: Rc<RefCell<Cartridge>>,
+    joysticks: Byte,
+}
+
+impl Atari2600 {
+    pub const TIA: Word = 0x0000;
+    pub const RIOT: Word = 0x0080;
+    pub const CARTRIDGE: Word = 0x1000;
+
+    // Joystick directions, for `set_joystick`.
+    pub const JOY_UP: Byte = 0x1;
+    pub const JOY_DOWN: Byte = 0x2;
+    pub const JOY_LEFT: Byte = 0x4;
+    pub const JOY_RIGHT: Byte = 0x8;
+
+    // The console switches on SWCHB. Reset and select are active low.
+    pub const SWITCH_RESET: Byte = 0x01;
+    pub const SWITCH_SELECT: Byte = 0x02;
+    pub const SWITCH_COLOR: Byte = 0x08;
+    pub const SWITCH_P0_DIFFICULTY: Byte = 0x40;
+    pub const SWITCH_P1_DIFFICULTY: Byte = 0x80;
+
+    // With the bank switching scheme picked from the size of the ROM.
+    pub fn new(rom: &[Byte]) -> io::Result<Self> {
+        Self::with_bank_switching(rom, BankSwitching::detect(rom.len())?)
+    }
+
+    pub fn with_bank_switching(rom: &[Byte], bank_switching: BankSwitching) -> io::Result<Self> {
+        let cartridge = Rc::new(RefCell::new(Cartridge::new(rom, bank_switching)?));
+
+        let mut cpu = CPU::new();
+        cpu.set_address_bits(ADDRESS_BITS);
+        cpu.set_clock_rate(clock::ATARI_2600_NTSC_HZ);
+
+        let tia = Rc::new(RefCell::new(Tia::new()));
+        let riot = Rc::new(RefCell::new(Riot::new()));
+        for page in 0..PAGES {
+            let base = page << 8;
+            cpu.map_device_with_line(
+                base..=base + RIOT_OFFSET - 1,
+                tia.clone(),
+                Line::Unconnected,
+            );
+            cpu.map_device_with_line(
+                base + RIOT_OFFSET..=base + 0xFF,
+                riot.clone(),
+                Line::Unconnected,
+            );
+        }
+        cpu.map_device_with_line(
+            Self::CARTRIDGE..=0x1FFF,
+            cartridge.clone(),
+            Line::Unconnected,
+        );
+
+        let mut atari = Self {
+            cpu,
+            tia,
+            riot,
+            cartridge,
+            joysticks: 0x0,
+        };
+        atari.set_switches(Self::SWITCH_RESET | Self::SWITCH_SELECT | Self::SWITCH_COLOR);
+        atari.cpu.reset();
+        Ok(atari)
+    }
+
+    pub fn get_cpu(&mut self) -> &mut CPU {
+        &mut self.cpu
+    }
+
+    pub fn get_tia(&self) -> Rc<RefCell<Tia>> {
+        self.tia.clone()
+    }
+
+    pub fn get_riot(&self) -> Rc<RefCell<Riot>> {
+        self.riot.clone()
+    }
+
+    // The cartridge bank showing at $1000, counting from 0.
+    pub fn get_bank(&self) -> usize {
+        self.cartridge.borrow().get_bank()
+    }
+
+    // Sets the directions held on joystick 0 or 1, as a mask of the `JOY_`
+    // constants.
+    pub fn set_joystick(&mut self, player: usize, directions: Byte) {
+        let shift = JOYSTICK_SHIFT[player];
+        self.joysticks &= !(JOYSTICK_MASK << shift);
+        self.joysticks |= (directions & JOYSTICK_MASK) << shift;
+        self.riot.borrow_mut().set_port_a_input(!self.joysticks);
+    }
+
+    pub fn set_fire(&mut self, player: usize, pressed: bool) {
+        self.tia
+            .borrow_mut()
+            .set_input(FIRE_INPUT + player, !pressed);
+    }
+
+    // Sets SWCHB as a mask of the `SWITCH_` constants, e.g. clearing
+    // `SWITCH_RESET` holds down game reset.
+    pub fn set_switches(&mut self, switches: Byte) {
+        self.riot.borrow_mut().set_port_b_input(switches);
+    }
+
+    pub fn reset(&mut self) {
+        self.cpu.reset();
+    }
+
+    pub fn tick(&mut self) {
+        self.cpu.tick();
+        let wsync = self.tia.borrow().is_wsync_pending();
+        self.cpu.set_rdy(!wsync);
+    }
+
+    pub fn run_until(&mut self, cycle: u64) {
+        self.cpu.update_devices();
+        while self.cpu.get_cycles() < cycle {
+            self.tick();
+        }
+        self.cpu.update_devices();
+    }
+
+    // Like `run_until`, keeping to the clock rate if the CPU is throttled.
+    pub fn run_throttled(&mut self, cycle: u64) {
+        self.cpu.update_devices();
+        while self.cpu.get_cycles() < cycle {
+            self.tick();
+            self.cpu.sync_throttle();
+        }
+        self.cpu.update_devices();
+    }
+}
@@ -0,0 +1,110 @@
+use std::io;
+
+use crate::{devices::Device, Byte, Word};
+
+const BANK_LENGTH: usize = 0x1000;
+const OFFSET_MASK: Word = 0x0FFF;
+
+// How a cartridge larger than the 4K window picks the bank that shows
+// through it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BankSwitching {
+    // A 2K or 4K ROM, with a 2K one repeated.
+    None,
+    // 8K in two banks, selected by accessing $1FF8 or $1FF9.
+    F8,
+    // 16K in four banks, selected by accessing $1FF6 to $1FF9.
+    F6,
+}
+
+impl BankSwitching {
+    // The scheme a ROM of this size normally uses.
+    pub fn detect(length: usize) -> io::Result<Self> {
+        match length {
+            0x800 | 0x1000 => Ok(Self::None),
+            0x2000 => Ok(Self::F8),
+            0x4000 => Ok(Self::F6),
+            _ => Err(invalid(format!(
+                "no bank switching for a {length} byte ROM"
+            ))),
+        }
+    }
+
+    fn get_hotspot(&self) -> Word {
+        match self {
+            Self::None => 0x0,
+            Self::F8 => 0xFF8,
+            Self::F6 => 0xFF6,
+        }
+    }
+
+    fn get_banks(&self) -> usize {
+        match self {
+            Self::None => 1,
+            Self::F8 => 2,
+            Self::F6 => 4,
+        }
+    }
+}
+
+// The cartridge slot at $1000-$1FFF. Bank switching hotspots respond to
+// reads and writes alike. The bank a cartridge starts in is undefined on
+// the real thing; here it is the last one.
+pub(super) struct Cartridge {
+    rom: Vec<Byte>,
+    bank_switching: BankSwitching,
+    bank: usize,
+}
+
+impl Cartridge {
+    pub(super) fn new(rom: &[Byte], bank_switching: BankSwitching) -> io::Result<Self> {
+        let fits = match bank_switching {
+            BankSwitching::None => rom.len() == 0x800 || rom.len() == BANK_LENGTH,
+            _ => rom.len() == bank_switching.get_banks() * BANK_LENGTH,
+        };
+        if !fits {
+            return Err(invalid(format!(
+                "a {} byte ROM does not fit {bank_switching:?} bank switching",
+                rom.len()
+            )));
+        }
+        Ok(Self {
+            rom: rom.to_vec(),
+            bank_switching,
+            bank: bank_switching.get_banks() - 1,
+        })
+    }
+
+    pub(super) fn get_bank(&self) -> usize {
+        self.bank
+    }
+
+    fn switch_bank(&mut self, offset: Word) {
+        let bank = offset.wrapping_sub(self.bank_switching.get_hotspot()) as usize;
+        if bank < self.bank_switching.get_banks() && self.bank_switching != BankSwitching::None {
+            self.bank = bank;
+        }
+    }
+}
+
+impl Device for Cartridge {
+    fn peek(&self, addr: Word) -> Byte {
+        let offset = addr & OFFSET_MASK;
+        self.rom[(self.bank * BANK_LENGTH + offset as usize) % self.rom.len()]
+    }
+
+    // The byte comes from the bank selected before the access.
+    fn read(&mut self, addr: Word) -> Byte {
+        let val = self.peek(addr);
+        self.switch_bank(addr & OFFSET_MASK);
+        val
+    }
+
+    fn write(&mut self, addr: Word, _val: Byte) {
+        self.switch_bank(addr & OFFSET_MASK);
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
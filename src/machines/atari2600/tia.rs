@@ -0,0 +1,190 @@
+use crate::{devices::Device, Byte, Word};
+
+const CLOCKS_PER_CYCLE: u64 = 3;
+const CLOCKS_PER_LINE: u64 = 228;
+
+// Write registers, decoded from A0-A5.
+const WRITE_MASK: Word = 0x3F;
+const VSYNC: Byte = 0x00;
+const VBLANK: Byte = 0x01;
+const WSYNC: Byte = 0x02;
+const RSYNC: Byte = 0x03;
+
+// Read registers, decoded from A0-A3. $0-$7 are the collision latches and
+// $8-$D the input ports.
+const READ_MASK: Word = 0x0F;
+const INPT0: Word = 0x8;
+const INPUTS: usize = 6;
+
+const VSYNC_ON: Byte = 0x02;
+const VBLANK_LATCH: Byte = 0x40;
+const VBLANK_DUMP: Byte = 0x80;
+const INPUT_HIGH: Byte = 0x80;
+
+// A TIA register write, with where the beam was when it happened.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TiaWrite {
+    pub frame: u64,
+    pub scanline: u64,
+    // Colour clock within the line, 0 to 227, of the CPU cycle that wrote.
+    pub clock: u64,
+    pub register: Byte,
+    pub val: Byte,
+}
+
+// The parts of the 2600's TIA the CPU can see, without any video or audio.
+// It runs three colour clocks per CPU cycle, 228 to the line. Writing WSYNC
+// holds RDY low until the start of the next line, and ending VSYNC starts a
+// new frame at scanline 0. The input ports read the levels set with
+// `set_input`: INPT0-INPT3 are grounded while VBLANK bit 7 is set, and
+// INPT4-INPT5 latch low while VBLANK bit 6 is set. The collision latches
+// always read clear. Writes can be logged for checking a kernel's timing.
+pub struct Tia {
+    cycle: u64,
+    registers: [Byte; 0x40],
+    inputs: [bool; INPUTS],
+    latched: [bool; 2],
+    clock: u64,
+    scanline: u64,
+    frame: u64,
+    wsync: bool,
+    logging: bool,
+    writes: Vec<TiaWrite>,
+}
+
+impl Default for Tia {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tia {
+    pub fn new() -> Self {
+        Self {
+            cycle: 0x0,
+            registers: [0x0; 0x40],
+            inputs: [true; INPUTS],
+            latched: [true; 2],
+            clock: 0x0,
+            scanline: 0x0,
+            frame: 0x0,
+            wsync: false,
+            logging: false,
+            writes: vec![],
+        }
+    }
+
+    // The level on INPT0-INPT5. The fire buttons on INPT4 and INPT5 are
+    // active low.
+    pub fn set_input(&mut self, input: usize, high: bool) {
+        self.inputs[input] = high;
+        if input >= 4 && !high {
+            self.latched[input - 4] = false;
+        }
+    }
+
+    // Whether RDY is being held low until the end of the line.
+    pub fn is_wsync_pending(&self) -> bool {
+        self.wsync
+    }
+
+    pub fn get_clock(&self) -> u64 {
+        self.clock
+    }
+
+    pub fn get_scanline(&self) -> u64 {
+        self.scanline
+    }
+
+    pub fn get_frame(&self) -> u64 {
+        self.frame
+    }
+
+    // The last value written to a write register.
+    pub fn get_register(&self, register: Byte) -> Byte {
+        self.registers[(register as Word & WRITE_MASK) as usize]
+    }
+
+    pub fn set_logging(&mut self, logging: bool) {
+        self.logging = logging;
+    }
+
+    // The writes logged since the last call.
+    pub fn take_writes(&mut self) -> Vec<TiaWrite> {
+        std::mem::take(&mut self.writes)
+    }
+
+    fn read_input(&self, input: usize) -> Byte {
+        let vblank = self.registers[VBLANK as usize];
+        let high = match input {
+            0..=3 => self.inputs[input] && vblank & VBLANK_DUMP == 0,
+            _ if vblank & VBLANK_LATCH != 0 => self.latched[input - 4],
+            _ => self.inputs[input],
+        };
+        if high {
+            INPUT_HIGH
+        } else {
+            0x0
+        }
+    }
+}
+
+impl Device for Tia {
+    fn peek(&self, addr: Word) -> Byte {
+        match addr & READ_MASK {
+            reg @ INPT0.. if ((reg - INPT0) as usize) < INPUTS => {
+                self.read_input((reg - INPT0) as usize)
+            }
+            _ => 0x0,
+        }
+    }
+
+    fn write(&mut self, addr: Word, val: Byte) {
+        let register = (addr & WRITE_MASK) as Byte;
+        if self.logging {
+            self.writes.push(TiaWrite {
+                frame: self.frame,
+                scanline: self.scanline,
+                clock: self.clock,
+                register,
+                val,
+            });
+        }
+
+        match register {
+            VSYNC if self.registers[VSYNC as usize] & VSYNC_ON != 0 && val & VSYNC_ON == 0 => {
+                self.frame += 1;
+                self.scanline = 0;
+            }
+            // Turning the latch on clears it to the current levels.
+            VBLANK if self.registers[VBLANK as usize] & VBLANK_LATCH == 0 => {
+                self.latched = [self.inputs[4], self.inputs[5]];
+            }
+            WSYNC => self.wsync = true,
+            RSYNC => self.clock = 0,
+            _ => {}
+        }
+        self.registers[register as usize] = val;
+    }
+
+    fn tick(&mut self, cycle: u64) {
+        if cycle <= self.cycle {
+            return;
+        }
+        let clock = self.clock + (cycle - self.cycle) * CLOCKS_PER_CYCLE;
+        self.cycle = cycle;
+        self.clock = clock % CLOCKS_PER_LINE;
+        let lines = clock / CLOCKS_PER_LINE;
+        if lines > 0 {
+            self.scanline += lines;
+            self.wsync = false;
+        }
+    }
+
+    // RDY is let go at the start of the next line.
+    fn get_next_event(&self) -> Option<u64> {
+        let clocks = CLOCKS_PER_LINE - self.clock;
+        self.wsync
+            .then_some(self.cycle + clocks.div_ceil(CLOCKS_PER_CYCLE))
+    }
+}
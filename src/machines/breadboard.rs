@@ -0,0 +1,146 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    clock,
+    cpu::{Variant, CPU},
+    devices::{Acia, Hd44780, Rom, Via},
+    serial::SerialBackend,
+    Byte, Word,
+};
+
+const LCD_COLUMNS: usize = 16;
+const LCD_ROWS: usize = 2;
+
+// Control lines in the 8-bit wiring, on port A.
+const PA_E: Byte = 0x80;
+const PA_RW: Byte = 0x40;
+const PA_RS: Byte = 0x20;
+
+// Control lines in the 4-bit wiring, on port B above the data nibble.
+const PB_E: Byte = 0x40;
+const PB_RW: Byte = 0x20;
+const PB_RS: Byte = 0x10;
+const PB_DATA: Byte = 0x0F;
+
+// How the 16x2 LCD hangs off the VIA.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LcdWiring {
+    // DB0-DB7 on port B, with E, RW and RS on PA7-PA5.
+    EightBit,
+    // DB4-DB7 on PB0-PB3, with RS, RW and E on PB4-PB6, leaving port A
+    // free for e.g. a keyboard.
+    FourBit,
+}
+
+// The popular breadboard computer: a 65C02 at 1 MHz with RAM from $0000, a
+// 6551 ACIA at $5000, a 6522 VIA at $6000 driving a 16x2 character LCD and
+// a 32K EEPROM at $8000. The RAM is a 32K part but only $0000-$3FFF is
+// decoded, the ACIA is selected by A12 and the VIA by A13, so both repeat
+// through their 4K and 8K blocks. Both interrupt outputs go to IRQ. The CPU
+// is reset on creation and boots through the vector in the ROM image.
+pub struct Breadboard {
+    cpu: CPU,
+    via: Rc<RefCell<Via>>,
+    acia: Rc<RefCell<Acia>>,
+    lcd: Hd44780,
+    wiring: LcdWiring,
+}
+
+impl Breadboard {
+    pub const ACIA: Word = 0x5000;
+    pub const VIA: Word = 0x6000;
+    pub const ROM: Word = 0x8000;
+    pub const ROM_LENGTH: usize = 0x8000;
+
+    pub fn new(rom: &[Byte], terminal: Box<dyn SerialBackend>, wiring: LcdWiring) -> Self {
+        assert_eq!(rom.len(), Self::ROM_LENGTH, "ROM image must be 32K");
+
+        let mut cpu = CPU::new();
+        cpu.set_variant(Variant::Cmos);
+        cpu.set_clock_rate(clock::DEFAULT_HZ);
+
+        let via = Rc::new(RefCell::new(Via::new()));
+        let acia = Rc::new(RefCell::new(Acia::new(terminal, cpu.get_clock_rate())));
+        cpu.map_device(Self::ACIA..=Self::ACIA + 0x0FFF, acia.clone());
+        cpu.map_device(Self::VIA..=Self::VIA + 0x1FFF, via.clone());
+        let rom = Rom::new(Self::ROM, rom);
+        cpu.map_device(rom.get_range(), Rc::new(RefCell::new(rom)));
+        cpu.reset();
+
+        let lcd = Hd44780::new(LCD_COLUMNS, LCD_ROWS, cpu.get_clock_rate());
+        Self {
+            cpu,
+            via,
+            acia,
+            lcd,
+            wiring,
+        }
+    }
+
+    pub fn get_cpu(&mut self) -> &mut CPU {
+        &mut self.cpu
+    }
+
+    pub fn get_via(&self) -> Rc<RefCell<Via>> {
+        self.via.clone()
+    }
+
+    pub fn get_acia(&self) -> Rc<RefCell<Acia>> {
+        self.acia.clone()
+    }
+
+    pub fn get_lcd(&self) -> &Hd44780 {
+        &self.lcd
+    }
+
+    // Both LCD lines, as shown.
+    pub fn get_lcd_text(&self) -> String {
+        self.lcd.get_text()
+    }
+
+    // Moves the LCD's pins to follow the VIA's, and while it is being read
+    // puts its output on the data lines.
+    fn update_lcd(&mut self) {
+        let mut via = self.via.borrow_mut();
+        let (control, rs, rw, e, data) = match self.wiring {
+            LcdWiring::EightBit => (via.get_port_a(), PA_RS, PA_RW, PA_E, via.get_port_b()),
+            LcdWiring::FourBit => {
+                let port_b = via.get_port_b();
+                (port_b, PB_RS, PB_RW, PB_E, (port_b & PB_DATA) << 4)
+            }
+        };
+        self.lcd
+            .set_pins(control & rs != 0, control & rw != 0, control & e != 0, data);
+        self.lcd.tick(self.cpu.get_cycles());
+
+        if let Some(data) = self.lcd.get_data() {
+            match self.wiring {
+                LcdWiring::EightBit => via.set_port_b_input(data),
+                LcdWiring::FourBit => via.set_port_b_input(!PB_DATA | data >> 4),
+            }
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.cpu.tick();
+        self.update_lcd();
+    }
+
+    pub fn run_until(&mut self, cycle: u64) {
+        self.cpu.update_devices();
+        while self.cpu.get_cycles() < cycle {
+            self.tick();
+        }
+        self.cpu.update_devices();
+    }
+
+    // Like `run_until`, keeping to the clock rate if the CPU is throttled.
+    pub fn run_throttled(&mut self, cycle: u64) {
+        self.cpu.update_devices();
+        while self.cpu.get_cycles() < cycle {
+            self.tick();
+            self.cpu.sync_throttle();
+        }
+        self.cpu.update_devices();
+    }
+}
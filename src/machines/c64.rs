@@ -0,0 +1,237 @@
+use std::{cell::RefCell, io, rc::Rc};
+
+use crate::{
+    clock,
+    cpu::CPU,
+    devices::{Cia, Line},
+    Byte, Word, MEMORY_LENGTH,
+};
+
+mod keyboard;
+mod pla;
+mod vic;
+
+pub use keyboard::Key;
+pub use pla::Pla;
+pub use vic::Vic;
+
+use keyboard::Keyboard;
+
+const TOD_HZ: u64 = 50;
+const BASIC_START: Word = 0x0801;
+// BASIC's pointer to the end of the program, and the KERNAL's to the end
+// of the last load.
+const VARTAB: Word = 0x002D;
+const EAL: Word = 0x00AE;
+const VIC_BANK_LENGTH: Word = 0x4000;
+const REVERSE: Byte = 0x80;
+
+// What the screen codes show as, for the upper case and graphics
+// character set and the lower and upper case one. Graphics characters
+// come out as `#`.
+fn screen_char(code: Byte, lower_case: bool) -> char {
+    let code = code & !REVERSE;
+    match code {
+        0x00 => '@',
+        0x01..=0x1A if lower_case => (b'a' + code - 0x01) as char,
+        0x01..=0x1A => (b'A' + code - 0x01) as char,
+        0x1B => '[',
+        0x1C => '£',
+        0x1D => ']',
+        0x1E => '^',
+        0x1F => '_',
+        0x20..=0x3F => code as char,
+        0x41..=0x5A if lower_case => (b'A' + code - 0x41) as char,
+        0x60 => ' ',
+        _ => '#',
+    }
+}
+
+// A PAL Commodore 64 without sound or video output: the 6510 with its
+// processor port, the PLA banking in BASIC at $A000, the I/O area or the
+// character ROM at $D000 and the KERNAL at $E000, CIA 1 with the keyboard
+// on IRQ and CIA 2 on NMI, and a VIC-II that counts raster lines and
+// raises raster interrupts. RESTORE pulls NMI directly. The ROMs are the
+// user's own, and the CPU is reset on creation, so with the real ones it
+// boots to BASIC's READY prompt.
+pub struct C64 {
+    cpu: CPU,
+    pla: Rc<RefCell<Pla>>,
+    vic: Rc<RefCell<Vic>>,
+    cia1: Rc<RefCell<Cia>>,
+    cia2: Rc<RefCell<Cia>>,
+    keyboard: Keyboard,
+}
+
+impl C64 {
+    pub const BASIC_LENGTH: usize = 0x2000;
+    pub const KERNAL_LENGTH: usize = 0x2000;
+    pub const CHARGEN_LENGTH: usize = 0x1000;
+
+    pub const VIC: Word = 0xD000;
+    pub const CIA1: Word = 0xDC00;
+    pub const CIA2: Word = 0xDD00;
+
+    pub const SCREEN_COLUMNS: usize = 40;
+    pub const SCREEN_ROWS: usize = 25;
+
+    pub const KEY_RETURN: Key = (0, 1);
+    pub const KEY_LEFT_SHIFT: Key = keyboard::LEFT_SHIFT;
+    pub const KEY_SPACE: Key = (7, 4);
+    pub const KEY_RUN_STOP: Key = (7, 7);
+
+    pub fn new(basic: &[Byte], kernal: &[Byte], chargen: &[Byte]) -> Self {
+        assert_eq!(basic.len(), Self::BASIC_LENGTH, "BASIC ROM must be 8K");
+        assert_eq!(kernal.len(), Self::KERNAL_LENGTH, "KERNAL ROM must be 8K");
+        assert_eq!(
+            chargen.len(),
+            Self::CHARGEN_LENGTH,
+            "character ROM must be 4K"
+        );
+
+        let mut cpu = CPU::new();
+        cpu.set_clock_rate(clock::C64_PAL_HZ);
+
+        let vic = Rc::new(RefCell::new(Vic::new()));
+        let cia1 = Rc::new(RefCell::new(Cia::new(cpu.get_clock_rate())));
+        let cia2 = Rc::new(RefCell::new(Cia::new(cpu.get_clock_rate())));
+        cia1.borrow_mut().set_tod_frequency(TOD_HZ);
+        cia2.borrow_mut().set_tod_frequency(TOD_HZ);
+        let pla = Rc::new(RefCell::new(Pla::new(basic, kernal, chargen)));
+
+        // The chips are mapped at their addresses with their interrupts
+        // wired, and the PLA over all of memory last, so that every access
+        // goes to it unless it leaves the chips selected.
+        cpu.map_device(Self::VIC..=Self::VIC + 0x3FF, vic.clone());
+        cpu.map_device(Self::CIA1..=Self::CIA1 + 0xFF, cia1.clone());
+        cpu.map_device_with_line(Self::CIA2..=Self::CIA2 + 0xFF, cia2.clone(), Line::Nmi);
+        cpu.map_device(0x0000..=0xFFFF, pla.clone());
+        cpu.reset();
+
+        Self {
+            cpu,
+            pla,
+            vic,
+            cia1,
+            cia2,
+            keyboard: Keyboard::new(),
+        }
+    }
+
+    pub fn get_cpu(&mut self) -> &mut CPU {
+        &mut self.cpu
+    }
+
+    pub fn get_pla(&self) -> Rc<RefCell<Pla>> {
+        self.pla.clone()
+    }
+
+    pub fn get_vic(&self) -> Rc<RefCell<Vic>> {
+        self.vic.clone()
+    }
+
+    pub fn get_cia1(&self) -> Rc<RefCell<Cia>> {
+        self.cia1.clone()
+    }
+
+    pub fn get_cia2(&self) -> Rc<RefCell<Cia>> {
+        self.cia2.clone()
+    }
+
+    // Copies a program into RAM, whatever is banked in over it.
+    pub fn load(&mut self, addr: Word, data: &[Byte]) {
+        let mut pla = self.pla.borrow_mut();
+        for (i, byte) in data.iter().enumerate() {
+            pla.get_mut_ram()[(addr as usize + i) % MEMORY_LENGTH] = *byte;
+        }
+    }
+
+    // Loads a PRG file, which starts with its load address, and returns
+    // that address. A BASIC program also has the end of program pointers
+    // set as LOAD would, ready to RUN, so it has to be loaded after BASIC
+    // has started.
+    pub fn load_prg(&mut self, prg: &[Byte]) -> io::Result<Word> {
+        let [low, high, data @ ..] = prg else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "PRG file too short",
+            ));
+        };
+        let addr = Word::from_le_bytes([*low, *high]);
+        self.load(addr, data);
+        if addr == BASIC_START {
+            let end = addr.wrapping_add(data.len() as Word).to_le_bytes();
+            self.load(VARTAB, &end);
+            self.load(EAL, &end);
+        }
+        Ok(addr)
+    }
+
+    pub fn set_key(&mut self, key: Key, pressed: bool) {
+        self.keyboard.set_key(key, pressed);
+    }
+
+    pub fn set_restore_key(&mut self, pressed: bool) {
+        self.cpu.set_nmi(pressed);
+    }
+
+    // Queues text to be typed on the keyboard, one key at a time. Lower
+    // case letters are typed as upper case, newlines press RETURN and
+    // characters without a key are skipped.
+    pub fn type_text(&mut self, text: &str) {
+        self.keyboard.type_text(text);
+    }
+
+    pub fn is_typing(&self) -> bool {
+        self.keyboard.is_typing()
+    }
+
+    // Where the VIC-II reads the screen codes from, which CIA 2 port A
+    // picks the 16K bank for.
+    pub fn get_screen_addr(&self) -> Word {
+        let bank = 0x3 - (self.cia2.borrow().get_port_a() & 0x3);
+        Word::from(bank) * VIC_BANK_LENGTH + self.vic.borrow().get_screen_offset()
+    }
+
+    // The 40x25 text screen, row by row. Reversed characters show as
+    // normal ones.
+    pub fn get_screen(&self) -> String {
+        let addr = self.get_screen_addr() as usize;
+        let lower_case = self.vic.borrow().is_lower_case();
+        let pla = self.pla.borrow();
+        let codes = &pla.get_ram()[addr..addr + Self::SCREEN_COLUMNS * Self::SCREEN_ROWS];
+        codes
+            .chunks(Self::SCREEN_COLUMNS)
+            .map(|row| {
+                row.iter()
+                    .map(|code| screen_char(*code, lower_case))
+                    .collect()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    pub fn tick(&mut self) {
+        self.cpu.tick();
+        self.keyboard.tick(self.cpu.get_cycles());
+        self.keyboard.update(&mut self.cia1.borrow_mut());
+    }
+
+    pub fn run_until(&mut self, cycle: u64) {
+        self.cpu.update_devices();
+        while self.cpu.get_cycles() < cycle {
+            self.tick();
+        }
+        self.cpu.update_devices();
+    }
+
+    // Like `run_until`, keeping to the clock rate if the CPU is throttled.
+    pub fn run_throttled(&mut self, cycle: u64) {
+        self.cpu.update_devices();
+        while self.cpu.get_cycles() < cycle {
+            self.tick();
+            self.cpu.sync_throttle();
+        }
+        self.cpu.update_devices();
+    }
+}
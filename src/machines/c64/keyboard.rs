@@ -0,0 +1,140 @@
+use std::collections::VecDeque;
+
+use crate::{devices::Cia, Byte};
+
+// A key's column, driven from CIA 1 port A, and row, read on port B.
+pub type Key = (usize, usize);
+
+pub const LEFT_SHIFT: Key = (1, 7);
+
+// How long each typed key is held and then left up. The KERNAL scans the
+// keyboard every 1/60 s and only sees a key when it changes, so the same
+// letter twice needs the gap.
+const HOLD_CYCLES: u64 = 40_000;
+const GAP_CYCLES: u64 = 20_000;
+
+// The character each key types without shift, or NUL for those that
+// don't type one.
+const LAYOUT: [[char; 8]; 8] = [
+    ['\x08', '\n', '\0', '\0', '\0', '\0', '\0', '\0'],
+    ['3', 'W', 'A', '4', 'Z', 'S', 'E', '\0'],
+    ['5', 'R', 'D', '6', 'C', 'F', 'T', 'X'],
+    ['7', 'Y', 'G', '8', 'B', 'H', 'U', 'V'],
+    ['9', 'I', 'J', '0', 'M', 'K', 'O', 'N'],
+    ['+', 'P', 'L', '-', '.', ':', '@', ','],
+    ['£', '*', ';', '\0', '\0', '=', '^', '/'],
+    ['1', '_', '\0', '2', ' ', '\0', 'Q', '\0'],
+];
+
+// Characters typed with shift, and the key they are on.
+const SHIFTED: [(char, char); 14] = [
+    ('!', '1'),
+    ('"', '2'),
+    ('#', '3'),
+    ('$', '4'),
+    ('%', '5'),
+    ('&', '6'),
+    ('\'', '7'),
+    ('(', '8'),
+    (')', '9'),
+    ('<', ','),
+    ('>', '.'),
+    ('?', '/'),
+    ('[', ':'),
+    (']', ';'),
+];
+
+fn find_key(c: char) -> Option<Key> {
+    (0..8)
+        .flat_map(|column| (0..8).map(move |row| (column, row)))
+        .find(|(column, row)| LAYOUT[*column][*row] == c)
+}
+
+// The keys to hold down to type `c`, if it can be typed. Lower case
+// letters type as upper case, which is what the unshifted keys give.
+pub fn get_keys(c: char) -> Option<Vec<Key>> {
+    let c = match c {
+        '\r' => '\n',
+        _ => c.to_ascii_uppercase(),
+    };
+    if c == '\0' {
+        return None;
+    }
+    if let Some((_, base)) = SHIFTED.iter().find(|(shifted, _)| *shifted == c) {
+        return Some(vec![LEFT_SHIFT, find_key(*base)?]);
+    }
+    Some(vec![find_key(c)?])
+}
+
+// The 8x8 key matrix between CIA 1's ports, and the text being typed on
+// it.
+pub struct Keyboard {
+    pressed: [Byte; 8],
+    typing: VecDeque<Vec<Key>>,
+    held: Option<Vec<Key>>,
+    next: u64,
+}
+
+impl Keyboard {
+    pub fn new() -> Self {
+        Self {
+            pressed: [0x0; 8],
+            typing: VecDeque::new(),
+            held: None,
+            next: 0,
+        }
+    }
+
+    pub fn set_key(&mut self, (column, row): Key, pressed: bool) {
+        match pressed {
+            true => self.pressed[column] |= 1 << row,
+            false => self.pressed[column] &= !(1 << row),
+        }
+    }
+
+    pub fn type_text(&mut self, text: &str) {
+        self.typing.extend(text.chars().filter_map(get_keys));
+    }
+
+    pub fn is_typing(&self) -> bool {
+        !self.typing.is_empty() || self.held.is_some()
+    }
+
+    pub fn tick(&mut self, cycle: u64) {
+        if cycle < self.next {
+            return;
+        }
+        if let Some(keys) = self.held.take() {
+            for key in keys {
+                self.set_key(key, false);
+            }
+            self.next = cycle + GAP_CYCLES;
+        } else if let Some(keys) = self.typing.pop_front() {
+            for key in &keys {
+                self.set_key(*key, true);
+            }
+            self.held = Some(keys);
+            self.next = cycle + HOLD_CYCLES;
+        }
+    }
+
+    // A pressed key connects its column and row, so a column driven low
+    // on port A pulls the key's row low on port B, and the other way
+    // round.
+    pub fn update(&self, cia: &mut Cia) {
+        let columns = cia.get_ddra() & !cia.get_port_a();
+        let rows = cia.get_ddrb() & !cia.get_port_b();
+        let mut row_input = 0xFF;
+        let mut column_input = 0xFF;
+        for (column, pressed) in self.pressed.iter().enumerate() {
+            if columns & (1 << column) != 0 {
+                row_input &= !pressed;
+            }
+            if rows & pressed != 0 {
+                column_input &= !(1 << column);
+            }
+        }
+        cia.set_port_a_input(column_input);
+        cia.set_port_b_input(row_input);
+    }
+}
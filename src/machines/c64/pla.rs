@@ -0,0 +1,141 @@
+use crate::{devices::Device, Byte, Word, MEMORY_LENGTH};
+
+const PORT_DDR: Word = 0x0000;
+const PORT_DATA: Word = 0x0001;
+
+// LORAM, HIRAM and CHAREN, and the cassette sense line, are pulled up
+// while they are inputs.
+const PORT_PULL_UPS: Byte = 0x17;
+const LORAM: Byte = 0x01;
+const HIRAM: Byte = 0x02;
+const CHAREN: Byte = 0x04;
+
+const BASIC: Word = 0xA000;
+const IO: Word = 0xD000;
+const KERNAL: Word = 0xE000;
+
+const COLOR_RAM_LENGTH: usize = 0x400;
+
+// The 6510's processor port and the PLA, which together decide what the
+// CPU sees in the BASIC, I/O and KERNAL areas, plus the 64K of RAM that is
+// there when it sees nothing else. Writes to the ROM areas go to the RAM
+// underneath. Only the banking of a machine without a cartridge is done,
+// so GAME and EXROM are taken to be high. While the I/O area is banked in
+// the PLA is not selected for the VIC-II and CIAs, so accesses to those go
+// to the chips mapped underneath it.
+pub struct Pla {
+    ram: Vec<Byte>,
+    basic: Vec<Byte>,
+    kernal: Vec<Byte>,
+    chargen: Vec<Byte>,
+    color_ram: [Byte; COLOR_RAM_LENGTH],
+    ddr: Byte,
+    port: Byte,
+}
+
+enum Area {
+    Ram,
+    Basic,
+    Kernal,
+    Chargen,
+    Io,
+}
+
+impl Pla {
+    pub fn new(basic: &[Byte], kernal: &[Byte], chargen: &[Byte]) -> Self {
+        Self {
+            ram: vec![0x0; MEMORY_LENGTH],
+            basic: basic.to_vec(),
+            kernal: kernal.to_vec(),
+            chargen: chargen.to_vec(),
+            color_ram: [0x0; COLOR_RAM_LENGTH],
+            ddr: 0x0,
+            port: 0x0,
+        }
+    }
+
+    pub fn get_ram(&self) -> &[Byte] {
+        &self.ram
+    }
+
+    pub fn get_mut_ram(&mut self) -> &mut [Byte] {
+        &mut self.ram
+    }
+
+    pub fn get_color_ram(&self) -> &[Byte] {
+        &self.color_ram
+    }
+
+    // What the processor port reads back: outputs as written, inputs as
+    // pulled up.
+    pub fn get_port(&self) -> Byte {
+        (self.port & self.ddr) | (PORT_PULL_UPS & !self.ddr)
+    }
+
+    fn get_area(&self, addr: Word) -> Area {
+        let port = self.get_port();
+        let (loram, hiram) = (port & LORAM != 0, port & HIRAM != 0);
+        match addr {
+            BASIC..=0xBFFF if loram && hiram => Area::Basic,
+            IO..=0xDFFF if (loram || hiram) && port & CHAREN != 0 => Area::Io,
+            IO..=0xDFFF if loram || hiram => Area::Chargen,
+            KERNAL..=0xFFFF if hiram => Area::Kernal,
+            _ => Area::Ram,
+        }
+    }
+
+    // $D000 VIC-II, $D400 SID, $D800 color RAM, $DC00 CIA 1 and $DD00
+    // CIA 2. The SID is not emulated and reads as zero, as do the
+    // expansion port areas at $DE00 and $DF00.
+    fn is_chip(addr: Word) -> bool {
+        matches!(addr, 0xD000..=0xD3FF | 0xDC00..=0xDDFF)
+    }
+
+    fn peek_io(&self, addr: Word) -> Byte {
+        match addr {
+            0xD800..=0xDBFF => self.color_ram[(addr & 0x3FF) as usize] & 0x0F,
+            _ => 0x0,
+        }
+    }
+
+    fn write_io(&mut self, addr: Word, val: Byte) {
+        if let 0xD800..=0xDBFF = addr {
+            self.color_ram[(addr & 0x3FF) as usize] = val & 0x0F;
+        }
+    }
+}
+
+impl Device for Pla {
+    fn peek(&self, addr: Word) -> Byte {
+        match addr {
+            PORT_DDR => return self.ddr,
+            PORT_DATA => return self.get_port(),
+            _ => {}
+        }
+        match self.get_area(addr) {
+            Area::Ram => self.ram[addr as usize],
+            Area::Basic => self.basic[(addr - BASIC) as usize],
+            Area::Kernal => self.kernal[(addr - KERNAL) as usize],
+            Area::Chargen => self.chargen[(addr - IO) as usize],
+            Area::Io => self.peek_io(addr),
+        }
+    }
+
+    // The port registers are also written through to RAM, like on the
+    // real machine.
+    fn write(&mut self, addr: Word, val: Byte) {
+        match addr {
+            PORT_DDR => self.ddr = val,
+            PORT_DATA => self.port = val,
+            _ => {}
+        }
+        match self.get_area(addr) {
+            Area::Io => self.write_io(addr, val),
+            _ => self.ram[addr as usize] = val,
+        }
+    }
+
+    fn is_selected(&self, addr: Word) -> bool {
+        !(Self::is_chip(addr) && matches!(self.get_area(addr), Area::Io))
+    }
+}
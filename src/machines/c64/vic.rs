@@ -0,0 +1,160 @@
+use crate::{devices::Device, Byte, Word};
+
+// PAL timing.
+const CYCLES_PER_LINE: u64 = 63;
+const LINES: Word = 312;
+
+const REGISTERS: usize = 0x40;
+const CONTROL_1: usize = 0x11;
+const RASTER: usize = 0x12;
+const SPRITE_COLLISION: usize = 0x1E;
+const BACKGROUND_COLLISION: usize = 0x1F;
+const CONTROL_2: usize = 0x16;
+const MEMORY_POINTERS: usize = 0x18;
+const IRQ_LATCH: usize = 0x19;
+const IRQ_ENABLE: usize = 0x1A;
+const BORDER_COLOR: usize = 0x20;
+const LAST_COLOR: usize = 0x2E;
+
+const RASTER_8: Byte = 0x80;
+const IRQ_RASTER: Byte = 0x01;
+const IRQ_SOURCES: Byte = 0x0F;
+const IRQ_ANY: Byte = 0x80;
+const CHARSET_LOWER: Byte = 0x02;
+
+// Enough of a PAL VIC-II to run the KERNAL: the raster counter and its
+// compare interrupt, and the registers, which read back with their unused
+// bits set. Nothing is drawn and no cycles are stolen from the CPU, so
+// the machine can only be looked at through the text screen. The
+// registers repeat every 64 bytes through $D000-$D3FF.
+pub struct Vic {
+    registers: [Byte; REGISTERS],
+    raster: Word,
+    compare: Word,
+    cycle: u64,
+    line_cycle: u64,
+    irq_latch: Byte,
+    irq_enable: Byte,
+}
+
+impl Default for Vic {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vic {
+    pub fn new() -> Self {
+        Self {
+            registers: [0x0; REGISTERS],
+            raster: 0x0,
+            compare: 0x0,
+            cycle: 0x0,
+            line_cycle: 0x0,
+            irq_latch: 0x0,
+            irq_enable: 0x0,
+        }
+    }
+
+    // How many lines on from the current one the raster next reaches the
+    // compare value, if it ever does.
+    fn get_lines_to_compare(&self) -> Option<u64> {
+        if self.compare >= LINES {
+            return None;
+        }
+        let lines = (self.compare + LINES - self.raster - 1) % LINES + 1;
+        Some(lines as u64)
+    }
+
+    pub fn get_raster(&self) -> Word {
+        self.raster
+    }
+
+    pub fn get_register(&self, reg: usize) -> Byte {
+        self.registers[reg % REGISTERS]
+    }
+
+    pub fn get_border_color(&self) -> Byte {
+        self.registers[BORDER_COLOR] & 0x0F
+    }
+
+    // Where the 1000 screen codes are within the VIC-II's 16K bank.
+    pub fn get_screen_offset(&self) -> Word {
+        Word::from(self.registers[MEMORY_POINTERS] >> 4) * 0x400
+    }
+
+    // Whether the character set with lower case is selected, assuming
+    // the usual character ROM at $1000 or $1800 of the bank.
+    pub fn is_lower_case(&self) -> bool {
+        self.registers[MEMORY_POINTERS] & CHARSET_LOWER != 0
+    }
+}
+
+impl Device for Vic {
+    fn peek(&self, addr: Word) -> Byte {
+        let reg = addr as usize % REGISTERS;
+        match reg {
+            CONTROL_1 => {
+                let raster_8 = if self.raster > 0xFF { RASTER_8 } else { 0x0 };
+                (self.registers[CONTROL_1] & !RASTER_8) | raster_8
+            }
+            RASTER => self.raster as Byte,
+            // Collisions are never detected.
+            SPRITE_COLLISION | BACKGROUND_COLLISION => 0x0,
+            CONTROL_2 => self.registers[reg] | 0xC0,
+            MEMORY_POINTERS => self.registers[reg] | 0x01,
+            IRQ_LATCH => {
+                let any = if self.irq() { IRQ_ANY } else { 0x0 };
+                self.irq_latch | any | 0x70
+            }
+            IRQ_ENABLE => self.irq_enable | 0xF0,
+            BORDER_COLOR..=LAST_COLOR => self.registers[reg] | 0xF0,
+            0x2F.. => 0xFF,
+            _ => self.registers[reg],
+        }
+    }
+
+    fn write(&mut self, addr: Word, val: Byte) {
+        let reg = addr as usize % REGISTERS;
+        match reg {
+            CONTROL_1 => {
+                self.compare = (self.compare & 0xFF) | (Word::from(val & RASTER_8) << 1);
+            }
+            RASTER => self.compare = (self.compare & 0x100) | Word::from(val),
+            // Writing a one acknowledges that source.
+            IRQ_LATCH => self.irq_latch &= !val,
+            IRQ_ENABLE => self.irq_enable = val & IRQ_SOURCES,
+            _ => {}
+        }
+        self.registers[reg] = val;
+    }
+
+    fn tick(&mut self, cycle: u64) {
+        if cycle <= self.cycle {
+            return;
+        }
+        let line_cycle = self.line_cycle + cycle - self.cycle;
+        self.cycle = cycle;
+        self.line_cycle = line_cycle % CYCLES_PER_LINE;
+        let lines = line_cycle / CYCLES_PER_LINE;
+        if self
+            .get_lines_to_compare()
+            .is_some_and(|compare| compare <= lines)
+        {
+            self.irq_latch |= IRQ_RASTER;
+        }
+        self.raster = ((self.raster as u64 + lines) % LINES as u64) as Word;
+    }
+
+    fn get_next_event(&self) -> Option<u64> {
+        if self.irq_enable & IRQ_RASTER == 0 || self.irq() {
+            return None;
+        }
+        let lines = self.get_lines_to_compare()?;
+        Some(self.cycle + lines * CYCLES_PER_LINE - self.line_cycle)
+    }
+
+    fn irq(&self) -> bool {
+        self.irq_latch & self.irq_enable != 0
+    }
+}
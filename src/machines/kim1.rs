@@ -0,0 +1,431 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    clock,
+    cpu::CPU,
+    devices::{Rom, Rriot},
+    serial::SerialBackend,
+    Byte, Word,
+};
+
+// 6530-002: keypad, display and TTY. 6530-003: free for applications.
+const ROM_002: Word = 0x1C00;
+const RAM_002: Word = 0x17C0;
+const IO_002: Word = 0x1740;
+const ROM_003: Word = 0x1800;
+const RAM_003: Word = 0x1780;
+const IO_003: Word = 0x1700;
+
+// A13-A15 are not decoded, so the 6530-002 ROM, and with it the vectors,
+// also appears at the top of memory.
+const VECTOR_MIRROR: Word = 0xFC00;
+
+const PA0: Byte = 0x01;
+const PA7: Byte = 0x80;
+const PB0: Byte = 0x01;
+const SEGMENTS: Byte = 0x7F;
+
+// Outputs of the 74145 decoder on PB1-PB4.
+const KEYPAD_ROWS: Byte = 3;
+const KEYPAD_COLUMNS: Byte = 7;
+const TTY_JUMPER: Byte = 3;
+const FIRST_DIGIT: Byte = 4;
+const DIGITS: usize = 6;
+
+// A digit has to be lit for this long to count as shown, which skips the
+// blanking between digits while the display is scanned.
+const SEGMENT_HOLD: u64 = 100;
+
+// Data bits plus one start and two stop bits.
+const FRAME_BITS: u64 = 11;
+
+// The monitor measures the bit time from the first RUBOUT it receives.
+const RUBOUT: Byte = 0x7F;
+const RUBOUT_DELAY: u64 = 20_000;
+
+// Segment patterns of the digits and of the few letters that can be told
+// apart on a seven-segment display. Segment a is bit 0 through g in bit 6.
+const GLYPHS: [(Byte, char); 25] = [
+    (0x3F, '0'),
+    (0x06, '1'),
+    (0x5B, '2'),
+    (0x4F, '3'),
+    (0x66, '4'),
+    (0x6D, '5'),
+    (0x7D, '6'),
+    (0x07, '7'),
+    (0x7F, '8'),
+    (0x6F, '9'),
+    (0x77, 'A'),
+    (0x7C, 'B'),
+    (0x39, 'C'),
+    (0x5E, 'D'),
+    (0x79, 'E'),
+    (0x71, 'F'),
+    (0x00, ' '),
+    (0x40, '-'),
+    (0x08, '_'),
+    (0x76, 'H'),
+    (0x38, 'L'),
+    (0x73, 'P'),
+    (0x3E, 'U'),
+    (0x54, 'N'),
+    (0x5C, 'O'),
+];
+
+fn glyph(segments: Byte) -> char {
+    GLYPHS
+        .iter()
+        .find(|(pattern, _)| *pattern == segments)
+        .map_or('?', |(_, c)| *c)
+}
+
+// The six digits share the segment lines on PA0-PA6 and are selected one at
+// a time through the decoder, so each digit keeps whatever it was last
+// lit with for long enough.
+struct Display {
+    digits: [Byte; DIGITS],
+    select: Byte,
+    segments: Byte,
+    since: u64,
+}
+
+impl Display {
+    fn sample(&mut self, cycle: u64, select: Byte, segments: Byte) {
+        if (select, segments) == (self.select, self.segments) {
+            return;
+        }
+        self.latch(cycle);
+        self.select = select;
+        self.segments = segments;
+        self.since = cycle;
+    }
+
+    fn latch(&mut self, cycle: u64) {
+        if cycle - self.since < SEGMENT_HOLD {
+            return;
+        }
+        if let Some(digit) = self.select.checked_sub(FIRST_DIGIT) {
+            if let Some(segments) = self.digits.get_mut(digit as usize) {
+                *segments = self.segments;
+            }
+        }
+    }
+}
+
+// The teletype loop: the monitor bit-bangs it, reading PA7 and driving PB0,
+// idle high. Characters from the host are sent as 8N2 frames at a fixed
+// rate, spaced out so that the monitor is back waiting for a start bit,
+// and nothing is sent while the monitor is talking. Frames from the
+// monitor are sampled in the middle of each bit.
+struct Tty {
+    terminal: Box<dyn SerialBackend>,
+    bit_cycles: u64,
+    last_cr: bool,
+    rubout: bool,
+
+    frame: Option<(u64, Word)>,
+    next_frame: u64,
+
+    receiving: Option<(u64, u64, Byte)>,
+    last_level: bool,
+    quiet_since: u64,
+}
+
+impl Tty {
+    fn reset(&mut self, cycle: u64) {
+        self.rubout = true;
+        self.frame = None;
+        self.next_frame = cycle + RUBOUT_DELAY;
+        self.receiving = None;
+    }
+
+    fn get_char_cycles(&self) -> u64 {
+        FRAME_BITS * self.bit_cycles
+    }
+
+    // A Teletype only has upper case, and Return sends CR.
+    fn next_char(&mut self) -> Option<Byte> {
+        if std::mem::take(&mut self.rubout) {
+            return Some(RUBOUT);
+        }
+        loop {
+            let byte = self.terminal.receive()?;
+            let last_cr = std::mem::replace(&mut self.last_cr, byte == b'\r');
+            return Some(match byte {
+                b'\n' if last_cr => continue,
+                b'\n' => b'\r',
+                _ => byte.to_ascii_uppercase(),
+            });
+        }
+    }
+
+    fn get_level(&mut self, cycle: u64) -> bool {
+        if let Some((start, bits)) = self.frame {
+            let bit = (cycle - start) / self.bit_cycles;
+            if bit < FRAME_BITS {
+                return bits >> bit & 0x1 != 0;
+            }
+            self.frame = None;
+        }
+        let quiet = self.receiving.is_none()
+            && cycle.saturating_sub(self.quiet_since) >= self.get_char_cycles();
+        if cycle >= self.next_frame && quiet {
+            if let Some(byte) = self.next_char() {
+                self.frame = Some((cycle, 0b11 << 9 | (byte as Word) << 1));
+                self.next_frame = cycle + 3 * self.get_char_cycles();
+                return false;
+            }
+        }
+        true
+    }
+
+    fn sample(&mut self, cycle: u64, level: bool) {
+        let last_level = std::mem::replace(&mut self.last_level, level);
+        let Some((start, bit, data)) = self.receiving else {
+            if last_level && !level && cycle >= self.quiet_since {
+                self.receiving = Some((cycle, 0, 0x0));
+            }
+            return;
+        };
+        if cycle < start + self.bit_cycles * (3 + 2 * bit) / 2 {
+            return;
+        }
+        let data = data >> 1 | if level { 0x80 } else { 0x0 };
+        if bit < 7 {
+            self.receiving = Some((start, bit + 1, data));
+            return;
+        }
+        self.receiving = None;
+        self.quiet_since = start + self.bit_cycles * 19 / 2;
+        match data & 0x7F {
+            0x00 | RUBOUT => {}
+            val => self.terminal.transmit(val),
+        }
+    }
+}
+
+// KIM-1: 1K of RAM from $0000, two 6530s with the monitor in their ROMs,
+// the six-digit LED display and the hex keypad on the 6530-002's ports.
+// The keypad is scanned by rows through the same decoder that selects the
+// digits, and PA0 on decoder output 3 is the TTY jumper: with a terminal
+// attached the monitor finds it and talks over the teletype loop instead.
+// After a reset it waits for a RUBOUT to measure the baud rate, which is
+// sent automatically.
+pub struct Kim1 {
+    cpu: CPU,
+    rriot_002: Rc<RefCell<Rriot>>,
+    rriot_003: Rc<RefCell<Rriot>>,
+    key: Option<Byte>,
+    display: Display,
+    tty: Option<Tty>,
+}
+
+impl Kim1 {
+    pub const KEY_AD: Byte = 0x10;
+    pub const KEY_DA: Byte = 0x11;
+    pub const KEY_PLUS: Byte = 0x12;
+    pub const KEY_GO: Byte = 0x13;
+    pub const KEY_PC: Byte = 0x14;
+
+    pub const DEFAULT_BAUD: u32 = 1200;
+
+    pub fn new(rom_002: &[Byte], rom_003: &[Byte]) -> Self {
+        let rriot_002 = Rc::new(RefCell::new(Rriot::new(rom_002, ROM_002, RAM_002, IO_002)));
+        let rriot_003 = Rc::new(RefCell::new(Rriot::new(rom_003, ROM_003, RAM_003, IO_003)));
+
+        let mut cpu = CPU::new();
+        cpu.set_clock_rate(clock::DEFAULT_HZ);
+        for rriot in [&rriot_002, &rriot_003] {
+            let (rom, ram, io) = {
+                let rriot = rriot.borrow();
+                (
+                    rriot.get_rom_range(),
+                    rriot.get_ram_range(),
+                    rriot.get_io_range(),
+                )
+            };
+            cpu.map_device(rom, rriot.clone());
+            cpu.map_device(ram, rriot.clone());
+            cpu.map_device(io, rriot.clone());
+        }
+        let mut image = rom_002.to_vec();
+        image.resize(Rriot::ROM_LENGTH, 0x0);
+        let mirror = Rom::new(VECTOR_MIRROR, &image);
+        cpu.map_device(mirror.get_range(), Rc::new(RefCell::new(mirror)));
+        cpu.reset();
+
+        Self {
+            cpu,
+            rriot_002,
+            rriot_003,
+            key: None,
+            display: Display {
+                digits: [0x0; DIGITS],
+                select: 0x0,
+                segments: 0x0,
+                since: 0x0,
+            },
+            tty: None,
+        }
+    }
+
+    pub fn with_tty(
+        rom_002: &[Byte],
+        rom_003: &[Byte],
+        terminal: Box<dyn SerialBackend>,
+        baud: u32,
+    ) -> Self {
+        let mut kim1 = Self::new(rom_002, rom_003);
+        let mut tty = Tty {
+            terminal,
+            bit_cycles: kim1.cpu.get_clock_rate() / baud as u64,
+            last_cr: false,
+            rubout: false,
+            frame: None,
+            next_frame: 0x0,
+            receiving: None,
+            last_level: true,
+            quiet_since: 0x0,
+        };
+        tty.reset(kim1.cpu.get_cycles());
+        kim1.tty = Some(tty);
+        kim1
+    }
+
+    pub fn get_cpu(&mut self) -> &mut CPU {
+        &mut self.cpu
+    }
+
+    pub fn get_rriot_002(&self) -> Rc<RefCell<Rriot>> {
+        self.rriot_002.clone()
+    }
+
+    pub fn get_rriot_003(&self) -> Rc<RefCell<Rriot>> {
+        self.rriot_003.clone()
+    }
+
+    pub fn load(&mut self, addr: Word, data: &[Byte]) {
+        for (i, byte) in data.iter().enumerate() {
+            self.cpu.write_byte(addr.wrapping_add(i as Word), *byte);
+        }
+    }
+
+    // Holds down a keypad key, 0-F or one of the `KEY_` codes, which are
+    // the codes the monitor's GETKEY returns.
+    pub fn press_key(&mut self, key: Byte) {
+        assert!(key < KEYPAD_ROWS * KEYPAD_COLUMNS, "no such key");
+        self.key = Some(key);
+    }
+
+    pub fn release_key(&mut self) {
+        self.key = None;
+    }
+
+    // ST is wired to NMI and RS to RESET.
+    pub fn set_stop_key(&mut self, pressed: bool) {
+        self.cpu.set_nmi(pressed);
+    }
+
+    pub fn reset(&mut self) {
+        self.cpu.reset();
+        let cycle = self.cpu.get_cycles();
+        if let Some(tty) = self.tty.as_mut() {
+            tty.reset(cycle);
+        }
+    }
+
+    // Segment patterns of the six digits, left to right.
+    pub fn get_digits(&mut self) -> [Byte; DIGITS] {
+        self.display.latch(self.cpu.get_cycles());
+        self.display.digits
+    }
+
+    // The display as text, address and data digits apart, e.g. "1C00 A9".
+    pub fn get_display(&mut self) -> String {
+        let digits = self.get_digits();
+        let mut text: String = digits[..4].iter().map(|d| glyph(*d)).collect();
+        text.push(' ');
+        text.extend(digits[4..].iter().map(|d| glyph(*d)));
+        text
+    }
+
+    // The display drawn segment by segment on three lines.
+    pub fn render_display(&mut self) -> String {
+        let digits = self.get_digits();
+        let segment = |d: Byte, bit: u8, c: char| if d >> bit & 0x1 != 0 { c } else { ' ' };
+        let mut lines = [String::new(), String::new(), String::new()];
+        for (i, d) in digits.into_iter().enumerate() {
+            if i > 0 {
+                let gap = if i == 4 { "  " } else { " " };
+                lines.iter_mut().for_each(|line| line.push_str(gap));
+            }
+            lines[0].extend([' ', segment(d, 0, '_'), ' ']);
+            lines[1].extend([segment(d, 5, '|'), segment(d, 6, '_'), segment(d, 1, '|')]);
+            lines[2].extend([segment(d, 4, '|'), segment(d, 3, '_'), segment(d, 2, '|')]);
+        }
+        lines.join("\n")
+    }
+
+    fn get_select(port_b: Byte) -> Byte {
+        port_b >> 1 & 0x0F
+    }
+
+    fn update_inputs(&mut self) {
+        let cycle = self.cpu.get_cycles();
+        let mut rriot = self.rriot_002.borrow_mut();
+        let select = Self::get_select(rriot.get_port_b());
+
+        let mut input = SEGMENTS;
+        if let Some(key) = self.key {
+            if key / KEYPAD_COLUMNS == select {
+                input &= !(0x40 >> (key % KEYPAD_COLUMNS));
+            }
+        }
+        if select == TTY_JUMPER && self.tty.is_some() {
+            input &= !PA0;
+        }
+        let rx = self.tty.as_mut().is_none_or(|tty| tty.get_level(cycle));
+        if rx {
+            input |= PA7;
+        }
+        rriot.set_port_a_input(input);
+    }
+
+    fn sample_outputs(&mut self) {
+        let cycle = self.cpu.get_cycles();
+        let rriot = self.rriot_002.borrow();
+        let port_b = rriot.get_port_b();
+        let segments = rriot.get_port_a() & rriot.get_ddra() & SEGMENTS;
+        self.display
+            .sample(cycle, Self::get_select(port_b), segments);
+        if let Some(tty) = self.tty.as_mut() {
+            let tx = rriot.get_ddrb() & PB0 == 0 || port_b & PB0 != 0;
+            tty.sample(cycle, tx);
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.update_inputs();
+        self.cpu.tick();
+        self.sample_outputs();
+    }
+
+    pub fn run_until(&mut self, cycle: u64) {
+        self.cpu.update_devices();
+        while self.cpu.get_cycles() < cycle {
+            self.tick();
+        }
+        self.cpu.update_devices();
+    }
+
+    // Like `run_until`, keeping to the clock rate if the CPU is throttled.
+    pub fn run_throttled(&mut self, cycle: u64) {
+        self.cpu.update_devices();
+        while self.cpu.get_cycles() < cycle {
+            self.tick();
+            self.cpu.sync_throttle();
+        }
+        self.cpu.update_devices();
+    }
+}
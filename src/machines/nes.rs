@@ -0,0 +1,252 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    clock,
+    cpu::{Variant, CPU},
+    devices::{Device, Line, Ram, Rom},
+    dma::Dma,
+    Byte, Word,
+};
+
+mod cartridge;
+mod ppu;
+mod trace;
+
+pub use cartridge::{Cartridge, Mirroring};
+pub use ppu::Ppu;
+
+const APU_REGISTERS: usize = 0x18;
+const APU_STATUS: Word = 0x4015;
+const OAM_DMA: Word = 0x4014;
+const OAM_DATA: Word = 0x2004;
+const OAM_LENGTH: usize = 0x100;
+const JOY1: Word = 0x4016;
+const JOY2: Word = 0x4017;
+
+// The upper bits of a controller read are open bus, left over from the
+// high byte of the address.
+const JOY_OPEN_BUS: Byte = 0x40;
+
+// What the nestest log shows for the I/O registers, whatever they hold.
+const LOG_OPEN_BUS: Byte = 0xFF;
+
+// The 2A03's registers at $4000-$4017. The APU ones are latched and make
+// no sound, $4014 starts OAM DMA and $4016-$4017 shift out the
+// controllers.
+struct Io {
+    apu: [Byte; APU_REGISTERS],
+    dma: Option<Dma>,
+    strobe: bool,
+    buttons: [Byte; 2],
+    shift: [Byte; 2],
+}
+
+impl Io {
+    fn new() -> Self {
+        Self {
+            apu: [0x0; APU_REGISTERS],
+            dma: None,
+            strobe: false,
+            buttons: [0x0; 2],
+            shift: [0x0; 2],
+        }
+    }
+
+    // While the strobe is high the shift registers keep reloading, so
+    // every read returns button A. Once they are empty they read 1.
+    fn read_controller(&mut self, controller: usize) -> Byte {
+        if self.strobe {
+            self.shift[controller] = self.buttons[controller];
+        }
+        let bit = self.shift[controller] & 0x1;
+        self.shift[controller] = (self.shift[controller] >> 1) | 0x80;
+        JOY_OPEN_BUS | bit
+    }
+
+    fn peek_controller(&self, controller: usize) -> Byte {
+        let shift = match self.strobe {
+            true => self.buttons[controller],
+            false => self.shift[controller],
+        };
+        JOY_OPEN_BUS | (shift & 0x1)
+    }
+}
+
+impl Device for Io {
+    fn peek(&self, addr: Word) -> Byte {
+        match addr {
+            JOY1 => self.peek_controller(0),
+            JOY2 => self.peek_controller(1),
+            APU_STATUS => 0x0,
+            _ => JOY_OPEN_BUS,
+        }
+    }
+
+    fn read(&mut self, addr: Word) -> Byte {
+        match addr {
+            JOY1 => self.read_controller(0),
+            JOY2 => self.read_controller(1),
+            APU_STATUS => 0x0,
+            _ => JOY_OPEN_BUS,
+        }
+    }
+
+    fn write(&mut self, addr: Word, val: Byte) {
+        match addr {
+            OAM_DMA => self.dma = Some(Dma::to_port(Word::from(val) << 8, OAM_DATA, OAM_LENGTH)),
+            // The buttons are latched for as long as the strobe is high,
+            // so until it falls.
+            JOY1 => {
+                if self.strobe || val & 0x1 != 0 {
+                    self.shift = self.buttons;
+                }
+                self.strobe = val & 0x1 != 0;
+            }
+            _ => {}
+        }
+        if let Some(reg) = self.apu.get_mut(addr.wrapping_sub(Nes::IO) as usize) {
+            *reg = val;
+        }
+    }
+
+    fn take_dma(&mut self) -> Option<Dma> {
+        self.dma.take()
+    }
+}
+
+// The CPU side of an NTSC NES: a 2A03, which is a 6502 without decimal
+// mode, with 2K of RAM repeated up to $1FFF, the PPU registers repeated
+// through $2000-$3FFF and the APU and controller registers at $4000. The
+// cartridge is NROM, with the PRG ROM at $8000 and a 16K one repeated at
+// $C000. The PPU raises NMI at the start of vertical blank when enabled,
+// and writing a page number to $4014 copies that page to OAM by DMA,
+// stalling the CPU for 513 or 514 cycles. The CPU is reset on creation.
+pub struct Nes {
+    cpu: CPU,
+    ram: Rc<RefCell<Ram>>,
+    ppu: Rc<RefCell<Ppu>>,
+    io: Rc<RefCell<Io>>,
+}
+
+impl Nes {
+    pub const RAM_LENGTH: usize = 0x800;
+    pub const PPU: Word = 0x2000;
+    pub const IO: Word = 0x4000;
+    pub const PRG_ROM: Word = 0x8000;
+    pub const NESTEST_START: Word = 0xC000;
+
+    pub const BUTTON_A: Byte = 0x01;
+    pub const BUTTON_B: Byte = 0x02;
+    pub const BUTTON_SELECT: Byte = 0x04;
+    pub const BUTTON_START: Byte = 0x08;
+    pub const BUTTON_UP: Byte = 0x10;
+    pub const BUTTON_DOWN: Byte = 0x20;
+    pub const BUTTON_LEFT: Byte = 0x40;
+    pub const BUTTON_RIGHT: Byte = 0x80;
+
+    pub fn new(cartridge: &Cartridge) -> Self {
+        let mut cpu = CPU::new();
+        cpu.set_variant(Variant::Ricoh2A03);
+        cpu.set_clock_rate(clock::NES_NTSC_HZ);
+
+        let ram = Rc::new(RefCell::new(Ram::new(0x0, Self::RAM_LENGTH)));
+        let ppu = Rc::new(RefCell::new(Ppu::new(cartridge)));
+        let io = Rc::new(RefCell::new(Io::new()));
+        cpu.map_device(0x0000..=0x1FFF, ram.clone());
+        cpu.map_device_with_line(Self::PPU..=0x3FFF, ppu.clone(), Line::Nmi);
+        cpu.map_device(Self::IO..=Self::IO + APU_REGISTERS as Word - 1, io.clone());
+        let rom = Rom::new(Self::PRG_ROM, cartridge.get_prg());
+        cpu.map_device(Self::PRG_ROM..=0xFFFF, Rc::new(RefCell::new(rom)));
+
+        // S comes up as $00, so reset leaves it at $FD as on a console.
+        *cpu.get_registers().get_mut_s() = 0x0;
+        cpu.reset();
+
+        Self { cpu, ram, ppu, io }
+    }
+
+    pub fn get_cpu(&mut self) -> &mut CPU {
+        &mut self.cpu
+    }
+
+    pub fn get_ram(&self) -> Rc<RefCell<Ram>> {
+        self.ram.clone()
+    }
+
+    pub fn get_ppu(&self) -> Rc<RefCell<Ppu>> {
+        self.ppu.clone()
+    }
+
+    // The last values written to $4000-$4017.
+    pub fn get_apu_registers(&self) -> [Byte; APU_REGISTERS] {
+        self.io.borrow().apu
+    }
+
+    // Sets the buttons held on controller 0 or 1, as a mask of the
+    // `BUTTON_` constants.
+    pub fn set_buttons(&mut self, controller: usize, buttons: Byte) {
+        self.io.borrow_mut().buttons[controller] = buttons;
+    }
+
+    pub fn reset(&mut self) {
+        self.cpu.reset();
+    }
+
+    // Starts nestest in its automated mode: once reset has run, the CPU
+    // is sent to $C000 with the registers as in the reference log, rather
+    // than through the reset vector to the interactive menu.
+    pub fn start_nestest(&mut self) {
+        if self.cpu.get_cycles() == 0 {
+            self.cpu.run();
+        }
+        let registers = self.cpu.get_registers();
+        *registers.get_mut_pc() = Self::NESTEST_START;
+        *registers.get_mut_s() = 0xFD;
+        registers.set_p(0x24);
+    }
+
+    // One line of the nestest log for the instruction about to run, e.g.
+    //
+    //     C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7
+    //
+    // P is shown without B and with bit 5 set, since the real register
+    // has neither.
+    pub fn trace(&mut self) -> String {
+        let registers = self.cpu.get_registers();
+        let (pc, a, x, y, s) = (
+            registers.get_pc(),
+            registers.get_a(),
+            registers.get_x(),
+            registers.get_y(),
+            registers.get_s(),
+        );
+        let p = (registers.get_p_byte() & !0x10) | 0x20;
+        let (bytes, text) = trace::disassemble(pc, x, y, |addr| self.peek(addr));
+        self.cpu.update_devices();
+        let (line, dot) = self.ppu.borrow().get_position();
+        format!(
+            "{pc:04X}  {bytes:<8} {text:<33}A:{a:02X} X:{x:02X} Y:{y:02X} P:{p:02X} SP:{s:02X} PPU:{line:>3},{dot:>3} CYC:{}",
+            self.cpu.get_cycles()
+        )
+    }
+
+    fn peek(&self, addr: Word) -> Byte {
+        match addr {
+            Self::PPU..=0x401F => LOG_OPEN_BUS,
+            _ => self.cpu.read_byte(addr),
+        }
+    }
+
+    // Runs one instruction, along with any DMA it started.
+    pub fn step(&mut self) {
+        self.cpu.run();
+    }
+
+    pub fn run_until(&mut self, cycle: u64) {
+        self.cpu.run_until(cycle);
+    }
+
+    pub fn run_throttled(&mut self, cycle: u64) {
+        self.cpu.run_throttled(cycle);
+    }
+}
@@ -0,0 +1,120 @@
+use std::io;
+
+use crate::Byte;
+
+const HEADER_LENGTH: usize = 0x10;
+const TRAINER_LENGTH: usize = 0x200;
+const MAGIC: [Byte; 4] = [b'N', b'E', b'S', 0x1A];
+
+const FLAGS6_VERTICAL: Byte = 0x01;
+const FLAGS6_BATTERY: Byte = 0x02;
+const FLAGS6_TRAINER: Byte = 0x04;
+const FLAGS7_NES2_MASK: Byte = 0x0C;
+const FLAGS7_NES2: Byte = 0x08;
+
+// How the PPU's two 1K nametables fill its four nametable slots.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mirroring {
+    Horizontal,
+    Vertical,
+}
+
+// A cartridge image as read from an iNES file. Only NROM (mapper 0) boards
+// are supported: 16K or 32K of PRG ROM and 8K of CHR ROM, or CHR RAM when
+// the image has none.
+pub struct Cartridge {
+    prg: Vec<Byte>,
+    chr: Vec<Byte>,
+    chr_ram: bool,
+    mapper: u8,
+    mirroring: Mirroring,
+    battery: bool,
+}
+
+impl Cartridge {
+    pub const PRG_BANK_LENGTH: usize = 0x4000;
+    pub const CHR_BANK_LENGTH: usize = 0x2000;
+
+    pub fn from_ines(bytes: &[Byte]) -> io::Result<Self> {
+        if bytes.len() < HEADER_LENGTH || bytes[..4] != MAGIC {
+            return Err(invalid("not an iNES file".to_string()));
+        }
+        let prg_banks = bytes[4] as usize;
+        let chr_banks = bytes[5] as usize;
+        let flags6 = bytes[6];
+        // Old dumps carry junk such as "DiskDude!" from byte 7 on, which
+        // would corrupt the upper mapper nibble.
+        let flags7 = if bytes[7] & FLAGS7_NES2_MASK != FLAGS7_NES2
+            && bytes[12..HEADER_LENGTH].iter().any(|byte| *byte != 0)
+        {
+            0x0
+        } else {
+            bytes[7]
+        };
+        let mapper = (flags6 >> 4) | (flags7 & 0xF0);
+        if mapper != 0 {
+            return Err(invalid(format!("mapper {mapper} is not supported")));
+        }
+        if !(1..=2).contains(&prg_banks) || chr_banks > 1 {
+            return Err(invalid(
+                "NROM has 16K or 32K of PRG ROM and up to 8K of CHR ROM".to_string(),
+            ));
+        }
+
+        let mut pos = HEADER_LENGTH;
+        if flags6 & FLAGS6_TRAINER != 0 {
+            pos += TRAINER_LENGTH;
+        }
+        let prg_end = pos + prg_banks * Self::PRG_BANK_LENGTH;
+        let chr_end = prg_end + chr_banks * Self::CHR_BANK_LENGTH;
+        if bytes.len() < chr_end {
+            return Err(invalid("truncated iNES file".to_string()));
+        }
+
+        let chr_ram = chr_banks == 0;
+        let chr = match chr_ram {
+            true => vec![0x0; Self::CHR_BANK_LENGTH],
+            false => bytes[prg_end..chr_end].to_vec(),
+        };
+        let mirroring = match flags6 & FLAGS6_VERTICAL {
+            0 => Mirroring::Horizontal,
+            _ => Mirroring::Vertical,
+        };
+        Ok(Self {
+            prg: bytes[pos..prg_end].to_vec(),
+            chr,
+            chr_ram,
+            mapper,
+            mirroring,
+            battery: flags6 & FLAGS6_BATTERY != 0,
+        })
+    }
+
+    pub fn get_prg(&self) -> &[Byte] {
+        &self.prg
+    }
+
+    pub fn get_chr(&self) -> &[Byte] {
+        &self.chr
+    }
+
+    pub fn has_chr_ram(&self) -> bool {
+        self.chr_ram
+    }
+
+    pub fn get_mapper(&self) -> u8 {
+        self.mapper
+    }
+
+    pub fn get_mirroring(&self) -> Mirroring {
+        self.mirroring
+    }
+
+    pub fn has_battery(&self) -> bool {
+        self.battery
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,266 @@
+use crate::{devices::Device, Byte, Word};
+
+use super::cartridge::{Cartridge, Mirroring};
+
+const DOTS_PER_CYCLE: u64 = 3;
+const DOTS_PER_LINE: u64 = 341;
+const LINES_PER_FRAME: u64 = 262;
+const VBLANK_LINE: u64 = 241;
+const PRE_RENDER_LINE: u64 = 261;
+const DOTS_PER_FRAME: u64 = DOTS_PER_LINE * LINES_PER_FRAME;
+const VBLANK_SET: u64 = VBLANK_LINE * DOTS_PER_LINE + 1;
+const VBLANK_CLEAR: u64 = PRE_RENDER_LINE * DOTS_PER_LINE + 1;
+
+const PPUCTRL: Word = 0x0;
+const PPUMASK: Word = 0x1;
+const PPUSTATUS: Word = 0x2;
+const OAMADDR: Word = 0x3;
+const OAMDATA: Word = 0x4;
+const PPUSCROLL: Word = 0x5;
+const PPUADDR: Word = 0x6;
+const PPUDATA: Word = 0x7;
+
+const CTRL_INCREMENT: Byte = 0x04;
+const CTRL_NMI: Byte = 0x80;
+const STATUS_VBLANK: Byte = 0x80;
+
+const NAMETABLES: Word = 0x2000;
+const PALETTE: Word = 0x3F00;
+const NAMETABLE_LENGTH: usize = 0x400;
+
+// The CPU side of the 2C02: its registers, OAM and video memory, and the
+// frame timing that sets the vertical blank flag and raises NMI. Nothing is
+// rendered, so the sprite 0 and overflow flags never set. It runs three
+// dots per CPU cycle from scanline 0, dot 0, and has no odd frame skip.
+pub struct Ppu {
+    ctrl: Byte,
+    mask: Byte,
+    vblank: bool,
+    oam_addr: Byte,
+    oam: [Byte; 0x100],
+    scroll: [Byte; 2],
+    vram_addr: Word,
+    addr_latch: Byte,
+    write_toggle: bool,
+    read_buffer: Byte,
+    chr: Vec<Byte>,
+    chr_ram: bool,
+    nametables: [Byte; 2 * NAMETABLE_LENGTH],
+    palette: [Byte; 0x20],
+    mirroring: Mirroring,
+    cycle: u64,
+    dot: u64,
+}
+
+impl Ppu {
+    pub fn new(cartridge: &Cartridge) -> Self {
+        Self {
+            ctrl: 0x0,
+            mask: 0x0,
+            vblank: false,
+            oam_addr: 0x0,
+            oam: [0x0; 0x100],
+            scroll: [0x0; 2],
+            vram_addr: 0x0,
+            addr_latch: 0x0,
+            write_toggle: false,
+            read_buffer: 0x0,
+            chr: cartridge.get_chr().to_vec(),
+            chr_ram: cartridge.has_chr_ram(),
+            nametables: [0x0; 2 * NAMETABLE_LENGTH],
+            palette: [0x0; 0x20],
+            mirroring: cartridge.get_mirroring(),
+            cycle: 0x0,
+            dot: 0x0,
+        }
+    }
+
+    // Scanline and dot within the frame.
+    pub fn get_position(&self) -> (u64, u64) {
+        (self.dot / DOTS_PER_LINE, self.dot % DOTS_PER_LINE)
+    }
+
+    pub fn get_ctrl(&self) -> Byte {
+        self.ctrl
+    }
+
+    pub fn get_mask(&self) -> Byte {
+        self.mask
+    }
+
+    pub fn get_scroll(&self) -> (Byte, Byte) {
+        (self.scroll[0], self.scroll[1])
+    }
+
+    pub fn get_oam(&self) -> &[Byte; 0x100] {
+        &self.oam
+    }
+
+    // Reads PPU memory without the side effects of PPUDATA.
+    pub fn peek_vram(&self, addr: Word) -> Byte {
+        let addr = addr & 0x3FFF;
+        match addr {
+            0x0000..=0x1FFF => self.chr[addr as usize],
+            0x2000..=0x3EFF => self.nametables[self.nametable_index(addr)],
+            _ => self.palette[palette_index(addr)],
+        }
+    }
+
+    fn write_vram(&mut self, addr: Word, val: Byte) {
+        let addr = addr & 0x3FFF;
+        match addr {
+            0x0000..=0x1FFF if self.chr_ram => self.chr[addr as usize] = val,
+            0x0000..=0x1FFF => {}
+            0x2000..=0x3EFF => {
+                let index = self.nametable_index(addr);
+                self.nametables[index] = val;
+            }
+            _ => self.palette[palette_index(addr)] = val,
+        }
+    }
+
+    fn nametable_index(&self, addr: Word) -> usize {
+        let offset = (addr - NAMETABLES) as usize % (4 * NAMETABLE_LENGTH);
+        let table = match self.mirroring {
+            Mirroring::Horizontal => offset / (2 * NAMETABLE_LENGTH),
+            Mirroring::Vertical => offset / NAMETABLE_LENGTH % 2,
+        };
+        table * NAMETABLE_LENGTH + offset % NAMETABLE_LENGTH
+    }
+
+    // Dots until the frame next reaches `target`, a whole frame if it is
+    // there now.
+    fn get_dots_to(&self, target: u64) -> u64 {
+        (target + DOTS_PER_FRAME - self.dot - 1) % DOTS_PER_FRAME + 1
+    }
+
+    fn increment_vram_addr(&mut self) {
+        let step = match self.ctrl & CTRL_INCREMENT {
+            0 => 1,
+            _ => 32,
+        };
+        self.vram_addr = self.vram_addr.wrapping_add(step) & 0x3FFF;
+    }
+}
+
+// $3F10, $3F14, $3F18 and $3F1C are the backdrop entries of $3F00-$3F0C.
+fn palette_index(addr: Word) -> usize {
+    let index = (addr - PALETTE) as usize % 0x20;
+    match index {
+        0x10 | 0x14 | 0x18 | 0x1C => index - 0x10,
+        _ => index,
+    }
+}
+
+impl Device for Ppu {
+    // The registers repeat every eight bytes through $2000-$3FFF. The
+    // write-only ones read back as zero.
+    fn peek(&self, addr: Word) -> Byte {
+        match addr & 0x7 {
+            PPUSTATUS if self.vblank => STATUS_VBLANK,
+            OAMDATA => self.oam[self.oam_addr as usize],
+            PPUDATA => match self.vram_addr & 0x3FFF {
+                0x3F00.. => self.peek_vram(self.vram_addr),
+                _ => self.read_buffer,
+            },
+            _ => 0x0,
+        }
+    }
+
+    fn read(&mut self, addr: Word) -> Byte {
+        match addr & 0x7 {
+            PPUSTATUS => {
+                let val = if self.vblank { STATUS_VBLANK } else { 0x0 };
+                self.vblank = false;
+                self.write_toggle = false;
+                val
+            }
+            OAMDATA => self.oam[self.oam_addr as usize],
+            // Reads below the palette come through a one byte buffer.
+            PPUDATA => {
+                let addr = self.vram_addr;
+                let val = match addr & 0x3FFF {
+                    0x3F00.. => {
+                        self.read_buffer = self.peek_vram(addr - 0x1000);
+                        self.peek_vram(addr)
+                    }
+                    _ => {
+                        let val = self.peek_vram(addr);
+                        std::mem::replace(&mut self.read_buffer, val)
+                    }
+                };
+                self.increment_vram_addr();
+                val
+            }
+            _ => 0x0,
+        }
+    }
+
+    fn write(&mut self, addr: Word, val: Byte) {
+        match addr & 0x7 {
+            PPUCTRL => self.ctrl = val,
+            PPUMASK => self.mask = val,
+            OAMADDR => self.oam_addr = val,
+            OAMDATA => {
+                self.oam[self.oam_addr as usize] = val;
+                self.oam_addr = self.oam_addr.wrapping_add(1);
+            }
+            PPUSCROLL => {
+                self.scroll[self.write_toggle as usize] = val;
+                self.write_toggle = !self.write_toggle;
+            }
+            PPUADDR => {
+                if self.write_toggle {
+                    self.vram_addr = Word::from_le_bytes([val, self.addr_latch]) & 0x3FFF;
+                } else {
+                    self.addr_latch = val;
+                }
+                self.write_toggle = !self.write_toggle;
+            }
+            PPUDATA => {
+                self.write_vram(self.vram_addr, val);
+                self.increment_vram_addr();
+            }
+            _ => {}
+        }
+    }
+
+    // The flag ends up as whichever of its set and clear dots was passed
+    // last, if either was.
+    fn tick(&mut self, cycle: u64) {
+        if cycle <= self.cycle {
+            return;
+        }
+        let dots = (cycle - self.cycle) * DOTS_PER_CYCLE;
+        self.cycle = cycle;
+        let passed = |target| {
+            dots.checked_sub(self.get_dots_to(target))
+                .map(|since| since % DOTS_PER_FRAME)
+        };
+        match (passed(VBLANK_SET), passed(VBLANK_CLEAR)) {
+            (Some(set), Some(clear)) => self.vblank = set < clear,
+            (Some(_), None) => self.vblank = true,
+            (None, Some(_)) => self.vblank = false,
+            (None, None) => {}
+        }
+        self.dot = (self.dot + dots) % DOTS_PER_FRAME;
+    }
+
+    // The next change of the flag, while it drives NMI.
+    fn get_next_event(&self) -> Option<u64> {
+        if self.ctrl & CTRL_NMI == 0 {
+            return None;
+        }
+        let target = if self.vblank {
+            VBLANK_CLEAR
+        } else {
+            VBLANK_SET
+        };
+        Some(self.cycle + self.get_dots_to(target).div_ceil(DOTS_PER_CYCLE))
+    }
+
+    // NMI follows the vertical blank flag while it is enabled in PPUCTRL.
+    fn irq(&self) -> bool {
+        self.vblank && self.ctrl & CTRL_NMI != 0
+    }
+}
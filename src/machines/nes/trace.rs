@@ -0,0 +1,394 @@
+use crate::{Byte, Word};
+
+use Mode::*;
+
+#[derive(Clone, Copy)]
+enum Mode {
+    Imp,
+    Acc,
+    Imm,
+    Zp,
+    ZpX,
+    ZpY,
+    Abs,
+    AbsX,
+    AbsY,
+    Ind,
+    IndX,
+    IndY,
+    Rel,
+}
+
+impl Mode {
+    fn get_length(&self) -> usize {
+        match self {
+            Imp | Acc => 1,
+            Imm | Zp | ZpX | ZpY | IndX | IndY | Rel => 2,
+            Abs | AbsX | AbsY | Ind => 3,
+        }
+    }
+}
+
+const JSR: Byte = 0x20;
+const JMP: Byte = 0x4C;
+
+// Every opcode, with the unofficial ones marked by a `*` the way nestest's
+// reference log has them.
+const OPCODES: [(&str, Mode); 256] = [
+    // $00
+    ("BRK", Imp),
+    ("ORA", IndX),
+    ("*KIL", Imp),
+    ("*SLO", IndX),
+    ("*NOP", Zp),
+    ("ORA", Zp),
+    ("ASL", Zp),
+    ("*SLO", Zp),
+    ("PHP", Imp),
+    ("ORA", Imm),
+    ("ASL", Acc),
+    ("*ANC", Imm),
+    ("*NOP", Abs),
+    ("ORA", Abs),
+    ("ASL", Abs),
+    ("*SLO", Abs),
+    // $10
+    ("BPL", Rel),
+    ("ORA", IndY),
+    ("*KIL", Imp),
+    ("*SLO", IndY),
+    ("*NOP", ZpX),
+    ("ORA", ZpX),
+    ("ASL", ZpX),
+    ("*SLO", ZpX),
+    ("CLC", Imp),
+    ("ORA", AbsY),
+    ("*NOP", Imp),
+    ("*SLO", AbsY),
+    ("*NOP", AbsX),
+    ("ORA", AbsX),
+    ("ASL", AbsX),
+    ("*SLO", AbsX),
+    // $20
+    ("JSR", Abs),
+    ("AND", IndX),
+    ("*KIL", Imp),
+    ("*RLA", IndX),
+    ("BIT", Zp),
+    ("AND", Zp),
+    ("ROL", Zp),
+    ("*RLA", Zp),
+    ("PLP", Imp),
+    ("AND", Imm),
+    ("ROL", Acc),
+    ("*ANC", Imm),
+    ("BIT", Abs),
+    ("AND", Abs),
+    ("ROL", Abs),
+    ("*RLA", Abs),
+    // $30
+    ("BMI", Rel),
+    ("AND", IndY),
+    ("*KIL", Imp),
+    ("*RLA", IndY),
+    ("*NOP", ZpX),
+    ("AND", ZpX),
+    ("ROL", ZpX),
+    ("*RLA", ZpX),
+    ("SEC", Imp),
+    ("AND", AbsY),
+    ("*NOP", Imp),
+    ("*RLA", AbsY),
+    ("*NOP", AbsX),
+    ("AND", AbsX),
+    ("ROL", AbsX),
+    ("*RLA", AbsX),
+    // $40
+    ("RTI", Imp),
+    ("EOR", IndX),
+    ("*KIL", Imp),
+    ("*SRE", IndX),
+    ("*NOP", Zp),
+    ("EOR", Zp),
+    ("LSR", Zp),
+    ("*SRE", Zp),
+    ("PHA", Imp),
+    ("EOR", Imm),
+    ("LSR", Acc),
+    ("*ALR", Imm),
+    ("JMP", Abs),
+    ("EOR", Abs),
+    ("LSR", Abs),
+    ("*SRE", Abs),
+    // $50
+    ("BVC", Rel),
+    ("EOR", IndY),
+    ("*KIL", Imp),
+    ("*SRE", IndY),
+    ("*NOP", ZpX),
+    ("EOR", ZpX),
+    ("LSR", ZpX),
+    ("*SRE", ZpX),
+    ("CLI", Imp),
+    ("EOR", AbsY),
+    ("*NOP", Imp),
+    ("*SRE", AbsY),
+    ("*NOP", AbsX),
+    ("EOR", AbsX),
+    ("LSR", AbsX),
+    ("*SRE", AbsX),
+    // $60
+    ("RTS", Imp),
+    ("ADC", IndX),
+    ("*KIL", Imp),
+    ("*RRA", IndX),
+    ("*NOP", Zp),
+    ("ADC", Zp),
+    ("ROR", Zp),
+    ("*RRA", Zp),
+    ("PLA", Imp),
+    ("ADC", Imm),
+    ("ROR", Acc),
+    ("*ARR", Imm),
+    ("JMP", Ind),
+    ("ADC", Abs),
+    ("ROR", Abs),
+    ("*RRA", Abs),
+    // $70
+    ("BVS", Rel),
+    ("ADC", IndY),
+    ("*KIL", Imp),
+    ("*RRA", IndY),
+    ("*NOP", ZpX),
+    ("ADC", ZpX),
+    ("ROR", ZpX),
+    ("*RRA", ZpX),
+    ("SEI", Imp),
+    ("ADC", AbsY),
+    ("*NOP", Imp),
+    ("*RRA", AbsY),
+    ("*NOP", AbsX),
+    ("ADC", AbsX),
+    ("ROR", AbsX),
+    ("*RRA", AbsX),
+    // $80
+    ("*NOP", Imm),
+    ("STA", IndX),
+    ("*NOP", Imm),
+    ("*SAX", IndX),
+    ("STY", Zp),
+    ("STA", Zp),
+    ("STX", Zp),
+    ("*SAX", Zp),
+    ("DEY", Imp),
+    ("*NOP", Imm),
+    ("TXA", Imp),
+    ("*XAA", Imm),
+    ("STY", Abs),
+    ("STA", Abs),
+    ("STX", Abs),
+    ("*SAX", Abs),
+    // $90
+    ("BCC", Rel),
+    ("STA", IndY),
+    ("*KIL", Imp),
+    ("*AHX", IndY),
+    ("STY", ZpX),
+    ("STA", ZpX),
+    ("STX", ZpY),
+    ("*SAX", ZpY),
+    ("TYA", Imp),
+    ("STA", AbsY),
+    ("TXS", Imp),
+    ("*TAS", AbsY),
+    ("*SHY", AbsX),
+    ("STA", AbsX),
+    ("*SHX", AbsY),
+    ("*AHX", AbsY),
+    // $A0
+    ("LDY", Imm),
+    ("LDA", IndX),
+    ("LDX", Imm),
+    ("*LAX", IndX),
+    ("LDY", Zp),
+    ("LDA", Zp),
+    ("LDX", Zp),
+    ("*LAX", Zp),
+    ("TAY", Imp),
+    ("LDA", Imm),
+    ("TAX", Imp),
+    ("*LAX", Imm),
+    ("LDY", Abs),
+    ("LDA", Abs),
+    ("LDX", Abs),
+    ("*LAX", Abs),
+    // $B0
+    ("BCS", Rel),
+    ("LDA", IndY),
+    ("*KIL", Imp),
+    ("*LAX", IndY),
+    ("LDY", ZpX),
+    ("LDA", ZpX),
+    ("LDX", ZpY),
+    ("*LAX", ZpY),
+    ("CLV", Imp),
+    ("LDA", AbsY),
+    ("TSX", Imp),
+    ("*LAS", AbsY),
+    ("LDY", AbsX),
+    ("LDA", AbsX),
+    ("LDX", AbsY),
+    ("*LAX", AbsY),
+    // $C0
+    ("CPY", Imm),
+    ("CMP", IndX),
+    ("*NOP", Imm),
+    ("*DCP", IndX),
+    ("CPY", Zp),
+    ("CMP", Zp),
+    ("DEC", Zp),
+    ("*DCP", Zp),
+    ("INY", Imp),
+    ("CMP", Imm),
+    ("DEX", Imp),
+    ("*AXS", Imm),
+    ("CPY", Abs),
+    ("CMP", Abs),
+    ("DEC", Abs),
+    ("*DCP", Abs),
+    // $D0
+    ("BNE", Rel),
+    ("CMP", IndY),
+    ("*KIL", Imp),
+    ("*DCP", IndY),
+    ("*NOP", ZpX),
+    ("CMP", ZpX),
+    ("DEC", ZpX),
+    ("*DCP", ZpX),
+    ("CLD", Imp),
+    ("CMP", AbsY),
+    ("*NOP", Imp),
+    ("*DCP", AbsY),
+    ("*NOP", AbsX),
+    ("CMP", AbsX),
+    ("DEC", AbsX),
+    ("*DCP", AbsX),
+    // $E0
+    ("CPX", Imm),
+    ("SBC", IndX),
+    ("*NOP", Imm),
+    ("*ISB", IndX),
+    ("CPX", Zp),
+    ("SBC", Zp),
+    ("INC", Zp),
+    ("*ISB", Zp),
+    ("INX", Imp),
+    ("SBC", Imm),
+    ("NOP", Imp),
+    ("*SBC", Imm),
+    ("CPX", Abs),
+    ("SBC", Abs),
+    ("INC", Abs),
+    ("*ISB", Abs),
+    // $F0
+    ("BEQ", Rel),
+    ("SBC", IndY),
+    ("*KIL", Imp),
+    ("*ISB", IndY),
+    ("*NOP", ZpX),
+    ("SBC", ZpX),
+    ("INC", ZpX),
+    ("*ISB", ZpX),
+    ("SED", Imp),
+    ("SBC", AbsY),
+    ("*NOP", Imp),
+    ("*ISB", AbsY),
+    ("*NOP", AbsX),
+    ("SBC", AbsX),
+    ("INC", AbsX),
+    ("*ISB", AbsX),
+];
+
+// Disassembles the instruction at `pc` in the style of the nestest log:
+// the instruction bytes, and the instruction with the address it works on
+// and the value found there, as things stand before it runs. Returns the
+// bytes and the instruction separately, the latter led by a space or by
+// the `*` of an unofficial opcode.
+pub fn disassemble(pc: Word, x: Byte, y: Byte, peek: impl Fn(Word) -> Byte) -> (String, String) {
+    let opcode = peek(pc);
+    let (mnemonic, mode) = OPCODES[opcode as usize];
+    let bytes: Vec<Byte> = (0..mode.get_length())
+        .map(|i| peek(pc.wrapping_add(i as Word)))
+        .collect();
+    let zp = bytes.get(1).copied().unwrap_or(0x0);
+    let abs = Word::from_le_bytes([zp, bytes.get(2).copied().unwrap_or(0x0)]);
+    // Pointers in zero page wrap around within it.
+    let zp_word =
+        |addr: Byte| Word::from_le_bytes([peek(addr as Word), peek(addr.wrapping_add(1) as Word)]);
+
+    let operand = match mode {
+        Imp => String::new(),
+        Acc => "A".to_string(),
+        Imm => format!("#${zp:02X}"),
+        Zp => format!("${zp:02X} = {:02X}", peek(zp as Word)),
+        ZpX => {
+            let addr = zp.wrapping_add(x);
+            format!("${zp:02X},X @ {addr:02X} = {:02X}", peek(addr as Word))
+        }
+        ZpY => {
+            let addr = zp.wrapping_add(y);
+            format!("${zp:02X},Y @ {addr:02X} = {:02X}", peek(addr as Word))
+        }
+        Abs if opcode == JMP || opcode == JSR => format!("${abs:04X}"),
+        Abs => format!("${abs:04X} = {:02X}", peek(abs)),
+        AbsX => {
+            let addr = abs.wrapping_add(x as Word);
+            format!("${abs:04X},X @ {addr:04X} = {:02X}", peek(addr))
+        }
+        AbsY => {
+            let addr = abs.wrapping_add(y as Word);
+            format!("${abs:04X},Y @ {addr:04X} = {:02X}", peek(addr))
+        }
+        // JMP ($xxFF) takes the high byte from $xx00.
+        Ind => {
+            let high = (abs & 0xFF00) | (abs.wrapping_add(1) & 0x00FF);
+            let target = Word::from_le_bytes([peek(abs), peek(high)]);
+            format!("(${abs:04X}) = {target:04X}")
+        }
+        IndX => {
+            let pointer = zp.wrapping_add(x);
+            let addr = zp_word(pointer);
+            format!(
+                "(${zp:02X},X) @ {pointer:02X} = {addr:04X} = {:02X}",
+                peek(addr)
+            )
+        }
+        IndY => {
+            let base = zp_word(zp);
+            let addr = base.wrapping_add(y as Word);
+            format!(
+                "(${zp:02X}),Y = {base:04X} @ {addr:04X} = {:02X}",
+                peek(addr)
+            )
+        }
+        Rel => {
+            let target = pc.wrapping_add(2).wrapping_add(zp as i8 as Word);
+            format!("${target:04X}")
+        }
+    };
+
+    let bytes = bytes
+        .iter()
+        .map(|byte| format!("{byte:02X}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mnemonic = match mnemonic.starts_with('*') {
+        true => mnemonic.to_string(),
+        false => format!(" {mnemonic}"),
+    };
+    let text = match operand.is_empty() {
+        true => mnemonic,
+        false => format!("{mnemonic} {operand}"),
+    };
+    (bytes, text)
+}
@@ -1,21 +1,210 @@
-use crate::{Byte, Word, MEMORY_LENGTH};
+use std::{cell::RefCell, ops::RangeInclusive, rc::Rc};
+
+use crate::{
+    devices::{Device, Line},
+    dma::Dma,
+    scheduler::EventId,
+    Byte, Word, MEMORY_LENGTH,
+};
+
+struct Mapping {
+    range: RangeInclusive<Word>,
+    index: usize,
+}
+
+struct Attached {
+    device: Rc<RefCell<dyn Device>>,
+    line: Line,
+    level: bool,
+    event: Option<(u64, EventId)>,
+}
 
 pub struct Memory {
     data: Vec<Byte>,
+    mappings: Vec<Mapping>,
+    devices: Vec<Attached>,
+    address_mask: Word,
+    cycle: u64,
+    touched: Vec<usize>,
+    irq: bool,
+    nmi: bool,
+    changed: bool,
 }
 
 impl Memory {
     pub fn new() -> Self {
         Self {
             data: vec![0; MEMORY_LENGTH],
+            mappings: vec![],
+            devices: vec![],
+            address_mask: 0xFFFF,
+            cycle: 0x0,
+            touched: vec![],
+            irq: false,
+            nmi: false,
+            changed: false,
         }
     }
 
-    pub fn read_byte(&self, addr: Word) -> Byte {
+    // Address lines that are not brought out are dropped from every
+    // access, so memory repeats through the 64K the CPU can address.
+    pub fn set_address_mask(&mut self, mask: Word) {
+        self.address_mask = mask;
+    }
+
+    pub fn get_address_mask(&self) -> Word {
+        self.address_mask
+    }
+
+    // The cycle devices are brought up to before they are accessed.
+    pub fn set_cycle(&mut self, cycle: u64) {
+        self.cycle = cycle;
+    }
+
+    // Later mappings take precedence over earlier ones, so a device can be
+    // mapped over part of a larger range. A device mapped more than once is
+    // attached once, with its interrupt on the line given last. It counts
+    // as touched, so the CPU looks at it at the end of its next cycle.
+    pub fn map(
+        &mut self,
+        range: RangeInclusive<Word>,
+        device: Rc<RefCell<dyn Device>>,
+        line: Line,
+    ) {
+        let index = match self
+            .devices
+            .iter()
+            .position(|attached| Rc::ptr_eq(&attached.device, &device))
+        {
+            Some(index) => {
+                self.devices[index].line = line;
+                index
+            }
+            None => {
+                self.devices.push(Attached {
+                    device,
+                    line,
+                    level: false,
+                    event: None,
+                });
+                self.devices.len() - 1
+            }
+        };
+        self.mappings.insert(0, Mapping { range, index });
+        if !self.touched.contains(&index) {
+            self.touched.push(index);
+        }
+        self.update_lines();
+    }
+
+    fn get_device(&self, addr: Word) -> Option<usize> {
+        self.mappings
+            .iter()
+            .find(|mapping| {
+                mapping.range.contains(&addr)
+                    && self.devices[mapping.index]
+                        .device
+                        .borrow()
+                        .is_selected(addr)
+            })
+            .map(|mapping| mapping.index)
+    }
+
+    // Brings the device up to date for a bus cycle, and notes it so that
+    // the CPU looks at it again at the end of the cycle.
+    fn touch(&mut self, index: usize) -> &Rc<RefCell<dyn Device>> {
+        if !self.touched.contains(&index) {
+            self.touched.push(index);
+        }
+        let device = &self.devices[index].device;
+        device.borrow_mut().tick(self.cycle);
+        device
+    }
+
+    pub fn read_byte(&mut self, addr: Word) -> Byte {
+        let addr = addr & self.address_mask;
+        if let Some(index) = self.get_device(addr) {
+            return self.touch(index).borrow_mut().read(addr);
+        }
+        *self.data.get(addr as usize).unwrap()
+    }
+
+    // A read that leaves devices alone, for anything that is not a CPU
+    // bus cycle. Devices show what they were when last brought up to date.
+    pub fn peek_byte(&self, addr: Word) -> Byte {
+        let addr = addr & self.address_mask;
+        if let Some(index) = self.get_device(addr) {
+            return self.devices[index].device.borrow().peek(addr);
+        }
         *self.data.get(addr as usize).unwrap()
     }
 
     pub fn write_byte(&mut self, addr: Word, val: Byte) {
+        let addr = addr & self.address_mask;
+        if let Some(index) = self.get_device(addr) {
+            self.touch(index).borrow_mut().write(addr, val);
+            return;
+        }
         *self.data.get_mut(addr as usize).unwrap() = val;
     }
+
+    pub fn is_touched(&self) -> bool {
+        !self.touched.is_empty()
+    }
+
+    pub fn pop_touched(&mut self) -> Option<usize> {
+        self.touched.pop()
+    }
+
+    pub fn get_device_count(&self) -> usize {
+        self.devices.len()
+    }
+
+    // Brings a device up to `cycle` and takes in its interrupt output.
+    // Returns any DMA it has started and the cycle of its next event.
+    pub fn update_device(&mut self, index: usize, cycle: u64) -> (Option<Dma>, Option<u64>) {
+        let attached = &mut self.devices[index];
+        let mut device = attached.device.borrow_mut();
+        device.tick(cycle);
+        let level = device.irq();
+        let update = (device.take_dma(), device.get_next_event());
+        drop(device);
+        if level != attached.level {
+            attached.level = level;
+            self.changed = true;
+            self.update_lines();
+        }
+        update
+    }
+
+    pub fn get_event(&self, index: usize) -> Option<(u64, EventId)> {
+        self.devices[index].event
+    }
+
+    pub fn set_event(&mut self, index: usize, event: Option<(u64, EventId)>) {
+        self.devices[index].event = event;
+    }
+
+    // Whether an interrupt output has changed since the last call.
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    fn update_lines(&mut self) {
+        let asserted = |line| {
+            self.devices
+                .iter()
+                .any(|attached| attached.line == line && attached.level)
+        };
+        self.irq = asserted(Line::Irq);
+        self.nmi = asserted(Line::Nmi);
+    }
+
+    pub fn irq(&self) -> bool {
+        self.irq
+    }
+
+    pub fn nmi(&self) -> bool {
+        self.nmi
+    }
 }
@@ -0,0 +1,94 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+};
+
+use crate::cpu::CPU;
+
+pub type EventId = u64;
+
+type Callback = Box<dyn FnOnce(&mut CPU)>;
+
+// Cycle-stamped callbacks, owned by the CPU and fired at the end of the
+// cycle they are due in. Mapped devices have theirs scheduled for them,
+// and the host can add its own with `CPU::get_scheduler`. Events are kept
+// in a min-heap by (cycle, id) so that events due on the same cycle fire in
+// the order they were scheduled. Cancelled events stay in the heap and are
+// dropped when they reach the top.
+pub struct Scheduler {
+    queue: BinaryHeap<Reverse<(u64, EventId)>>,
+    callbacks: HashMap<EventId, Callback>,
+    next_id: EventId,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self {
+            queue: BinaryHeap::new(),
+            callbacks: HashMap::new(),
+            next_id: 0x0,
+        }
+    }
+
+    pub fn schedule<F>(&mut self, cycle: u64, callback: F) -> EventId
+    where
+        F: FnOnce(&mut CPU) + 'static,
+    {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.queue.push(Reverse((cycle, id)));
+        self.callbacks.insert(id, Box::new(callback));
+        id
+    }
+
+    pub fn cancel(&mut self, id: EventId) -> bool {
+        self.callbacks.remove(&id).is_some()
+    }
+
+    pub fn is_scheduled(&self, id: EventId) -> bool {
+        self.callbacks.contains_key(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.callbacks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.callbacks.is_empty()
+    }
+
+    pub fn get_next_due(&mut self) -> Option<u64> {
+        while let Some(Reverse((cycle, id))) = self.queue.peek() {
+            if self.callbacks.contains_key(id) {
+                return Some(*cycle);
+            }
+            self.queue.pop();
+        }
+        None
+    }
+
+    // Checked by the CPU every cycle, so it only looks at the top of the
+    // heap. A cancelled event there can make it say yes early, which
+    // `pop_due` then sorts out.
+    pub(crate) fn is_due(&self, cycle: u64) -> bool {
+        self.queue
+            .peek()
+            .is_some_and(|Reverse((due, _))| *due <= cycle)
+    }
+
+    pub(crate) fn pop_due(&mut self, cycle: u64) -> Option<Callback> {
+        match self.get_next_due() {
+            Some(due) if due <= cycle => {
+                let Reverse((_, id)) = self.queue.pop()?;
+                self.callbacks.remove(&id)
+            }
+            _ => None,
+        }
+    }
+}
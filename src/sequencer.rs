@@ -1,12 +1,17 @@
 use std::vec;
 
-use crate::{instructions::Instructions, memory::Memory, registers::Registers};
+use crate::{
+    cpu::Variant, instructions::Instructions, memory::Memory, registers::Registers, Byte, Word,
+};
 
 mod branch;
+mod cmos;
 mod group_one;
 mod group_three;
 mod group_two;
+mod interrupt;
 mod other;
+mod unofficial;
 
 const GROUP_ONE: u8 = 0b01;
 const GROUP_TWO: u8 = 0b10;
@@ -16,8 +21,25 @@ const OPCODE_MASK: u8 = 0b11100000;
 const ADDR_MODE_MASK: u8 = 0b00011100;
 const OPCODE_GROUP_MASK: u8 = 0b00000011;
 
-pub fn get_seqeunce(instruction: u8, reg: &Registers, mem: &Memory) -> Vec<Instructions> {
-    if let Some(res) = get_group_sequence(instruction) {
+pub fn get_seqeunce(
+    instruction: u8,
+    variant: Variant,
+    reg: &Registers,
+    mem: &Memory,
+) -> Vec<Instructions> {
+    if variant == Variant::Cmos {
+        if let Some(res) = cmos::get_seqeunce(instruction, reg, mem) {
+            return res;
+        }
+    } else if let Some(res) = unofficial::get_seqeunce(instruction, reg, mem) {
+        return res;
+    }
+
+    if let Some(res) = interrupt::get_seqeunce(instruction, variant) {
+        return res;
+    }
+
+    if let Some(res) = get_group_sequence(instruction, reg, mem) {
         return res;
     }
 
@@ -32,16 +54,39 @@ pub fn get_seqeunce(instruction: u8, reg: &Registers, mem: &Memory) -> Vec<Instr
     vec![]
 }
 
-fn get_group_sequence(instruction: u8) -> Option<Vec<Instructions>> {
+fn get_group_sequence(instruction: u8, reg: &Registers, mem: &Memory) -> Option<Vec<Instructions>> {
     let opcode_group: u8 = instruction & OPCODE_GROUP_MASK;
     match opcode_group {
-        GROUP_ONE => group_one::get_seqeunce(instruction),
-        GROUP_TWO => group_two::get_seqeunce(instruction),
-        GROUP_THREE => group_three::get_seqeunce(instruction),
+        GROUP_ONE => group_one::get_seqeunce(instruction, reg, mem),
+        GROUP_TWO => group_two::get_seqeunce(instruction, reg, mem),
+        GROUP_THREE => group_three::get_seqeunce(instruction, reg, mem),
         _ => None,
     }
 }
 
+// Indexed reads only spend a cycle fixing up the high byte of the address
+// when adding the index carries into it. Like a branch crossing a page,
+// that is decided up front by peeking at the operand, which starts at PC.
+fn is_crossing_page(base: Word, index: Byte) -> bool {
+    (base & 0xFF) + index as Word > 0xFF
+}
+
+fn is_abs_crossing(reg: &Registers, mem: &Memory, index: Byte) -> bool {
+    let pc = reg.get_pc();
+    let base = Word::from_le_bytes([mem.peek_byte(pc), mem.peek_byte(pc.wrapping_add(1))]);
+    is_crossing_page(base, index)
+}
+
+// The pointer wraps around within the zero page.
+fn is_zp_ind_crossing(reg: &Registers, mem: &Memory) -> bool {
+    let zp = mem.peek_byte(reg.get_pc());
+    let base = Word::from_le_bytes([
+        mem.peek_byte(zp as Word),
+        mem.peek_byte(zp.wrapping_add(1) as Word),
+    ]);
+    is_crossing_page(base, reg.get_y())
+}
+
 fn get_branch_sequence(
     instruction: u8,
     reg: &Registers,
@@ -53,3 +98,11 @@ fn get_branch_sequence(
 fn get_other_sequence(instruction: u8) -> Option<Vec<Instructions>> {
     other::get_seqeunce(instruction)
 }
+
+pub fn get_interrupt_sequence(variant: Variant) -> Vec<Instructions> {
+    interrupt::get_interrupt_sequence(variant)
+}
+
+pub fn get_reset_sequence(variant: Variant) -> Vec<Instructions> {
+    interrupt::get_reset_sequence(variant)
+}
@@ -40,14 +40,14 @@ pub fn get_seqeunce(instruction: u8, reg: &Registers, mem: &Memory) -> Option<Ve
         return Some(sequence);
     }
     sequence.push(AddToPC);
-    if is_crossing_pb(reg.get_pc() + 0b1, mem.read_byte(reg.get_pc())) {
+    if is_crossing_pb(reg.get_pc() + 0b1, mem.peek_byte(reg.get_pc())) {
         sequence.push(Idle)
     }
 
     Some(sequence)
 }
 
-fn is_crossing_pb(pc: Word, rel: Byte) -> bool {
+pub(super) fn is_crossing_pb(pc: Word, rel: Byte) -> bool {
     let mut operand = rel as u16;
     if rel >> 7 == 1 {
         operand |= 0xFF00;
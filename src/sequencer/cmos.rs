@@ -0,0 +1,239 @@
+use crate::{
+    instructions::{
+        AddrSource,
+        Instructions::{self, *},
+    },
+    memory::Memory,
+    registers::{IndexedReg, Registers},
+    Word,
+};
+use std::vec;
+
+use super::{branch, group_one, is_abs_crossing, OPCODE_GROUP_MASK, OPCODE_MASK};
+
+const TSB_ZP: u8 = 0x04;
+const TSB_A: u8 = 0x0C;
+const TRB_ZP: u8 = 0x14;
+const TRB_A: u8 = 0x1C;
+const INC_ACC: u8 = 0x1A;
+const DEC_ACC: u8 = 0x3A;
+const BIT_ZP_X: u8 = 0x34;
+const BIT_A_X: u8 = 0x3C;
+const BIT_IM: u8 = 0x89;
+const PHY: u8 = 0x5A;
+const PLY: u8 = 0x7A;
+const PHX: u8 = 0xDA;
+const PLX: u8 = 0xFA;
+const STZ_ZP: u8 = 0x64;
+const STZ_ZP_X: u8 = 0x74;
+const STZ_A: u8 = 0x9C;
+const STZ_A_X: u8 = 0x9E;
+const JMP_A_IND: u8 = 0x6C;
+const JMP_A_X_IND: u8 = 0x7C;
+const BRA: u8 = 0x80;
+const WAI: u8 = 0xCB;
+const STP: u8 = 0xDB;
+
+// (zp) takes the slot of the NMOS group one opcodes ending in 0b10010.
+const ZP_IND_MASK: u8 = 0b00011111;
+const ZP_IND: u8 = 0b10010;
+const ZP: u8 = 0b00101;
+
+// RMB and SMB fill the column ending in 0x7, BBR and BBS the one ending in
+// 0xF. Bits 4 to 6 of the opcode pick the bit, bit 7 sets or tests for set.
+const COLUMN_MASK: u8 = 0x0F;
+const BIT_OPS: u8 = 0x07;
+const BRANCH_ON_BIT: u8 = 0x0F;
+const BIT_SET: u8 = 0x80;
+
+// The opcodes left undefined are NOPs of fixed width and length. Those in
+// the columns ending in 0x3 and 0xB take one byte and one cycle, which
+// overlaps the next opcode fetch, so interrupts aren't polled in between.
+const NOP_IM: [u8; 7] = [0x02, 0x22, 0x42, 0x62, 0x82, 0xC2, 0xE2];
+const NOP_ZP: u8 = 0x44;
+const NOP_ZP_X: [u8; 3] = [0x54, 0xD4, 0xF4];
+const NOP_A: [u8; 2] = [0xDC, 0xFC];
+const NOP_LONG: u8 = 0x5C;
+const NOP_ONE_BYTE: [u8; 2] = [0x03, 0x0B];
+
+const GROUP_ONE: u8 = 0b01;
+const ADC: u8 = 0b011;
+const SBC: u8 = 0b111;
+
+// Instructions the W65C02S adds or changes, including the Rockwell bit
+// instructions, WAI and STP.
+pub fn get_seqeunce(instruction: u8, reg: &Registers, mem: &Memory) -> Option<Vec<Instructions>> {
+    let mut sequence = match instruction {
+        TSB_ZP => read_modify_write(vec![LoadZPAddr], TestAndSetBits),
+        TSB_A => read_modify_write(vec![LoadAddr(AddrSource::PC), Idle], TestAndSetBits),
+        TRB_ZP => read_modify_write(vec![LoadZPAddr], TestAndResetBits),
+        TRB_A => read_modify_write(vec![LoadAddr(AddrSource::PC), Idle], TestAndResetBits),
+
+        INC_ACC => vec![Idle, IncReg(IndexedReg::A)],
+        DEC_ACC => vec![Idle, DecReg(IndexedReg::A)],
+
+        BIT_IM => vec![MemToDataBus(AddrSource::PC), SetBitTestZeroFlag],
+        BIT_ZP_X => vec![
+            LoadZPAddr,
+            AddToZPAddr(IndexedReg::X),
+            MemToDataBus(AddrSource::AddrBus),
+            SetBitTestFlags,
+        ],
+        BIT_A_X => {
+            let mut sequence = vec![LoadAddr(AddrSource::PC)];
+            if is_abs_crossing(reg, mem, reg.get_x()) {
+                sequence.push(Idle);
+            }
+            sequence.push(AddToAddrBus(IndexedReg::X));
+            sequence.push(MemToDataBus(AddrSource::AddrBus));
+            sequence.push(SetBitTestFlags);
+            sequence
+        }
+
+        PHX => vec![Idle, Idle, PushFromReg(IndexedReg::X)],
+        PHY => vec![Idle, Idle, PushFromReg(IndexedReg::Y)],
+        PLX => vec![Idle, Idle, Idle, PullToReg(IndexedReg::X)],
+        PLY => vec![Idle, Idle, Idle, PullToReg(IndexedReg::Y)],
+
+        STZ_ZP => vec![LoadZPAddr, ClearDataBus, DataBusToMem(AddrSource::AddrBus)],
+        STZ_ZP_X => vec![
+            LoadZPAddr,
+            AddToZPAddr(IndexedReg::X),
+            ClearDataBus,
+            DataBusToMem(AddrSource::AddrBus),
+        ],
+        STZ_A => vec![
+            LoadAddr(AddrSource::PC),
+            Idle,
+            ClearDataBus,
+            DataBusToMem(AddrSource::AddrBus),
+        ],
+        STZ_A_X => vec![
+            LoadAddr(AddrSource::PC),
+            Idle,
+            AddToAddrBus(IndexedReg::X),
+            ClearDataBus,
+            DataBusToMem(AddrSource::AddrBus),
+        ],
+
+        // Unlike NMOS parts, the pointer carries into its high byte, at the
+        // cost of a cycle.
+        JMP_A_IND => vec![
+            LoadAddr(AddrSource::PC),
+            Idle,
+            LoadAddr(AddrSource::AddrBus),
+            Idle,
+            Idle,
+            MoveAddrToPc,
+        ],
+        JMP_A_X_IND => vec![
+            LoadAddr(AddrSource::PC),
+            Idle,
+            AddToAddrBus(IndexedReg::X),
+            LoadAddr(AddrSource::AddrBus),
+            Idle,
+            MoveAddrToPc,
+        ],
+
+        BRA => {
+            let mut sequence = vec![MemToDataBus(AddrSource::PC), Idle, AddToPC];
+            if branch::is_crossing_pb(reg.get_pc() + 0b1, mem.peek_byte(reg.get_pc())) {
+                sequence.push(Idle);
+            }
+            sequence
+        }
+
+        WAI => vec![Idle, Idle, Wait],
+        STP => vec![Idle, Idle, Stop],
+
+        // (zp) is zp with the address read from the pointer there first,
+        // five cycles like (zp),Y without a page crossing.
+        _ if instruction & ZP_IND_MASK == ZP_IND => {
+            let mut sequence = group_one::get_seqeunce((instruction & OPCODE_MASK) | ZP, reg, mem)?;
+            sequence.splice(1..1, [LoadIndirectAddr, Idle]);
+            sequence
+        }
+
+        _ if instruction & COLUMN_MASK == BIT_OPS => {
+            let bit = (instruction >> 4) & 0x7;
+            match instruction & BIT_SET {
+                0 => read_modify_write(vec![LoadZPAddr], ResetDataBusBit(bit)),
+                _ => read_modify_write(vec![LoadZPAddr], SetDataBusBit(bit)),
+            }
+        }
+        _ if instruction & COLUMN_MASK == BRANCH_ON_BIT => branch_on_bit(instruction, reg, mem),
+
+        _ if NOP_IM.contains(&instruction) => vec![MemToDataBus(AddrSource::PC), Idle],
+        NOP_ZP => vec![LoadZPAddr, MemToDataBus(AddrSource::AddrBus), Idle],
+        _ if NOP_ZP_X.contains(&instruction) => vec![
+            LoadZPAddr,
+            AddToZPAddr(IndexedReg::X),
+            MemToDataBus(AddrSource::AddrBus),
+            Idle,
+        ],
+        _ if NOP_A.contains(&instruction) => vec![
+            LoadAddr(AddrSource::PC),
+            Idle,
+            MemToDataBus(AddrSource::AddrBus),
+            Idle,
+        ],
+        NOP_LONG => {
+            let mut sequence = vec![LoadAddr(AddrSource::PC)];
+            sequence.extend((0..7).map(|_| Idle));
+            sequence
+        }
+        _ if NOP_ONE_BYTE.contains(&(instruction & COLUMN_MASK)) => vec![Idle],
+
+        _ if is_decimal_op(instruction) && reg.get_p().d => {
+            group_one::get_seqeunce(instruction, reg, mem)?
+        }
+
+        _ => return None,
+    };
+
+    // Decimal ADC and SBC take an extra cycle to set N and Z from the
+    // adjusted result.
+    if is_decimal_op(instruction) && reg.get_p().d {
+        sequence.push(UpdateFlags(IndexedReg::A));
+    }
+
+    Some(sequence)
+}
+
+fn read_modify_write(mut sequence: Vec<Instructions>, op: Instructions) -> Vec<Instructions> {
+    sequence.push(MemToDataBus(AddrSource::AddrBus));
+    sequence.push(op);
+    sequence.push(Idle);
+    sequence.push(DataBusToMem(AddrSource::AddrBus));
+    sequence
+}
+
+// BBR and BBS test a bit of a zero page byte and branch on it. Like the
+// other branches, whether they are taken is decided up front, here by
+// peeking at the byte.
+fn branch_on_bit(instruction: u8, reg: &Registers, mem: &Memory) -> Vec<Instructions> {
+    let pc = reg.get_pc();
+    let val = mem.peek_byte(mem.peek_byte(pc) as Word);
+    let set = (val >> ((instruction >> 4) & 0x7)) & 0x1 == 1;
+    let mut sequence = vec![
+        LoadZPAddr,
+        MemToDataBus(AddrSource::AddrBus),
+        Idle,
+        MemToDataBus(AddrSource::PC),
+        Idle,
+    ];
+    if set == (instruction & BIT_SET != 0) {
+        sequence.push(AddToPC);
+        if branch::is_crossing_pb(pc.wrapping_add(2), mem.peek_byte(pc.wrapping_add(1))) {
+            sequence.push(Idle);
+        }
+    }
+    sequence
+}
+
+fn is_decimal_op(instruction: u8) -> bool {
+    let opcode = (instruction & OPCODE_MASK) >> 5;
+    let group_one =
+        instruction & OPCODE_GROUP_MASK == GROUP_ONE || instruction & ZP_IND_MASK == ZP_IND;
+    group_one && (opcode == ADC || opcode == SBC)
+}
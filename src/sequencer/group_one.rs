@@ -1,10 +1,11 @@
 use crate::{
     instructions::{Instructions::{self, *}, AddrSource},
-    registers::IndexedReg,
+    memory::Memory,
+    registers::{IndexedReg, Registers},
 };
 use std::vec;
 
-use super::{ADDR_MODE_MASK, OPCODE_MASK};
+use super::{is_abs_crossing, is_zp_ind_crossing, ADDR_MODE_MASK, OPCODE_MASK};
 
 const ORA: u8 = 0b000;
 const AND: u8 = 0b001;
@@ -24,7 +25,7 @@ const ZP_X: u8 = 0b101;
 const A_Y: u8 = 0b110;
 const A_X: u8 = 0b111;
 
-pub fn get_seqeunce(instruction: u8) -> Option<Vec<Instructions>> {
+pub fn get_seqeunce(instruction: u8, reg: &Registers, mem: &Memory) -> Option<Vec<Instructions>> {
     let opcode = (instruction & OPCODE_MASK) >> 5;
     let addr_mode = (instruction & ADDR_MODE_MASK) >> 2;
     let mut sequence = vec![];
@@ -33,18 +34,20 @@ pub fn get_seqeunce(instruction: u8) -> Option<Vec<Instructions>> {
         ZP => sequence.push(LoadZPAddr),
         ZP_X => {
             sequence.push(LoadZPAddr);
-            sequence.push(AddToAddrBus(IndexedReg::X));
+            sequence.push(AddToZPAddr(IndexedReg::X));
         }
         ZP_X_IND => {
             sequence.push(LoadZPAddr);
-            sequence.push(AddToAddrBus(IndexedReg::X));
-            sequence.push(LoadAddr(AddrSource::AddrBus));
+            sequence.push(AddToZPAddr(IndexedReg::X));
+            sequence.push(LoadIndirectAddr);
             sequence.push(Idle);
         }
         ZP_Y_IND => {
             sequence.push(LoadZPAddr);
-            sequence.push(LoadAddr(AddrSource::AddrBus));
-            sequence.push(Idle);
+            sequence.push(LoadIndirectAddr);
+            if opcode == STA || is_zp_ind_crossing(reg, mem) {
+                sequence.push(Idle);
+            }
             sequence.push(AddToAddrBus(IndexedReg::Y));
         }
         A => {
@@ -53,12 +56,16 @@ pub fn get_seqeunce(instruction: u8) -> Option<Vec<Instructions>> {
         }
         A_X => {
             sequence.push(LoadAddr(AddrSource::PC));
-            sequence.push(Idle);
+            if opcode == STA || is_abs_crossing(reg, mem, reg.get_x()) {
+                sequence.push(Idle);
+            }
             sequence.push(AddToAddrBus(IndexedReg::X));
         }
         A_Y => {
             sequence.push(LoadAddr(AddrSource::PC));
-            sequence.push(Idle);
+            if opcode == STA || is_abs_crossing(reg, mem, reg.get_y()) {
+                sequence.push(Idle);
+            }
             sequence.push(AddToAddrBus(IndexedReg::Y));
         }
         IM => {}
@@ -1,10 +1,11 @@
 use crate::{
     instructions::{Instructions::{self, *}, AddrSource},
-    registers::IndexedReg,
+    memory::Memory,
+    registers::{IndexedReg, Registers},
 };
 use std::vec;
 
-use super::{ADDR_MODE_MASK, OPCODE_MASK};
+use super::{is_abs_crossing, ADDR_MODE_MASK, OPCODE_MASK};
 
 const BIT: u8 = 0b001;
 const JMP: u8 = 0b010;
@@ -20,7 +21,7 @@ const A: u8 = 0b011;
 const ZP_X: u8 = 0b101;
 const A_X: u8 = 0b111;
 
-pub fn get_seqeunce(instruction: u8) -> Option<Vec<Instructions>> {
+pub fn get_seqeunce(instruction: u8, reg: &Registers, mem: &Memory) -> Option<Vec<Instructions>> {
     let opcode = (instruction & OPCODE_MASK) >> 5;
     let addr_mode = (instruction & ADDR_MODE_MASK) >> 2;
     let mut sequence = vec![];
@@ -29,7 +30,7 @@ pub fn get_seqeunce(instruction: u8) -> Option<Vec<Instructions>> {
         ZP => sequence.push(LoadZPAddr),
         ZP_X => {
             sequence.push(LoadZPAddr);
-            sequence.push(AddToAddrBus(IndexedReg::X));
+            sequence.push(AddToZPAddr(IndexedReg::X));
         }
         A => {
             sequence.push(LoadAddr(AddrSource::PC));
@@ -37,7 +38,9 @@ pub fn get_seqeunce(instruction: u8) -> Option<Vec<Instructions>> {
         }
         A_X => {
             sequence.push(LoadAddr(AddrSource::PC));
-            sequence.push(Idle);
+            if opcode == STY || is_abs_crossing(reg, mem, reg.get_x()) {
+                sequence.push(Idle);
+            }
             sequence.push(AddToAddrBus(IndexedReg::X));
         }
         IM => {}
@@ -78,8 +81,10 @@ pub fn get_seqeunce(instruction: u8) -> Option<Vec<Instructions>> {
 
         (JMP, A) => sequence.push(MoveAddrToPc),
 
+        // NMOS parts don't carry into the high byte of the pointer, so
+        // JMP ($xxFF) reads it from $xx00.
         (JMP_ABS, A) => {
-            sequence.push(LoadAddr(AddrSource::AddrBus));
+            sequence.push(LoadIndirectAddr);
             sequence.push(Idle);
             sequence.push(MoveAddrToPc);
         }
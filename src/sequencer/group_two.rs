@@ -1,10 +1,11 @@
 use crate::{
     instructions::{Instructions::{self, *}, Direction, DataSource, AddrSource},
-    registers::IndexedReg,
+    memory::Memory,
+    registers::{IndexedReg, Registers},
 };
 use std::vec;
 
-use super::{ADDR_MODE_MASK, OPCODE_MASK};
+use super::{is_abs_crossing, ADDR_MODE_MASK, OPCODE_MASK};
 
 const ASL: u8 = 0b000;
 const ROL: u8 = 0b001;
@@ -22,7 +23,7 @@ const A: u8 = 0b011;
 const ZP_X: u8 = 0b101;
 const A_X: u8 = 0b111;
 
-pub fn get_seqeunce(instruction: u8) -> Option<Vec<Instructions>> {
+pub fn get_seqeunce(instruction: u8, reg: &Registers, mem: &Memory) -> Option<Vec<Instructions>> {
     let opcode = (instruction & OPCODE_MASK) >> 5;
     let addr_mode = (instruction & ADDR_MODE_MASK) >> 2;
     let mut sequence = vec![];
@@ -35,7 +36,7 @@ pub fn get_seqeunce(instruction: u8) -> Option<Vec<Instructions>> {
             if opcode == STX || opcode == LDX {
                 reg = IndexedReg::Y;
             }
-            sequence.push(AddToAddrBus(reg));
+            sequence.push(AddToZPAddr(reg));
         }
         A => {
             sequence.push(LoadAddr(AddrSource::PC));
@@ -43,7 +44,10 @@ pub fn get_seqeunce(instruction: u8) -> Option<Vec<Instructions>> {
         }
         A_X => {
             sequence.push(LoadAddr(AddrSource::PC));
-            sequence.push(Idle);
+            // Only LDX reads, the rest are read-modify-write.
+            if opcode != LDX || is_abs_crossing(reg, mem, reg.get_y()) {
+                sequence.push(Idle);
+            }
             let mut reg = IndexedReg::X;
             if opcode == LDX {
                 reg = IndexedReg::Y;
@@ -56,7 +60,7 @@ pub fn get_seqeunce(instruction: u8) -> Option<Vec<Instructions>> {
     }
 
     match (opcode, addr_mode) {
-        (DEC, ACC) | (STX, ACC) | (LDX, ACC) => return None,
+        (DEC, ACC) | (STX, ACC) | (LDX, ACC) | (INC, ACC) => return None,
 
         (ASL, ACC) => sequence.push(Shift(Direction::Left, DataSource::Reg)),
         (ASL, _) => {
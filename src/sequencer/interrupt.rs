@@ -0,0 +1,59 @@
+use crate::{
+    cpu::Variant,
+    instructions::{Instructions::{self, *}, AddrSource},
+    registers::Flag,
+};
+use std::vec;
+
+const BRK: u8 = 0x00;
+
+pub fn get_seqeunce(instruction: u8, variant: Variant) -> Option<Vec<Instructions>> {
+    if instruction != BRK {
+        return None;
+    }
+
+    // The padding byte after BRK is read and skipped, so the pushed return
+    // address points past it.
+    let mut sequence = vec![MemToDataBus(AddrSource::PC)];
+    push_frame(&mut sequence, true, variant);
+
+    Some(sequence)
+}
+
+pub fn get_interrupt_sequence(variant: Variant) -> Vec<Instructions> {
+    let mut sequence = vec![Idle];
+    push_frame(&mut sequence, false, variant);
+
+    sequence
+}
+
+// Reset runs the interrupt entry with the stack writes turned into reads,
+// so S ends up three lower and nothing is written.
+pub fn get_reset_sequence(variant: Variant) -> Vec<Instructions> {
+    vec![
+        Idle,
+        DecStackPointer,
+        DecStackPointer,
+        DecStackPointer,
+        LoadResetVector,
+        mask_interrupts(variant),
+        MoveAddrToPc,
+    ]
+}
+
+fn push_frame(sequence: &mut Vec<Instructions>, brk: bool, variant: Variant) {
+    sequence.push(PushPCHigh);
+    sequence.push(PushPCLow);
+    sequence.push(PushStatus(brk));
+    sequence.push(LoadVector);
+    sequence.push(mask_interrupts(variant));
+    sequence.push(MoveAddrToPc);
+}
+
+// The CMOS parts also clear D, so handlers start out in binary mode.
+fn mask_interrupts(variant: Variant) -> Instructions {
+    match variant {
+        Variant::Nmos | Variant::Ricoh2A03 => SetFlags(Flag::I),
+        Variant::Cmos => MaskInterrupts,
+    }
+}
@@ -4,7 +4,6 @@ use crate::{
 };
 use std::vec;
 
-const BRK: u8 = 0x00;
 const JSR_ABS: u8 = 0x20;
 const RTI: u8 = 0x40;
 const RTS: u8 = 0x60;
@@ -52,20 +51,12 @@ pub fn get_seqeunce(instruction: u8) -> Option<Vec<Instructions>> {
             sequence.push(Idle);
         }
         RTI => {
+            sequence.push(Idle);
             sequence.push(Idle);
             sequence.push(PullToStatus);
             sequence.push(PullPC);
             sequence.push(Idle);
         }
-        BRK => {
-            // TODO
-            sequence.push(PushPC);
-            sequence.push(Idle);
-            sequence.push(SetFlags(Flag::B));
-            sequence.push(Idle);
-            sequence.push(Idle);
-            sequence.push(Idle);
-        }
         INX => sequence.push(IncReg(IndexedReg::X)),
         INY => sequence.push(IncReg(IndexedReg::Y)),
         DEX => sequence.push(DecReg(IndexedReg::X)),
@@ -77,10 +68,10 @@ pub fn get_seqeunce(instruction: u8) -> Option<Vec<Instructions>> {
         TXA => sequence.push(TransferReg(IndexedReg::X, IndexedReg::A)),
         TXS => sequence.push(TransferReg(IndexedReg::X, IndexedReg::S)),
         TYA => sequence.push(TransferReg(IndexedReg::Y, IndexedReg::A)),
-        PLA => sequence.push(PullToReg(IndexedReg::A)),
-        PHA => sequence.push(PushFromReg(IndexedReg::A)),
-        PLP => sequence.push(PullToReg(IndexedReg::S)),
-        PHP => sequence.push(PushFromReg(IndexedReg::S)),
+        PLA => sequence.extend([Idle, Idle, PullToReg(IndexedReg::A)]),
+        PHA => sequence.extend([Idle, PushFromReg(IndexedReg::A)]),
+        PLP => sequence.extend([Idle, Idle, PullToStatus]),
+        PHP => sequence.extend([Idle, PushStatus(true)]),
         CLC => sequence.push(ClearFlags(Flag::C)),
         CLD => sequence.push(ClearFlags(Flag::D)),
         CLI => sequence.push(ClearFlags(Flag::I)),
@@ -0,0 +1,245 @@
+use crate::{
+    instructions::{
+        AddrSource, DataSource, Direction,
+        Instructions::{self, *},
+    },
+    memory::Memory,
+    registers::{IndexedReg, Registers},
+};
+use std::vec;
+
+use super::{is_abs_crossing, is_zp_ind_crossing, ADDR_MODE_MASK, OPCODE_GROUP_MASK, OPCODE_MASK};
+
+const KIL: [u8; 12] = [
+    0x02, 0x12, 0x22, 0x32, 0x42, 0x52, 0x62, 0x72, 0x92, 0xB2, 0xD2, 0xF2,
+];
+const NOP: [u8; 6] = [0x1A, 0x3A, 0x5A, 0x7A, 0xDA, 0xFA];
+const NOP_IM: [u8; 5] = [0x80, 0x82, 0x89, 0xC2, 0xE2];
+const NOP_ZP: [u8; 3] = [0x04, 0x44, 0x64];
+const NOP_ZP_X: [u8; 6] = [0x14, 0x34, 0x54, 0x74, 0xD4, 0xF4];
+const NOP_A: u8 = 0x0C;
+const NOP_A_X: [u8; 6] = [0x1C, 0x3C, 0x5C, 0x7C, 0xDC, 0xFC];
+const ANC_IM: [u8; 2] = [0x0B, 0x2B];
+const ALR_IM: u8 = 0x4B;
+const ARR_IM: u8 = 0x6B;
+const XAA_IM: u8 = 0x8B;
+const LAX_IM: u8 = 0xAB;
+const AXS_IM: u8 = 0xCB;
+const SBC_IM: u8 = 0xEB;
+const AHX_ZP_Y_IND: u8 = 0x93;
+const TAS_A_Y: u8 = 0x9B;
+const SHY_A_X: u8 = 0x9C;
+const SHX_A_Y: u8 = 0x9E;
+const AHX_A_Y: u8 = 0x9F;
+const LAS_A_Y: u8 = 0xBB;
+
+// The rest of the unofficial opcodes fill the group ending in 0b11, where
+// each does the group one and group two operations of its row at once.
+const GROUP_COMBINED: u8 = 0b11;
+
+const SLO: u8 = 0b000;
+const RLA: u8 = 0b001;
+const SRE: u8 = 0b010;
+const RRA: u8 = 0b011;
+const SAX: u8 = 0b100;
+const LAX: u8 = 0b101;
+const DCP: u8 = 0b110;
+const ISB: u8 = 0b111;
+
+const ZP_X_IND: u8 = 0b000;
+const ZP: u8 = 0b001;
+const A: u8 = 0b011;
+const ZP_Y_IND: u8 = 0b100;
+const ZP_X: u8 = 0b101;
+const A_Y: u8 = 0b110;
+const A_X: u8 = 0b111;
+
+// The opcodes NMOS parts leave undocumented. They are as stable as the
+// rest, except for XAA, LAX #imm, AHX, SHX, SHY and TAS, which depend on
+// the chip and follow the common reading of them here.
+pub fn get_seqeunce(instruction: u8, reg: &Registers, mem: &Memory) -> Option<Vec<Instructions>> {
+    let sequence = match instruction {
+        // Halts the CPU until it is reset.
+        _ if KIL.contains(&instruction) => vec![Idle, Stop],
+
+        _ if NOP.contains(&instruction) => vec![Idle, Idle],
+        _ if NOP_IM.contains(&instruction) => vec![MemToDataBus(AddrSource::PC), Idle],
+        _ if NOP_ZP.contains(&instruction) => {
+            vec![LoadZPAddr, MemToDataBus(AddrSource::AddrBus), Idle]
+        }
+        _ if NOP_ZP_X.contains(&instruction) => vec![
+            LoadZPAddr,
+            AddToZPAddr(IndexedReg::X),
+            MemToDataBus(AddrSource::AddrBus),
+            Idle,
+        ],
+        NOP_A => vec![
+            LoadAddr(AddrSource::PC),
+            Idle,
+            MemToDataBus(AddrSource::AddrBus),
+            Idle,
+        ],
+        _ if NOP_A_X.contains(&instruction) => {
+            let mut sequence = abs_indexed(IndexedReg::X, false, reg, mem);
+            sequence.extend([MemToDataBus(AddrSource::AddrBus), Idle]);
+            sequence
+        }
+
+        _ if ANC_IM.contains(&instruction) => vec![MemToDataBus(AddrSource::PC), ANDSetCarry],
+        ALR_IM => vec![MemToDataBus(AddrSource::PC), ANDShiftRight],
+        ARR_IM => vec![MemToDataBus(AddrSource::PC), ANDRotateRight],
+        XAA_IM => vec![MemToDataBus(AddrSource::PC), ANDXToA],
+        // Like XAA, taking the magic constant as $FF.
+        LAX_IM => vec![MemToDataBus(AddrSource::PC), DataBusToAX],
+        AXS_IM => vec![MemToDataBus(AddrSource::PC), ANDSubFromX],
+        SBC_IM => vec![MemToDataBus(AddrSource::PC), SubFromReg(IndexedReg::A)],
+
+        AHX_ZP_Y_IND => vec![
+            LoadZPAddr,
+            LoadIndirectAddr,
+            Idle,
+            Idle,
+            AXToDataBus,
+            StoreHighAnd(IndexedReg::Y),
+        ],
+        AHX_A_Y => store_high_and(AXToDataBus, IndexedReg::Y),
+        SHY_A_X => store_high_and(RegToDataBus(IndexedReg::Y), IndexedReg::X),
+        SHX_A_Y => store_high_and(RegToDataBus(IndexedReg::X), IndexedReg::Y),
+        // S = A AND X, then stores S AND (H + 1) like AHX.
+        TAS_A_Y => vec![
+            LoadAddr(AddrSource::PC),
+            Idle,
+            AXToDataBus,
+            DataBusToReg(IndexedReg::S),
+            StoreHighAnd(IndexedReg::Y),
+        ],
+        LAS_A_Y => {
+            let mut sequence = abs_indexed(IndexedReg::Y, false, reg, mem);
+            sequence.extend([MemToDataBus(AddrSource::AddrBus), ANDWithStack]);
+            sequence
+        }
+
+        _ if instruction & OPCODE_GROUP_MASK == GROUP_COMBINED => {
+            get_combined_sequence(instruction, reg, mem)?
+        }
+
+        _ => return None,
+    };
+
+    Some(sequence)
+}
+
+fn get_combined_sequence(
+    instruction: u8,
+    reg: &Registers,
+    mem: &Memory,
+) -> Option<Vec<Instructions>> {
+    let opcode = (instruction & OPCODE_MASK) >> 5;
+    let addr_mode = (instruction & ADDR_MODE_MASK) >> 2;
+    // SAX and LAX index by Y where the others index by X, like STX and LDX.
+    let index = match opcode {
+        SAX | LAX => IndexedReg::Y,
+        _ => IndexedReg::X,
+    };
+    // Only LAX reads, the rest store or read-modify-write.
+    let read = opcode == LAX;
+    let mut sequence = vec![];
+
+    match addr_mode {
+        ZP => sequence.push(LoadZPAddr),
+        ZP_X => {
+            sequence.push(LoadZPAddr);
+            sequence.push(AddToZPAddr(index));
+        }
+        ZP_X_IND => {
+            sequence.push(LoadZPAddr);
+            sequence.push(AddToZPAddr(IndexedReg::X));
+            sequence.push(LoadIndirectAddr);
+            sequence.push(Idle);
+        }
+        ZP_Y_IND => {
+            sequence.push(LoadZPAddr);
+            sequence.push(LoadIndirectAddr);
+            if !read || is_zp_ind_crossing(reg, mem) {
+                sequence.push(Idle);
+            }
+            sequence.push(AddToAddrBus(IndexedReg::Y));
+        }
+        A => {
+            sequence.push(LoadAddr(AddrSource::PC));
+            sequence.push(Idle);
+        }
+        A_X => sequence = abs_indexed(index, !read, reg, mem),
+        A_Y => sequence = abs_indexed(IndexedReg::Y, !read, reg, mem),
+        _ => return None,
+    }
+
+    let (modify, combine) = match opcode {
+        SAX => {
+            sequence.extend([AXToDataBus, DataBusToMem(AddrSource::AddrBus)]);
+            return Some(sequence);
+        }
+        LAX => {
+            sequence.extend([MemToDataBus(AddrSource::AddrBus), DataBusToAX]);
+            return Some(sequence);
+        }
+        SLO => (
+            Shift(Direction::Left, DataSource::DataBus),
+            ORWithReg(IndexedReg::A),
+        ),
+        RLA => (
+            Rotate(Direction::Left, DataSource::DataBus),
+            ANDWithReg(IndexedReg::A),
+        ),
+        SRE => (
+            Shift(Direction::Right, DataSource::DataBus),
+            XORWithReg(IndexedReg::A),
+        ),
+        RRA => (
+            Rotate(Direction::Right, DataSource::DataBus),
+            AddToReg(IndexedReg::A),
+        ),
+        DCP => (DecDataBus, CompareWithReg(IndexedReg::A)),
+        ISB => (IncDataBus, SubFromReg(IndexedReg::A)),
+        _ => return None,
+    };
+
+    // The accumulator is worked on in the cycle that writes back the
+    // unmodified value.
+    sequence.extend([
+        MemToDataBus(AddrSource::AddrBus),
+        modify,
+        combine,
+        DataBusToMem(AddrSource::AddrBus),
+    ]);
+    Some(sequence)
+}
+
+fn abs_indexed(
+    index: IndexedReg,
+    always_fix_up: bool,
+    reg: &Registers,
+    mem: &Memory,
+) -> Vec<Instructions> {
+    let mut sequence = vec![LoadAddr(AddrSource::PC)];
+    let val = match index {
+        IndexedReg::Y => reg.get_y(),
+        _ => reg.get_x(),
+    };
+    if always_fix_up || is_abs_crossing(reg, mem, val) {
+        sequence.push(Idle);
+    }
+    sequence.push(AddToAddrBus(index));
+    sequence
+}
+
+// The index is added by StoreHighAnd itself, which needs the base address.
+fn store_high_and(load: Instructions, index: IndexedReg) -> Vec<Instructions> {
+    vec![
+        LoadAddr(AddrSource::PC),
+        Idle,
+        Idle,
+        load,
+        StoreHighAnd(index),
+    ]
+}
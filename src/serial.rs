@@ -0,0 +1,165 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    io::{self, ErrorKind, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    rc::Rc,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use crate::Byte;
+
+// The host end of a serial line. `receive` is polled whenever the device is
+// ready to take a character and must not block.
+pub trait SerialBackend {
+    fn receive(&mut self) -> Option<Byte>;
+
+    fn transmit(&mut self, val: Byte);
+}
+
+// In-memory byte queues. Clones share the same queues, so a test can keep
+// one handle and give the other to the device.
+#[derive(Clone, Default)]
+pub struct QueueBackend {
+    rx: Rc<RefCell<VecDeque<Byte>>>,
+    tx: Rc<RefCell<Vec<Byte>>>,
+}
+
+impl QueueBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn send(&self, data: &[Byte]) {
+        self.rx.borrow_mut().extend(data);
+    }
+
+    pub fn take_output(&self) -> Vec<Byte> {
+        std::mem::take(&mut *self.tx.borrow_mut())
+    }
+
+    pub fn pending_input(&self) -> usize {
+        self.rx.borrow().len()
+    }
+}
+
+impl SerialBackend for QueueBackend {
+    fn receive(&mut self) -> Option<Byte> {
+        self.rx.borrow_mut().pop_front()
+    }
+
+    fn transmit(&mut self, val: Byte) {
+        self.tx.borrow_mut().push(val);
+    }
+}
+
+// The process's stdin and stdout. Stdin is read on a background thread so
+// that polling never blocks the emulator.
+pub struct StdioBackend {
+    rx: Receiver<Byte>,
+}
+
+impl Default for StdioBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StdioBackend {
+    pub fn new() -> Self {
+        let (sender, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for byte in io::stdin().lock().bytes() {
+                let Ok(byte) = byte else { break };
+                if sender.send(byte).is_err() {
+                    break;
+                }
+            }
+        });
+        Self { rx }
+    }
+}
+
+impl SerialBackend for StdioBackend {
+    fn receive(&mut self) -> Option<Byte> {
+        self.rx.try_recv().ok()
+    }
+
+    fn transmit(&mut self, val: Byte) {
+        let mut stdout = io::stdout().lock();
+        let _ = stdout.write_all(&[val]);
+        let _ = stdout.flush();
+    }
+}
+
+// Listens on a TCP port so that a terminal program (telnet, nc, ...) can
+// connect to the emulated serial port. One client at a time; output is
+// dropped while nobody is connected.
+pub struct TcpBackend {
+    listener: TcpListener,
+    client: Option<TcpStream>,
+}
+
+impl TcpBackend {
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            client: None,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.client.is_some()
+    }
+
+    fn accept(&mut self) {
+        if self.client.is_some() {
+            return;
+        }
+        if let Ok((stream, _)) = self.listener.accept() {
+            if stream.set_nonblocking(true).is_ok() {
+                let _ = stream.set_nodelay(true);
+                self.client = Some(stream);
+            }
+        }
+    }
+}
+
+impl SerialBackend for TcpBackend {
+    fn receive(&mut self) -> Option<Byte> {
+        self.accept();
+        let client = self.client.as_mut()?;
+        let mut buf = [0x0];
+        match client.read(&mut buf) {
+            Ok(1) => Some(buf[0]),
+            Ok(_) => {
+                self.client = None;
+                None
+            }
+            Err(err) if err.kind() == ErrorKind::WouldBlock => None,
+            Err(_) => {
+                self.client = None;
+                None
+            }
+        }
+    }
+
+    fn transmit(&mut self, val: Byte) {
+        self.accept();
+        let Some(client) = self.client.as_mut() else {
+            return;
+        };
+        match client.write_all(&[val]) {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::WouldBlock => {}
+            Err(_) => self.client = None,
+        }
+    }
+}
@@ -0,0 +1,109 @@
+// Mono 16-bit PCM WAV files, for listening to rendered sound output and
+// feeding recordings back in as input.
+
+use std::io;
+
+const HEADER_LENGTH: u32 = 36;
+
+pub fn encode_wav(sample_rate: u32, samples: &[i16]) -> Vec<u8> {
+    let data_length = samples.len() as u32 * 2;
+    let mut out = Vec::with_capacity(HEADER_LENGTH as usize + 8 + data_length as usize);
+    out.extend(b"RIFF");
+    out.extend((HEADER_LENGTH + data_length).to_le_bytes());
+    out.extend(b"WAVE");
+
+    out.extend(b"fmt ");
+    out.extend(16_u32.to_le_bytes());
+    // PCM, one channel, two bytes per frame, 16 bits per sample.
+    out.extend(1_u16.to_le_bytes());
+    out.extend(1_u16.to_le_bytes());
+    out.extend(sample_rate.to_le_bytes());
+    out.extend((sample_rate * 2).to_le_bytes());
+    out.extend(2_u16.to_le_bytes());
+    out.extend(16_u16.to_le_bytes());
+
+    out.extend(b"data");
+    out.extend(data_length.to_le_bytes());
+    for sample in samples {
+        out.extend(sample.to_le_bytes());
+    }
+    out
+}
+
+// Reads a PCM WAV file with 8 or 16-bit samples back into mono 16-bit
+// samples, averaging the channels. Returns the sample rate and samples.
+pub fn decode_wav(bytes: &[u8]) -> io::Result<(u32, Vec<i16>)> {
+    if bytes.len() < 12 || bytes[..4] != *b"RIFF" || bytes[8..12] != *b"WAVE" {
+        return Err(invalid("not a WAV file"));
+    }
+
+    let mut format = None;
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let length = u32::from_le_bytes(bytes[pos + 4..pos + 8].try_into().unwrap()) as usize;
+        let start = pos + 8;
+        let end = (start + length).min(bytes.len());
+        let chunk = &bytes[start..end];
+        match id {
+            b"fmt " => format = Some(Format::parse(chunk)?),
+            b"data" => {
+                let format = format.ok_or_else(|| invalid("data before fmt chunk"))?;
+                return Ok((format.sample_rate, format.samples(chunk)));
+            }
+            _ => {}
+        }
+        // Chunks are padded to an even length.
+        pos = start + length + (length & 0x1);
+    }
+    Err(invalid("no data chunk"))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[derive(Clone, Copy)]
+struct Format {
+    channels: usize,
+    sample_rate: u32,
+    bits: u16,
+}
+
+impl Format {
+    fn parse(chunk: &[u8]) -> io::Result<Self> {
+        if chunk.len() < 16 {
+            return Err(invalid("short fmt chunk"));
+        }
+        let field = |at: usize| u16::from_le_bytes([chunk[at], chunk[at + 1]]);
+        if field(0) != 1 {
+            return Err(invalid("not PCM"));
+        }
+        let format = Self {
+            channels: field(2) as usize,
+            sample_rate: u32::from_le_bytes(chunk[4..8].try_into().unwrap()),
+            bits: field(14),
+        };
+        if format.channels == 0 || !matches!(format.bits, 8 | 16) {
+            return Err(invalid("unsupported sample format"));
+        }
+        Ok(format)
+    }
+
+    fn samples(&self, data: &[u8]) -> Vec<i16> {
+        let width = self.bits as usize / 8;
+        data.chunks_exact(width * self.channels)
+            .map(|frame| {
+                let sum: i32 = frame
+                    .chunks_exact(width)
+                    .map(|sample| match width {
+                        // 8-bit samples are unsigned.
+                        1 => (sample[0] as i32 - 0x80) << 8,
+                        _ => i16::from_le_bytes([sample[0], sample[1]]) as i32,
+                    })
+                    .sum();
+                (sum / self.channels as i32) as i16
+            })
+            .collect()
+    }
+}
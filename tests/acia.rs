@@ -0,0 +1,223 @@
+mod common;
+
+use std::{
+    cell::RefCell,
+    io::{Read, Write},
+    net::TcpStream,
+    rc::Rc,
+    time::Duration,
+};
+
+use rem6502::{
+    cpu::CPU,
+    devices::{Acia, Device},
+    serial::{QueueBackend, TcpBackend},
+};
+
+use common::load;
+
+fn tick(acia: &mut Acia, cycle: &mut u64, cycles: u64) {
+    *cycle += cycles;
+    acia.tick(*cycle);
+}
+
+fn tick_frames(acia: &mut Acia, cycle: &mut u64, frames: u64) {
+    let cycles = acia.get_frame_cycles() * frames;
+    tick(acia, cycle, cycles);
+}
+
+#[test]
+fn test_frame_timing() {
+    let mut cycle = 0;
+    let backend = QueueBackend::new();
+    let mut acia = Acia::new(Box::new(backend.clone()), 1_000_000);
+
+    // 9600 baud, 8N1
+    acia.write(0x3, 0x1E);
+    assert_eq!(acia.get_baud_rate(), 9600.0);
+    assert_eq!(acia.get_frame_cycles(), 1041);
+
+    acia.write(0x0, b'A');
+    assert_eq!(acia.read(0x1) & 0x10, 0x10);
+
+    acia.write(0x0, b'B');
+    assert_eq!(acia.read(0x1) & 0x10, 0x00);
+
+    tick(&mut acia, &mut cycle, 1040);
+    assert!(backend.take_output().is_empty());
+
+    tick(&mut acia, &mut cycle, 1);
+    assert_eq!(backend.take_output(), b"A");
+    assert_eq!(acia.read(0x1) & 0x10, 0x10);
+
+    tick(&mut acia, &mut cycle, 1041);
+    assert_eq!(backend.take_output(), b"B");
+}
+
+#[test]
+fn test_word_length_and_stop_bits() {
+    let mut cycle = 0;
+    let backend = QueueBackend::new();
+    let mut acia = Acia::new(Box::new(backend.clone()), 1_000_000);
+
+    // 300 baud, 7 data bits, 2 stop bits, odd parity
+    acia.write(0x3, 0xA6);
+    acia.write(0x2, 0x20);
+    assert_eq!(acia.get_frame_cycles(), 36666);
+
+    acia.write(0x0, 0xC1);
+    tick(&mut acia, &mut cycle, 36666);
+    assert_eq!(backend.take_output(), vec![0x41]);
+}
+
+#[test]
+fn test_receive_irq() {
+    let mut cycle = 0;
+    let backend = QueueBackend::new();
+    let mut acia = Acia::new(Box::new(backend.clone()), 1_000_000);
+    acia.write(0x3, 0x1F);
+    acia.write(0x2, 0x09);
+    backend.send(b"hi");
+
+    tick_frames(&mut acia, &mut cycle, 1);
+    assert!(acia.irq());
+
+    let status = acia.read(0x1);
+    assert_eq!(status & 0x88, 0x88);
+    assert!(!acia.irq());
+    assert_eq!(acia.read(0x0), b'h');
+    assert_eq!(acia.read(0x1) & 0x08, 0x00);
+
+    tick_frames(&mut acia, &mut cycle, 1);
+    assert_eq!(acia.read(0x0), b'i');
+}
+
+#[test]
+fn test_receiver_disabled_without_dtr() {
+    let mut cycle = 0;
+    let backend = QueueBackend::new();
+    let mut acia = Acia::new(Box::new(backend.clone()), 1_000_000);
+    acia.write(0x3, 0x1F);
+    backend.send(b"x");
+
+    tick_frames(&mut acia, &mut cycle, 4);
+
+    assert_eq!(acia.read(0x1) & 0x08, 0x00);
+    assert_eq!(backend.pending_input(), 1);
+}
+
+#[test]
+fn test_overrun_and_programmed_reset() {
+    let mut cycle = 0;
+    let backend = QueueBackend::new();
+    let mut acia = Acia::new(Box::new(backend.clone()), 1_000_000);
+    acia.write(0x3, 0x1F);
+    acia.write(0x2, 0x0B);
+    backend.send(b"ab");
+
+    tick_frames(&mut acia, &mut cycle, 2);
+    assert_eq!(acia.read(0x1) & 0x0C, 0x0C);
+
+    acia.write(0x1, 0x00);
+    assert_eq!(acia.read(0x1) & 0x04, 0x00);
+    assert_eq!(acia.read(0x2), 0x00);
+    assert_eq!(acia.read(0x0), b'a');
+}
+
+#[test]
+fn test_echo_mode() {
+    let mut cycle = 0;
+    let backend = QueueBackend::new();
+    let mut acia = Acia::new(Box::new(backend.clone()), 1_000_000);
+    acia.write(0x3, 0x1F);
+    acia.write(0x2, 0x13);
+    backend.send(b"e");
+
+    tick_frames(&mut acia, &mut cycle, 1);
+
+    assert_eq!(backend.take_output(), b"e");
+}
+
+#[test]
+fn test_transmit_irq() {
+    let mut cycle = 0;
+    let backend = QueueBackend::new();
+    let mut acia = Acia::new(Box::new(backend.clone()), 1_000_000);
+    acia.write(0x3, 0x1F);
+    acia.write(0x2, 0x07);
+
+    acia.write(0x0, b'1');
+    acia.read(0x1);
+    acia.write(0x0, b'2');
+    assert!(!acia.irq());
+
+    tick_frames(&mut acia, &mut cycle, 1);
+    assert!(acia.irq());
+}
+
+#[test]
+fn test_cpu_echo_program() {
+    let backend = QueueBackend::new();
+    let acia = Rc::new(RefCell::new(Acia::new(
+        Box::new(backend.clone()),
+        1_000_000,
+    )));
+    let mut cpu = CPU::new();
+    cpu.map_device(0x5000..=0x5003, acia.clone());
+
+    load(
+        &mut cpu,
+        0x0,
+        &[
+            0xA9, 0x1F, // LDA #$1F
+            0x8D, 0x03, 0x50, // STA $5003
+            0xA9, 0x0B, // LDA #$0B
+            0x8D, 0x02, 0x50, // STA $5002
+            0xAD, 0x01, 0x50, // LDA $5001
+            0x29, 0x08, // AND #$08
+            0xF0, 0xF9, // BEQ -7
+            0xAD, 0x00, 0x50, // LDA $5000
+            0x8D, 0x00, 0x50, // STA $5000
+            0x4C, 0x0A, 0x00, // JMP $000A
+        ],
+    );
+    backend.send(b"ok");
+
+    cpu.run_until(20_000);
+
+    assert_eq!(backend.take_output(), b"ok");
+}
+
+#[test]
+fn test_tcp_backend() {
+    let mut cycle = 0;
+    let backend = TcpBackend::bind("127.0.0.1:0").unwrap();
+    let addr = backend.local_addr().unwrap();
+    let mut acia = Acia::new(Box::new(backend), 1_000_000);
+    acia.write(0x3, 0x1F);
+    acia.write(0x2, 0x0B);
+
+    let mut client = TcpStream::connect(addr).unwrap();
+    client
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    client.write_all(b"z").unwrap();
+
+    let mut received = None;
+    for _ in 0..10_000 {
+        tick_frames(&mut acia, &mut cycle, 1);
+        if acia.read(0x1) & 0x08 != 0 {
+            received = Some(acia.read(0x0));
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(received, Some(b'z'));
+
+    acia.write(0x0, b'!');
+    tick_frames(&mut acia, &mut cycle, 1);
+
+    let mut buf = [0x0];
+    client.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"!");
+}
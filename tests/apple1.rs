@@ -0,0 +1,101 @@
+use rem6502::{machines::Apple1, serial::QueueBackend};
+
+// Stand-in for Wozmon that drives the PIA the same way: it prints the
+// `\` prompt, then echoes every key.
+fn monitor_rom() -> Vec<u8> {
+    let program = [
+        // RESET: $FF00
+        0xD8, // CLD
+        0x58, // CLI
+        0xA0, 0x7F, // LDY #$7F
+        0x8C, 0x12, 0xD0, // STY DSP
+        0xA9, 0xA7, // LDA #$A7
+        0x8D, 0x11, 0xD0, // STA KBDCR
+        0x8D, 0x13, 0xD0, // STA DSPCR
+        0xA9, 0xDC, // LDA #'\'
+        0x20, 0x27, 0xFF, // JSR ECHO
+        0xA9, 0x8D, // LDA #CR
+        0x20, 0x27, 0xFF, // JSR ECHO
+        // NEXTCHAR: $FF19
+        0xAD, 0x11, 0xD0, // LDA KBDCR
+        0x10, 0xFB, // BPL NEXTCHAR
+        0xAD, 0x10, 0xD0, // LDA KBD
+        0x20, 0x27, 0xFF, // JSR ECHO
+        0x4C, 0x19, 0xFF, // JMP NEXTCHAR
+        // ECHO: $FF27
+        0x2C, 0x12, 0xD0, // BIT DSP
+        0x30, 0xFB, // BMI ECHO
+        0x8D, 0x12, 0xD0, // STA DSP
+        0x60, // RTS
+    ];
+    let mut rom = vec![0x0; Apple1::WOZMON_LENGTH];
+    rom[..program.len()].copy_from_slice(&program);
+    // NMI $0F00, RESET $FF00, IRQ $0000, as in Wozmon.
+    rom[0xFA..].copy_from_slice(&[0x00, 0x0F, 0x00, 0xFF, 0x00, 0x00]);
+    rom
+}
+
+fn boot() -> (Apple1, QueueBackend) {
+    let terminal = QueueBackend::new();
+    let apple1 = Apple1::new(&monitor_rom(), Box::new(terminal.clone()));
+    (apple1, terminal)
+}
+
+fn output(terminal: &QueueBackend) -> String {
+    String::from_utf8(terminal.take_output()).unwrap()
+}
+
+#[test]
+fn test_boots_to_prompt() {
+    let (mut apple1, terminal) = boot();
+    apple1.run_until(1_000);
+    assert_eq!(output(&terminal), "\\\n");
+    assert_eq!(apple1.get_cpu().get_clock_rate(), 1_022_727);
+    assert_eq!(apple1.get_pia().borrow().get_ddrb(), 0x7F);
+}
+
+#[test]
+fn test_keyboard_echo() {
+    let (mut apple1, terminal) = boot();
+    terminal.send(b"hello, 6502\n");
+    apple1.run_until(10_000);
+    assert_eq!(output(&terminal), "\\\nHELLO, 6502\n");
+    assert_eq!(terminal.pending_input(), 0);
+}
+
+#[test]
+fn test_key_translation() {
+    let (mut apple1, terminal) = boot();
+    apple1.run_until(1_000);
+    terminal.take_output();
+
+    // CR LF is one Return and backspace is rubout. Escape reaches the
+    // program but the display ignores it, and keys the Apple-1 keyboard
+    // doesn't have are dropped.
+    terminal.send(b"a\x08\r\nb\x01\x1b`~c");
+    apple1.run_until(10_000);
+    assert_eq!(output(&terminal), "A_\nBC");
+}
+
+#[test]
+fn test_display_wraps_at_40_columns() {
+    let (mut apple1, terminal) = boot();
+    terminal.send(&[b'X'; 45]);
+    apple1.run_until(20_000);
+    assert_eq!(
+        output(&terminal),
+        format!("\\\n{}\n{}", "X".repeat(40), "X".repeat(5))
+    );
+}
+
+#[test]
+fn test_rom_and_ram() {
+    let (mut apple1, _) = boot();
+    apple1.load(0x0280, &[0x12, 0x34]);
+    let cpu = apple1.get_cpu();
+    assert_eq!(cpu.read_byte(0x0281), 0x34);
+
+    cpu.write_byte(0xFF00, 0x00);
+    assert_eq!(cpu.read_byte(0xFF00), 0xD8);
+    assert_eq!(cpu.read_byte(0xFFFD), 0xFF);
+}
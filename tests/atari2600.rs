@@ -0,0 +1,177 @@
+use rem6502::machines::{Atari2600, BankSwitching, TiaWrite};
+
+const BANK_LENGTH: usize = 0x1000;
+
+const VSYNC: u16 = 0x00;
+const VBLANK: u16 = 0x01;
+const WSYNC: u8 = 0x02;
+const COLUBK: u8 = 0x09;
+const INPT4: u16 = 0x0C;
+const INPT5: u16 = 0x0D;
+const SWCHA: u16 = 0x0280;
+const SWCHB: u16 = 0x0282;
+
+// A ROM of the given number of 4K banks, each starting with its number
+// and then the program, and with the reset vector pointing at the program
+// in every bank.
+fn rom(banks: usize, program: &[u8]) -> Vec<u8> {
+    let mut rom = vec![0xEA; banks * BANK_LENGTH];
+    for (bank, chunk) in rom.chunks_mut(BANK_LENGTH).enumerate() {
+        chunk[0] = bank as u8;
+        chunk[1..1 + program.len()].copy_from_slice(program);
+        chunk[0xFFC] = 0x01;
+        chunk[0xFFD] = 0xF0;
+    }
+    rom
+}
+
+#[test]
+fn test_address_aliasing() {
+    let program = [
+        0xA9, 0x42, // LDA #$42
+        0x85, 0x80, // STA $80
+        0xAE, 0x80, 0x21, // LDX $2180
+    ];
+    let mut atari = Atari2600::new(&rom(1, &program)).unwrap();
+    atari.run_until(7);
+    assert_eq!(atari.get_cpu().get_registers().get_pc(), 0xF001);
+
+    atari.run_until(7 + 2 + 3 + 4);
+    assert_eq!(atari.get_riot().borrow().get_ram()[0x0], 0x42);
+    assert_eq!(atari.get_cpu().get_registers().get_x(), 0x42);
+    assert_eq!(atari.get_cpu().read_byte(0x3FFC), 0x01);
+
+    // A 2K ROM repeats through the cartridge space.
+    let mut atari = Atari2600::new(&vec![0x5A; 0x800]).unwrap();
+    atari.get_cpu().read_byte(0x1000);
+    assert_eq!(atari.get_cpu().read_byte(0x1800), 0x5A);
+}
+
+#[test]
+fn test_bank_switching() {
+    let program = [
+        0xAD, 0xF8, 0x1F, // LDA $1FF8
+        0xAE, 0x00, 0x10, // LDX $1000
+        0x8D, 0xF9, 0x1F, // STA $1FF9
+        0xAC, 0x00, 0x10, // LDY $1000
+    ];
+    let mut atari = Atari2600::new(&rom(2, &program)).unwrap();
+    assert_eq!(atari.get_bank(), 1);
+    assert_eq!(atari.get_cpu().read_byte(0x1000), 0x1);
+    // Looking at a hotspot from outside is not an access.
+    assert_eq!(atari.get_cpu().read_byte(0x1FF8), 0xEA);
+    assert_eq!(atari.get_bank(), 1);
+
+    // The hotspot read still comes from the old bank.
+    atari.run_until(7 + 4 + 4);
+    assert_eq!(atari.get_cpu().get_registers().get_a(), 0xEA);
+    assert_eq!(atari.get_cpu().get_registers().get_x(), 0x0);
+    assert_eq!(atari.get_bank(), 0);
+    atari.run_until(7 + 4 + 4 + 4 + 4);
+    assert_eq!(atari.get_cpu().get_registers().get_y(), 0x1);
+    assert_eq!(atari.get_bank(), 1);
+
+    let program = [
+        0xAD, 0xF7, 0x1F, // LDA $1FF7
+        0xAE, 0x00, 0x10, // LDX $1000
+        0xAD, 0xF6, 0x1F, // LDA $1FF6
+        0xAC, 0x00, 0x10, // LDY $1000
+    ];
+    let mut atari = Atari2600::new(&rom(4, &program)).unwrap();
+    assert_eq!(atari.get_bank(), 3);
+    atari.run_until(7 + 4 + 4 + 4 + 4);
+    assert_eq!(atari.get_cpu().get_registers().get_x(), 0x1);
+    assert_eq!(atari.get_cpu().get_registers().get_y(), 0x0);
+    assert_eq!(atari.get_bank(), 0);
+
+    // Without bank switching the hotspots are plain ROM.
+    let mut atari = Atari2600::new(&rom(1, &program)).unwrap();
+    atari.run_until(7 + 4 + 4 + 4 + 4);
+    assert_eq!(atari.get_cpu().get_registers().get_x(), 0x0);
+    assert_eq!(atari.get_bank(), 0);
+
+    assert!(Atari2600::new(&[0x0; 3000]).is_err());
+    assert!(Atari2600::with_bank_switching(&rom(2, &[]), BankSwitching::F6).is_err());
+    assert!(Atari2600::with_bank_switching(&rom(4, &[]), BankSwitching::F6).is_ok());
+}
+
+#[test]
+fn test_wsync() {
+    let program = [
+        0x85, WSYNC, // STA WSYNC
+        0x85, COLUBK, // STA COLUBK
+        0x4C, 0x05, 0xF0, // JMP *
+    ];
+    let mut atari = Atari2600::new(&rom(1, &program)).unwrap();
+    atari.get_tia().borrow_mut().set_logging(true);
+
+    // The write lands on the tenth cycle, after reset.
+    atari.run_until(11);
+    assert!(atari.get_cpu().is_halted());
+    atari.run_until(76);
+    assert_eq!(atari.get_cpu().get_registers().get_pc(), 0xF003);
+
+    atari.run_until(79);
+    assert!(!atari.get_cpu().is_halted());
+    assert_eq!(
+        atari.get_tia().borrow_mut().take_writes(),
+        vec![
+            TiaWrite {
+                frame: 0,
+                scanline: 0,
+                clock: 27,
+                register: WSYNC,
+                val: 0x0,
+            },
+            TiaWrite {
+                frame: 0,
+                scanline: 1,
+                clock: 6,
+                register: COLUBK,
+                val: 0x0,
+            },
+        ]
+    );
+    assert!(atari.get_tia().borrow_mut().take_writes().is_empty());
+    assert_eq!(atari.get_tia().borrow().get_register(COLUBK), 0x0);
+}
+
+#[test]
+fn test_frames() {
+    let mut atari = Atari2600::new(&rom(1, &[])).unwrap();
+    atari.run_until(76 * 3);
+    assert_eq!(atari.get_tia().borrow().get_scanline(), 3);
+
+    atari.get_cpu().write_byte(VSYNC, 0x02);
+    assert_eq!(atari.get_tia().borrow().get_frame(), 0);
+    atari.get_cpu().write_byte(VSYNC, 0x00);
+    assert_eq!(atari.get_tia().borrow().get_frame(), 1);
+    assert_eq!(atari.get_tia().borrow().get_scanline(), 0);
+}
+
+#[test]
+fn test_inputs() {
+    let mut atari = Atari2600::new(&rom(1, &[])).unwrap();
+    assert_eq!(atari.get_cpu().read_byte(SWCHA), 0xFF);
+    assert_eq!(atari.get_cpu().read_byte(SWCHB), 0x0B);
+
+    atari.set_joystick(0, Atari2600::JOY_UP | Atari2600::JOY_RIGHT);
+    atari.set_joystick(1, Atari2600::JOY_LEFT);
+    assert_eq!(atari.get_cpu().read_byte(SWCHA), 0x6B);
+    atari.set_switches(Atari2600::SWITCH_SELECT | Atari2600::SWITCH_COLOR);
+    assert_eq!(atari.get_cpu().read_byte(SWCHB), 0x0A);
+
+    atari.set_fire(1, true);
+    assert_eq!(atari.get_cpu().read_byte(INPT5), 0x00);
+    assert_eq!(atari.get_cpu().read_byte(INPT4), 0x80);
+    atari.set_fire(1, false);
+    assert_eq!(atari.get_cpu().read_byte(INPT5 + 0x30), 0x80);
+
+    // With the latch on, a press is held until the latch is turned off.
+    atari.get_cpu().write_byte(VBLANK, 0x40);
+    atari.set_fire(0, true);
+    atari.set_fire(0, false);
+    assert_eq!(atari.get_cpu().read_byte(INPT4), 0x00);
+    atari.get_cpu().write_byte(VBLANK, 0x00);
+    assert_eq!(atari.get_cpu().read_byte(INPT4), 0x80);
+}
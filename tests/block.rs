@@ -0,0 +1,196 @@
+mod common;
+
+use std::{cell::RefCell, io::Cursor, rc::Rc};
+
+use rem6502::{
+    cpu::CPU,
+    devices::{BlockDevice, Device},
+};
+
+use common::{load, set_vector, IRQ_VECTOR};
+
+const STATUS: u16 = 0xC000;
+const CONTROL: u16 = 0xC001;
+const SECTOR_0: u16 = 0xC002;
+const SECTOR_1: u16 = 0xC003;
+const ADDR_L: u16 = 0xC005;
+const ADDR_H: u16 = 0xC006;
+const DATA: u16 = 0xC007;
+
+type Disk = BlockDevice<Cursor<Vec<u8>>>;
+
+// Each sector is filled with its own number.
+fn disk(sectors: usize) -> Rc<RefCell<Disk>> {
+    let image = (0..sectors * 512).map(|i| (i / 512) as u8).collect();
+    Rc::new(RefCell::new(BlockDevice::new(Cursor::new(image)).unwrap()))
+}
+
+fn setup(sectors: usize) -> (CPU, Rc<RefCell<Disk>>) {
+    let disk = disk(sectors);
+    let mut cpu = CPU::new();
+    cpu.map_device(0xC000..=0xC0FF, disk.clone());
+    (cpu, disk)
+}
+
+#[test]
+fn test_registers() {
+    let disk = disk(4);
+    let mut disk = disk.borrow_mut();
+    disk.write(SECTOR_0, 0x34);
+    disk.write(SECTOR_1 + 0x8, 0x12);
+    disk.write(ADDR_L, 0x00);
+    disk.write(ADDR_H, 0x80);
+    disk.write(CONTROL, 0x01);
+
+    assert_eq!(disk.read(SECTOR_0), 0x34);
+    assert_eq!(disk.read(SECTOR_1), 0x12);
+    assert_eq!(disk.read(ADDR_H), 0x80);
+    assert_eq!(disk.read(CONTROL), 0x01);
+    assert_eq!(disk.read(STATUS), 0x00);
+    assert_eq!(disk.read(DATA), 0xFF);
+    assert_eq!(disk.get_sectors(), 4);
+}
+
+#[test]
+fn test_read_steals_dma_cycles() {
+    let (mut cpu, disk) = setup(4);
+    // JMP $1000
+    load(&mut cpu, 0x1000, &[0x4C, 0x00, 0x10]);
+    *cpu.get_registers().get_mut_pc() = 0x1000;
+    cpu.run();
+
+    cpu.write_byte(SECTOR_0, 0x03);
+    cpu.write_byte(ADDR_H, 0x20);
+    cpu.write_byte(STATUS, 0x01);
+    assert!(disk.borrow().is_busy());
+    assert_eq!(cpu.read_byte(STATUS), 0x80);
+
+    let start = cpu.get_cycles();
+    cpu.tick();
+    assert!(cpu.is_dma_active());
+    while cpu.is_dma_active() {
+        cpu.tick();
+    }
+    // Halt, maybe an alignment cycle, then a get and a put per byte.
+    let stolen = cpu.get_cycles() - start;
+    assert!((1025..=1026).contains(&stolen), "{}", stolen);
+
+    assert!(!disk.borrow().is_busy());
+    assert!((0x2000..0x2200).all(|addr| cpu.read_byte(addr) == 0x03));
+    assert_eq!(cpu.read_byte(0x2200), 0x00);
+    // Looking at the status leaves it, reading it clears done.
+    assert_eq!(cpu.read_byte(STATUS), 0x40);
+    assert_eq!(disk.borrow_mut().read(STATUS), 0x40);
+    assert_eq!(cpu.read_byte(STATUS), 0x00);
+}
+
+#[test]
+fn test_read_program() {
+    let (mut cpu, _) = setup(4);
+    load(
+        &mut cpu,
+        0x1000,
+        &[
+            0xA9, 0x02, // LDA #$02
+            0x8D, 0x02, 0xC0, // STA SECTOR_0
+            0xA9, 0x00, // LDA #$00
+            0x8D, 0x03, 0xC0, // STA SECTOR_1
+            0x8D, 0x04, 0xC0, // STA SECTOR_2
+            0x8D, 0x05, 0xC0, // STA ADDR_L
+            0xA9, 0x04, // LDA #$04
+            0x8D, 0x06, 0xC0, // STA ADDR_H
+            0xA9, 0x01, // LDA #READ
+            0x8D, 0x00, 0xC0, // STA COMMAND
+            // wait: $101A
+            0xAD, 0x00, 0xC0, // LDA STATUS
+            0x85, 0x10, // STA $10
+            0x29, 0x80, // AND #BUSY
+            0xD0, 0xF7, // BNE wait
+        ],
+    );
+    *cpu.get_registers().get_mut_pc() = 0x1000;
+
+    cpu.run_loop();
+
+    assert_eq!(cpu.read_byte(0x10), 0x40);
+    assert!((0x0400..0x0600).all(|addr| cpu.read_byte(addr) == 0x02));
+    assert!(cpu.get_cycles() > 1024);
+}
+
+#[test]
+fn test_write_with_irq() {
+    let (mut cpu, disk) = setup(4);
+    set_vector(&mut cpu, IRQ_VECTOR, 0x4000);
+    for i in 0..512 {
+        cpu.write_byte(0x0300 + i, i as u8);
+    }
+
+    load(
+        &mut cpu,
+        0x1000,
+        &[
+            0xA9, 0x01, // LDA #IRQ
+            0x8D, 0x01, 0xC0, // STA CONTROL
+            0xA9, 0x00, // LDA #$00
+            0x8D, 0x02, 0xC0, // STA SECTOR_0
+            0x8D, 0x05, 0xC0, // STA ADDR_L
+            0xA9, 0x03, // LDA #$03
+            0x8D, 0x06, 0xC0, // STA ADDR_H
+            0xA9, 0x02, // LDA #WRITE
+            0x8D, 0x00, 0xC0, // STA COMMAND
+            0x58, // CLI
+            // idle: $1018
+            0x4C, 0x18, 0x10, // JMP idle
+        ],
+    );
+    load(
+        &mut cpu,
+        0x4000,
+        &[
+            0xAD, 0x00, 0xC0, // LDA STATUS
+            0x85, 0x10, // STA $10
+        ],
+    );
+    *cpu.get_registers().get_mut_pc() = 0x1000;
+
+    cpu.run_loop();
+
+    assert_eq!(cpu.read_byte(0x10), 0x40);
+    assert!(!disk.borrow().irq());
+    let disk = disk.borrow();
+    let image = disk.get_storage().get_ref();
+    assert!((0..512).all(|i| image[i] == i as u8));
+    assert_eq!(image[512], 0x01);
+}
+
+#[test]
+fn test_errors() {
+    let disk = disk(2);
+    let mut disk = disk.borrow_mut();
+    disk.write(CONTROL, 0x01);
+
+    disk.write(SECTOR_0, 0x02);
+    disk.write(STATUS, 0x01);
+    assert!(disk.take_dma().is_none());
+    assert!(disk.irq());
+    assert_eq!(disk.read(STATUS), 0x41);
+    assert!(!disk.irq());
+
+    disk.write(STATUS, 0x02);
+    assert!(disk.take_dma().is_none());
+    assert_eq!(disk.read(STATUS), 0x41);
+
+    disk.write(SECTOR_0, 0x01);
+    disk.write(STATUS, 0x7F);
+    assert_eq!(disk.read(STATUS), 0x41);
+
+    // Commands are ignored while a transfer is in progress.
+    disk.write(STATUS, 0x01);
+    assert!(disk.take_dma().is_some());
+    disk.write(STATUS, 0x7F);
+    assert_eq!(disk.read(STATUS), 0x80);
+    for _ in 0..512 {
+        assert_eq!(disk.read(DATA), 0x01);
+    }
+    assert_eq!(disk.read(STATUS), 0x40);
+}
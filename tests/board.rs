@@ -0,0 +1,254 @@
+use std::{env, fs, path::PathBuf, process};
+
+use rem6502::{
+    board::{BoardBuilder, Component, DeviceConfig, DeviceKind, Serial, Wiring},
+    cpu::Variant,
+};
+
+// A scratch directory for image files, unique to the test.
+fn scratch(test: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("rem6502-board-{}-{test}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// A 256 byte ROM for $FF00 with the program at its start and the reset
+// vector pointing at it.
+fn rom(program: &[u8]) -> Vec<u8> {
+    let mut rom = vec![0xEA; 0x100];
+    rom[..program.len()].copy_from_slice(program);
+    rom[0xFC] = 0x00;
+    rom[0xFD] = 0xFF;
+    rom
+}
+
+#[test]
+fn test_board_from_toml() {
+    let dir = scratch("toml");
+    let program = [
+        0xA9, 0xFF, // LDA #$FF
+        0x8D, 0x03, 0x70, // STA $7003
+        0xA9, 0x55, // LDA #$55
+        0x8D, 0x01, 0x60, // STA $6001
+        0x8D, 0x00, 0x42, // STA $4200
+        0x4C, 0x0D, 0xFF, // JMP *
+    ];
+    fs::write(dir.join("monitor.bin"), rom(&program)).unwrap();
+    let description = r#"
+        # Test board, rev #2
+        name = "rev #2"
+
+        [cpu]
+        variant = "cmos"
+        clock = 2_000_000
+
+        [[ram]]
+        start = 0x0000
+        end = 0x7FFF
+        length = 0x4000     # A14 is not decoded
+
+        [[rom]]
+        name = 'monitor'
+        start = 0xFF00
+        image = "monitor.bin"
+
+        [[device]]
+        name = "via"
+        type = "via"
+        start = 0x6000
+        end = 0x7FFF
+
+        [[device]]
+        name = "acia"
+        type = "acia"
+        start = 0x5000
+        irq = "nmi"
+    "#;
+    let builder = BoardBuilder::from_toml(description, &dir).unwrap();
+    assert_eq!(builder.get_devices().len(), 4);
+    assert_eq!(builder.get_devices()[0].name, "ram0");
+    assert_eq!(builder.get_devices()[3].wiring, Wiring::Nmi);
+    assert_eq!(
+        builder.get_devices()[3].kind,
+        DeviceKind::Acia(Serial::Queue)
+    );
+
+    let mut board = builder.build().unwrap();
+    assert_eq!(board.get_name(), "rev #2");
+    assert_eq!(board.get_cpu().get_variant(), Variant::Cmos);
+    assert_eq!(board.get_cpu().get_clock_rate(), 2_000_000);
+    assert!(board.get_serial("acia").is_some());
+    assert!(board.get_serial("via").is_none());
+
+    board.run_until(7 + 2 + 4 + 2 + 4 + 4);
+    let Some(Component::Via(via)) = board.get_component("via") else {
+        panic!("no VIA");
+    };
+    assert_eq!(via.borrow().get_port_a(), 0x55);
+    let Some(Component::Ram(ram)) = board.get_component("ram0") else {
+        panic!("no RAM");
+    };
+    assert_eq!(ram.borrow().get_data()[0x0200], 0x55);
+    assert_eq!(board.get_cpu().read_byte(0x0200), 0x55);
+    assert!(matches!(
+        board.get_component("monitor"),
+        Some(Component::Rom(_))
+    ));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_reset() {
+    let dir = scratch("reset");
+    fs::write(dir.join("program.bin"), [0xE8, 0x4C, 0x00, 0x02]).unwrap(); // INX, JMP $0200
+    let description = "
+        [[ram]]
+        start = 0x0000
+        length = 0x10000
+
+        [[ram]]
+        name = 'program'
+        start = 0x0200
+        length = 0x100
+        image = 'program.bin'
+
+        [reset]
+        pc = 0x0200
+        s = 0xFD
+        p = 0x01
+    ";
+    let mut board = BoardBuilder::from_toml(description, &dir)
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(board.get_cpu().get_cycles(), 7);
+    assert_eq!(board.get_cpu().get_registers().get_pc(), 0x0200);
+    assert_eq!(board.get_cpu().get_registers().get_s(), 0xFD);
+    // Reset set I, and the description clears it again.
+    assert_eq!(board.get_cpu().get_registers().get_p_byte(), 0x01);
+
+    board.run_until(7 + 2 + 3 + 2);
+    assert_eq!(board.get_cpu().get_registers().get_x(), 0x2);
+
+    board.get_cpu().write_byte(0x0300, 0x12);
+    board.reset();
+    assert_eq!(board.get_cpu().get_registers().get_pc(), 0x0200);
+    assert_eq!(board.get_cpu().read_byte(0x0300), 0x12);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_interrupt_controller() {
+    let description = "
+        [[device]]
+        name = 'pic'
+        type = 'interrupt_controller'
+        start = 0xD000
+
+        [[device]]
+        name = 'via'
+        type = 'via'
+        start = 0xD010
+        irq = 'pic'
+
+        [[device]]
+        name = 'entropy'
+        type = 'rng'
+        start = 0xD020
+        seed = 0x6502
+        irq = 'none'
+    ";
+    let board = BoardBuilder::from_toml(description, &env::temp_dir())
+        .unwrap()
+        .build()
+        .unwrap();
+    let Some(Component::InterruptController(pic)) = board.get_component("pic") else {
+        panic!("no interrupt controller");
+    };
+    assert_eq!(pic.borrow().get_name(0), "via");
+    let Some(Component::Rng(rng)) = board.get_component("entropy") else {
+        panic!("no RNG");
+    };
+    assert_eq!(rng.borrow().get_seed(), 0x6502);
+}
+
+#[test]
+fn test_builder() {
+    let mut builder = BoardBuilder::new();
+    builder.set_name("6507 test");
+    builder.set_address_bits(13);
+    builder.add_device(DeviceConfig {
+        name: "ram".to_string(),
+        kind: DeviceKind::Ram {
+            length: 0x80,
+            image: vec![0x42],
+        },
+        start: 0x0080,
+        end: None,
+        wiring: Wiring::None,
+    });
+    builder.add_device(DeviceConfig {
+        name: "video".to_string(),
+        kind: DeviceKind::TextVideo {
+            columns: 16,
+            rows: 2,
+        },
+        start: 0x0400,
+        end: None,
+        wiring: Wiring::None,
+    });
+
+    let mut board = builder.build().unwrap();
+    assert_eq!(board.get_cpu().get_address_bits(), 13);
+    assert_eq!(board.get_cpu().read_byte(0x2080), 0x42);
+    assert_eq!(board.get_cpu().read_byte(0x0100), 0x00);
+    board.get_cpu().write_byte(0x0400, b'H');
+    let Some(Component::TextVideo(video)) = board.get_component("video") else {
+        panic!("no video");
+    };
+    assert_eq!(video.borrow().get_line(0).trim_end(), "H");
+}
+
+#[test]
+fn test_errors() {
+    let dir = env::temp_dir();
+    let error = |description: &str| match BoardBuilder::from_toml(description, &dir) {
+        Ok(builder) => builder.build().err().unwrap().to_string(),
+        Err(err) => err.to_string(),
+    };
+
+    assert_eq!(
+        error("[cpu]\nvariant = 'cmos'\nclok = 1"),
+        "line 3: unknown key clok"
+    );
+    assert_eq!(error("[cpu]\n[cpu]"), "line 2: table cpu is defined twice");
+    assert_eq!(error("[disk]"), "line 1: unknown table disk");
+    assert_eq!(
+        error("[cpu]\nvariant = '65816'"),
+        "line 1: [cpu] has a bad variant: 65816"
+    );
+    assert_eq!(
+        error("[cpu]\naddress_bits = 17"),
+        "line 1: [cpu] has a bad address_bits: 17"
+    );
+    assert_eq!(
+        error("[cpu]\nclock = 1.5e6"),
+        "line 2: unsupported value 1.5e6"
+    );
+    assert_eq!(
+        error("[cpu]\nclock = '1 MHz'"),
+        "line 2: clock must be an integer"
+    );
+    assert_eq!(error("[[ram]]\nend = 0x1FF"), "line 1: [ram] needs start");
+    assert_eq!(
+        error("[[device]]\nname = 'a'\ntype = 'via'\nstart = 0x10\nirq = 'b'"),
+        "a: b is not an interrupt controller"
+    );
+    assert_eq!(
+        error("[[device]]\nname = 'a'\ntype = 'via'\nstart = 0x10\n[[device]]\nname = 'a'\ntype = 'pia'\nstart = 0x20"),
+        "a is defined twice"
+    );
+    assert!(error("[[rom]]\nstart = 0xE000\nimage = 'no-such-rom.bin'").contains("no-such-rom.bin"));
+}
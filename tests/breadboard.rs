@@ -0,0 +1,272 @@
+use rem6502::{
+    cpu::Variant,
+    machines::{Breadboard, LcdWiring},
+    serial::QueueBackend,
+};
+
+// Prints a greeting on the LCD with the 8-bit wiring, polling the busy
+// flag, then echoes everything received by the ACIA at 19200 baud.
+const LCD_8BIT: &[u8] = &[
+    // reset: $8000
+    0xA2, 0xFF, // LDX #$FF
+    0x9A, // TXS
+    0xA9, 0xFF, // LDA #$FF
+    0x8D, 0x02, 0x60, // STA DDRB
+    0xA9, 0xE0, // LDA #$E0
+    0x8D, 0x03, 0x60, // STA DDRA
+    0xA9, 0x38, // LDA #$38
+    0x20, 0x83, 0x80, // JSR lcd_instruction
+    0xA9, 0x0E, // LDA #$0E
+    0x20, 0x83, 0x80, // JSR lcd_instruction
+    0xA9, 0x06, // LDA #$06
+    0x20, 0x83, 0x80, // JSR lcd_instruction
+    0xA9, 0x01, // LDA #$01
+    0x20, 0x83, 0x80, // JSR lcd_instruction
+    0xA2, 0x00, // LDX #0
+    // print: $8023
+    0xBD, 0xAB, 0x80, // LDA message,X
+    0xF0, 0x11, // BEQ serial
+    0xC9, 0x0A, // CMP #LF
+    0xD0, 0x07, // BNE char
+    0xA9, 0xC0, // LDA #$C0
+    0x20, 0x83, 0x80, // JSR lcd_instruction
+    0x80, 0x03, // BRA next
+    // char: $8033
+    0x20, 0x95, 0x80, // JSR print_char
+    // next: $8036
+    0xE8, // INX
+    0x80, 0xEA, // BRA print
+    // serial: $8039
+    0x9C, 0x01, 0x50, // STZ ACIA_STATUS
+    0xA9, 0x1F, // LDA #$1F
+    0x8D, 0x03, 0x50, // STA ACIA_CTRL
+    0xA9, 0x0B, // LDA #$0B
+    0x8D, 0x02, 0x50, // STA ACIA_CMD
+    // rx_wait: $8046
+    0xAD, 0x01, 0x50, // LDA ACIA_STATUS
+    0x29, 0x08, // AND #$08
+    0xF0, 0xF9, // BEQ rx_wait
+    0xAD, 0x00, 0x50, // LDA ACIA_DATA
+    0x20, 0x55, 0x80, // JSR send_char
+    0x80, 0xF1, // BRA rx_wait
+    // send_char: $8055
+    0x48, // PHA
+    // tx_wait: $8056
+    0xAD, 0x01, 0x50, // LDA ACIA_STATUS
+    0x29, 0x10, // AND #$10
+    0xF0, 0xF9, // BEQ tx_wait
+    0x68, // PLA
+    0x8D, 0x00, 0x50, // STA ACIA_DATA
+    0x60, // RTS
+    // lcd_wait: $8062
+    0x48, // PHA
+    0x9C, 0x02, 0x60, // STZ DDRB
+    // lcd_busy: $8066
+    0xA9, 0x40, // LDA #RW
+    0x8D, 0x01, 0x60, // STA PORTA
+    0xA9, 0xC0, // LDA #RW | E
+    0x8D, 0x01, 0x60, // STA PORTA
+    0xAD, 0x00, 0x60, // LDA PORTB
+    0x29, 0x80, // AND #$80
+    0xD0, 0xEF, // BNE lcd_busy
+    0xA9, 0x40, // LDA #RW
+    0x8D, 0x01, 0x60, // STA PORTA
+    0xA9, 0xFF, // LDA #$FF
+    0x8D, 0x02, 0x60, // STA DDRB
+    0x68, // PLA
+    0x60, // RTS
+    // lcd_instruction: $8083
+    0x20, 0x62, 0x80, // JSR lcd_wait
+    0x8D, 0x00, 0x60, // STA PORTB
+    0x9C, 0x01, 0x60, // STZ PORTA
+    0xA9, 0x80, // LDA #E
+    0x8D, 0x01, 0x60, // STA PORTA
+    0x9C, 0x01, 0x60, // STZ PORTA
+    0x60, // RTS
+    // print_char: $8095
+    0x20, 0x62, 0x80, // JSR lcd_wait
+    0x8D, 0x00, 0x60, // STA PORTB
+    0xA9, 0x20, // LDA #RS
+    0x8D, 0x01, 0x60, // STA PORTA
+    0xA9, 0xA0, // LDA #RS | E
+    0x8D, 0x01, 0x60, // STA PORTA
+    0xA9, 0x20, // LDA #RS
+    0x8D, 0x01, 0x60, // STA PORTA
+    0x60, // RTS
+    // message: $80AB
+    0x48, 0x65, 0x6C, 0x6C, 0x6F, 0x2C, 0x20, 0x77, 0x6F, 0x72, 0x6C, 0x64, 0x21, 0x0A, 0x36, 0x35,
+    0x43, 0x30, 0x32, 0x00, // "Hello, world!\n65C02", 0
+];
+
+// The same with the LCD in 4-bit mode on port B.
+const LCD_4BIT: &[u8] = &[
+    // reset: $8000
+    0xA2, 0xFF, // LDX #$FF
+    0x9A, // TXS
+    0xA9, 0xFF, // LDA #$FF
+    0x8D, 0x02, 0x60, // STA DDRB
+    0xA9, 0x00, // LDA #$00
+    0x8D, 0x03, 0x60, // STA DDRA
+    0xA9, 0x02, // LDA #$02
+    0x20, 0x67, 0x80, // JSR pulse
+    0xA9, 0x28, // LDA #$28
+    0x20, 0xA7, 0x80, // JSR lcd_instruction
+    0xA9, 0x0E, // LDA #$0E
+    0x20, 0xA7, 0x80, // JSR lcd_instruction
+    0xA9, 0x06, // LDA #$06
+    0x20, 0xA7, 0x80, // JSR lcd_instruction
+    0xA9, 0x01, // LDA #$01
+    0x20, 0xA7, 0x80, // JSR lcd_instruction
+    0xA2, 0x00, // LDX #0
+    // print: $8028
+    0xBD, 0xCD, 0x80, // LDA message,X
+    0xF0, 0x11, // BEQ serial
+    0xC9, 0x0A, // CMP #LF
+    0xD0, 0x07, // BNE char
+    0xA9, 0xC0, // LDA #$C0
+    0x20, 0xA7, 0x80, // JSR lcd_instruction
+    0x80, 0x03, // BRA next
+    // char: $8038
+    0x20, 0xB8, 0x80, // JSR print_char
+    // next: $803B
+    0xE8, // INX
+    0x80, 0xEA, // BRA print
+    // serial: $803E
+    0x9C, 0x01, 0x50, // STZ ACIA_STATUS
+    0xA9, 0x1F, // LDA #$1F
+    0x8D, 0x03, 0x50, // STA ACIA_CTRL
+    0xA9, 0x0B, // LDA #$0B
+    0x8D, 0x02, 0x50, // STA ACIA_CMD
+    // rx_wait: $804B
+    0xAD, 0x01, 0x50, // LDA ACIA_STATUS
+    0x29, 0x08, // AND #$08
+    0xF0, 0xF9, // BEQ rx_wait
+    0xAD, 0x00, 0x50, // LDA ACIA_DATA
+    0x20, 0x5A, 0x80, // JSR send_char
+    0x80, 0xF1, // BRA rx_wait
+    // send_char: $805A
+    0x48, // PHA
+    // tx_wait: $805B
+    0xAD, 0x01, 0x50, // LDA ACIA_STATUS
+    0x29, 0x10, // AND #$10
+    0xF0, 0xF9, // BEQ tx_wait
+    0x68, // PLA
+    0x8D, 0x00, 0x50, // STA ACIA_DATA
+    0x60, // RTS
+    // pulse: $8067
+    0x8D, 0x00, 0x60, // STA PORTB
+    0x09, 0x40, // ORA #E
+    0x8D, 0x00, 0x60, // STA PORTB
+    0x49, 0x40, // EOR #E
+    0x8D, 0x00, 0x60, // STA PORTB
+    0x60, // RTS
+    // lcd_wait: $8075
+    0x48, // PHA
+    0xA9, 0xF0, // LDA #$F0
+    0x8D, 0x02, 0x60, // STA DDRB
+    // lcd_busy: $807B
+    0xA9, 0x20, // LDA #RW
+    0x8D, 0x00, 0x60, // STA PORTB
+    0xA9, 0x60, // LDA #RW | E
+    0x8D, 0x00, 0x60, // STA PORTB
+    0xAD, 0x00, 0x60, // LDA PORTB
+    0x48, // PHA
+    0xA9, 0x20, // LDA #RW
+    0x8D, 0x00, 0x60, // STA PORTB
+    0xA9, 0x60, // LDA #RW | E
+    0x8D, 0x00, 0x60, // STA PORTB
+    0xAD, 0x00, 0x60, // LDA PORTB
+    0x68, // PLA
+    0x29, 0x08, // AND #$08
+    0xD0, 0xE0, // BNE lcd_busy
+    0xA9, 0x20, // LDA #RW
+    0x8D, 0x00, 0x60, // STA PORTB
+    0xA9, 0xFF, // LDA #$FF
+    0x8D, 0x02, 0x60, // STA DDRB
+    0x68, // PLA
+    0x60, // RTS
+    // lcd_instruction: $80A7
+    0x20, 0x75, 0x80, // JSR lcd_wait
+    0x48, // PHA
+    0x4A, 0x4A, 0x4A, 0x4A, // LSR A x4
+    0x20, 0x67, 0x80, // JSR pulse
+    0x68, // PLA
+    0x29, 0x0F, // AND #$0F
+    0x4C, 0x67, 0x80, // JMP pulse
+    // print_char: $80B8
+    0x20, 0x75, 0x80, // JSR lcd_wait
+    0x48, // PHA
+    0x4A, 0x4A, 0x4A, 0x4A, // LSR A x4
+    0x09, 0x10, // ORA #RS
+    0x20, 0x67, 0x80, // JSR pulse
+    0x68, // PLA
+    0x29, 0x0F, // AND #$0F
+    0x09, 0x10, // ORA #RS
+    0x4C, 0x67, 0x80, // JMP pulse
+    // message: $80CD
+    0x34, 0x2D, 0x62, 0x69, 0x74, 0x0A, 0x4C, 0x43, 0x44, 0x00, // "4-bit\nLCD", 0
+];
+
+fn rom_image(program: &[u8]) -> Vec<u8> {
+    let mut rom = vec![0xEA; Breadboard::ROM_LENGTH];
+    rom[..program.len()].copy_from_slice(program);
+    // RESET $8000
+    rom[0x7FFC..0x7FFE].copy_from_slice(&[0x00, 0x80]);
+    rom
+}
+
+fn boot(program: &[u8], wiring: LcdWiring) -> (Breadboard, QueueBackend) {
+    let terminal = QueueBackend::new();
+    let board = Breadboard::new(&rom_image(program), Box::new(terminal.clone()), wiring);
+    (board, terminal)
+}
+
+#[test]
+fn test_boots_from_rom() {
+    let (mut board, _) = boot(LCD_8BIT, LcdWiring::EightBit);
+    let cpu = board.get_cpu();
+    assert_eq!(cpu.get_variant(), Variant::Cmos);
+    assert_eq!(cpu.read_byte(0xFFFC), 0x00);
+
+    cpu.run();
+    assert_eq!(cpu.get_registers().get_pc(), 0x8000);
+
+    // ROM ignores writes, RAM below $4000 takes them.
+    cpu.write_byte(0x8000, 0x00);
+    cpu.write_byte(0x3FFF, 0x42);
+    assert_eq!(cpu.read_byte(0x8000), 0xA2);
+    assert_eq!(cpu.read_byte(0x3FFF), 0x42);
+}
+
+#[test]
+fn test_lcd_8bit() {
+    let (mut board, _) = boot(LCD_8BIT, LcdWiring::EightBit);
+    board.run_until(100_000);
+
+    assert_eq!(board.get_lcd_text(), "Hello, world!   \n65C02           ");
+    assert_eq!(board.get_lcd().get_cursor(), Some((5, 1)));
+    assert!(!board.get_lcd().is_four_bit());
+
+    // The VIA repeats through $6000-$7FFF.
+    assert_eq!(board.get_cpu().read_byte(0x7FF3), 0xE0);
+}
+
+#[test]
+fn test_lcd_4bit() {
+    let (mut board, _) = boot(LCD_4BIT, LcdWiring::FourBit);
+    board.run_until(100_000);
+
+    assert_eq!(board.get_lcd_text(), "4-bit           \nLCD             ");
+    assert!(board.get_lcd().is_four_bit());
+}
+
+#[test]
+fn test_serial_echo() {
+    let (mut board, terminal) = boot(LCD_8BIT, LcdWiring::EightBit);
+    board.run_until(100_000);
+    terminal.send(b"grade me\r\n");
+    board.run_until(200_000);
+
+    assert_eq!(terminal.take_output(), b"grade me\r\n");
+    assert_eq!(board.get_acia().borrow().get_baud_rate(), 19_200.0);
+}
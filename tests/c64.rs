@@ -0,0 +1,390 @@
+use rem6502::machines::C64;
+
+// Stand-in for the KERNAL: it sets up the processor port, the screen at
+// $0400 and a 1/60 s CIA 1 timer interrupt that scans the keyboard like
+// the real one, then starts BASIC through the vector at $A000. CHROUT and
+// GETIN are at their usual addresses in the jump table.
+const KERNAL: &[u8] = &[
+    // reset: $E000
+    0x78, // SEI
+    0xD8, // CLD
+    0xA2, 0xFF, // LDX #$FF
+    0x9A, // TXS
+    0xA9, 0x37, // LDA #$37
+    0x85, 0x01, // STA R6510
+    0xA9, 0x2F, // LDA #$2F
+    0x85, 0x00, // STA D6510
+    0xA9, 0x03, // LDA #$03
+    0x8D, 0x02, 0xDD, // STA CI2DDRA
+    0x8D, 0x00, 0xDD, // STA CI2PRA
+    0xA9, 0x14, // LDA #$14
+    0x8D, 0x18, 0xD0, // STA VMCSB
+    0xA9, 0xFF, // LDA #$FF
+    0x8D, 0x02, 0xDC, // STA CIDDRA
+    0x85, 0xC5, // STA LSTX
+    0xA9, 0x00, // LDA #$00
+    0x8D, 0x03, 0xDC, // STA CIDDRB
+    0x85, 0xC6, // STA NDX
+    0x85, 0xD3, // STA PNTR
+    0x85, 0xD1, // STA PNT
+    0xA9, 0x04, // LDA #$04
+    0x85, 0xD2, // STA PNT+1
+    0xA9, 0x20, // LDA #' '
+    0xA2, 0x00, // LDX #0
+    // clear: $E034
+    0x9D, 0x00, 0x04, // STA $0400,X
+    0x9D, 0x00, 0x05, // STA $0500,X
+    0x9D, 0x00, 0x06, // STA $0600,X
+    0x9D, 0x00, 0x07, // STA $0700,X
+    0xE8, // INX
+    0xD0, 0xF1, // BNE clear
+    0xA9, 0x25, // LDA #<16421
+    0x8D, 0x04, 0xDC, // STA CIA1TAL
+    0xA9, 0x40, // LDA #>16421
+    0x8D, 0x05, 0xDC, // STA CIA1TAH
+    0xA9, 0x81, // LDA #$81
+    0x8D, 0x0D, 0xDC, // STA CIAICR
+    0xA9, 0x11, // LDA #$11
+    0x8D, 0x0E, 0xDC, // STA CIACRA
+    0x58, // CLI
+    0x6C, 0x00, 0xA0, // JMP ($A000)
+    // irq: $E05B
+    0x48, // PHA
+    0x8A, // TXA
+    0x48, // PHA
+    0x98, // TYA
+    0x48, // PHA
+    0xAD, 0x0D, 0xDC, // LDA CIAICR
+    0x20, 0x6C, 0xE0, // JSR scnkey
+    0x68, // PLA
+    0xA8, // TAY
+    0x68, // PLA
+    0xAA, // TAX
+    0x68, // PLA
+    // nmi: $E06B
+    0x40, // RTI
+    // scnkey: $E06C
+    0xA2, 0x00, // LDX #0
+    // column: $E06E
+    0xBD, 0xED, 0xE0, // LDA colmask,X
+    0x8D, 0x00, 0xDC, // STA CIAPRA
+    0xAD, 0x01, 0xDC, // LDA CIAPRB
+    0x49, 0xFF, // EOR #$FF
+    0x3D, 0xF5, 0xE0, // AND rowmask,X
+    0xD0, 0x0A, // BNE found
+    0xE8, // INX
+    0xE0, 0x08, // CPX #8
+    0xD0, 0xEB, // BNE column
+    0xA9, 0xFF, // LDA #$FF
+    0x85, 0xC5, // STA LSTX
+    0x60, // RTS
+    // found: $E088
+    0xA0, 0x00, // LDY #0
+    // row: $E08A
+    0x4A, // LSR A
+    0xB0, 0x03, // BCS got
+    0xC8, // INY
+    0xD0, 0xFA, // BNE row
+    // got: $E090
+    0x8A, // TXA
+    0x0A, // ASL A
+    0x0A, // ASL A
+    0x0A, // ASL A
+    0x85, 0xFD, // STA TMP
+    0x98, // TYA
+    0x05, 0xFD, // ORA TMP
+    0xC5, 0xC5, // CMP LSTX
+    0xF0, 0x0F, // BEQ done
+    0x85, 0xC5, // STA LSTX
+    0xAA, // TAX
+    0xBD, 0xFD, 0xE0, // LDA keytab,X
+    0xF0, 0x07, // BEQ done
+    0x8D, 0x77, 0x02, // STA KEYD
+    0xA9, 0x01, // LDA #1
+    0x85, 0xC6, // STA NDX
+    // done: $E0AC
+    0x60, // RTS
+    // getin: $E0AD
+    0x78, // SEI
+    0xA5, 0xC6, // LDA NDX
+    0xF0, 0x07, // BEQ empty
+    0xA9, 0x00, // LDA #0
+    0x85, 0xC6, // STA NDX
+    0xAD, 0x77, 0x02, // LDA KEYD
+    // empty: $E0B9
+    0x58, // CLI
+    0xC9, 0x00, // CMP #0
+    0x60, // RTS
+    // chrout: $E0BD
+    0x85, 0xFB, // STA SAVE
+    0x98, // TYA
+    0x48, // PHA
+    0xA5, 0xFB, // LDA SAVE
+    0xC9, 0x0D, // CMP #CR
+    0xF0, 0x12, // BEQ newline
+    0xC9, 0x40, // CMP #$40
+    0x90, 0x02, // BCC store
+    0x29, 0x3F, // AND #$3F
+    // store: $E0CD
+    0xA4, 0xD3, // LDY PNTR
+    0x91, 0xD1, // STA (PNT),Y
+    0xE6, 0xD3, // INC PNTR
+    0xA5, 0xD3, // LDA PNTR
+    0xC9, 0x28, // CMP #40
+    0xD0, 0x0F, // BNE out
+    // newline: $E0D9
+    0xA9, 0x00, // LDA #0
+    0x85, 0xD3, // STA PNTR
+    0x18, // CLC
+    0xA5, 0xD1, // LDA PNT
+    0x69, 0x28, // ADC #40
+    0x85, 0xD1, // STA PNT
+    0x90, 0x02, // BCC out
+    0xE6, 0xD2, // INC PNT+1
+    // out: $E0E8
+    0x68, // PLA
+    0xA8, // TAY
+    0xA5, 0xFB, // LDA SAVE
+    0x60, // RTS
+    // colmask: $E0ED
+    0xFE, 0xFD, 0xFB, 0xF7, 0xEF, 0xDF, 0xBF, 0x7F, // column selects
+    // rowmask: $E0F5
+    0xFF, 0x7F, 0xFF, 0xFF, 0xFF, 0xFF, 0xEF, 0xFF, // all but the shift keys
+    // keytab: $E0FD
+    0x00, 0x0D, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // column 0
+    0x33, 0x57, 0x41, 0x34, 0x5A, 0x53, 0x45, 0x00, // column 1
+    0x35, 0x52, 0x44, 0x36, 0x43, 0x46, 0x54, 0x58, // column 2
+    0x37, 0x59, 0x47, 0x38, 0x42, 0x48, 0x55, 0x56, // column 3
+    0x39, 0x49, 0x4A, 0x30, 0x4D, 0x4B, 0x4F, 0x4E, // column 4
+    0x2B, 0x50, 0x4C, 0x2D, 0x2E, 0x3A, 0x40, 0x2C, // column 5
+    0x00, 0x2A, 0x3B, 0x00, 0x00, 0x3D, 0x5E, 0x2F, // column 6
+    0x31, 0x5F, 0x00, 0x32, 0x20, 0x00, 0x51, 0x00, // column 7
+];
+
+// Stand-in for BASIC: prints READY. and echoes what is typed.
+const BASIC: &[u8] = &[
+    // cold_vector: $A000
+    0x04, 0xA0, 0x04, 0xA0, // cold and warm start vectors
+    // cold: $A004
+    0xA2, 0x00, // LDX #0
+    // message: $A006
+    0xBD, 0x1C, 0xA0, // LDA ready,X
+    0xF0, 0x06, // BEQ loop
+    0x20, 0xD2, 0xFF, // JSR CHROUT
+    0xE8, // INX
+    0xD0, 0xF5, // BNE message
+    // loop: $A011
+    0x20, 0xE4, 0xFF, // JSR GETIN
+    0xF0, 0xFB, // BEQ loop
+    0x20, 0xD2, 0xFF, // JSR CHROUT
+    0x4C, 0x11, 0xA0, // JMP loop
+    // ready: $A01C
+    0x52, 0x45, 0x41, 0x44, 0x59, 0x2E, 0x0D, 0x00, // "READY.", CR
+];
+
+const CHROUT: u16 = 0xE0BD;
+const GETIN: u16 = 0xE0AD;
+const NMI: u16 = 0xE06B;
+const RESET: u16 = 0xE000;
+const IRQ: u16 = 0xE05B;
+
+fn kernal_rom() -> Vec<u8> {
+    let mut rom = vec![0x0; C64::KERNAL_LENGTH];
+    rom[..KERNAL.len()].copy_from_slice(KERNAL);
+    for (addr, target) in [(0xFFD2, CHROUT), (0xFFE4, GETIN)] {
+        let offset = addr - 0xE000;
+        rom[offset..offset + 3].copy_from_slice(&[0x4C, target as u8, (target >> 8) as u8]);
+    }
+    for (i, vector) in [NMI, RESET, IRQ].iter().enumerate() {
+        rom[0x1FFA + i * 2..0x1FFC + i * 2].copy_from_slice(&vector.to_le_bytes());
+    }
+    rom
+}
+
+fn basic_rom() -> Vec<u8> {
+    let mut rom = vec![0x0; C64::BASIC_LENGTH];
+    rom[..BASIC.len()].copy_from_slice(BASIC);
+    rom
+}
+
+fn boot() -> C64 {
+    let mut chargen = vec![0x0; C64::CHARGEN_LENGTH];
+    chargen[0] = 0x3C;
+    C64::new(&basic_rom(), &kernal_rom(), &chargen)
+}
+
+fn screen_row(c64: &C64, row: usize) -> String {
+    c64.get_screen()
+        .lines()
+        .nth(row)
+        .unwrap()
+        .trim_end()
+        .to_string()
+}
+
+#[test]
+fn test_boots_to_ready() {
+    let mut c64 = boot();
+    c64.run_until(100_000);
+
+    assert_eq!(screen_row(&c64, 0), "READY.");
+    let screen = c64.get_screen();
+    assert_eq!(screen.lines().count(), C64::SCREEN_ROWS);
+    assert!(screen
+        .lines()
+        .all(|line| line.chars().count() == C64::SCREEN_COLUMNS));
+}
+
+#[test]
+fn test_typed_text() {
+    let mut c64 = boot();
+    c64.run_until(100_000);
+
+    c64.type_text("hello\n10 PRINT 2+2\n");
+    while c64.is_typing() {
+        let cycle = c64.get_cpu().get_cycles();
+        c64.run_until(cycle + 10_000);
+    }
+    let cycle = c64.get_cpu().get_cycles();
+    c64.run_until(cycle + 50_000);
+
+    assert_eq!(screen_row(&c64, 1), "HELLO");
+    assert_eq!(screen_row(&c64, 2), "10 PRINT 2+2");
+}
+
+#[test]
+fn test_banking() {
+    let mut c64 = boot();
+    c64.run_until(100_000);
+    let cpu = c64.get_cpu();
+    assert_eq!(cpu.read_byte(0x0001), 0x37);
+    assert_eq!(cpu.read_byte(0xA000), 0x04);
+    assert_eq!(cpu.read_byte(0xE000), 0x78);
+
+    // Writes to ROM land in the RAM underneath.
+    cpu.write_byte(0xA000, 0x55);
+    cpu.write_byte(0xE000, 0x66);
+    assert_eq!(cpu.read_byte(0xA000), 0x04);
+
+    // LORAM low: BASIC out.
+    cpu.write_byte(0x0001, 0x36);
+    assert_eq!(cpu.read_byte(0xA000), 0x55);
+    assert_eq!(cpu.read_byte(0xE000), 0x78);
+
+    // CHAREN low: the character ROM in place of I/O.
+    cpu.write_byte(0x0001, 0x33);
+    assert_eq!(cpu.read_byte(0xD000), 0x3C);
+
+    // All RAM.
+    cpu.write_byte(0x0001, 0x30);
+    cpu.write_byte(0xD000, 0x77);
+    assert_eq!(cpu.read_byte(0xD000), 0x77);
+    assert_eq!(cpu.read_byte(0xE000), 0x66);
+
+    // Inputs are pulled up, so a port left floating banks the ROMs in.
+    cpu.write_byte(0x0000, 0x0);
+    assert_eq!(cpu.read_byte(0x0001) & 0x7, 0x7);
+    assert_eq!(cpu.read_byte(0xA000), 0x04);
+}
+
+#[test]
+fn test_raster_irq() {
+    let mut c64 = boot();
+    c64.run_until(100_000);
+    // With the ROMs banked out, a loop in RAM runs while an IRQ handler
+    // counts raster interrupts at line 100 and acknowledges them.
+    c64.load(
+        0xC000,
+        &[
+            0xEE, 0x00, 0xC1, // INC $C100
+            0xA9, 0x01, // LDA #$01
+            0x8D, 0x19, 0xD0, // STA VICIRQ
+            0x40, // RTI
+            0x4C, 0x09, 0xC0, // loop: JMP loop
+        ],
+    );
+    c64.load(0xFFFE, &[0x00, 0xC0]);
+    let cpu = c64.get_cpu();
+    cpu.run();
+    *cpu.get_registers().get_mut_pc() = 0xC009;
+    cpu.write_byte(0xDC0D, 0x7F);
+    cpu.read_byte(0xDC0D);
+    cpu.write_byte(0x0001, 0x35);
+    cpu.write_byte(0xD012, 100);
+    cpu.write_byte(0xD011, 0x1B);
+    cpu.write_byte(0xD019, 0xFF);
+    cpu.write_byte(0xD01A, 0x01);
+
+    // The VIC-II is only brought up to date when accessed or when its
+    // interrupt is due, so it has to be before each look at the raster.
+    let vic = c64.get_vic();
+    loop {
+        c64.get_cpu().update_devices();
+        if vic.borrow().get_raster() == 99 {
+            break;
+        }
+        c64.tick();
+    }
+    assert_eq!(c64.get_cpu().read_byte(0xC100), 0);
+    let start = c64.get_cpu().get_cycles();
+    // One PAL frame is 312 lines of 63 cycles.
+    c64.run_until(start + 2 * 312 * 63);
+    assert_eq!(c64.get_cpu().read_byte(0xC100), 2);
+    assert_eq!(c64.get_cpu().read_byte(0xD019) & 0x81, 0x0);
+}
+
+#[test]
+fn test_keyboard_matrix() {
+    let mut c64 = boot();
+    c64.run_until(100_000);
+    // Hold RUN/STOP while the KERNAL's interrupts are off.
+    c64.get_cpu().write_byte(0xDC0D, 0x7F);
+    c64.set_key(C64::KEY_RUN_STOP, true);
+    c64.tick();
+
+    let cpu = c64.get_cpu();
+    cpu.write_byte(0xDC00, 0x7F);
+    c64.tick();
+    assert_eq!(c64.get_cpu().read_byte(0xDC01), 0x7F);
+    c64.get_cpu().write_byte(0xDC00, 0xFE);
+    c64.tick();
+    assert_eq!(c64.get_cpu().read_byte(0xDC01), 0xFF);
+
+    // Scanning the other way, with the rows driven.
+    let cpu = c64.get_cpu();
+    cpu.write_byte(0xDC02, 0x00);
+    cpu.write_byte(0xDC03, 0xFF);
+    cpu.write_byte(0xDC01, 0x7F);
+    c64.tick();
+    assert_eq!(c64.get_cpu().read_byte(0xDC00), 0x7F);
+}
+
+#[test]
+fn test_screen_codes() {
+    let mut c64 = boot();
+    c64.run_until(100_000);
+    // "Ab1", reversed "@" and a graphics character.
+    c64.load(0x0400 + 40 * 24, &[0x01, 0x02, 0x31, 0x80, 0x51]);
+    assert_eq!(screen_row(&c64, 24), "AB1@#");
+
+    // The lower case character set, and the screen moved to $0800.
+    c64.load(0x0800, &[0x01, 0x42]);
+    c64.get_cpu().write_byte(0xD018, 0x26);
+    assert_eq!(c64.get_screen_addr(), 0x0800);
+    assert_eq!(&screen_row(&c64, 0)[..2], "aB");
+}
+
+#[test]
+fn test_load_prg() {
+    let mut c64 = boot();
+    assert!(c64.load_prg(&[0x01]).is_err());
+
+    let addr = c64.load_prg(&[0x01, 0x08, 0x0B, 0x08, 0x0A, 0x00]).unwrap();
+    assert_eq!(addr, 0x0801);
+    let ram = c64.get_pla();
+    assert_eq!(
+        ram.borrow().get_ram()[0x0801..0x0805],
+        [0x0B, 0x08, 0x0A, 0x00]
+    );
+    assert_eq!(ram.borrow().get_ram()[0x2D..0x2F], [0x05, 0x08]);
+}
@@ -0,0 +1,134 @@
+mod common;
+
+use std::{cell::RefCell, rc::Rc};
+
+use rem6502::{
+    cpu::CPU,
+    devices::{Cassette, Device},
+    wav::{decode_wav, encode_wav},
+};
+
+use common::load;
+
+const CPU_HZ: u64 = 1_000_000;
+const CASSETTE: u16 = 0xC060;
+
+// 1 kHz square wave at 48 kHz, starting high.
+fn square_wave(periods: usize) -> Vec<i16> {
+    (0..periods * 48)
+        .map(|i| match i % 48 < 24 {
+            true => 10_000,
+            false => -10_000,
+        })
+        .collect()
+}
+
+#[test]
+fn test_level() {
+    let mut cassette = Cassette::new(48_000, square_wave(2), CPU_HZ);
+    cassette.tick(1_000);
+    assert_eq!(cassette.read(CASSETTE), 0x00);
+    assert!(!cassette.is_playing());
+
+    // Playback counts from the cycle it was started at.
+    cassette.play();
+    assert_eq!(cassette.position(), Some(0));
+    assert_eq!(cassette.read(CASSETTE), 0x80);
+    cassette.tick(1_499);
+    assert_eq!(cassette.read(CASSETTE), 0x80);
+    cassette.tick(1_500);
+    assert_eq!(cassette.position(), Some(24));
+    assert_eq!(cassette.read(CASSETTE), 0x00);
+    cassette.tick(2_000);
+    assert!(cassette.get_level());
+
+    // End of tape.
+    cassette.tick(3_000);
+    assert!(!cassette.is_playing());
+    assert_eq!(cassette.read(CASSETTE), 0x00);
+}
+
+#[test]
+fn test_silence_keeps_level() {
+    let mut cassette = Cassette::new(1_000, vec![100, 0, 0, -100, 0], CPU_HZ);
+    cassette.play();
+    let levels: Vec<bool> = (0..5)
+        .map(|i| {
+            cassette.tick(i * 1_000);
+            cassette.get_level()
+        })
+        .collect();
+    assert_eq!(levels, [true, true, true, false, false]);
+
+    cassette.stop();
+    assert_eq!(cassette.position(), None);
+}
+
+#[test]
+fn test_loader_counts_edges() {
+    let wav = encode_wav(48_000, &square_wave(10));
+    let cassette = Rc::new(RefCell::new(Cassette::from_wav(&wav, CPU_HZ).unwrap()));
+    let mut cpu = CPU::new();
+    cpu.map_device(CASSETTE..=CASSETTE, cassette.clone());
+    load(
+        &mut cpu,
+        0x1000,
+        &[
+            0xAD, 0x60, 0xC0, // WAIT_LOW: LDA CASSETTE
+            0x29, 0x80, // AND #$80
+            0xD0, 0xF9, // BNE WAIT_LOW
+            0xAD, 0x60, 0xC0, // WAIT_HIGH: LDA CASSETTE
+            0x29, 0x80, // AND #$80
+            0xF0, 0xF9, // BEQ WAIT_HIGH
+            0xE6, 0x10, // INC $10
+            0xA5, 0x10, // LDA $10
+            0xC9, 0x04, // CMP #$04
+            0xD0, 0xEA, // BNE WAIT_LOW
+        ],
+    );
+    *cpu.get_registers().get_mut_pc() = 0x1000;
+    cassette.borrow_mut().play();
+    cpu.run_loop();
+
+    // Four rising edges, the last one 4 ms into the tape.
+    assert_eq!(cpu.read_byte(0x10), 0x04);
+    assert!((4_000..4_040).contains(&cpu.get_cycles()));
+}
+
+#[test]
+fn test_decode_wav() {
+    let samples = [0, 1, -1, i16::MAX, i16::MIN];
+    assert_eq!(
+        decode_wav(&encode_wav(22_050, &samples)).unwrap(),
+        (22_050, samples.to_vec())
+    );
+
+    // 8-bit stereo, with an odd-length chunk ahead of the data.
+    let mut wav = b"RIFF\0\0\0\0WAVE".to_vec();
+    wav.extend(b"fmt ");
+    wav.extend(16_u32.to_le_bytes());
+    for field in [1_u16, 2] {
+        wav.extend(field.to_le_bytes());
+    }
+    wav.extend(11_025_u32.to_le_bytes());
+    wav.extend(22_050_u32.to_le_bytes());
+    for field in [2_u16, 8] {
+        wav.extend(field.to_le_bytes());
+    }
+    wav.extend(b"LIST\x03\0\0\0abc\0");
+    wav.extend(b"data\x06\0\0\0");
+    wav.extend([0x80, 0x80, 0xC0, 0xC0, 0x00, 0x80]);
+    assert_eq!(
+        decode_wav(&wav).unwrap(),
+        (11_025, vec![0, 0x4000, -0x4000])
+    );
+}
+
+#[test]
+fn test_decode_wav_errors() {
+    assert!(decode_wav(b"RIFX\0\0\0\0WAVE").is_err());
+    assert!(decode_wav(b"RIFF\0\0\0\0WAVEdata\0\0\0\0").is_err());
+    let wav = encode_wav(8_000, &[]);
+    assert!(decode_wav(&wav[..36]).is_err());
+    assert!(Cassette::open("/nonexistent.wav", CPU_HZ).is_err());
+}
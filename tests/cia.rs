@@ -0,0 +1,369 @@
+mod common;
+
+use std::{cell::RefCell, rc::Rc};
+
+use rem6502::{
+    cpu::CPU,
+    devices::{Cia, Device, Line},
+};
+
+use common::{load, set_vector, IRQ_VECTOR, NMI_VECTOR};
+
+const CLOCK_HZ: u64 = 1_000_000;
+
+const TA_LO: u16 = 0xDC04;
+const TA_HI: u16 = 0xDC05;
+const TB_LO: u16 = 0xDC06;
+const TB_HI: u16 = 0xDC07;
+const TOD_10THS: u16 = 0xDC08;
+const TOD_SEC: u16 = 0xDC09;
+const TOD_MIN: u16 = 0xDC0A;
+const TOD_HR: u16 = 0xDC0B;
+const SDR: u16 = 0xDC0C;
+const ICR: u16 = 0xDC0D;
+const CRA: u16 = 0xDC0E;
+const CRB: u16 = 0xDC0F;
+
+fn tick(cia: &mut Cia, cycle: &mut u64, cycles: u64) {
+    *cycle += cycles;
+    cia.tick(*cycle);
+}
+
+fn start_timer_a(cia: &mut Cia, latch: u16, control: u8) {
+    cia.write(TA_LO, latch as u8);
+    cia.write(TA_HI, (latch >> 8) as u8);
+    cia.write(CRA, control);
+}
+
+#[test]
+fn test_latch_loads_stopped_timer() {
+    let mut cia = Cia::new(CLOCK_HZ);
+    cia.write(TA_LO, 0x34);
+    assert_eq!(cia.get_timer_a(), 0xFFFF);
+
+    cia.write(TA_HI, 0x12);
+    assert_eq!(cia.get_timer_a(), 0x1234);
+    assert_eq!(cia.read(TA_LO), 0x34);
+    assert_eq!(cia.read(TA_HI), 0x12);
+}
+
+#[test]
+fn test_continuous_timer_period() {
+    let mut cycle = 0;
+    let mut cia = Cia::new(CLOCK_HZ);
+    start_timer_a(&mut cia, 0x0010, 0x01);
+
+    // The write cycle, then 0x10 counts down to zero.
+    tick(&mut cia, &mut cycle, 0x11);
+    assert_eq!(cia.get_timer_a(), 0x0000);
+    assert_eq!(cia.read(ICR), 0x00);
+
+    tick(&mut cia, &mut cycle, 1);
+    assert_eq!(cia.get_timer_a(), 0x0010);
+    assert_eq!(cia.read(ICR), 0x01);
+
+    // latch + 1 cycles per underflow from here on.
+    tick(&mut cia, &mut cycle, 0x10);
+    assert_eq!(cia.read(ICR), 0x00);
+    tick(&mut cia, &mut cycle, 1);
+    assert_eq!(cia.read(ICR), 0x01);
+    assert_eq!(cia.read(CRA) & 0x01, 0x01);
+}
+
+#[test]
+fn test_one_shot_timer_stops() {
+    let mut cycle = 0;
+    let mut cia = Cia::new(CLOCK_HZ);
+    start_timer_a(&mut cia, 0x0004, 0x09);
+
+    tick(&mut cia, &mut cycle, 6);
+    assert_eq!(cia.read(ICR), 0x01);
+    assert_eq!(cia.read(CRA) & 0x01, 0x00);
+    assert_eq!(cia.get_timer_a(), 0x0004);
+
+    tick(&mut cia, &mut cycle, 20);
+    assert_eq!(cia.read(ICR), 0x00);
+    assert_eq!(cia.get_timer_a(), 0x0004);
+}
+
+#[test]
+fn test_force_load() {
+    let mut cycle = 0;
+    let mut cia = Cia::new(CLOCK_HZ);
+    start_timer_a(&mut cia, 0x1000, 0x01);
+    tick(&mut cia, &mut cycle, 0x100);
+    assert_eq!(cia.get_timer_a(), 0x0F01);
+
+    // The running counter keeps going when the latch is written...
+    cia.write(TA_LO, 0x20);
+    cia.write(TA_HI, 0x00);
+    assert_eq!(cia.get_timer_a(), 0x0F01);
+
+    // ...until a force load.
+    cia.write(CRA, 0x11);
+    assert_eq!(cia.get_timer_a(), 0x0020);
+    assert_eq!(cia.read(CRA), 0x01);
+}
+
+#[test]
+fn test_cascaded_timers() {
+    let mut cycle = 0;
+    let mut cia = Cia::new(CLOCK_HZ);
+    cia.write(TB_LO, 0x02);
+    cia.write(TB_HI, 0x00);
+    cia.write(CRB, 0x41);
+    start_timer_a(&mut cia, 0x0009, 0x01);
+
+    // Timer B counts one per timer A underflow, i.e. every 10 cycles.
+    tick(&mut cia, &mut cycle, 11);
+    assert_eq!(cia.get_timer_b(), 0x0001);
+    tick(&mut cia, &mut cycle, 10);
+    assert_eq!(cia.get_timer_b(), 0x0000);
+    assert_eq!(cia.read(ICR) & 0x02, 0x00);
+    tick(&mut cia, &mut cycle, 10);
+    assert_eq!(cia.read(ICR), 0x03);
+    assert_eq!(cia.get_timer_b(), 0x0002);
+}
+
+#[test]
+fn test_timer_b_counts_cnt() {
+    let mut cycle = 0;
+    let mut cia = Cia::new(CLOCK_HZ);
+    cia.write(TB_LO, 0x05);
+    cia.write(TB_HI, 0x00);
+    cia.write(CRB, 0x21);
+    tick(&mut cia, &mut cycle, 10);
+    assert_eq!(cia.get_timer_b(), 0x0005);
+
+    for _ in 0..3 {
+        cia.set_cnt(false);
+        cia.set_cnt(true);
+        tick(&mut cia, &mut cycle, 1);
+    }
+    assert_eq!(cia.get_timer_b(), 0x0002);
+}
+
+#[test]
+fn test_pb6_toggle_output() {
+    let mut cycle = 0;
+    let mut cia = Cia::new(CLOCK_HZ);
+    start_timer_a(&mut cia, 0x0003, 0x07);
+    assert_eq!(cia.get_port_b() & 0x40, 0x40);
+
+    tick(&mut cia, &mut cycle, 5);
+    assert_eq!(cia.get_port_b() & 0x40, 0x00);
+    tick(&mut cia, &mut cycle, 4);
+    assert_eq!(cia.get_port_b() & 0x40, 0x40);
+
+    // Pulse mode: high for one cycle per underflow.
+    cia.write(CRA, 0x03);
+    tick(&mut cia, &mut cycle, 4);
+    assert_eq!(cia.get_port_b() & 0x40, 0x40);
+    tick(&mut cia, &mut cycle, 1);
+    assert_eq!(cia.get_port_b() & 0x40, 0x00);
+}
+
+#[test]
+fn test_icr_read_clears() {
+    let mut cycle = 0;
+    let mut cia = Cia::new(CLOCK_HZ);
+    cia.write(ICR, 0x81);
+    start_timer_a(&mut cia, 0x0002, 0x09);
+    tick(&mut cia, &mut cycle, 4);
+    assert!(cia.irq());
+
+    assert_eq!(cia.read(ICR), 0x81);
+    assert!(!cia.irq());
+    assert_eq!(cia.read(ICR), 0x00);
+
+    // Masked sources still show up, without IR.
+    cia.write(ICR, 0x01);
+    cia.write(CRA, 0x09);
+    tick(&mut cia, &mut cycle, 4);
+    assert!(!cia.irq());
+    assert_eq!(cia.read(ICR), 0x01);
+}
+
+#[test]
+fn test_flag_pin() {
+    let mut cia = Cia::new(CLOCK_HZ);
+    cia.write(ICR, 0x90);
+    cia.set_flag(true);
+    assert!(!cia.irq());
+    cia.set_flag(false);
+    assert!(cia.irq());
+    assert_eq!(cia.read(ICR), 0x90);
+}
+
+#[test]
+fn test_tod_counts() {
+    let mut cycle = 0;
+    let mut cia = Cia::new(CLOCK_HZ);
+    cia.write(TOD_HR, 0x11);
+    cia.write(TOD_MIN, 0x59);
+    cia.write(TOD_SEC, 0x59);
+    cia.write(TOD_10THS, 0x08);
+
+    // Six 60 Hz pulses per tenth.
+    tick(&mut cia, &mut cycle, 100_000);
+    assert_eq!(cia.get_tod(), [0x09, 0x59, 0x59, 0x11]);
+    tick(&mut cia, &mut cycle, 100_000);
+    assert_eq!(cia.get_tod(), [0x00, 0x00, 0x00, 0x92]);
+
+    // 50 Hz mains with CRA bit 7 set.
+    cia.set_tod_frequency(50);
+    cia.write(CRA, 0x80);
+    tick(&mut cia, &mut cycle, 1_000_000);
+    assert_eq!(cia.get_tod(), [0x00, 0x01, 0x00, 0x92]);
+}
+
+#[test]
+fn test_tod_latch_and_stop() {
+    let mut cycle = 0;
+    let mut cia = Cia::new(CLOCK_HZ);
+    cia.write(TOD_HR, 0x05);
+    cia.write(TOD_10THS, 0x09);
+
+    // Reading hours freezes the other registers until tenths are read.
+    assert_eq!(cia.read(TOD_HR), 0x05);
+    tick(&mut cia, &mut cycle, 100_000);
+    assert_eq!(cia.read(TOD_SEC), 0x00);
+    assert_eq!(cia.read(TOD_10THS), 0x09);
+    assert_eq!(cia.read(TOD_SEC), 0x01);
+    assert_eq!(cia.read(TOD_10THS), 0x00);
+
+    // Writing hours stops the clock until tenths are written.
+    cia.write(TOD_HR, 0x07);
+    tick(&mut cia, &mut cycle, 200_000);
+    assert_eq!(cia.get_tod(), [0x00, 0x01, 0x00, 0x07]);
+    cia.write(TOD_10THS, 0x00);
+    tick(&mut cia, &mut cycle, 100_000);
+    assert_eq!(cia.get_tod(), [0x01, 0x01, 0x00, 0x07]);
+}
+
+#[test]
+fn test_tod_alarm() {
+    let mut cycle = 0;
+    let mut cia = Cia::new(CLOCK_HZ);
+    cia.write(ICR, 0x84);
+    cia.write(CRB, 0x80);
+    cia.write(TOD_10THS, 0x03);
+    cia.write(TOD_SEC, 0x00);
+    cia.write(TOD_MIN, 0x00);
+    cia.write(TOD_HR, 0x01);
+    cia.write(CRB, 0x00);
+    assert_eq!(cia.get_tod(), [0x00, 0x00, 0x00, 0x01]);
+
+    tick(&mut cia, &mut cycle, 200_000);
+    assert!(!cia.irq());
+    tick(&mut cia, &mut cycle, 100_000);
+    assert!(cia.irq());
+    assert_eq!(cia.read(ICR), 0x84);
+}
+
+#[test]
+fn test_serial_output() {
+    let mut cycle = 0;
+    let mut cia = Cia::new(CLOCK_HZ);
+    cia.write(ICR, 0x88);
+    start_timer_a(&mut cia, 0x0001, 0x41);
+    cia.write(SDR, 0xA5);
+
+    // Two underflows per bit, one every two cycles.
+    tick(&mut cia, &mut cycle, 1 + 2 * 16 - 1);
+    assert!(!cia.irq());
+    tick(&mut cia, &mut cycle, 1);
+    assert!(cia.irq());
+    assert_eq!(cia.take_serial_output(), vec![0xA5]);
+    assert!(cia.get_cnt());
+}
+
+#[test]
+fn test_serial_input() {
+    let mut cia = Cia::new(CLOCK_HZ);
+    cia.write(ICR, 0x88);
+    for i in (0..8).rev() {
+        cia.set_sp(0x3C & (1 << i) != 0);
+        cia.set_cnt(false);
+        cia.set_cnt(true);
+    }
+    assert!(cia.irq());
+    assert_eq!(cia.read(SDR), 0x3C);
+}
+
+#[test]
+fn test_timer_irq_on_cpu() {
+    let cia = Rc::new(RefCell::new(Cia::new(CLOCK_HZ)));
+    let mut cpu = CPU::new();
+    cpu.map_device(0xDC00..=0xDCFF, cia.clone());
+    set_vector(&mut cpu, IRQ_VECTOR, 0x4000);
+
+    load(
+        &mut cpu,
+        0x1000,
+        &[
+            0xA9, 0x81, // LDA #$81
+            0x8D, 0x0D, 0xDC, // STA ICR
+            0xA9, 0x20, // LDA #$20
+            0x8D, 0x04, 0xDC, // STA TA_LO
+            0xA9, 0x00, // LDA #$00
+            0x8D, 0x05, 0xDC, // STA TA_HI
+            0xA9, 0x19, // LDA #$19
+            0x8D, 0x0E, 0xDC, // STA CRA
+            0x58, // CLI
+            0x4C, 0x15, 0x10, // JMP $1015
+        ],
+    );
+    load(
+        &mut cpu,
+        0x4000,
+        &[
+            0xAD, 0x0D, 0xDC, // LDA ICR
+            0x85, 0x10, // STA $10
+        ],
+    );
+    *cpu.get_registers().get_mut_pc() = 0x1000;
+
+    cpu.run_loop();
+
+    assert_eq!(cpu.read_byte(0x10), 0x81);
+    assert!(!cia.borrow().irq());
+}
+
+#[test]
+fn test_nmi_line() {
+    let cia = Rc::new(RefCell::new(Cia::new(CLOCK_HZ)));
+    let mut cpu = CPU::new();
+    cpu.map_device_with_line(0xDD00..=0xDDFF, cia.clone(), Line::Nmi);
+    set_vector(&mut cpu, NMI_VECTOR, 0x5000);
+
+    load(
+        &mut cpu,
+        0x1000,
+        &[
+            0x78, // SEI
+            0xA9, 0x90, // LDA #$90
+            0x8D, 0x0D, 0xDD, // STA ICR
+            0x4C, 0x06, 0x10, // JMP $1006
+        ],
+    );
+    load(
+        &mut cpu,
+        0x5000,
+        &[
+            0xAD, 0x0D, 0xDD, // LDA ICR
+            0x85, 0x10, // STA $10
+        ],
+    );
+    *cpu.get_registers().get_mut_pc() = 0x1000;
+
+    for _ in 0..10 {
+        cpu.run();
+    }
+    assert_eq!(cpu.get_registers().get_pc() & 0xFF00, 0x1000);
+
+    cia.borrow_mut().set_flag(false);
+    cpu.run_loop();
+
+    assert_eq!(cpu.read_byte(0x10), 0x90);
+}
@@ -0,0 +1,91 @@
+use std::time::{Duration, Instant};
+
+use rem6502::{clock, cpu::CPU};
+
+fn inx_loop() -> CPU {
+    let mut cpu = CPU::new();
+
+    // INX
+    cpu.write_byte(0x0, 0xE8);
+
+    // CPX #$64
+    cpu.write_byte(0x1, 0xE0);
+    cpu.write_byte(0x2, 0x64);
+
+    // BNE
+    cpu.write_byte(0x3, 0xD0);
+    cpu.write_byte(0x4, 0b1111_1011);
+
+    cpu
+}
+
+#[test]
+fn test_cycles_start_at_zero() {
+    let mut cpu = inx_loop();
+    assert_eq!(cpu.get_cycles(), 0);
+
+    cpu.run();
+    assert_eq!(cpu.get_cycles(), 2);
+
+    cpu.run_loop();
+    assert_eq!(cpu.get_cycles(), 100 * 2 + 100 * 2 + 99 * 3 + 2);
+}
+
+#[test]
+fn test_elapsed_time() {
+    let mut cpu = inx_loop();
+    cpu.set_clock_rate(clock::NES_NTSC_HZ);
+    assert_eq!(cpu.get_clock_rate(), 1_789_773);
+
+    cpu.run_loop();
+
+    let expected = cpu.get_cycles() as f64 / 1_789_773.0;
+    assert!((cpu.get_elapsed().as_secs_f64() - expected).abs() < 1e-9);
+}
+
+#[test]
+fn test_unthrottled_by_default() {
+    let mut cpu = inx_loop();
+    cpu.set_clock_rate(1_000);
+    assert!(!cpu.is_throttled());
+
+    let start = Instant::now();
+    cpu.run_loop();
+
+    assert!(start.elapsed() < Duration::from_millis(500));
+}
+
+#[test]
+fn test_throttled_run_loop() {
+    let mut cpu = inx_loop();
+    cpu.set_clock_rate(10_000);
+    cpu.set_throttled(true);
+
+    let start = Instant::now();
+    cpu.run_loop();
+
+    assert!(start.elapsed() >= cpu.get_elapsed() - Duration::from_millis(2));
+}
+
+#[test]
+fn test_run_throttled_through_brk() {
+    let mut cpu = CPU::new();
+
+    // BRK, with the handler at $0200 counting in X forever:
+    // INX, JMP $0200
+    cpu.write_byte(0x0, 0x00);
+    cpu.write_byte(0xFFFE, 0x00);
+    cpu.write_byte(0xFFFF, 0x02);
+    for (i, byte) in [0xE8, 0x4C, 0x00, 0x02].iter().enumerate() {
+        cpu.write_byte(0x0200 + i as u16, *byte);
+    }
+    cpu.set_clock_rate(100_000);
+    cpu.set_throttled(true);
+
+    let start = Instant::now();
+    cpu.run_throttled(5_000);
+
+    assert_eq!(cpu.get_cycles(), 5_000);
+    assert!(cpu.get_registers().get_x() > 0);
+    assert!(start.elapsed() >= cpu.get_elapsed() - Duration::from_millis(2));
+}
@@ -0,0 +1,18 @@
+// Helpers shared by the integration tests. Each test binary builds its own
+// copy of this module and not all of them use every helper.
+#![allow(dead_code)]
+
+use rem6502::cpu::CPU;
+
+pub const IRQ_VECTOR: u16 = 0xFFFE;
+pub const NMI_VECTOR: u16 = 0xFFFA;
+
+pub fn load(cpu: &mut CPU, addr: u16, program: &[u8]) {
+    for (i, byte) in program.iter().enumerate() {
+        cpu.write_byte(addr + i as u16, *byte);
+    }
+}
+
+pub fn set_vector(cpu: &mut CPU, vector: u16, addr: u16) {
+    load(cpu, vector, &addr.to_le_bytes());
+}
@@ -0,0 +1,304 @@
+use std::{cell::RefCell, rc::Rc};
+
+use rem6502::{
+    cpu::CPU,
+    devices::{Hd44780, Via},
+};
+
+const CLOCK_HZ: u64 = 1_000_000;
+
+const E: u8 = 0x80;
+const RW: u8 = 0x40;
+const RS: u8 = 0x20;
+
+// Drives the LCD's pins directly, keeping track of time.
+struct Bench {
+    lcd: Hd44780,
+    cycle: u64,
+}
+
+impl Bench {
+    fn new() -> Self {
+        Self {
+            lcd: Hd44780::new(16, 2, CLOCK_HZ),
+            cycle: 0,
+        }
+    }
+
+    fn wait(&mut self, cycles: u64) {
+        self.cycle += cycles;
+        self.lcd.tick(self.cycle);
+    }
+
+    fn pulse_write(&mut self, rs: bool, data: u8) {
+        self.lcd.set_pins(rs, false, true, data);
+        self.wait(1);
+        self.lcd.set_pins(rs, false, false, data);
+        self.wait(1);
+    }
+
+    fn pulse_read(&mut self, rs: bool) -> u8 {
+        self.lcd.set_pins(rs, true, true, 0xFF);
+        let data = self.lcd.get_data().unwrap();
+        self.wait(1);
+        self.lcd.set_pins(rs, true, false, 0xFF);
+        assert_eq!(self.lcd.get_data(), None);
+        self.wait(1);
+        data
+    }
+
+    fn write8(&mut self, rs: bool, val: u8) {
+        self.pulse_write(rs, val);
+        self.wait(2_000);
+    }
+
+    fn write4(&mut self, rs: bool, val: u8) {
+        self.pulse_write(rs, val & 0xF0);
+        self.pulse_write(rs, val << 4);
+        self.wait(2_000);
+    }
+
+    fn read4(&mut self, rs: bool) -> u8 {
+        let high = self.pulse_read(rs);
+        let low = self.pulse_read(rs);
+        high | (low >> 4)
+    }
+
+    fn print8(&mut self, text: &str) {
+        for c in text.bytes() {
+            self.write8(true, c);
+        }
+    }
+}
+
+#[test]
+fn test_8bit_text() {
+    let mut bench = Bench::new();
+    bench.write8(false, 0x38);
+    bench.write8(false, 0x0C);
+    bench.write8(false, 0x06);
+    bench.write8(false, 0x01);
+    bench.print8("HELLO");
+    bench.write8(false, 0xC0);
+    bench.print8("WORLD!");
+
+    assert_eq!(bench.lcd.get_line(0), "HELLO           ");
+    assert_eq!(bench.lcd.get_text(), "HELLO           \nWORLD!          ");
+    assert_eq!(bench.lcd.get_address(), 0x46);
+    assert_eq!(bench.lcd.get_cursor(), None);
+}
+
+#[test]
+fn test_4bit_init_and_text() {
+    let mut bench = Bench::new();
+
+    // Reset by instruction: only DB4-DB7 are wired.
+    for _ in 0..3 {
+        bench.pulse_write(false, 0x30);
+        bench.wait(5_000);
+    }
+    bench.pulse_write(false, 0x20);
+    bench.wait(100);
+    assert!(bench.lcd.is_four_bit());
+
+    bench.write4(false, 0x28);
+    bench.write4(false, 0x0E);
+    bench.write4(false, 0x01);
+    for c in b"4-BIT" {
+        bench.write4(true, *c);
+    }
+
+    assert_eq!(bench.lcd.get_line(0), "4-BIT           ");
+    assert_eq!(bench.lcd.get_cursor(), Some((5, 0)));
+    assert_eq!(bench.read4(false), 0x05);
+
+    // Reading data advances the address.
+    bench.write4(false, 0x80);
+    assert_eq!(bench.read4(true), b'4');
+    assert_eq!(bench.read4(true), b'-');
+    assert_eq!(bench.lcd.get_address(), 0x02);
+}
+
+#[test]
+fn test_busy_flag() {
+    let mut bench = Bench::new();
+    bench.write8(false, 0x38);
+    bench.write8(false, 0x0C);
+
+    bench.pulse_write(false, 0x01);
+    assert_eq!(bench.pulse_read(false) & 0x80, 0x80);
+    assert!(bench.lcd.is_busy());
+
+    // Written while busy: ignored.
+    bench.pulse_write(true, b'X');
+    bench.wait(1_520);
+    assert_eq!(bench.pulse_read(false), 0x00);
+
+    bench.pulse_write(true, b'Y');
+    assert_eq!(bench.pulse_read(false), 0x81);
+    bench.wait(41);
+    assert_eq!(bench.pulse_read(false), 0x01);
+    assert_eq!(bench.lcd.get_line(0), "Y               ");
+}
+
+#[test]
+fn test_cgram() {
+    let mut bench = Bench::new();
+    bench.write8(false, 0x38);
+    bench.write8(false, 0x0C);
+    bench.write8(false, 0x48);
+    for row in [0x1F, 0x11, 0x0E] {
+        bench.write8(true, row);
+    }
+    assert_eq!(&bench.lcd.get_cgram()[0x08..0x0B], &[0x1F, 0x11, 0x0E]);
+
+    bench.write8(false, 0x48);
+    assert_eq!(bench.pulse_read(true), 0x1F);
+    bench.wait(100);
+    assert_eq!(bench.pulse_read(true), 0x11);
+
+    // Custom characters render as '?'.
+    bench.write8(false, 0x80);
+    bench.write8(true, 0x01);
+    bench.print8("A");
+    assert_eq!(bench.lcd.get_line(0), "?A              ");
+}
+
+#[test]
+fn test_shift_and_entry_mode() {
+    let mut bench = Bench::new();
+    bench.write8(false, 0x38);
+    bench.write8(false, 0x0C);
+    bench.print8("ABC");
+
+    // Shift the display left: the first character scrolls out of view.
+    bench.write8(false, 0x18);
+    assert_eq!(bench.lcd.get_line(0), "BC              ");
+    bench.write8(false, 0x1C);
+    bench.write8(false, 0x1C);
+    assert_eq!(bench.lcd.get_line(0), " ABC            ");
+
+    // Return home undoes the shift, decrementing entry writes backwards.
+    bench.write8(false, 0x02);
+    bench.write8(false, 0x85);
+    bench.write8(false, 0x04);
+    bench.print8("XY");
+    assert_eq!(bench.lcd.get_line(0), "ABC YX          ");
+    assert_eq!(bench.lcd.get_address(), 0x03);
+
+    // Cursor moves without writing.
+    bench.write8(false, 0x14);
+    assert_eq!(bench.lcd.get_address(), 0x04);
+}
+
+#[test]
+fn test_display_off_and_wrap() {
+    let mut bench = Bench::new();
+    bench.write8(false, 0x38);
+    bench.write8(false, 0x0F);
+    bench.write8(false, 0xE7);
+    bench.print8("AB");
+
+    assert_eq!(bench.lcd.get_address(), 0x01);
+    assert_eq!(bench.lcd.get_ddram()[0x67], b'A');
+    assert_eq!(bench.lcd.get_line(0), "B               ");
+    assert_eq!(bench.lcd.get_cursor(), Some((1, 0)));
+
+    bench.write8(false, 0x08);
+    assert_eq!(bench.lcd.get_line(0), "                ");
+    assert_eq!(bench.lcd.get_cursor(), None);
+}
+
+#[test]
+fn test_driven_from_via() {
+    let via = Rc::new(RefCell::new(Via::new()));
+    let mut lcd = Hd44780::new(16, 2, CLOCK_HZ);
+    let mut cpu = CPU::new();
+    cpu.map_device(0x6000..=0x600F, via.clone());
+
+    // LCD data on port B, E/RW/RS on PA7-PA5, polling the busy flag.
+    let program = [
+        0xA9, 0xFF, // LDA #$FF
+        0x8D, 0x02, 0x60, // STA DDRB
+        0xA9, 0xE0, // LDA #$E0
+        0x8D, 0x03, 0x60, // STA DDRA
+        0xA9, 0x38, // LDA #$38
+        0x20, 0x2B, 0x10, // JSR cmd
+        0xA9, 0x0E, // LDA #$0E
+        0x20, 0x2B, 0x10, // JSR cmd
+        0xA9, 0x06, // LDA #$06
+        0x20, 0x2B, 0x10, // JSR cmd
+        0xA9, 0x01, // LDA #$01
+        0x20, 0x2B, 0x10, // JSR cmd
+        0xA9, 0x48, // LDA #'H'
+        0x20, 0x41, 0x10, // JSR chr
+        0xA9, 0x49, // LDA #'I'
+        0x20, 0x41, 0x10, // JSR chr
+        // end: $1028
+        0x4C, 0x28, 0x10, // JMP end
+        // cmd: $102B
+        0x20, 0x57, 0x10, // JSR wait
+        0x8D, 0x00, 0x60, // STA PORTB
+        0xA9, 0x00, // LDA #0
+        0x8D, 0x01, 0x60, // STA PORTA
+        0xA9, 0x80, // LDA #E
+        0x8D, 0x01, 0x60, // STA PORTA
+        0xA9, 0x00, // LDA #0
+        0x8D, 0x01, 0x60, // STA PORTA
+        0x60, // RTS
+        // chr: $1041
+        0x20, 0x57, 0x10, // JSR wait
+        0x8D, 0x00, 0x60, // STA PORTB
+        0xA9, 0x20, // LDA #RS
+        0x8D, 0x01, 0x60, // STA PORTA
+        0xA9, 0xA0, // LDA #RS | E
+        0x8D, 0x01, 0x60, // STA PORTA
+        0xA9, 0x20, // LDA #RS
+        0x8D, 0x01, 0x60, // STA PORTA
+        0x60, // RTS
+        // wait: $1057
+        0x48, // PHA
+        0xA9, 0x00, // LDA #0
+        0x8D, 0x02, 0x60, // STA DDRB
+        // busy: $105D
+        0xA9, 0xC0, // LDA #RW | E
+        0x8D, 0x01, 0x60, // STA PORTA
+        0xAD, 0x00, 0x60, // LDA PORTB
+        0xAA, // TAX
+        0xA9, 0x40, // LDA #RW
+        0x8D, 0x01, 0x60, // STA PORTA
+        0x8A, // TXA
+        0x29, 0x80, // AND #$80
+        0xD0, 0xED, // BNE busy
+        0xA9, 0xFF, // LDA #$FF
+        0x8D, 0x02, 0x60, // STA DDRB
+        0x68, // PLA
+        0x60, // RTS
+    ];
+    for (i, byte) in program.iter().enumerate() {
+        cpu.write_byte(0x1000 + i as u16, *byte);
+    }
+    *cpu.get_registers().get_mut_pc() = 0x1000;
+
+    // Writes while the LCD is busy are lost, so this only passes if the
+    // program waits out the 1.52 ms clear.
+    for _ in 0..5_000 {
+        cpu.tick();
+        let mut via = via.borrow_mut();
+        let control = via.get_port_a();
+        lcd.set_pins(
+            control & RS != 0,
+            control & RW != 0,
+            control & E != 0,
+            via.get_port_b(),
+        );
+        lcd.tick(cpu.get_cycles());
+        if let Some(data) = lcd.get_data() {
+            via.set_port_b_input(data);
+        }
+    }
+
+    assert_eq!(lcd.get_text(), "HI              \n                ");
+    assert_eq!(lcd.get_cursor(), Some((2, 0)));
+    assert_eq!(cpu.get_registers().get_pc() & 0xFFF0, 0x1020);
+}
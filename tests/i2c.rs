@@ -0,0 +1,267 @@
+use std::{cell::RefCell, rc::Rc};
+
+use rem6502::devices::{Ds1307, Eeprom24, I2c, I2cPins};
+
+const CLOCK_HZ: u64 = 1_000;
+
+const PINS: I2cPins = I2cPins {
+    scl: 0x01,
+    sda: 0x02,
+};
+
+// Bit-bangs the bus through the port interface, the way a 6502 driver
+// would with the data direction register.
+struct Master {
+    bus: I2c,
+    cycle: u64,
+}
+
+impl Master {
+    fn new() -> Self {
+        Self {
+            bus: I2c::new(PINS),
+            cycle: 0,
+        }
+    }
+
+    fn drive(&mut self, scl: bool, sda: bool) {
+        let mut ddr = 0x0;
+        if !scl {
+            ddr |= PINS.scl;
+        }
+        if !sda {
+            ddr |= PINS.sda;
+        }
+        self.bus.set_port(0x0, ddr);
+    }
+
+    fn sda(&self) -> bool {
+        self.bus.drive_port(0x0) & PINS.sda != 0
+    }
+
+    fn start(&mut self) {
+        self.drive(true, true);
+        self.drive(true, false);
+        self.drive(false, false);
+    }
+
+    fn stop(&mut self) {
+        self.drive(false, false);
+        self.drive(true, false);
+        self.drive(true, true);
+    }
+
+    fn clock(&mut self, sda: bool) -> bool {
+        self.drive(false, sda);
+        self.drive(true, sda);
+        let level = self.sda();
+        self.drive(false, sda);
+        level
+    }
+
+    fn write(&mut self, val: u8) -> bool {
+        for bit in (0..8).rev() {
+            self.clock(val & (1 << bit) != 0);
+        }
+        !self.clock(true)
+    }
+
+    fn read(&mut self, ack: bool) -> u8 {
+        let mut val = 0;
+        for _ in 0..8 {
+            val = (val << 1) | self.clock(true) as u8;
+        }
+        self.clock(!ack);
+        val
+    }
+
+    fn wait(&mut self, cycles: u64) {
+        for _ in 0..cycles {
+            self.cycle += 1;
+            self.bus.tick(self.cycle);
+        }
+    }
+
+    fn write_registers(&mut self, addr: u8, pointer: u8, data: &[u8]) {
+        self.start();
+        assert!(self.write(addr << 1));
+        assert!(self.write(pointer));
+        for byte in data {
+            assert!(self.write(*byte));
+        }
+        self.stop();
+    }
+
+    fn read_registers(&mut self, addr: u8, pointer: u8, len: usize) -> Vec<u8> {
+        self.start();
+        assert!(self.write(addr << 1));
+        assert!(self.write(pointer));
+        self.start();
+        assert!(self.write((addr << 1) | 1));
+        let data = (0..len).map(|i| self.read(i + 1 < len)).collect();
+        self.stop();
+        data
+    }
+}
+
+#[test]
+fn test_unknown_address_is_not_acknowledged() {
+    let mut master = Master::new();
+    master
+        .bus
+        .attach(0x50, Rc::new(RefCell::new(Eeprom24::new(0x100, 8))));
+
+    master.start();
+    assert!(!master.write(0x51 << 1));
+    assert!(!master.write(0x00));
+    master.stop();
+
+    master.start();
+    assert!(master.write(0x50 << 1));
+    master.stop();
+    assert!(master.sda());
+}
+
+#[test]
+fn test_eeprom_small_address() {
+    let eeprom = Rc::new(RefCell::new(Eeprom24::new(0x100, 8)));
+    let mut master = Master::new();
+    master.bus.attach(0x50, eeprom.clone());
+
+    master.write_registers(0x50, 0x10, &[0x12, 0x34, 0x56]);
+    assert_eq!(
+        eeprom.borrow().get_data()[0x10..0x14],
+        [0x12, 0x34, 0x56, 0xFF]
+    );
+    assert_eq!(
+        master.read_registers(0x50, 0x0F, 4),
+        [0xFF, 0x12, 0x34, 0x56]
+    );
+}
+
+#[test]
+fn test_eeprom_page_wrap() {
+    let eeprom = Rc::new(RefCell::new(Eeprom24::new(0x8000, 64)));
+    let mut master = Master::new();
+    master.bus.attach(0x50, eeprom.clone());
+
+    master.start();
+    assert!(master.write(0x50 << 1));
+    assert!(master.write(0x12));
+    assert!(master.write(0x3E));
+    for byte in [0xA0, 0xA1, 0xA2, 0xA3] {
+        assert!(master.write(byte));
+    }
+    master.stop();
+
+    let eeprom = eeprom.borrow();
+    let page = &eeprom.get_data()[0x1200..0x1240];
+    assert_eq!(page[0x3E..], [0xA0, 0xA1]);
+    assert_eq!(page[..2], [0xA2, 0xA3]);
+    assert_eq!(eeprom.get_data()[0x1240], 0xFF);
+}
+
+#[test]
+fn test_eeprom_current_and_sequential_read() {
+    let eeprom = Rc::new(RefCell::new(Eeprom24::new(0x8000, 64)));
+    let mut data = vec![0x0; 0x8000];
+    data[0x7FFE] = 0x11;
+    data[0x7FFF] = 0x22;
+    data[0x0] = 0x33;
+    eeprom.borrow_mut().load(&data);
+    let mut master = Master::new();
+    master.bus.attach(0x50, eeprom.clone());
+
+    // Sequential reads wrap around at the end of memory.
+    master.start();
+    assert!(master.write(0x50 << 1));
+    assert!(master.write(0x7F));
+    assert!(master.write(0xFE));
+    master.start();
+    assert!(master.write((0x50 << 1) | 1));
+    assert_eq!(master.read(true), 0x11);
+    assert_eq!(master.read(true), 0x22);
+    assert_eq!(master.read(false), 0x33);
+    master.stop();
+
+    // A current address read carries on from there.
+    master.start();
+    assert!(master.write((0x50 << 1) | 1));
+    assert_eq!(master.read(false), 0x00);
+    master.stop();
+    assert_eq!(eeprom.borrow().get_address(), 0x2);
+}
+
+#[test]
+fn test_ds1307_starts_halted() {
+    let rtc = Rc::new(RefCell::new(Ds1307::new(CLOCK_HZ)));
+    let mut master = Master::new();
+    master.bus.attach(Ds1307::ADDRESS, rtc.clone());
+
+    master.wait(CLOCK_HZ * 3);
+    assert!(rtc.borrow().is_halted());
+    assert_eq!(
+        master.read_registers(Ds1307::ADDRESS, 0x0, 7),
+        [0x80, 0x00, 0x00, 0x01, 0x01, 0x01, 0x00]
+    );
+
+    master.write_registers(Ds1307::ADDRESS, 0x0, &[0x58]);
+    assert!(!rtc.borrow().is_halted());
+    master.wait(CLOCK_HZ * 3);
+    assert_eq!(master.read_registers(Ds1307::ADDRESS, 0x0, 2), [0x01, 0x01]);
+}
+
+#[test]
+fn test_ds1307_calendar_rollover() {
+    let rtc = Rc::new(RefCell::new(Ds1307::new(CLOCK_HZ)));
+    let mut master = Master::new();
+    master.bus.attach(Ds1307::ADDRESS, rtc.clone());
+
+    // 23:59:59 on Wednesday 28/02/24, a leap year.
+    master.write_registers(
+        Ds1307::ADDRESS,
+        0x0,
+        &[0x59, 0x59, 0x23, 0x03, 0x28, 0x02, 0x24],
+    );
+    master.wait(CLOCK_HZ);
+    assert_eq!(
+        master.read_registers(Ds1307::ADDRESS, 0x0, 7),
+        [0x00, 0x00, 0x00, 0x04, 0x29, 0x02, 0x24]
+    );
+
+    // 11:59:59 PM on Saturday 31/12/99 in 12-hour mode.
+    master.write_registers(
+        Ds1307::ADDRESS,
+        0x0,
+        &[0x59, 0x59, 0x40 | 0x20 | 0x11, 0x07, 0x31, 0x12, 0x99],
+    );
+    master.wait(CLOCK_HZ);
+    assert_eq!(
+        master.read_registers(Ds1307::ADDRESS, 0x0, 7),
+        [0x00, 0x00, 0x40 | 0x12, 0x01, 0x01, 0x01, 0x00]
+    );
+
+    // 12:59:59 AM goes to 1 AM.
+    master.write_registers(Ds1307::ADDRESS, 0x0, &[0x59, 0x59, 0x40 | 0x12]);
+    master.wait(CLOCK_HZ);
+    assert_eq!(
+        master.read_registers(Ds1307::ADDRESS, 0x2, 1),
+        [0x40 | 0x01]
+    );
+}
+
+#[test]
+fn test_ds1307_ram() {
+    let rtc = Rc::new(RefCell::new(Ds1307::new(CLOCK_HZ)));
+    let mut master = Master::new();
+    master.bus.attach(Ds1307::ADDRESS, rtc.clone());
+
+    master.write_registers(Ds1307::ADDRESS, 0x3E, &[0xAA, 0xBB, 0x10]);
+    assert_eq!(rtc.borrow().get_registers()[0x3E..], [0xAA, 0xBB]);
+    // The pointer wraps around to the seconds register.
+    assert_eq!(rtc.borrow().get_registers()[0x0], 0x10);
+    assert_eq!(
+        master.read_registers(Ds1307::ADDRESS, 0x3F, 2),
+        [0xBB, 0x10]
+    );
+}
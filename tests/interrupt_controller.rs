@@ -0,0 +1,206 @@
+mod common;
+
+use std::{cell::RefCell, rc::Rc};
+
+use rem6502::{
+    cpu::CPU,
+    devices::{Device, InterruptController, Line, Trigger},
+};
+
+use common::{load, set_vector, IRQ_VECTOR};
+
+const SOURCES: u16 = 0xD000;
+const CONTROLLER: u16 = 0xD100;
+
+// Asserts its IRQ output while a non-zero value is written to it.
+struct Flag {
+    irq: bool,
+}
+
+impl Device for Flag {
+    fn peek(&self, _addr: u16) -> u8 {
+        self.irq as u8
+    }
+
+    fn write(&mut self, _addr: u16, val: u8) {
+        self.irq = val != 0;
+    }
+
+    fn irq(&self) -> bool {
+        self.irq
+    }
+}
+
+fn setup(mask: u8) -> (CPU, Rc<RefCell<InterruptController>>) {
+    let controller = Rc::new(RefCell::new(InterruptController::new()));
+    let mut cpu = CPU::new();
+    for (n, name) in ["uart", "timer"].iter().enumerate() {
+        let flag = Rc::new(RefCell::new(Flag { irq: false }));
+        let addr = SOURCES + n as u16;
+        cpu.map_device_with_line(addr..=addr, flag.clone(), Line::Unconnected);
+        controller.borrow_mut().add_source(name, flag);
+    }
+    cpu.map_device(CONTROLLER..=CONTROLLER + 0x3, controller.clone());
+
+    load(
+        &mut cpu,
+        0x1000,
+        &[
+            0xA9, mask, // LDA #MASK
+            0x8D, 0x01, 0xD1, // STA MASK
+            0x58, // CLI
+            0xA9, 0x01, // LDA #$01
+            0x8D, 0x01, 0xD0, // STA TIMER
+            0x4C, 0x0B, 0x10, // JMP $100B
+        ],
+    );
+    load(
+        &mut cpu,
+        0x4000,
+        &[
+            0xAD, 0x02, 0xD1, // LDA ACK
+            0x85, 0x10, // STA $10
+            0xA9, 0x00, // LDA #$00
+            0x8D, 0x01, 0xD0, // STA TIMER
+            0xE6, 0x11, // INC $11
+            0x40, // RTI
+        ],
+    );
+    set_vector(&mut cpu, IRQ_VECTOR, 0x4000);
+    *cpu.get_registers().get_mut_pc() = 0x1000;
+    (cpu, controller)
+}
+
+#[test]
+fn test_cpu_handler_acknowledges_source() {
+    let (mut cpu, controller) = setup(0xFF);
+    cpu.run_until(200);
+
+    assert_eq!(cpu.read_byte(0x10), 0x01);
+    assert_eq!(cpu.read_byte(0x11), 0x01);
+    let controller = controller.borrow();
+    assert_eq!(controller.get_counts(), [0, 1]);
+    let events = controller.get_events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].1, "timer");
+    assert!(events[0].0 > 0 && events[0].0 < 30);
+    assert!(!controller.irq());
+}
+
+#[test]
+fn test_masked_source_does_not_reach_cpu() {
+    let (mut cpu, controller) = setup(0x01);
+    cpu.run_until(200);
+
+    // The timer asserts, but only through the controller, which masks it.
+    assert_eq!(cpu.read_byte(0x11), 0x00);
+    let controller = controller.borrow();
+    assert_eq!(controller.get_pending(), 0x02);
+    assert_eq!(controller.get_active(), None);
+    assert_eq!(controller.get_counts(), [0, 1]);
+}
+
+#[test]
+fn test_priority() {
+    let mut controller = InterruptController::new();
+    for name in ["a", "b", "c"] {
+        controller.add_input(name);
+    }
+    controller.set_input(0, true);
+    controller.set_input(2, true);
+    assert_eq!(controller.read(0x0), 0x05);
+    assert_eq!(controller.get_cause(), Some("a"));
+
+    controller.set_priority(2, 1);
+    assert_eq!(controller.read(0x2), 0x02);
+    // Level requests stay pending after the acknowledge.
+    assert_eq!(controller.read(0x2), 0x02);
+
+    controller.write(0x1, 0xFB);
+    assert_eq!(controller.read(0x1), 0xFB);
+    assert_eq!(controller.get_cause(), Some("a"));
+    controller.set_input(0, false);
+    assert_eq!(controller.read(0x2), 0xFF);
+    assert!(!controller.irq());
+}
+
+#[test]
+fn test_edge_trigger() {
+    let mut controller = InterruptController::new();
+    let button = controller.add_input("button");
+    controller.set_trigger(button, Trigger::Edge);
+
+    controller.set_input(button, true);
+    assert!(!controller.irq());
+    controller.tick(10);
+    controller.set_input(button, false);
+    controller.tick(11);
+    assert!(controller.irq());
+
+    assert_eq!(controller.read(0x6), 0x00);
+    assert!(!controller.irq());
+    assert_eq!(controller.read(0x2), 0xFF);
+
+    // Cleared through the status and acknowledge registers too.
+    for (reg, val) in [(0x0, 0x01), (0x2, 0x00)] {
+        controller.set_input(button, true);
+        controller.tick(12);
+        controller.set_input(button, false);
+        controller.tick(13);
+        assert!(controller.irq());
+        controller.write(reg, val);
+        assert!(!controller.irq());
+    }
+    assert_eq!(controller.get_counts(), [3]);
+}
+
+#[test]
+fn test_output_enable() {
+    let mut controller = InterruptController::new();
+    controller.add_input("a");
+    controller.set_input(0, true);
+    assert_eq!(controller.read(0x3), 0x01);
+    controller.write(0x3, 0x00);
+    assert!(!controller.irq());
+    assert_eq!(controller.get_active(), Some(0));
+}
+
+#[test]
+fn test_debug_output() {
+    let mut controller = InterruptController::new();
+    controller.add_input("via");
+    controller.add_input("acia");
+    assert!(controller.to_string().starts_with("IRQ idle\n"));
+
+    controller.set_input(1, true);
+    controller.tick(100);
+    controller.write(0x1, 0x01);
+    controller.set_input(0, true);
+    controller.tick(101);
+    assert_eq!(
+        controller.to_string(),
+        "IRQ asserted by via\n\
+         \x20 0: via (Level, priority 0) pending, asserted 1 times\n\
+         \x20 1: acia (Level, priority 0) pending, masked, asserted 1 times\n"
+    );
+    assert_eq!(controller.get_events(), [(100, "acia"), (101, "via")]);
+
+    controller.write(0x3, 0x00);
+    assert!(controller
+        .to_string()
+        .starts_with("IRQ disabled, via pending\n"));
+}
+
+#[test]
+fn test_event_log_is_bounded() {
+    let mut controller = InterruptController::new();
+    controller.add_input("storm");
+    for cycle in 0..600 {
+        controller.set_input(0, cycle % 2 == 0);
+        controller.tick(cycle);
+    }
+    assert_eq!(controller.get_counts(), [300]);
+    let events = controller.get_events();
+    assert_eq!(events.len(), 256);
+    assert_eq!(events[0], (88, "storm"));
+}
@@ -0,0 +1,277 @@
+use rem6502::{
+    devices::{Device, Rriot},
+    machines::Kim1,
+    serial::QueueBackend,
+};
+
+// Stand-in for the 6530-002 monitor. It checks the TTY jumper like the
+// real one: with it fitted it echoes characters over the teletype loop at
+// 1200 baud, bit-banged the same way, otherwise it jumps to a program in
+// RAM.
+fn monitor_rom() -> Vec<u8> {
+    let program = [
+        // RESET: $1C00
+        0xA2, 0xFF, // LDX #$FF
+        0x9A, // TXS
+        0xD8, // CLD
+        0xA9, 0x1F, // LDA #$1F
+        0x8D, 0x43, 0x17, // STA PBDD
+        0xA9, 0x07, // LDA #$07
+        0x8D, 0x42, 0x17, // STA SBD
+        0xA9, 0x01, // LDA #$01
+        0x2C, 0x40, 0x17, // BIT SAD
+        0xF0, 0x03, // BEQ WAIT
+        0x4C, 0x00, 0x02, // JMP $0200
+        // WAIT: $1C18
+        0xAD, 0x40, 0x17, // LDA SAD
+        0x30, 0xFB, // BMI WAIT
+        0x20, 0x5D, 0x1C, // JSR HALF
+        0xA2, 0x08, // LDX #$08
+        // INBIT: $1C22
+        0x20, 0x57, 0x1C, // JSR DELAY
+        0xAD, 0x40, 0x17, // LDA SAD
+        0x0A, // ASL A
+        0x66, 0x10, // ROR $10
+        0xCA, // DEX
+        0xD0, 0xF4, // BNE INBIT
+        0x20, 0x57, 0x1C, // JSR DELAY
+        0xA9, 0x06, // LDA #$06
+        0x8D, 0x42, 0x17, // STA SBD
+        0x20, 0x57, 0x1C, // JSR DELAY
+        0xA2, 0x08, // LDX #$08
+        // OUTBIT: $1C3B
+        0xA9, 0x03, // LDA #$03
+        0x46, 0x10, // LSR $10
+        0x2A, // ROL A
+        0x8D, 0x42, 0x17, // STA SBD
+        0x20, 0x57, 0x1C, // JSR DELAY
+        0xCA, // DEX
+        0xD0, 0xF2, // BNE OUTBIT
+        0xA9, 0x07, // LDA #$07
+        0x8D, 0x42, 0x17, // STA SBD
+        0x20, 0x57, 0x1C, // JSR DELAY
+        0x20, 0x57, 0x1C, // JSR DELAY
+        0x4C, 0x18, 0x1C, // JMP WAIT
+        // DELAY: $1C57
+        0xA0, 0xA0, // LDY #160
+        // DELAY1: $1C59
+        0x88, // DEY
+        0xD0, 0xFD, // BNE DELAY1
+        0x60, // RTS
+        // HALF: $1C5D
+        0xA0, 0x50, // LDY #80
+        // HALF1: $1C5F
+        0x88, // DEY
+        0xD0, 0xFD, // BNE HALF1
+        0x60, // RTS
+    ];
+    let mut rom = vec![0x0; Rriot::ROM_LENGTH];
+    rom[..program.len()].copy_from_slice(&program);
+    // NMI, RESET and IRQ all at $1C00.
+    rom[0x3FA..].copy_from_slice(&[0x00, 0x1C, 0x00, 0x1C, 0x00, 0x1C]);
+    rom
+}
+
+// Scans the display with the bytes at $10-$12, then the keypad, storing the
+// key code at $20 as the monitor's GETKEY would return it.
+const SCAN_PROGRAM: [u8; 121] = [
+    // SCAN: $0200
+    0xA9, 0x7F, // LDA #$7F
+    0x8D, 0x41, 0x17, // STA PADD
+    0xA2, 0x09, // LDX #$09
+    0xA0, 0x00, // LDY #$00
+    // BYTE: $0209
+    0xB9, 0x10, 0x00, // LDA $0010,Y
+    0x4A, 0x4A, 0x4A, 0x4A, // LSR A x4
+    0x20, 0x28, 0x02, // JSR DIGIT
+    0xB9, 0x10, 0x00, // LDA $0010,Y
+    0x29, 0x0F, // AND #$0F
+    0x20, 0x28, 0x02, // JSR DIGIT
+    0xC8, // INY
+    0xC0, 0x03, // CPY #$03
+    0xD0, 0xE9, // BNE BYTE
+    0x20, 0x43, 0x02, // JSR GETKEY
+    0x85, 0x20, // STA $20
+    0x4C, 0x00, 0x02, // JMP SCAN
+    // DIGIT: $0228
+    0x84, 0x13, // STY $13
+    0xA8, // TAY
+    0xB9, 0x00, 0x03, // LDA TABLE,Y
+    0xA0, 0x00, // LDY #$00
+    0x8C, 0x40, 0x17, // STY SAD
+    0x8E, 0x42, 0x17, // STX SBD
+    0x8D, 0x40, 0x17, // STA SAD
+    0xA0, 0x7F, // LDY #$7F
+    // DIGIT1: $023B
+    0x88, // DEY
+    0xD0, 0xFD, // BNE DIGIT1
+    0xE8, 0xE8, // INX, INX
+    0xA4, 0x13, // LDY $13
+    0x60, // RTS
+    // GETKEY: $0243
+    0xA9, 0x00, // LDA #$00
+    0x8D, 0x41, 0x17, // STA PADD
+    0xA2, 0x21, // LDX #$21
+    // ROW: $024A
+    0x8E, 0x42, 0x17, // STX SBD
+    0xAD, 0x40, 0x17, // LDA SAD
+    0x09, 0x80, // ORA #$80
+    0x49, 0xFF, // EOR #$FF
+    0xD0, 0x09, // BNE FOUND
+    0xE8, 0xE8, // INX, INX
+    0xE0, 0x27, // CPX #$27
+    0xD0, 0xEE, // BNE ROW
+    0xA9, 0x15, // LDA #$15
+    0x60, // RTS
+    // FOUND: $025F
+    0xA0, 0x00, // LDY #$00
+    // COLUMN: $0261
+    0x0A, // ASL A
+    0x30, 0x03, // BMI ADDROW
+    0xC8, // INY
+    0xD0, 0xFA, // BNE COLUMN
+    // ADDROW: $0267
+    0x8A, // TXA
+    0x38, // SEC
+    0xE9, 0x21, // SBC #$21
+    0x4A, // LSR A
+    0xAA, // TAX
+    0x98, // TYA
+    // ROWS: $026E
+    0xE0, 0x00, // CPX #$00
+    0xF0, 0x06, // BEQ DONE
+    0x18, // CLC
+    0x69, 0x07, // ADC #$07
+    0xCA, // DEX
+    0xD0, 0xF6, // BNE ROWS
+    // DONE: $0278
+    0x60, // RTS
+];
+
+const SEGMENT_TABLE: [u8; 16] = [
+    0xBF, 0x86, 0xDB, 0xCF, 0xE6, 0xED, 0xFD, 0x87, 0xFF, 0xEF, 0xF7, 0xFC, 0xB9, 0xDE, 0xF9, 0xF1,
+];
+
+fn boot_keypad(bytes: [u8; 3]) -> Kim1 {
+    let mut kim1 = Kim1::new(&monitor_rom(), &[]);
+    kim1.load(0x0200, &SCAN_PROGRAM);
+    kim1.load(0x0300, &SEGMENT_TABLE);
+    kim1.load(0x0010, &bytes);
+    kim1
+}
+
+fn run_for(kim1: &mut Kim1, cycles: u64) {
+    let cycle = kim1.get_cpu().get_cycles();
+    kim1.run_until(cycle + cycles);
+}
+
+#[test]
+fn test_vectors_mirrored() {
+    let mut kim1 = Kim1::new(&monitor_rom(), &[0xEA; 0x400]);
+    let cpu = kim1.get_cpu();
+    assert_eq!(cpu.read_byte(0xFFFC), 0x00);
+    assert_eq!(cpu.read_byte(0xFFFD), 0x1C);
+    assert_eq!(cpu.read_byte(0x1800), 0xEA);
+
+    cpu.run();
+    assert_eq!(cpu.get_registers().get_pc(), 0x1C00);
+}
+
+#[test]
+fn test_display() {
+    let mut kim1 = boot_keypad([0x1C, 0x00, 0xA9]);
+    run_for(&mut kim1, 50_000);
+
+    assert_eq!(kim1.get_display(), "1C00 A9");
+    assert_eq!(kim1.get_digits(), [0x06, 0x39, 0x3F, 0x3F, 0x77, 0x6F]);
+    assert_eq!(
+        kim1.render_display(),
+        concat!(
+            "     _   _   _    _   _ \n",
+            "  | |   | | | |  |_| |_|\n",
+            "  | |_  |_| |_|  | |  _|",
+        )
+    );
+
+    kim1.load(0x0010, &[0xDE, 0xAD, 0x5F]);
+    run_for(&mut kim1, 50_000);
+    assert_eq!(kim1.get_display(), "DEAD 5F");
+}
+
+#[test]
+fn test_keypad() {
+    let mut kim1 = boot_keypad([0x0; 3]);
+    run_for(&mut kim1, 20_000);
+    assert_eq!(kim1.get_cpu().read_byte(0x20), 0x15);
+
+    for key in [
+        0x0,
+        0x6,
+        0x7,
+        0xA,
+        0xF,
+        Kim1::KEY_AD,
+        Kim1::KEY_DA,
+        Kim1::KEY_PLUS,
+        Kim1::KEY_GO,
+        Kim1::KEY_PC,
+    ] {
+        kim1.press_key(key);
+        run_for(&mut kim1, 20_000);
+        assert_eq!(kim1.get_cpu().read_byte(0x20), key);
+    }
+
+    kim1.release_key();
+    run_for(&mut kim1, 20_000);
+    assert_eq!(kim1.get_cpu().read_byte(0x20), 0x15);
+}
+
+#[test]
+fn test_stop_key() {
+    let mut kim1 = boot_keypad([0x0; 3]);
+    run_for(&mut kim1, 1_000);
+    kim1.set_stop_key(true);
+
+    // The NMI handler is the reset code in ROM.
+    let in_rom = (0..20).any(|_| {
+        kim1.tick();
+        (0x1C00..0x2000).contains(&kim1.get_cpu().get_registers().get_pc())
+    });
+    assert!(in_rom);
+}
+
+#[test]
+fn test_tty_echo() {
+    let terminal = QueueBackend::new();
+    let mut kim1 = Kim1::with_tty(&monitor_rom(), &[], Box::new(terminal.clone()), 1200);
+
+    // The RUBOUT sent after reset is echoed but never reaches the host.
+    terminal.send(b"kim-1\n");
+    run_for(&mut kim1, 400_000);
+    assert_eq!(terminal.take_output(), b"KIM-1\r");
+    assert_eq!(terminal.pending_input(), 0);
+
+    // Pressing RS sends another RUBOUT.
+    kim1.reset();
+    terminal.send(b"0");
+    run_for(&mut kim1, 100_000);
+    assert_eq!(terminal.take_output(), b"0");
+}
+
+#[test]
+fn test_tty_lines() {
+    let terminal = QueueBackend::new();
+    let mut kim1 = Kim1::with_tty(&monitor_rom(), &[], Box::new(terminal.clone()), 1200);
+
+    // The 6530-002 drives the TTY line, and its RAM and the 6530-003's are
+    // separate.
+    kim1.run_until(10_000);
+    let rriot = kim1.get_rriot_002();
+    assert_eq!(rriot.borrow().get_ddrb(), 0x1F);
+    assert_eq!(rriot.borrow().get_port_b() & 0x1, 0x1);
+
+    kim1.get_cpu().write_byte(0x17C0, 0x12);
+    kim1.get_cpu().write_byte(0x1780, 0x34);
+    assert_eq!(rriot.borrow().get_ram()[0x0], 0x12);
+    assert_eq!(kim1.get_rriot_003().borrow_mut().read(0x1780), 0x34);
+}
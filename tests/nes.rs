@@ -0,0 +1,277 @@
+use rem6502::machines::{Cartridge, Mirroring, Nes};
+
+const PRG_LENGTH: usize = 0x4000;
+
+// An NROM-128 image with the given flags 6 and number of CHR banks, and
+// the patches copied into the PRG ROM as seen from $C000. Everything else
+// is NOP.
+fn ines(flags6: u8, chr_banks: u8, patches: &[(u16, &[u8])]) -> Vec<u8> {
+    let mut prg = vec![0xEA; PRG_LENGTH];
+    for (addr, bytes) in patches {
+        let offset = (addr - 0xC000) as usize;
+        prg[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+    let mut image = vec![b'N', b'E', b'S', 0x1A, 0x1, chr_banks, flags6, 0x0];
+    image.resize(0x10, 0x0);
+    image.extend(prg);
+    image.extend(vec![0x0; 0x2000 * chr_banks as usize]);
+    image
+}
+
+fn boot(patches: &[(u16, &[u8])]) -> Nes {
+    let cartridge = Cartridge::from_ines(&ines(0x0, 0x1, patches)).unwrap();
+    Nes::new(&cartridge)
+}
+
+#[test]
+fn test_ines_header() {
+    let cartridge = Cartridge::from_ines(&ines(0x1, 0x0, &[(0xC000, &[0x12])])).unwrap();
+    assert_eq!(cartridge.get_mapper(), 0);
+    assert_eq!(cartridge.get_mirroring(), Mirroring::Vertical);
+    assert_eq!(cartridge.get_prg()[0], 0x12);
+    assert!(cartridge.has_chr_ram());
+    assert_eq!(cartridge.get_chr().len(), Cartridge::CHR_BANK_LENGTH);
+
+    assert!(Cartridge::from_ines(b"NES").is_err());
+    assert!(Cartridge::from_ines(&ines(0x10, 0x1, &[])).is_err());
+    let mut truncated = ines(0x0, 0x1, &[]);
+    truncated.truncate(0x1000);
+    assert!(Cartridge::from_ines(&truncated).is_err());
+}
+
+#[test]
+fn test_memory_map() {
+    let mut nes = boot(&[(0xFFFC, &[0x00, 0xC0]), (0xC000, &[0xAB])]);
+    let cpu = nes.get_cpu();
+    cpu.write_byte(0x0001, 0x42);
+    assert_eq!(cpu.read_byte(0x1801), 0x42);
+    // NROM-128 repeats the 16K PRG ROM at $8000.
+    assert_eq!(cpu.read_byte(0x8000), 0xAB);
+    assert_eq!(cpu.read_byte(0xC000), 0xAB);
+
+    nes.step();
+    assert_eq!(nes.get_cpu().get_registers().get_pc(), 0xC000);
+    assert_eq!(nes.get_cpu().get_registers().get_s(), 0xFD);
+}
+
+// The opening of the reference nestest.log, with the instructions it runs
+// copied to where they are in nestest. It is followed by a stretch worked
+// out by hand in the same format, through the stack, the indexed modes
+// with page crossings and zero page wrapping, unofficial opcodes and an
+// indirect JMP across a page boundary.
+#[test]
+fn test_nestest_log() {
+    let mut nes = boot(&[
+        (0xC000, &[0x4C, 0xF5, 0xC5]),
+        (
+            0xC5F5,
+            &[
+                0xA2, 0x00, 0x86, 0x00, 0x86, 0x10, 0x86, 0x11, 0x20, 0x2D, 0xC7,
+            ],
+        ),
+        (0xC72D, &[0xEA, 0x38, 0xB0, 0x04]),
+        (0xC736, &[0x18, 0xB0, 0x03, 0x4C, 0x40, 0xC7]),
+        (0xC741, &[0x38, 0x90, 0x03, 0x4C, 0x4B, 0xC7]),
+        (0xC74C, &[0x18, 0x90, 0x04]),
+        (0xC754, &[0xA9, 0x00, 0xF0, 0x04]),
+        (
+            0xC75D,
+            &[
+                0xA9, 0xFF, 0x48, 0xA9, 0x00, 0x68, 0x8D, 0x00, 0x04, 0xA2, 0x80, 0xBD, 0x80, 0x03,
+                0x85, 0x10, 0xA9, 0x03, 0x85, 0x11, 0xA0, 0x01, 0xB1, 0x10, 0xB5, 0x90, 0xA7, 0x11,
+                0x87, 0x12, 0xA9, 0x90, 0x8D, 0xFF, 0x02, 0xA9, 0xC7, 0x8D, 0x00, 0x02, 0x6C, 0xFF,
+                0x02,
+            ],
+        ),
+    ]);
+    nes.start_nestest();
+
+    let expected = [
+        "C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7",
+        "C5F5  A2 00     LDX #$00                        A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 30 CYC:10",
+        "C5F7  86 00     STX $00 = 00                    A:00 X:00 Y:00 P:26 SP:FD PPU:  0, 36 CYC:12",
+        "C5F9  86 10     STX $10 = 00                    A:00 X:00 Y:00 P:26 SP:FD PPU:  0, 45 CYC:15",
+        "C5FB  86 11     STX $11 = 00                    A:00 X:00 Y:00 P:26 SP:FD PPU:  0, 54 CYC:18",
+        "C5FD  20 2D C7  JSR $C72D                       A:00 X:00 Y:00 P:26 SP:FD PPU:  0, 63 CYC:21",
+        "C72D  EA        NOP                             A:00 X:00 Y:00 P:26 SP:FB PPU:  0, 81 CYC:27",
+        "C72E  38        SEC                             A:00 X:00 Y:00 P:26 SP:FB PPU:  0, 87 CYC:29",
+        "C72F  B0 04     BCS $C735                       A:00 X:00 Y:00 P:27 SP:FB PPU:  0, 93 CYC:31",
+        "C735  EA        NOP                             A:00 X:00 Y:00 P:27 SP:FB PPU:  0,102 CYC:34",
+        "C736  18        CLC                             A:00 X:00 Y:00 P:27 SP:FB PPU:  0,108 CYC:36",
+        "C737  B0 03     BCS $C73C                       A:00 X:00 Y:00 P:26 SP:FB PPU:  0,114 CYC:38",
+        "C739  4C 40 C7  JMP $C740                       A:00 X:00 Y:00 P:26 SP:FB PPU:  0,120 CYC:40",
+        "C740  EA        NOP                             A:00 X:00 Y:00 P:26 SP:FB PPU:  0,129 CYC:43",
+        "C741  38        SEC                             A:00 X:00 Y:00 P:26 SP:FB PPU:  0,135 CYC:45",
+        "C742  90 03     BCC $C747                       A:00 X:00 Y:00 P:27 SP:FB PPU:  0,141 CYC:47",
+        "C744  4C 4B C7  JMP $C74B                       A:00 X:00 Y:00 P:27 SP:FB PPU:  0,147 CYC:49",
+        "C74B  EA        NOP                             A:00 X:00 Y:00 P:27 SP:FB PPU:  0,156 CYC:52",
+        "C74C  18        CLC                             A:00 X:00 Y:00 P:27 SP:FB PPU:  0,162 CYC:54",
+        "C74D  90 04     BCC $C753                       A:00 X:00 Y:00 P:26 SP:FB PPU:  0,168 CYC:56",
+        "C753  EA        NOP                             A:00 X:00 Y:00 P:26 SP:FB PPU:  0,177 CYC:59",
+        "C754  A9 00     LDA #$00                        A:00 X:00 Y:00 P:26 SP:FB PPU:  0,183 CYC:61",
+        "C756  F0 04     BEQ $C75C                       A:00 X:00 Y:00 P:26 SP:FB PPU:  0,189 CYC:63",
+        "C75C  EA        NOP                             A:00 X:00 Y:00 P:26 SP:FB PPU:  0,198 CYC:66",
+        "C75D  A9 FF     LDA #$FF                        A:00 X:00 Y:00 P:26 SP:FB PPU:  0,204 CYC:68",
+        "C75F  48        PHA                             A:FF X:00 Y:00 P:A4 SP:FB PPU:  0,210 CYC:70",
+        "C760  A9 00     LDA #$00                        A:FF X:00 Y:00 P:A4 SP:FA PPU:  0,219 CYC:73",
+        "C762  68        PLA                             A:00 X:00 Y:00 P:26 SP:FA PPU:  0,225 CYC:75",
+        "C763  8D 00 04  STA $0400 = 00                  A:FF X:00 Y:00 P:A4 SP:FB PPU:  0,237 CYC:79",
+        "C766  A2 80     LDX #$80                        A:FF X:00 Y:00 P:A4 SP:FB PPU:  0,249 CYC:83",
+        "C768  BD 80 03  LDA $0380,X @ 0400 = FF         A:FF X:80 Y:00 P:A4 SP:FB PPU:  0,255 CYC:85",
+        "C76B  85 10     STA $10 = 00                    A:FF X:80 Y:00 P:A4 SP:FB PPU:  0,270 CYC:90",
+        "C76D  A9 03     LDA #$03                        A:FF X:80 Y:00 P:A4 SP:FB PPU:  0,279 CYC:93",
+        "C76F  85 11     STA $11 = 00                    A:03 X:80 Y:00 P:24 SP:FB PPU:  0,285 CYC:95",
+        "C771  A0 01     LDY #$01                        A:03 X:80 Y:00 P:24 SP:FB PPU:  0,294 CYC:98",
+        "C773  B1 10     LDA ($10),Y = 03FF @ 0400 = FF  A:03 X:80 Y:01 P:24 SP:FB PPU:  0,300 CYC:100",
+        "C775  B5 90     LDA $90,X @ 10 = FF             A:FF X:80 Y:01 P:A4 SP:FB PPU:  0,318 CYC:106",
+        "C777  A7 11    *LAX $11 = 03                    A:FF X:80 Y:01 P:A4 SP:FB PPU:  0,330 CYC:110",
+        "C779  87 12    *SAX $12 = 00                    A:03 X:03 Y:01 P:24 SP:FB PPU:  0,339 CYC:113",
+        "C77B  A9 90     LDA #$90                        A:03 X:03 Y:01 P:24 SP:FB PPU:  1,  7 CYC:116",
+        "C77D  8D FF 02  STA $02FF = 00                  A:90 X:03 Y:01 P:A4 SP:FB PPU:  1, 13 CYC:118",
+        "C780  A9 C7     LDA #$C7                        A:90 X:03 Y:01 P:A4 SP:FB PPU:  1, 25 CYC:122",
+        "C782  8D 00 02  STA $0200 = 00                  A:C7 X:03 Y:01 P:A4 SP:FB PPU:  1, 31 CYC:124",
+        "C785  6C FF 02  JMP ($02FF) = C790              A:C7 X:03 Y:01 P:A4 SP:FB PPU:  1, 43 CYC:128",
+        "C790  EA        NOP                             A:C7 X:03 Y:01 P:A4 SP:FB PPU:  1, 58 CYC:133",
+    ];
+    for line in expected {
+        assert_eq!(nes.trace(), line);
+        nes.step();
+    }
+}
+
+#[test]
+fn test_trace_operands() {
+    let mut nes = boot(&[(
+        0xC000,
+        &[
+            0xA1, 0x80, // LDA ($80,X)
+            0xB1, 0x89, // LDA ($89),Y
+            0xBD, 0x00, 0x03, // LDA $0300,X
+            0x8D, 0x15, 0x40, // STA $4015
+            0x6C, 0xFF, 0x02, // JMP ($02FF)
+        ],
+    )]);
+    nes.start_nestest();
+    let cpu = nes.get_cpu();
+    for (addr, val) in [(0x80, 0x00), (0x81, 0x02), (0x89, 0x00), (0x8A, 0x03)] {
+        cpu.write_byte(addr, val);
+    }
+    for (addr, val) in [
+        (0x0200, 0x5A),
+        (0x0300, 0x89),
+        (0x02FF, 0x7E),
+        (0x0301, 0xDB),
+    ] {
+        cpu.write_byte(addr, val);
+    }
+
+    let expected = [
+        "LDA ($80,X) @ 80 = 0200 = 5A",
+        "LDA ($89),Y = 0300 @ 0300 = 89",
+        "LDA $0300,X @ 0300 = 89",
+        "STA $4015 = FF",
+    ];
+    for text in expected {
+        assert_eq!(nes.trace()[16..48].trim_end(), text);
+        nes.step();
+    }
+    // JMP ($02FF) takes the high byte from $0200.
+    assert_eq!(&nes.trace()[16..35], "JMP ($02FF) = 5A7E ");
+
+    // Unofficial opcodes are marked.
+    let mut nes = boot(&[(0xC000, &[0x04, 0xA9])]);
+    nes.start_nestest();
+    assert_eq!(&nes.trace()[6..24], "04 A9    *NOP $A9 ");
+}
+
+#[test]
+fn test_decimal_mode_disabled() {
+    // SED, LDA #$09, CLC, ADC #$01
+    let mut nes = boot(&[(0xC000, &[0xF8, 0xA9, 0x09, 0x18, 0x69, 0x01])]);
+    nes.start_nestest();
+    for _ in 0..4 {
+        nes.step();
+    }
+    assert_eq!(nes.get_cpu().get_registers().get_a(), 0x0A);
+}
+
+#[test]
+fn test_oam_dma() {
+    // LDA #$02, STA $4014
+    let mut nes = boot(&[(0xC000, &[0xA9, 0x02, 0x8D, 0x14, 0x40])]);
+    nes.start_nestest();
+    for i in 0..0x100 {
+        nes.get_cpu().write_byte(0x0200 + i, i as u8 ^ 0xFF);
+    }
+
+    nes.step();
+    nes.step();
+    let cycle = nes.get_cpu().get_cycles();
+    nes.step();
+
+    let oam = *nes.get_ppu().borrow().get_oam();
+    assert_eq!(oam[0x00], 0xFF);
+    assert_eq!(oam[0xFF], 0x00);
+    // The transfer takes 513 or 514 cycles before the NOP's two.
+    let cycles = nes.get_cpu().get_cycles() - cycle;
+    assert!(cycles == 515 || cycles == 516, "{cycles}");
+}
+
+#[test]
+fn test_vblank_nmi() {
+    // LDA #$80, STA $2000, then a loop; the NMI handler at $C100 counts.
+    let mut nes = boot(&[
+        (0xC000, &[0xA9, 0x80, 0x8D, 0x00, 0x20, 0x4C, 0x05, 0xC0]),
+        (0xC100, &[0xE6, 0x10, 0xAD, 0x02, 0x20, 0x40]),
+        (0xFFFA, &[0x00, 0xC1]),
+    ]);
+    nes.start_nestest();
+
+    // Vertical blank starts at scanline 241, 27394 cycles in.
+    nes.run_until(27_000);
+    assert_eq!(nes.get_cpu().read_byte(0x10), 0);
+    nes.run_until(28_000);
+    assert_eq!(nes.get_cpu().read_byte(0x10), 1);
+    // A frame is 29780.67 cycles.
+    nes.run_until(57_000);
+    assert_eq!(nes.get_cpu().read_byte(0x10), 1);
+    nes.run_until(58_000);
+    assert_eq!(nes.get_cpu().read_byte(0x10), 2);
+}
+
+#[test]
+fn test_controllers() {
+    let program: &[u8] = &[
+        0xA9, 0x01, // LDA #$01
+        0x8D, 0x16, 0x40, // STA $4016
+        0xAD, 0x16, 0x40, // LDA $4016
+        0x85, 0x00, // STA $00
+        0xAD, 0x16, 0x40, // LDA $4016
+        0x85, 0x01, // STA $01
+        0xA9, 0x00, // LDA #$00
+        0x8D, 0x16, 0x40, // STA $4016
+        0xA2, 0x00, // LDX #$00
+        0xAD, 0x16, 0x40, // LDA $4016
+        0x95, 0x10, // STA $10,X
+        0xE8, // INX
+        0xE0, 0x09, // CPX #$09
+        0xD0, 0xF6, // BNE $C016
+        0xAD, 0x17, 0x40, // LDA $4017
+        0x85, 0x20, // STA $20
+        0x4C, 0x25, 0xC0, // JMP *
+    ];
+    let mut nes = boot(&[(0xFFFC, &[0x00, 0xC0]), (0xC000, program)]);
+    nes.set_buttons(0, Nes::BUTTON_A | Nes::BUTTON_START | Nes::BUTTON_RIGHT);
+    nes.run_until(1_000);
+
+    let cpu = nes.get_cpu();
+    assert_eq!(cpu.read_byte(0x00), 0x41);
+    assert_eq!(cpu.read_byte(0x01), 0x41);
+    let bits: Vec<u8> = (0x10..0x19).map(|addr| cpu.read_byte(addr) & 0x1).collect();
+    assert_eq!(bits, [1, 0, 0, 1, 0, 0, 0, 1, 1]);
+    assert_eq!(cpu.read_byte(0x20) & 0x1, 0);
+    // Looking at the port does not shift it.
+    assert_eq!(cpu.read_byte(0x4016), 0x41);
+    assert_eq!(cpu.read_byte(0x4017), 0x40);
+    assert_eq!(cpu.read_byte(0x4017), 0x40);
+}
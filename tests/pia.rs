@@ -0,0 +1,206 @@
+mod common;
+
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+use rem6502::{
+    cpu::CPU,
+    devices::{Device, Pia, PiaPeripheral},
+};
+
+use common::load;
+
+const PORT_A: u16 = 0xD010;
+const CRA: u16 = 0xD011;
+const PORT_B: u16 = 0xD012;
+const CRB: u16 = 0xD013;
+
+#[derive(Clone, Default)]
+struct Keyboard {
+    keys: Rc<RefCell<VecDeque<u8>>>,
+}
+
+impl PiaPeripheral for Keyboard {
+    fn strobe(&mut self) -> Option<u8> {
+        self.keys.borrow_mut().pop_front()
+    }
+}
+
+#[derive(Clone, Default)]
+struct Display {
+    output: Rc<RefCell<Vec<u8>>>,
+}
+
+impl PiaPeripheral for Display {
+    fn output(&mut self, val: u8) {
+        self.output.borrow_mut().push(val);
+    }
+}
+
+fn tick(pia: &mut Pia, cycle: &mut u64, cycles: u64) {
+    *cycle += cycles;
+    pia.tick(*cycle);
+}
+
+#[test]
+fn test_ddr_and_output_select() {
+    let mut pia = Pia::new();
+    pia.write(PORT_B, 0xF0);
+    assert_eq!(pia.get_ddrb(), 0xF0);
+
+    pia.write(CRB, 0x04);
+    pia.write(PORT_B, 0x55);
+    pia.set_port_b_input(0x0A);
+    assert_eq!(pia.get_ddrb(), 0xF0);
+    assert_eq!(pia.get_port_b(), 0x5A);
+    assert_eq!(pia.read(PORT_B), 0x5A);
+
+    pia.write(CRB, 0x00);
+    assert_eq!(pia.read(PORT_B), 0xF0);
+}
+
+#[test]
+fn test_ca1_edge_and_irq() {
+    let mut pia = Pia::new();
+    pia.write(CRA, 0x04);
+
+    // Negative edge by default, interrupt disabled.
+    pia.set_ca1(false);
+    assert_eq!(pia.read(CRA), 0x84);
+    assert!(!pia.irq());
+
+    pia.read(PORT_A);
+    assert_eq!(pia.read(CRA), 0x04);
+
+    // Positive edge with IRQA enabled.
+    pia.write(CRA, 0x07);
+    pia.set_ca1(true);
+    assert!(pia.irq());
+    assert!(pia.get_irqa());
+    assert!(!pia.get_irqb());
+    pia.read(PORT_A);
+    assert!(!pia.irq());
+}
+
+#[test]
+fn test_flags_are_read_only() {
+    let mut pia = Pia::new();
+    pia.write(CRB, 0xFF);
+    assert_eq!(pia.read(CRB), 0x3F);
+}
+
+#[test]
+fn test_c2_input_edge() {
+    let mut pia = Pia::new();
+    pia.write(CRB, 0x1C);
+    pia.set_cb2(false);
+    assert_eq!(pia.read(CRB) & 0x40, 0x00);
+
+    pia.set_cb2(true);
+    assert_eq!(pia.read(CRB) & 0x40, 0x40);
+    assert!(pia.get_irqb());
+
+    pia.read(PORT_B);
+    assert!(!pia.get_irqb());
+}
+
+#[test]
+fn test_ca2_handshake() {
+    let mut cycle = 0;
+    let mut pia = Pia::new();
+    pia.write(CRA, 0x24);
+    assert!(pia.get_ca2());
+
+    pia.read(PORT_A);
+    assert!(!pia.get_ca2());
+    tick(&mut pia, &mut cycle, 1);
+    assert!(!pia.get_ca2());
+
+    pia.set_ca1(false);
+    assert!(pia.get_ca2());
+}
+
+#[test]
+fn test_cb2_pulse_and_manual() {
+    let mut cycle = 0;
+    let mut pia = Pia::new();
+    pia.write(CRB, 0x2C);
+    pia.write(PORT_B, 0x12);
+    assert!(!pia.get_cb2());
+    tick(&mut pia, &mut cycle, 1);
+    assert!(pia.get_cb2());
+
+    pia.write(CRB, 0x34);
+    assert!(!pia.get_cb2());
+    pia.write(CRB, 0x3C);
+    assert!(pia.get_cb2());
+}
+
+#[test]
+fn test_peripherals() {
+    let mut cycle = 0;
+    let keyboard = Keyboard::default();
+    let display = Display::default();
+    let mut pia = Pia::new();
+    pia.set_peripheral_a(Box::new(keyboard.clone()));
+    pia.set_peripheral_b(Box::new(display.clone()));
+    keyboard.keys.borrow_mut().extend([0xC1, 0xC2]);
+
+    pia.write(CRA, 0x07);
+    pia.write(PORT_B, 0x7F);
+    pia.write(CRB, 0x04);
+
+    tick(&mut pia, &mut cycle, 1);
+    assert!(pia.irq());
+    tick(&mut pia, &mut cycle, 1);
+    assert_eq!(pia.read(PORT_A), 0xC1);
+
+    // The next key is only strobed in once the previous one was read.
+    tick(&mut pia, &mut cycle, 1);
+    assert_eq!(pia.read(PORT_A), 0xC2);
+    tick(&mut pia, &mut cycle, 1);
+    assert!(!pia.irq());
+
+    pia.set_port_b_input(0x80);
+    pia.write(PORT_B, 0x48);
+    assert_eq!(*display.output.borrow(), vec![0xC8]);
+}
+
+#[test]
+fn test_keyboard_echo_on_cpu() {
+    let keyboard = Keyboard::default();
+    let display = Display::default();
+    let pia = Rc::new(RefCell::new(Pia::new()));
+    pia.borrow_mut()
+        .set_peripheral_a(Box::new(keyboard.clone()));
+    pia.borrow_mut().set_peripheral_b(Box::new(display.clone()));
+    keyboard.keys.borrow_mut().extend(b"HI");
+
+    let mut cpu = CPU::new();
+    cpu.map_device(0xD010..=0xD013, pia.clone());
+    load(
+        &mut cpu,
+        0x1000,
+        &[
+            0xA9, 0x7F, // LDA #$7F
+            0x8D, 0x12, 0xD0, // STA PORT_B (DDRB)
+            0xA9, 0x04, // LDA #$04
+            0x8D, 0x11, 0xD0, // STA CRA
+            0x8D, 0x13, 0xD0, // STA CRB
+            0xA2, 0x00, // LDX #$00
+            0xAD, 0x11, 0xD0, // LDA CRA
+            0x29, 0x80, // AND #$80
+            0xC9, 0x80, // CMP #$80
+            0xD0, 0xF7, // BNE -9
+            0xAD, 0x10, 0xD0, // LDA PORT_A
+            0x8D, 0x12, 0xD0, // STA PORT_B
+            0xE8, // INX
+            0xE0, 0x02, // CPX #$02
+            0xD0, 0xEC, // BNE -20
+        ],
+    );
+    *cpu.get_registers().get_mut_pc() = 0x1000;
+
+    cpu.run_loop();
+
+    assert_eq!(*display.output.borrow(), b"HI".map(|c| c | 0x80));
+}
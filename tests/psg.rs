@@ -0,0 +1,258 @@
+mod common;
+
+use std::{cell::RefCell, rc::Rc};
+
+use rem6502::{
+    cpu::CPU,
+    devices::{Ay38910, Device, Sn76489},
+};
+
+use common::load;
+
+const CLOCK_HZ: u64 = 1_000_000;
+const SAMPLE_RATE: u32 = 44_100;
+
+fn ay_write(ay: &mut Ay38910, reg: u8, val: u8) {
+    ay.write(0xC000, reg);
+    ay.write(0xC001, val);
+}
+
+// Rising edges through the middle of the signal's range.
+fn count_cycles(samples: &[i16]) -> usize {
+    let max = *samples.iter().max().unwrap() as i32;
+    let min = *samples.iter().min().unwrap() as i32;
+    let mid = ((max + min) / 2) as i16;
+    samples
+        .windows(2)
+        .filter(|pair| pair[0] <= mid && pair[1] > mid)
+        .count()
+}
+
+#[test]
+fn test_ay_registers() {
+    let mut ay = Ay38910::new(CLOCK_HZ, CLOCK_HZ);
+    ay_write(&mut ay, 0x1, 0xFF);
+    ay_write(&mut ay, 0xD, 0xFF);
+    ay_write(&mut ay, 0x0, 0x12);
+    assert_eq!(ay.read(0xC001), 0x12);
+    ay.write(0xC000, 0x1);
+    assert_eq!(ay.read(0xC000), 0x0F);
+    assert_eq!(ay.get_registers()[0xD], 0x0F);
+
+    // Out of range addresses deselect the chip.
+    ay_write(&mut ay, 0x10, 0x34);
+    assert_eq!(ay.read(0xC001), 0xFF);
+    assert_eq!(ay.get_writes().len(), 3);
+}
+
+#[test]
+fn test_ay_ports() {
+    let mut ay = Ay38910::new(CLOCK_HZ, CLOCK_HZ);
+    ay.set_port_a_input(0x11);
+    ay.set_port_b_input(0x22);
+    ay_write(&mut ay, 0xE, 0x5A);
+    ay_write(&mut ay, 0xF, 0xA5);
+    assert_eq!(ay.read(0xC000), 0x22);
+    assert_eq!(ay.get_port_a(), 0x11);
+
+    ay_write(&mut ay, 0x7, 0x40);
+    assert_eq!(ay.get_port_a(), 0x5A);
+    assert_eq!(ay.get_port_b(), 0x22);
+    ay.write(0xC000, 0xE);
+    assert_eq!(ay.read(0xC000), 0x5A);
+}
+
+#[test]
+fn test_ay_bus_pins() {
+    let mut ay = Ay38910::new(CLOCK_HZ, CLOCK_HZ);
+    // Latch, write, then read back register 2.
+    ay.set_pins(true, true, 0x02);
+    ay.set_pins(false, false, 0x02);
+    ay.set_pins(true, false, 0x34);
+    ay.set_pins(false, false, 0x34);
+    assert_eq!(ay.get_data(), None);
+    ay.set_pins(false, true, 0xFF);
+    assert_eq!(ay.get_data(), Some(0x34));
+    ay.set_pins(false, false, 0xFF);
+    assert_eq!(ay.get_data(), None);
+    assert_eq!(ay.get_registers()[0x2], 0x34);
+}
+
+#[test]
+fn test_ay_writes_from_cpu() {
+    let ay = Rc::new(RefCell::new(Ay38910::new(CLOCK_HZ, CLOCK_HZ)));
+    let mut cpu = CPU::new();
+    cpu.map_device(0xC000..=0xC001, ay.clone());
+    load(
+        &mut cpu,
+        0x1000,
+        &[
+            0xA9, 0x07, // LDA #$07
+            0x8D, 0x00, 0xC0, // STA ADDRESS
+            0xA9, 0x3E, // LDA #$3E
+            0x8D, 0x01, 0xC0, // STA DATA
+            0xA9, 0x08, // LDA #$08
+            0x8D, 0x00, 0xC0, // STA ADDRESS
+            0xA9, 0x0F, // LDA #$0F
+            0x8D, 0x01, 0xC0, // STA DATA
+        ],
+    );
+    *cpu.get_registers().get_mut_pc() = 0x1000;
+    cpu.run_loop();
+
+    let ay = ay.borrow();
+    let writes = ay.get_writes();
+    assert_eq!(writes.len(), 2);
+    assert_eq!(writes[0].1, (0x07, 0x3E));
+    assert_eq!(writes[1].1, (0x08, 0x0F));
+    assert!(writes[0].0 > 0);
+    assert_eq!(writes[1].0 - writes[0].0, 12);
+}
+
+#[test]
+fn test_ay_tone() {
+    let mut ay = Ay38910::new(CLOCK_HZ, CLOCK_HZ);
+    // 1 MHz / (16 * 125) = 500 Hz on channel A at full volume.
+    ay_write(&mut ay, 0x0, 125);
+    ay_write(&mut ay, 0x7, 0x3E);
+    ay_write(&mut ay, 0x8, 0x0F);
+    ay.tick(CLOCK_HZ);
+
+    let samples = ay.render(SAMPLE_RATE);
+    assert_eq!(samples.len(), SAMPLE_RATE as usize);
+    assert!((499..=501).contains(&count_cycles(&samples)));
+    assert_eq!(*samples.iter().max().unwrap(), i16::MAX / 3);
+    assert_eq!(*samples.iter().min().unwrap(), 0);
+}
+
+#[test]
+fn test_ay_tone_change() {
+    let mut ay = Ay38910::new(CLOCK_HZ, CLOCK_HZ);
+    ay_write(&mut ay, 0x0, 125);
+    ay_write(&mut ay, 0x7, 0x3E);
+    ay_write(&mut ay, 0x8, 0x0F);
+    ay.tick(CLOCK_HZ / 2);
+    // An octave up for the second half second.
+    ay_write(&mut ay, 0x0, 62);
+    ay.tick(CLOCK_HZ);
+
+    let samples = ay.render(SAMPLE_RATE);
+    let half = SAMPLE_RATE as usize / 2;
+    assert!((249..=251).contains(&count_cycles(&samples[..half])));
+    assert!((502..=506).contains(&count_cycles(&samples[half..])));
+}
+
+#[test]
+fn test_ay_envelope() {
+    let mut ay = Ay38910::new(CLOCK_HZ, CLOCK_HZ);
+    // Tone and noise off leave the channel high, so the output follows the
+    // envelope: a 65 ms attack, then held at the top.
+    ay_write(&mut ay, 0x7, 0x3F);
+    ay_write(&mut ay, 0x8, 0x10);
+    ay_write(&mut ay, 0xC, 0x01);
+    ay_write(&mut ay, 0xD, 0x0D);
+    ay.tick(CLOCK_HZ / 10);
+
+    let samples = ay.render(8_000);
+    assert!(samples[0] < 100);
+    assert!(samples.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert!(samples[500..].iter().all(|sample| *sample == i16::MAX / 3));
+
+    // Repeating sawtooth.
+    ay_write(&mut ay, 0xD, 0x08);
+    ay.tick(CLOCK_HZ / 5);
+    let samples = ay.render(8_000);
+    let resets = samples[800..]
+        .windows(2)
+        .filter(|pair| pair[1] as i32 - pair[0] as i32 > 5_000)
+        .count();
+    assert!((1..=2).contains(&resets));
+}
+
+#[test]
+fn test_ay_noise() {
+    let mut ay = Ay38910::new(CLOCK_HZ, CLOCK_HZ);
+    ay_write(&mut ay, 0x6, 0x01);
+    ay_write(&mut ay, 0x7, 0x37);
+    ay_write(&mut ay, 0x8, 0x0F);
+    ay.tick(CLOCK_HZ / 10);
+
+    // Sampled at the noise clock, the output is random bits.
+    let samples = ay.render(62_500);
+    let high = samples.iter().filter(|sample| **sample > 0).count();
+    assert!(high > samples.len() / 4 && high < samples.len() * 3 / 4);
+}
+
+#[test]
+fn test_sn_registers() {
+    let mut sn = Sn76489::new(4 * CLOCK_HZ, CLOCK_HZ);
+    assert_eq!(sn.get_registers(), [0, 0xF, 0, 0xF, 0, 0xF, 0, 0xF]);
+
+    sn.write(0x0, 0x8E);
+    sn.write(0x0, 0x0F);
+    sn.write(0x0, 0x90);
+    sn.write(0x0, 0xC5);
+    sn.write(0x0, 0xE4);
+    sn.write(0x0, 0xFF);
+    // A data byte after a volume latch replaces the low bits.
+    sn.write(0x0, 0x03);
+    assert_eq!(sn.get_registers(), [0xFE, 0x0, 0, 0xF, 0x5, 0xF, 0x4, 0x3]);
+    assert_eq!(sn.read(0x0), 0xFF);
+}
+
+#[test]
+fn test_sn_write_pins() {
+    let mut sn = Sn76489::new(4 * CLOCK_HZ, CLOCK_HZ);
+    sn.tick(10);
+    sn.set_pins(false, 0x9A);
+    sn.set_pins(false, 0x95);
+    sn.set_pins(true, 0x95);
+    sn.tick(20);
+    sn.set_pins(false, 0xB3);
+    assert_eq!(sn.get_writes(), [(10, 0x9A), (20, 0xB3)]);
+    assert_eq!(sn.get_registers()[1], 0xA);
+    assert_eq!(sn.get_registers()[3], 0x3);
+}
+
+#[test]
+fn test_sn_tone() {
+    let mut sn = Sn76489::new(4 * CLOCK_HZ, CLOCK_HZ);
+    // 4 MHz / (32 * 250) = 500 Hz.
+    sn.write(0x0, 0x8A);
+    sn.write(0x0, 0x0F);
+    sn.write(0x0, 0x90);
+    sn.tick(CLOCK_HZ);
+
+    let samples = sn.render(SAMPLE_RATE);
+    assert_eq!(samples.len(), SAMPLE_RATE as usize);
+    assert!((499..=501).contains(&count_cycles(&samples)));
+    assert_eq!(*samples.iter().max().unwrap(), i16::MAX / 4);
+}
+
+#[test]
+fn test_sn_periodic_noise() {
+    let mut sn = Sn76489::new(4 * CLOCK_HZ, CLOCK_HZ);
+    // One pulse every 15 shifts, each 32 steps of 4 MHz / 16.
+    sn.write(0x0, 0xE0);
+    sn.write(0x0, 0xF0);
+    sn.tick(CLOCK_HZ);
+
+    let samples = sn.render(SAMPLE_RATE);
+    assert!((519..=522).contains(&count_cycles(&samples)));
+}
+
+#[test]
+fn test_wav() {
+    let mut sn = Sn76489::new(4 * CLOCK_HZ, CLOCK_HZ);
+    sn.write(0x0, 0x90);
+    sn.tick(CLOCK_HZ / 100);
+
+    let wav = sn.render_wav(8_000);
+    assert_eq!(wav.len(), 44 + 80 * 2);
+    assert_eq!(wav[..4], *b"RIFF");
+    assert_eq!(wav[4..8], (36_u32 + 160).to_le_bytes());
+    assert_eq!(wav[8..16], *b"WAVEfmt ");
+    assert_eq!(wav[24..28], 8_000_u32.to_le_bytes());
+    assert_eq!(wav[36..40], *b"data");
+    assert_eq!(wav[40..44], 160_u32.to_le_bytes());
+}
@@ -0,0 +1,25 @@
+use std::{cell::RefCell, rc::Rc};
+
+use rem6502::{
+    cpu::CPU,
+    devices::{Device, Ram},
+};
+
+#[test]
+fn test_read_write() {
+    let mut ram = Ram::new(0x0200, 0x100);
+    assert_eq!(ram.get_range(), 0x0200..=0x02FF);
+    ram.write(0x0210, 0x5A);
+    assert_eq!(ram.read(0x0210), 0x5A);
+    assert_eq!(ram.get_data()[0x10], 0x5A);
+}
+
+#[test]
+fn test_mirrored_through_range() {
+    let ram = Rc::new(RefCell::new(Ram::new(0x0000, 0x800)));
+    let mut cpu = CPU::new();
+    cpu.map_device(0x0000..=0x1FFF, ram.clone());
+    cpu.write_byte(0x1801, 0x42);
+    assert_eq!(cpu.read_byte(0x0001), 0x42);
+    assert_eq!(ram.borrow().get_data()[0x1], 0x42);
+}
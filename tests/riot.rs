@@ -0,0 +1,158 @@
+use std::{cell::RefCell, rc::Rc};
+
+use rem6502::{
+    cpu::CPU,
+    devices::{Device, Riot},
+};
+
+const SWCHA: u16 = 0x280;
+const SWACNT: u16 = 0x281;
+const INTIM: u16 = 0x284;
+const TIMINT: u16 = 0x285;
+const TIM1T: u16 = 0x294;
+const TIM8T: u16 = 0x295;
+const TIM64T: u16 = 0x296;
+const T1024T: u16 = 0x297;
+
+fn tick(riot: &mut Riot, cycle: &mut u64, cycles: u64) {
+    *cycle += cycles;
+    riot.tick(*cycle);
+}
+
+#[test]
+fn test_ram() {
+    let mut riot = Riot::new();
+
+    riot.write(0x80, 0x12);
+    riot.write(0xFF, 0x34);
+
+    assert_eq!(riot.read(0x180), 0x12);
+    assert_eq!(riot.read(0x7F), 0x34);
+    assert_eq!(riot.get_ram()[0x0], 0x12);
+}
+
+#[test]
+fn test_ports() {
+    let mut riot = Riot::new();
+    riot.write(SWACNT, 0xF0);
+    riot.write(SWCHA, 0xA5);
+    riot.set_port_a_input(0x0C);
+
+    assert_eq!(riot.get_port_a(), 0xAC);
+    assert_eq!(riot.read(SWCHA), 0xAC);
+    assert_eq!(riot.read(SWACNT), 0xF0);
+}
+
+#[test]
+fn test_timer_prescalers() {
+    for (addr, interval) in [(TIM1T, 1), (TIM8T, 8), (TIM64T, 64), (T1024T, 1024)] {
+        let mut cycle = 0;
+        let mut riot = Riot::new();
+        riot.write(addr, 0x03);
+        tick(&mut riot, &mut cycle, 1);
+        assert_eq!(riot.read(INTIM), 0x03);
+
+        tick(&mut riot, &mut cycle, 1);
+        assert_eq!(riot.read(INTIM), 0x02);
+
+        tick(&mut riot, &mut cycle, interval);
+        assert_eq!(riot.read(INTIM), 0x01);
+
+        tick(&mut riot, &mut cycle, interval * 2 - 1);
+        assert_eq!(riot.read(TIMINT) & 0x80, 0x00);
+        assert_eq!(riot.read(INTIM), 0x00);
+
+        tick(&mut riot, &mut cycle, 1);
+        assert_eq!(riot.read(TIMINT) & 0x80, 0x80);
+        assert_eq!(riot.get_timer(), 0xFF);
+    }
+}
+
+#[test]
+fn test_timer_counts_every_cycle_after_underflow() {
+    let mut cycle = 0;
+    let mut riot = Riot::new();
+    riot.write(TIM64T, 0x00);
+    tick(&mut riot, &mut cycle, 2);
+    assert_eq!(riot.get_timer(), 0xFF);
+
+    tick(&mut riot, &mut cycle, 5);
+    assert_eq!(riot.get_timer(), 0xFA);
+
+    assert_eq!(riot.read(INTIM), 0xFA);
+    assert_eq!(riot.read(TIMINT) & 0x80, 0x00);
+
+    tick(&mut riot, &mut cycle, 5);
+    assert_eq!(riot.get_timer(), 0xF5);
+}
+
+#[test]
+fn test_timer_irq() {
+    let mut cycle = 0;
+    let mut riot = Riot::new();
+    riot.write(TIM1T | 0x08, 0x01);
+    tick(&mut riot, &mut cycle, 2);
+    assert!(!riot.irq());
+
+    tick(&mut riot, &mut cycle, 1);
+    assert!(riot.irq());
+
+    riot.read(INTIM);
+    assert!(!riot.irq());
+
+    riot.write(TIM1T, 0x00);
+    tick(&mut riot, &mut cycle, 2);
+    assert_eq!(riot.read(TIMINT) & 0x80, 0x80);
+    assert!(!riot.irq());
+}
+
+#[test]
+fn test_pa7_edge_detect() {
+    let mut riot = Riot::new();
+
+    // Negative edge, interrupt enabled
+    riot.write(0x286, 0x00);
+    riot.set_port_a_input(0x00);
+    assert!(riot.irq());
+    assert_eq!(riot.read(TIMINT) & 0x40, 0x40);
+    assert!(!riot.irq());
+    assert_eq!(riot.read(TIMINT) & 0x40, 0x00);
+
+    // Positive edge, interrupt disabled
+    riot.write(0x285, 0x00);
+    riot.set_port_a_input(0x80);
+    assert!(!riot.irq());
+    assert_eq!(riot.read(TIMINT) & 0x40, 0x40);
+
+    riot.set_port_a_input(0x00);
+    assert_eq!(riot.read(TIMINT) & 0x40, 0x00);
+}
+
+#[test]
+fn test_timer_loop_on_cpu() {
+    let riot = Rc::new(RefCell::new(Riot::new()));
+    let mut cpu = CPU::new();
+    cpu.map_device(0x0080..=0x00FF, riot.clone());
+    cpu.map_device(0x0280..=0x029F, riot.clone());
+
+    // The timer drops to 1 right after the write and reaches 0 one
+    // interval later.
+    let program = [
+        0xA9, 0x02, // LDA #$02
+        0x8D, 0x96, 0x02, // STA TIM64T
+        0xAD, 0x84, 0x02, // LDA INTIM
+        0xC9, 0x00, // CMP #$00
+        0xD0, 0xF9, // BNE -7
+        0x85, 0x80, // STA $80
+    ];
+    for (i, byte) in program.iter().enumerate() {
+        cpu.write_byte(0x1000 + i as u16, *byte);
+    }
+    *cpu.get_registers().get_mut_pc() = 0x1000;
+
+    cpu.run_loop();
+
+    assert!(cpu.get_cycles() > 64);
+    assert!(cpu.get_cycles() < 64 + 30);
+    assert_eq!(riot.borrow().get_ram()[0x0], 0x00);
+}
@@ -0,0 +1,24 @@
+use std::{cell::RefCell, rc::Rc};
+
+use rem6502::{
+    cpu::CPU,
+    devices::{Device, Rom},
+};
+
+#[test]
+fn test_read_only() {
+    let mut rom = Rom::new(0xE000, &[0x11, 0x22, 0x33]);
+    assert_eq!(rom.get_range(), 0xE000..=0xE002);
+    rom.write(0xE001, 0xFF);
+    assert_eq!(rom.read(0xE001), 0x22);
+    assert_eq!(rom.get_data(), [0x11, 0x22, 0x33]);
+}
+
+#[test]
+fn test_mirrored_through_range() {
+    let rom = Rc::new(RefCell::new(Rom::new(0xF000, &[0xA0, 0xA1, 0xA2, 0xA3])));
+    let mut cpu = CPU::new();
+    cpu.map_device(0xF000..=0xFFFF, rom);
+    assert_eq!(cpu.read_byte(0xF005), 0xA1);
+    assert_eq!(cpu.read_byte(0xFFFF), 0xA3);
+}
@@ -0,0 +1,136 @@
+use std::{cell::RefCell, rc::Rc};
+
+use rem6502::{
+    cpu::CPU,
+    devices::{Device, Rriot},
+};
+
+// Addresses of the 6530-003 on the KIM-1.
+const ROM: u16 = 0x1800;
+const RAM: u16 = 0x1780;
+const IO: u16 = 0x1700;
+
+const PAD: u16 = IO;
+const PADD: u16 = IO + 0x1;
+const PBD: u16 = IO + 0x2;
+const PBDD: u16 = IO + 0x3;
+const CLK1T: u16 = IO + 0x4;
+const CLK64T: u16 = IO + 0x6;
+const CLKRDI: u16 = IO + 0x7;
+const CLK8T_IRQ: u16 = IO + 0xD;
+const CLKRDT: u16 = IO + 0x6;
+const CLKRDT_IRQ: u16 = IO + 0xE;
+
+fn tick(rriot: &mut Rriot, cycle: &mut u64, cycles: u64) {
+    *cycle += cycles;
+    rriot.tick(*cycle);
+}
+
+fn rriot() -> Rriot {
+    Rriot::new(&[0x12, 0x34], ROM, RAM, IO)
+}
+
+#[test]
+fn test_ranges() {
+    let rriot = rriot();
+    assert_eq!(rriot.get_rom_range(), 0x1800..=0x1BFF);
+    assert_eq!(rriot.get_ram_range(), 0x1780..=0x17BF);
+    assert_eq!(rriot.get_io_range(), 0x1700..=0x173F);
+}
+
+#[test]
+fn test_rom_and_ram() {
+    let mut rriot = rriot();
+    assert_eq!(rriot.read(ROM), 0x12);
+    assert_eq!(rriot.read(ROM + 0x1), 0x34);
+    assert_eq!(rriot.read(ROM + 0x3FF), 0x0);
+
+    rriot.write(ROM, 0xFF);
+    assert_eq!(rriot.read(ROM), 0x12);
+
+    rriot.write(RAM, 0x56);
+    rriot.write(RAM + 0x3F, 0x78);
+    assert_eq!(rriot.read(RAM), 0x56);
+    assert_eq!(rriot.get_ram()[0x3F], 0x78);
+}
+
+#[test]
+fn test_ports() {
+    let mut rriot = rriot();
+    rriot.write(PADD, 0x7F);
+    rriot.write(PAD, 0x05);
+    rriot.set_port_a_input(0x80);
+    assert_eq!(rriot.read(PAD), 0x85);
+
+    rriot.write(PBDD, 0x01);
+    rriot.write(PBD, 0x00);
+    rriot.set_port_b_input(0xFE);
+    assert_eq!(rriot.get_port_b(), 0xFE);
+
+    // The I/O block repeats every 16 bytes.
+    assert_eq!(rriot.read(PBDD + 0x10), 0x01);
+}
+
+#[test]
+fn test_timer() {
+    let mut cycle = 0;
+    let mut rriot = rriot();
+    rriot.write(CLK64T, 0x02);
+    tick(&mut rriot, &mut cycle, 1);
+    assert_eq!(rriot.read(CLKRDT), 0x02);
+
+    tick(&mut rriot, &mut cycle, 1);
+    assert_eq!(rriot.read(CLKRDT), 0x01);
+
+    tick(&mut rriot, &mut cycle, 128);
+    assert_eq!(rriot.read(CLKRDI), 0x80);
+    assert_eq!(rriot.read(CLKRDT), 0xFF);
+    assert_eq!(rriot.read(CLKRDI), 0x00);
+
+    tick(&mut rriot, &mut cycle, 1);
+    assert_eq!(rriot.get_timer(), 0xFE);
+
+    rriot.write(CLK1T, 0x00);
+    tick(&mut rriot, &mut cycle, 2);
+    assert_eq!(rriot.read(CLKRDI), 0x80);
+    assert!(!rriot.irq());
+}
+
+#[test]
+fn test_timer_irq() {
+    let rriot = Rc::new(RefCell::new(rriot()));
+    let mut cpu = CPU::new();
+    cpu.map_device(rriot.borrow().get_io_range(), rriot.clone());
+
+    // IRQ handler at $0300 reads the timer with interrupts off.
+    cpu.write_byte(0xFFFE, 0x00);
+    cpu.write_byte(0xFFFF, 0x03);
+    let handler = [0xAD, 0x06, 0x17]; // LDA CLKRDT
+    for (i, byte) in handler.iter().enumerate() {
+        cpu.write_byte(0x0300 + i as u16, *byte);
+    }
+
+    rriot.borrow_mut().write(CLK8T_IRQ, 0x01);
+    cpu.write_byte(0x0000, 0x58); // CLI
+    for i in 0x1..0x40 {
+        cpu.write_byte(i, 0xEA); // NOP
+    }
+    // The timer was written from outside the CPU.
+    cpu.update_devices();
+    cpu.run();
+    while cpu.get_registers().get_pc() < 0x0300 {
+        cpu.run();
+        assert!(cpu.get_cycles() < 100);
+    }
+    assert!(rriot.borrow().irq());
+
+    cpu.run();
+    assert!(!rriot.borrow().irq());
+
+    let mut cycle = cpu.get_cycles();
+    rriot.borrow_mut().write(CLK8T_IRQ, 0x01);
+    tick(&mut rriot.borrow_mut(), &mut cycle, 20);
+    assert!(rriot.borrow().irq());
+    rriot.borrow_mut().read(CLKRDT_IRQ);
+    assert!(!rriot.borrow().irq());
+}
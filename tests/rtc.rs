@@ -0,0 +1,194 @@
+mod common;
+
+use std::{cell::RefCell, rc::Rc};
+
+use rem6502::{
+    cpu::CPU,
+    devices::{Device, Rng, Rtc, TimeSource},
+};
+
+use common::load;
+
+const CPU_HZ: u64 = 1_000_000;
+const RTC: u16 = 0xD000;
+
+// 2024-02-29 23:59:58, a Thursday.
+const LEAP_DAY: u64 = 1_709_251_198;
+
+fn read_time(rtc: &mut Rtc) -> Vec<u8> {
+    (0..8).map(|reg| rtc.read(RTC + reg)).collect()
+}
+
+#[test]
+fn test_fixed_bcd() {
+    let mut rtc = Rtc::new(TimeSource::Fixed(LEAP_DAY), CPU_HZ);
+    assert_eq!(
+        read_time(&mut rtc),
+        [0x58, 0x59, 0x23, 0x04, 0x29, 0x02, 0x24, 0x20]
+    );
+
+    // Two seconds later it is March, and a Friday.
+    rtc.tick(2 * CPU_HZ);
+    assert_eq!(rtc.get_time(), LEAP_DAY + 2);
+    assert_eq!(
+        read_time(&mut rtc),
+        [0x00, 0x00, 0x00, 0x05, 0x01, 0x03, 0x24, 0x20]
+    );
+}
+
+#[test]
+fn test_binary_and_unix() {
+    let mut rtc = Rtc::new(TimeSource::Fixed(LEAP_DAY), CPU_HZ);
+    rtc.write(RTC + 0x8, 0x01);
+    assert!(rtc.is_binary());
+    assert_eq!(read_time(&mut rtc), [58, 59, 23, 4, 29, 2, 24, 20]);
+
+    let unix: Vec<u8> = (0x9..=0xC).map(|reg| rtc.read(RTC + reg)).collect();
+    assert_eq!(unix, (LEAP_DAY as u32).to_le_bytes());
+    // Registers mirror every 16 bytes.
+    assert_eq!(rtc.read(RTC + 0x16), 24);
+}
+
+#[test]
+fn test_snapshot() {
+    let mut rtc = Rtc::new(TimeSource::Fixed(LEAP_DAY), CPU_HZ);
+    assert_eq!(rtc.read(RTC), 0x58);
+    rtc.tick(2 * CPU_HZ);
+    // Still the time latched by the seconds read.
+    assert_eq!(rtc.read(RTC + 0x2), 0x23);
+    assert_eq!(rtc.read(RTC), 0x00);
+    assert_eq!(rtc.read(RTC + 0x2), 0x00);
+}
+
+#[test]
+fn test_set_time() {
+    let mut rtc = Rtc::new(TimeSource::Fixed(0), CPU_HZ);
+    assert_eq!(
+        read_time(&mut rtc),
+        [0x00, 0x00, 0x00, 0x04, 0x01, 0x01, 0x70, 0x19]
+    );
+
+    for (reg, val) in [
+        (7, 0x19),
+        (6, 0x99),
+        (5, 0x12),
+        (4, 0x31),
+        (2, 0x23),
+        (1, 0x59),
+        (0, 0x59),
+    ] {
+        rtc.write(RTC + reg, val);
+    }
+    assert_eq!(
+        read_time(&mut rtc),
+        [0x59, 0x59, 0x23, 0x05, 0x31, 0x12, 0x99, 0x19]
+    );
+
+    // The set time keeps running.
+    rtc.tick(CPU_HZ);
+    assert_eq!(
+        read_time(&mut rtc),
+        [0x00, 0x00, 0x00, 0x06, 0x01, 0x01, 0x00, 0x20]
+    );
+    assert_eq!(rtc.get_time(), 946_684_800);
+
+    for (i, byte) in (LEAP_DAY as u32).to_le_bytes().iter().enumerate() {
+        rtc.write(RTC + 0x9 + i as u16, *byte);
+    }
+    assert_eq!(rtc.get_time(), LEAP_DAY);
+}
+
+#[test]
+fn test_host_time() {
+    let mut rtc = Rtc::new(TimeSource::Host, CPU_HZ);
+    let time = read_time(&mut rtc);
+    assert_eq!(time[7], 0x20);
+    assert!(time[6] >= 0x24);
+    assert!(rtc.get_time() >= LEAP_DAY);
+}
+
+#[test]
+fn test_rtc_from_cpu() {
+    let rtc = Rc::new(RefCell::new(Rtc::new(TimeSource::Fixed(LEAP_DAY), CPU_HZ)));
+    let mut cpu = CPU::new();
+    cpu.map_device(RTC..=RTC + 0xF, rtc.clone());
+    load(
+        &mut cpu,
+        0x1000,
+        &[
+            0xAD, 0x00, 0xD0, // LDA SECONDS
+            0x85, 0x10, // STA $10
+            0xAD, 0x02, 0xD0, // LDA HOURS
+            0x85, 0x11, // STA $11
+            0xAD, 0x06, 0xD0, // LDA YEAR
+            0x85, 0x12, // STA $12
+        ],
+    );
+    *cpu.get_registers().get_mut_pc() = 0x1000;
+    cpu.run_loop();
+    assert_eq!(cpu.read_byte(0x10), 0x58);
+    assert_eq!(cpu.read_byte(0x11), 0x23);
+    assert_eq!(cpu.read_byte(0x12), 0x24);
+}
+
+#[test]
+fn test_rng_seeded() {
+    let mut rng = Rng::new(0);
+    // First SplitMix64 output for seed 0.
+    let first: Vec<u8> = (0..8).map(|_| rng.next_byte()).collect();
+    assert_eq!(first, 0xE220_A839_7B1D_CDAF_u64.to_le_bytes());
+
+    let mut a = Rng::new(1234);
+    let mut b = Rng::new(1234);
+    let mut c = Rng::new(1235);
+    let a: Vec<u8> = (0..64).map(|_| a.read(0x0)).collect();
+    let b: Vec<u8> = (0..64).map(|_| b.read(0x5)).collect();
+    let c: Vec<u8> = (0..64).map(|_| c.read(0x0)).collect();
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+}
+
+#[test]
+fn test_rng_reseed() {
+    let mut rng = Rng::new(0);
+    rng.read(0x0);
+    rng.write(0x0, 0x12);
+    rng.write(0x0, 0x34);
+    assert_eq!(rng.get_seed(), 0x1234);
+    let reseeded: Vec<u8> = (0..16).map(|_| rng.read(0x0)).collect();
+
+    rng.set_seed(0x1234);
+    let fresh: Vec<u8> = (0..16).map(|_| rng.next_byte()).collect();
+    assert_eq!(reseeded, fresh);
+}
+
+#[test]
+fn test_rng_from_cpu() {
+    let rng = Rc::new(RefCell::new(Rng::new(42)));
+    let mut cpu = CPU::new();
+    cpu.map_device(0xD100..=0xD100, rng.clone());
+    load(
+        &mut cpu,
+        0x1000,
+        &[
+            0xAD, 0x00, 0xD1, // LDA RNG
+            0x85, 0x10, // STA $10
+            0xAD, 0x00, 0xD1, // LDA RNG
+            0x85, 0x11, // STA $11
+        ],
+    );
+    *cpu.get_registers().get_mut_pc() = 0x1000;
+    cpu.run_loop();
+
+    let mut expected = Rng::new(42);
+    assert_eq!(cpu.read_byte(0x10), expected.next_byte());
+    assert_eq!(cpu.read_byte(0x11), expected.next_byte());
+
+    // Bytes are spread over the whole range.
+    let mut rng = rng.borrow_mut();
+    let mut seen = [false; 256];
+    for _ in 0..4096 {
+        seen[rng.next_byte() as usize] = true;
+    }
+    assert!(seen.iter().all(|seen| *seen));
+}
@@ -0,0 +1,117 @@
+use std::{cell::RefCell, rc::Rc};
+
+use rem6502::{cpu::CPU, devices::Via};
+
+fn nop_loop() -> CPU {
+    let mut cpu = CPU::new();
+
+    // JMP $0000
+    cpu.write_byte(0x0, 0x4C);
+    cpu.write_byte(0x1, 0x00);
+    cpu.write_byte(0x2, 0x00);
+
+    cpu
+}
+
+#[test]
+fn test_events_fire_in_order() {
+    let mut cpu = nop_loop();
+    let fired = Rc::new(RefCell::new(vec![]));
+
+    for (cycle, name) in [(40, "b"), (10, "a"), (40, "c")] {
+        let fired = fired.clone();
+        cpu.get_scheduler().schedule(cycle, move |cpu| {
+            fired.borrow_mut().push((name, cpu.get_cycles()));
+        });
+    }
+
+    cpu.run_until(100);
+
+    assert_eq!(*fired.borrow(), vec![("a", 10), ("b", 40), ("c", 40)]);
+    assert_eq!(cpu.get_cycles(), 100);
+    assert!(cpu.get_scheduler().is_empty());
+}
+
+#[test]
+fn test_cancel() {
+    let mut cpu = nop_loop();
+    let fired = Rc::new(RefCell::new(0));
+
+    let counter = fired.clone();
+    let scheduler = cpu.get_scheduler();
+    let id = scheduler.schedule(10, move |_| *counter.borrow_mut() += 1);
+    assert!(scheduler.is_scheduled(id));
+    assert!(scheduler.cancel(id));
+    assert!(!scheduler.cancel(id));
+
+    cpu.run_until(50);
+
+    assert_eq!(*fired.borrow(), 0);
+    assert_eq!(cpu.get_scheduler().get_next_due(), None);
+}
+
+#[test]
+fn test_periodic_event() {
+    fn timer(fired: Rc<RefCell<Vec<u64>>>, period: u64) -> impl FnOnce(&mut CPU) {
+        move |cpu| {
+            fired.borrow_mut().push(cpu.get_cycles());
+            let next = cpu.get_cycles() + period;
+            cpu.get_scheduler().schedule(next, timer(fired, period));
+        }
+    }
+
+    let mut cpu = nop_loop();
+    let fired = Rc::new(RefCell::new(vec![]));
+
+    cpu.get_scheduler().schedule(25, timer(fired.clone(), 25));
+    cpu.run_until(110);
+
+    assert_eq!(*fired.borrow(), vec![25, 50, 75, 100]);
+    assert_eq!(cpu.get_scheduler().get_next_due(), Some(125));
+}
+
+#[test]
+fn test_event_can_drive_cpu() {
+    let mut cpu = nop_loop();
+    cpu.write_byte(0xFFFE, 0x00);
+    cpu.write_byte(0xFFFF, 0x40);
+    cpu.write_byte(0x4000, 0xE8);
+    cpu.write_byte(0x4001, 0x4C);
+    cpu.write_byte(0x4002, 0x01);
+    cpu.write_byte(0x4003, 0x40);
+
+    cpu.get_scheduler().schedule(30, |cpu| cpu.set_irq(true));
+    cpu.get_scheduler().schedule(60, |cpu| cpu.set_irq(false));
+    cpu.run_until(200);
+
+    assert!(cpu.get_registers().get_pc() >= 0x4000);
+    assert!(cpu.get_registers().get_x() > 0);
+}
+
+#[test]
+fn test_device_event() {
+    let mut cpu = nop_loop();
+    let via = Rc::new(RefCell::new(Via::new()));
+    cpu.map_device(0x8000..=0x800F, via.clone());
+    cpu.write_byte(0xFFFE, 0x00);
+    cpu.write_byte(0xFFFF, 0x40);
+    // INX; JMP $4001
+    cpu.write_byte(0x4000, 0xE8);
+    cpu.write_byte(0x4001, 0x4C);
+    cpu.write_byte(0x4002, 0x01);
+    cpu.write_byte(0x4003, 0x40);
+
+    // T1 one-shot of $10 with its interrupt enabled. The program never
+    // touches the VIA, so only the event brings it up to date.
+    cpu.write_byte(0x800E, 0xC0);
+    cpu.write_byte(0x8004, 0x10);
+    cpu.write_byte(0x8005, 0x00);
+    assert_eq!(cpu.get_scheduler().get_next_due(), Some(18));
+
+    cpu.run_until(17);
+    assert_eq!(cpu.get_registers().get_x(), 0);
+    cpu.run_until(40);
+    assert_eq!(cpu.get_registers().get_x(), 1);
+    assert_eq!(via.borrow().get_t1(), 0xFFFF - 22);
+    assert_eq!(cpu.get_scheduler().get_next_due(), None);
+}
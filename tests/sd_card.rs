@@ -0,0 +1,346 @@
+use std::{cell::RefCell, io::Cursor, rc::Rc};
+
+use rem6502::{
+    cpu::CPU,
+    devices::{SdCard, Spi, SpiDevice, SpiPins, Via},
+};
+
+const CS: u8 = 0x01;
+const SCLK: u8 = 0x02;
+const MOSI: u8 = 0x04;
+const MISO: u8 = 0x80;
+
+const PINS: SpiPins = SpiPins {
+    cs: CS,
+    sclk: SCLK,
+    mosi: MOSI,
+    miso: MISO,
+};
+
+// Answers each byte with its complement during the next one.
+struct Echo {
+    next: u8,
+    selects: usize,
+}
+
+impl SpiDevice for Echo {
+    fn select(&mut self) {
+        self.selects += 1;
+    }
+
+    fn transfer(&mut self, mosi: u8) -> u8 {
+        self.next = !mosi;
+        self.next
+    }
+}
+
+type Card = SdCard<Cursor<Vec<u8>>>;
+
+// Bit-bangs the bus the way a 6502 driver would.
+struct Master {
+    spi: Spi,
+    mode: u8,
+}
+
+impl Master {
+    fn new(mode: u8, device: Rc<RefCell<dyn SpiDevice>>) -> Self {
+        let mut spi = Spi::new(mode, PINS, device);
+        spi.set_pins(true, mode & 0x2 != 0, true);
+        Self { spi, mode }
+    }
+
+    fn select(&mut self, selected: bool) {
+        self.spi.set_pins(!selected, self.mode & 0x2 != 0, true);
+    }
+
+    fn exchange(&mut self, val: u8) -> u8 {
+        let idle = self.mode & 0x2 != 0;
+        let cpha = self.mode & 0x1 != 0;
+        let mut result = 0;
+        for bit in (0..8).rev() {
+            let mosi = val & (1 << bit) != 0;
+            if cpha {
+                self.spi.set_pins(false, !idle, mosi);
+                self.spi.set_pins(false, idle, mosi);
+                result = (result << 1) | self.spi.get_miso() as u8;
+            } else {
+                self.spi.set_pins(false, idle, mosi);
+                result = (result << 1) | self.spi.get_miso() as u8;
+                self.spi.set_pins(false, !idle, mosi);
+            }
+        }
+        self.spi.set_pins(false, idle, true);
+        result
+    }
+
+    // Sends a command and polls for the R1 response.
+    fn command(&mut self, cmd: u8, arg: u32, crc: u8) -> u8 {
+        self.exchange(0xFF);
+        self.exchange(0x40 | cmd);
+        for byte in arg.to_be_bytes() {
+            self.exchange(byte);
+        }
+        self.exchange(crc);
+        for _ in 0..8 {
+            let r1 = self.exchange(0xFF);
+            if r1 != 0xFF {
+                return r1;
+            }
+        }
+        0xFF
+    }
+
+    fn response(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.exchange(0xFF)).collect()
+    }
+
+    fn init(&mut self) {
+        self.select(false);
+        for _ in 0..10 {
+            self.exchange(0xFF);
+        }
+        self.select(true);
+        assert_eq!(self.command(0, 0, 0x95), 0x01);
+        assert_eq!(self.command(8, 0x1AA, 0x87), 0x01);
+        assert_eq!(self.response(4), [0x00, 0x00, 0x01, 0xAA]);
+        assert_eq!(self.command(55, 0, 0x01), 0x01);
+        assert_eq!(self.command(41, 0x4000_0000, 0x01), 0x00);
+    }
+
+    // Skips to the start token and returns the block and its CRC.
+    fn read_data(&mut self) -> (Vec<u8>, u16) {
+        while self.exchange(0xFF) != 0xFE {}
+        let data = (0..512).map(|_| self.exchange(0xFF)).collect();
+        let crc = u16::from_be_bytes([self.exchange(0xFF), self.exchange(0xFF)]);
+        (data, crc)
+    }
+
+    fn write_data(&mut self, token: u8, data: &[u8]) -> u8 {
+        self.exchange(token);
+        for byte in data {
+            self.exchange(*byte);
+        }
+        self.exchange(0xFF);
+        self.exchange(0xFF);
+        let response = self.exchange(0xFF);
+        while self.exchange(0xFF) != 0xFF {}
+        response & 0x1F
+    }
+}
+
+fn card(blocks: usize) -> Rc<RefCell<Card>> {
+    let image = (0..blocks * 512).map(|i| (i / 512) as u8).collect();
+    Rc::new(RefCell::new(SdCard::new(Cursor::new(image)).unwrap()))
+}
+
+#[test]
+fn test_spi_modes() {
+    for mode in 0..4 {
+        let echo = Rc::new(RefCell::new(Echo {
+            next: 0xFF,
+            selects: 0,
+        }));
+        let mut master = Master::new(mode, echo.clone());
+        assert!(master.spi.get_miso());
+        master.select(true);
+        assert_eq!(master.exchange(0x5A), 0xFF, "mode {}", mode);
+        assert_eq!(master.exchange(0x0F), 0xA5, "mode {}", mode);
+        assert_eq!(master.exchange(0x00), 0xF0, "mode {}", mode);
+        master.select(false);
+        assert!(master.spi.get_miso());
+        assert_eq!(echo.borrow().selects, 1);
+    }
+}
+
+#[test]
+fn test_spi_ignores_clock_while_deselected() {
+    let echo = Rc::new(RefCell::new(Echo {
+        next: 0xFF,
+        selects: 0,
+    }));
+    let mut master = Master::new(0, echo.clone());
+    master.spi.set_pins(true, true, false);
+    master.spi.set_pins(true, false, false);
+    master.select(true);
+    assert_eq!(master.exchange(0x12), 0xFF);
+    assert_eq!(master.exchange(0xFF), 0xED);
+    assert_eq!(echo.borrow().next, 0x00);
+}
+
+#[test]
+fn test_sd_init() {
+    let card = card(4);
+    let mut master = Master::new(0, card.clone());
+    master.select(true);
+
+    // Nothing happens until CMD0.
+    assert_eq!(master.command(8, 0x1AA, 0x87), 0xFF);
+    master.init();
+    assert_eq!(master.command(58, 0, 0x01), 0x00);
+    assert_eq!(master.response(4), [0x80, 0xFF, 0x80, 0x00]);
+    assert_eq!(master.command(16, 512, 0x01), 0x00);
+    assert_eq!(master.command(16, 1024, 0x01), 0x40);
+    assert_eq!(master.command(9, 0, 0x01), 0x04);
+    assert_eq!(card.borrow().get_blocks(), 4);
+    assert!(!card.borrow().is_high_capacity());
+}
+
+#[test]
+fn test_sd_read_block() {
+    let card = card(4);
+    let mut master = Master::new(0, card.clone());
+    master.init();
+
+    assert_eq!(master.command(17, 2 * 512, 0x01), 0x00);
+    let (data, crc) = master.read_data();
+    assert_eq!(data, [0x02; 512]);
+    // CRC16-CCITT of 512 bytes of 0x02.
+    let mut expected: u16 = 0;
+    for _ in 0..512 {
+        expected ^= 0x02 << 8;
+        for _ in 0..8 {
+            expected = match expected & 0x8000 {
+                0 => expected << 1,
+                _ => (expected << 1) ^ 0x1021,
+            };
+        }
+    }
+    assert_eq!(crc, expected);
+
+    // Byte addresses must be block aligned, and within the image.
+    assert_eq!(master.command(17, 100, 0x01), 0x20);
+    assert_eq!(master.command(17, 4 * 512, 0x01), 0x40);
+}
+
+#[test]
+fn test_sd_read_multiple() {
+    let card = card(4);
+    let mut master = Master::new(0, card.clone());
+    master.init();
+
+    assert_eq!(master.command(18, 512, 0x01), 0x00);
+    assert_eq!(master.read_data().0, [0x01; 512]);
+    assert_eq!(master.read_data().0, [0x02; 512]);
+    assert_eq!(master.read_data().0, [0x03; 512]);
+    assert_eq!(master.command(12, 0, 0x01), 0x00);
+    while master.exchange(0xFF) != 0xFF {}
+    assert_eq!(master.command(13, 0, 0x01), 0x04);
+}
+
+#[test]
+fn test_sd_write_blocks() {
+    let card = card(4);
+    let mut master = Master::new(0, card.clone());
+    master.init();
+
+    assert_eq!(master.command(24, 512, 0x01), 0x00);
+    assert_eq!(master.write_data(0xFE, &[0xAB; 512]), 0x05);
+    assert_eq!(master.command(17, 512, 0x01), 0x00);
+    assert_eq!(master.read_data().0, [0xAB; 512]);
+
+    assert_eq!(master.command(25, 2 * 512, 0x01), 0x00);
+    assert_eq!(master.write_data(0xFC, &[0xC2; 512]), 0x05);
+    assert_eq!(master.write_data(0xFC, &[0xC3; 512]), 0x05);
+    master.exchange(0xFD);
+    master.exchange(0xFF);
+    while master.exchange(0xFF) != 0xFF {}
+    master.select(false);
+
+    let card = card.borrow();
+    let image = card.get_storage().get_ref();
+    assert_eq!(image[..512], [0x00; 512]);
+    assert_eq!(image[512..1024], [0xAB; 512]);
+    assert_eq!(image[1024..1536], [0xC2; 512]);
+    assert_eq!(image[1536..], [0xC3; 512]);
+}
+
+#[test]
+fn test_sd_deselect_aborts_command() {
+    let card = card(2);
+    let mut master = Master::new(0, card.clone());
+    master.init();
+
+    master.exchange(0x40 | 17);
+    master.exchange(0x00);
+    master.select(false);
+    master.select(true);
+    assert_eq!(master.command(17, 0, 0x01), 0x00);
+    assert_eq!(master.read_data().0, [0x00; 512]);
+}
+
+#[test]
+fn test_spi_driven_from_via() {
+    let via = Rc::new(RefCell::new(Via::new()));
+    let echo = Rc::new(RefCell::new(Echo {
+        next: 0xFF,
+        selects: 0,
+    }));
+    let mut spi = Spi::new(0, PINS, echo.clone());
+    let mut cpu = CPU::new();
+    cpu.map_device(0x6000..=0x600F, via.clone());
+
+    // CS, SCLK and MOSI on PA0-PA2 and MISO on PA7, exchanging two bytes.
+    let program = [
+        0xA9, 0x01, // LDA #CS
+        0x8D, 0x01, 0x60, // STA PORTA
+        0xA9, 0x07, // LDA #$07
+        0x8D, 0x03, 0x60, // STA DDRA
+        0xA9, 0x00, // LDA #$00
+        0x8D, 0x01, 0x60, // STA PORTA
+        0xA9, 0x40, // LDA #$40
+        0x20, 0x25, 0x10, // JSR spi_byte
+        0x85, 0x10, // STA $10
+        0xA9, 0xFF, // LDA #$FF
+        0x20, 0x25, 0x10, // JSR spi_byte
+        0x85, 0x11, // STA $11
+        0xA9, 0x01, // LDA #CS
+        0x8D, 0x01, 0x60, // STA PORTA
+        // end: $1022
+        0x4C, 0x22, 0x10, // JMP end
+        // spi_byte: $1025
+        0x85, 0x00, // STA $00
+        0xA9, 0x00, // LDA #$00
+        0x85, 0x01, // STA $01
+        0xA2, 0x08, // LDX #$08
+        // bit: $102D
+        0xA9, 0x00, // LDA #$00
+        0x06, 0x00, // ASL $00
+        0x90, 0x02, // BCC low
+        0xA9, 0x04, // LDA #MOSI
+        // low: $1035
+        0x8D, 0x01, 0x60, // STA PORTA
+        0x09, 0x02, // ORA #SCLK
+        0x8D, 0x01, 0x60, // STA PORTA
+        0x06, 0x01, // ASL $01
+        0xAD, 0x01, 0x60, // LDA PORTA
+        0x29, 0x80, // AND #MISO
+        0xF0, 0x02, // BEQ zero
+        0xE6, 0x01, // INC $01
+        // zero: $1048
+        0xCA, // DEX
+        0xE0, 0x00, // CPX #$00
+        0xD0, 0xE0, // BNE bit
+        0xA9, 0x00, // LDA #$00
+        0x8D, 0x01, 0x60, // STA PORTA
+        0xA5, 0x01, // LDA $01
+        0x60, // RTS
+    ];
+    for (i, byte) in program.iter().enumerate() {
+        cpu.write_byte(0x1000 + i as u16, *byte);
+    }
+    *cpu.get_registers().get_mut_pc() = 0x1000;
+
+    for _ in 0..2_000 {
+        cpu.tick();
+        let mut via = via.borrow_mut();
+        spi.set_port(via.get_port_a());
+        let input = spi.drive_port(0xFF);
+        via.set_port_a_input(input);
+    }
+
+    assert_eq!(cpu.read_byte(0x10), 0xFF);
+    assert_eq!(cpu.read_byte(0x11), 0xBF);
+    assert_eq!(echo.borrow().next, 0x00);
+    assert_eq!(echo.borrow().selects, 1);
+    assert!(spi.get_miso());
+}
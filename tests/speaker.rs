@@ -0,0 +1,78 @@
+mod common;
+
+use std::{cell::RefCell, rc::Rc};
+
+use rem6502::{
+    cpu::CPU,
+    devices::{Device, Speaker},
+    wav::decode_wav,
+};
+
+use common::load;
+
+const CPU_HZ: u64 = 1_000_000;
+const SPEAKER: u16 = 0xC030;
+
+#[test]
+fn test_toggle_from_cpu() {
+    let speaker = Rc::new(RefCell::new(Speaker::new(CPU_HZ)));
+    let mut cpu = CPU::new();
+    cpu.map_device(SPEAKER..=SPEAKER, speaker.clone());
+    load(
+        &mut cpu,
+        0x1000,
+        &[
+            0xAD, 0x30, 0xC0, // LDA SPEAKER
+            0xAD, 0x30, 0xC0, // LDA SPEAKER
+            0x8D, 0x30, 0xC0, // STA SPEAKER
+        ],
+    );
+    *cpu.get_registers().get_mut_pc() = 0x1000;
+    cpu.run_loop();
+    // Looking at the speaker from outside does not click it.
+    assert_eq!(cpu.read_byte(SPEAKER), 0x0);
+
+    let speaker = speaker.borrow();
+    let toggles = speaker.get_toggles();
+    assert_eq!(toggles.len(), 3);
+    assert_eq!(toggles[1] - toggles[0], 4);
+    assert!(toggles[2] > toggles[1]);
+    assert!(speaker.get_level());
+}
+
+#[test]
+fn test_render_square_wave() {
+    let mut speaker = Speaker::new(CPU_HZ);
+    // A toggle every 500 cycles is a 1 kHz tone.
+    for i in 1..=2000 {
+        speaker.tick(i * 500);
+        speaker.read(SPEAKER);
+    }
+    assert_eq!(speaker.get_toggles().len(), 2000);
+
+    let samples = speaker.render(44_100);
+    assert_eq!(samples.len(), 44_100);
+    let rising = samples
+        .windows(2)
+        .filter(|pair| pair[0] < i16::MAX / 2 && pair[1] >= i16::MAX / 2)
+        .count();
+    assert!((999..=1000).contains(&rising));
+    assert_eq!(*samples.iter().max().unwrap(), i16::MAX);
+    assert_eq!(*samples.iter().min().unwrap(), 0);
+}
+
+#[test]
+fn test_render_wav() {
+    let mut speaker = Speaker::new(CPU_HZ);
+    speaker.tick(100);
+    speaker.write(SPEAKER, 0x0);
+    speaker.tick(CPU_HZ / 10);
+
+    let (sample_rate, samples) = decode_wav(&speaker.render_wav(8_000)).unwrap();
+    assert_eq!(sample_rate, 8_000);
+    assert_eq!(samples, speaker.render(8_000));
+    assert_eq!(samples.len(), 800);
+    // The first sample is high for its last fifth.
+    assert_eq!(samples[0], (i16::MAX as f32 * 0.2) as i16);
+    assert!(samples[1..].iter().all(|sample| *sample == i16::MAX));
+}
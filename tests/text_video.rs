@@ -0,0 +1,216 @@
+mod common;
+
+use std::{cell::RefCell, rc::Rc};
+
+use rem6502::{
+    cpu::CPU,
+    devices::{Device, TextVideo},
+};
+
+use common::load;
+
+const SCREEN: u16 = 0x0400;
+
+fn pixel(rgb: &[u8], width: usize, x: usize, y: usize) -> [u8; 3] {
+    let i = (y * width + x) * 3;
+    [rgb[i], rgb[i + 1], rgb[i + 2]]
+}
+
+// Pulls the image data back out of the stored deflate blocks.
+fn decode_png(png: &[u8]) -> (usize, usize, Vec<u8>) {
+    assert_eq!(png[..8], [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]);
+    let mut pos = 8;
+    let mut size = (0, 0);
+    let mut idat = vec![];
+    while pos < png.len() {
+        let len = u32::from_be_bytes(png[pos..pos + 4].try_into().unwrap()) as usize;
+        let kind = &png[pos + 4..pos + 8];
+        let data = &png[pos + 8..pos + 8 + len];
+        match kind {
+            b"IHDR" => {
+                size = (
+                    u32::from_be_bytes(data[..4].try_into().unwrap()) as usize,
+                    u32::from_be_bytes(data[4..8].try_into().unwrap()) as usize,
+                );
+                assert_eq!(data[8..], [8, 2, 0, 0, 0]);
+            }
+            b"IDAT" => idat.extend_from_slice(data),
+            b"IEND" => assert_eq!(png[pos + 8..pos + 12], [0xAE, 0x42, 0x60, 0x82]),
+            _ => panic!("unexpected chunk"),
+        }
+        pos += len + 12;
+    }
+
+    let mut raw = vec![];
+    let mut pos = 2;
+    loop {
+        let last = idat[pos] & 0x1 != 0;
+        let len = u16::from_le_bytes([idat[pos + 1], idat[pos + 2]]) as usize;
+        raw.extend_from_slice(&idat[pos + 5..pos + 5 + len]);
+        pos += len + 5;
+        if last {
+            break;
+        }
+    }
+    let rgb = raw
+        .chunks(size.0 * 3 + 1)
+        .flat_map(|line| {
+            assert_eq!(line[0], 0x0);
+            line[1..].to_vec()
+        })
+        .collect();
+    (size.0, size.1, rgb)
+}
+
+#[test]
+fn test_screen_from_cpu() {
+    let video = Rc::new(RefCell::new(TextVideo::new(SCREEN, 40, 25)));
+    let mut cpu = CPU::new();
+    let range = video.borrow().get_range();
+    assert_eq!(range, 0x0400..=0x07EA);
+    cpu.map_device(range, video.clone());
+
+    load(
+        &mut cpu,
+        0x1000,
+        &[
+            0xA9, 0x48, // LDA #'H'
+            0x8D, 0x29, 0x04, // STA $0429
+            0xA9, 0x49, // LDA #'I'
+            0x8D, 0x2A, 0x04, // STA $042A
+            0xA9, 0x02, // LDA #$02
+            0x8D, 0xE8, 0x07, // STA CURSOR_X
+            0xA9, 0x01, // LDA #$01
+            0x8D, 0xE9, 0x07, // STA CURSOR_Y
+            0x8D, 0xEA, 0x07, // STA CONTROL
+        ],
+    );
+    *cpu.get_registers().get_mut_pc() = 0x1000;
+    cpu.run_loop();
+    assert_eq!(cpu.read_byte(0x07E9), 0x01);
+
+    let video = video.borrow();
+    assert_eq!(video.get_line(0), " ".repeat(40));
+    assert_eq!(video.get_line(1), format!(" HI{}", " ".repeat(37)));
+    assert_eq!(video.get_text().lines().count(), 25);
+    assert_eq!(video.get_cursor(), Some((2, 1)));
+}
+
+#[test]
+fn test_cursor() {
+    let mut video = TextVideo::new(0x8000, 4, 2);
+    assert_eq!(video.get_cursor(), None);
+    video.write(0x8008, 0x03);
+    video.write(0x8009, 0x01);
+    video.write(0x800A, 0x01);
+    assert_eq!(video.get_cursor(), Some((3, 1)));
+
+    // Off screen.
+    video.write(0x8008, 0x04);
+    assert_eq!(video.get_cursor(), None);
+    assert_eq!(video.read(0x8008), 0x04);
+}
+
+#[test]
+fn test_default_charset() {
+    let mut video = TextVideo::new(0x0, 4, 1);
+    for (i, val) in [0x41, 0xC2, 0x01, 0x7F].iter().enumerate() {
+        video.write(i as u16, *val);
+    }
+    assert_eq!(video.get_text(), "AB??");
+
+    let mut charset = ['.'; 0x100];
+    charset[0x01] = 'A';
+    video.set_charset(charset);
+    assert_eq!(video.get_text(), "..A.");
+}
+
+#[test]
+fn test_ansi() {
+    let mut video = TextVideo::new(0x0, 3, 2);
+    video.write(0x0, b'A');
+    video.write(0x4, b'B');
+    assert_eq!(video.render_ansi(), "\x1b[HA  \x1b[K\r\n B \x1b[K");
+
+    video.write(0x6, 0x01);
+    video.write(0x7, 0x01);
+    video.write(0x8, 0x01);
+    assert_eq!(
+        video.render_ansi(),
+        "\x1b[HA  \x1b[K\r\n \x1b[7mB\x1b[27m \x1b[K"
+    );
+}
+
+#[test]
+fn test_char_rom_pixels() {
+    let mut video = TextVideo::new(0x0, 2, 1);
+    // Glyph 1 is a diagonal line, glyph 2 a single pixel at the right.
+    let mut rom = vec![0x0; 0x18];
+    for line in 0..8 {
+        rom[0x08 + line] = 0x80 >> line;
+    }
+    rom[0x10] = 0x01;
+    video.set_char_rom(&rom);
+    video.set_colors([0x10, 0x20, 0x30], [0x01, 0x02, 0x03]);
+    video.write(0x0, 0x01);
+    video.write(0x1, 0x02);
+
+    assert_eq!(video.get_pixel_size(), (16, 8));
+    let rgb = video.render_rgb();
+    assert_eq!(rgb.len(), 16 * 8 * 3);
+    for y in 0..8 {
+        for x in 0..8 {
+            let expected = match x == y {
+                true => [0x10, 0x20, 0x30],
+                false => [0x01, 0x02, 0x03],
+            };
+            assert_eq!(pixel(&rgb, 16, x, y), expected);
+        }
+    }
+    assert_eq!(pixel(&rgb, 16, 15, 0), [0x10, 0x20, 0x30]);
+    assert_eq!(pixel(&rgb, 16, 15, 1), [0x01, 0x02, 0x03]);
+
+    // Codes past the end of the ROM are blank, and the cursor inverts.
+    video.write(0x0, 0x40);
+    let rgb = video.render_rgb();
+    assert!((0..8).all(|x| pixel(&rgb, 16, x, 4) == [0x01, 0x02, 0x03]));
+    video.write(0x2, 0x01);
+    video.write(0x4, 0x01);
+    let rgb = video.render_rgb();
+    assert_eq!(pixel(&rgb, 16, 15, 0), [0x01, 0x02, 0x03]);
+    assert_eq!(pixel(&rgb, 16, 15, 1), [0x10, 0x20, 0x30]);
+}
+
+#[test]
+fn test_default_font_inverse() {
+    let mut video = TextVideo::new(0x0, 2, 1);
+    video.write(0x0, b' ');
+    video.write(0x1, b' ' | 0x80);
+    let rgb = video.render_rgb();
+    for y in 0..8 {
+        assert_eq!(pixel(&rgb, 16, 3, y), [0x00, 0x00, 0x00]);
+        assert_eq!(pixel(&rgb, 16, 11, y), [0xFF, 0xFF, 0xFF]);
+    }
+}
+
+#[test]
+fn test_ppm() {
+    let mut video = TextVideo::new(0x0, 3, 2);
+    video.write(0x0, b'#');
+    let ppm = video.render_ppm();
+    let header = b"P6\n24 16\n255\n";
+    assert_eq!(ppm[..header.len()], header[..]);
+    assert_eq!(ppm[header.len()..], video.render_rgb()[..]);
+}
+
+#[test]
+fn test_png() {
+    let mut video = TextVideo::new(0x0, 80, 30);
+    for i in 0..80 * 30 {
+        video.write(i, b'!' + (i % 94) as u8);
+    }
+    // Large enough to need more than one stored block.
+    let (width, height, rgb) = decode_png(&video.render_png());
+    assert_eq!((width, height), (640, 240));
+    assert_eq!(rgb, video.render_rgb());
+}
@@ -0,0 +1,273 @@
+mod common;
+
+use std::{cell::RefCell, rc::Rc};
+
+use rem6502::{
+    cpu::CPU,
+    devices::{Device, Via},
+};
+
+use common::{load, set_vector, IRQ_VECTOR};
+
+fn tick(via: &mut Via, cycle: &mut u64, cycles: u64) {
+    *cycle += cycles;
+    via.tick(*cycle);
+}
+
+#[test]
+fn test_port_b_output() {
+    let via = Rc::new(RefCell::new(Via::new()));
+    let mut cpu = CPU::new();
+    cpu.map_device(0x6000..=0x600F, via.clone());
+
+    load(
+        &mut cpu,
+        0x0,
+        &[
+            0xA9, 0xF0, // LDA #$F0
+            0x8D, 0x02, 0x60, // STA $6002
+            0xA9, 0x55, // LDA #$55
+            0x8D, 0x00, 0x60, // STA $6000
+        ],
+    );
+    via.borrow_mut().set_port_b_input(0x0A);
+
+    cpu.run_loop();
+
+    assert_eq!(via.borrow().get_ddrb(), 0xF0);
+    assert_eq!(via.borrow().get_port_b(), 0x5A);
+}
+
+#[test]
+fn test_port_a_input() {
+    let via = Rc::new(RefCell::new(Via::new()));
+    let mut cpu = CPU::new();
+    cpu.map_device(0x6000..=0x600F, via.clone());
+
+    // LDA $6001
+    load(&mut cpu, 0x0, &[0xAD, 0x01, 0x60]);
+    via.borrow_mut().set_port_a_input(0x3C);
+
+    cpu.run_loop();
+
+    assert_eq!(cpu.get_registers().get_a(), 0x3C);
+}
+
+#[test]
+fn test_mirrored_registers() {
+    let via = Rc::new(RefCell::new(Via::new()));
+    let mut cpu = CPU::new();
+    cpu.map_device(0x6000..=0x60FF, via.clone());
+
+    cpu.write_byte(0x6013, 0xAA);
+
+    assert_eq!(via.borrow().get_ddra(), 0xAA);
+    assert_eq!(cpu.read_byte(0x6003), 0xAA);
+}
+
+#[test]
+fn test_t1_one_shot() {
+    let mut cycle = 0;
+    let mut via = Via::new();
+    via.write(0xE, 0xC0);
+    via.write(0x4, 0x05);
+    via.write(0x5, 0x00);
+
+    tick(&mut via, &mut cycle, 1);
+    assert_eq!(via.get_t1(), 0x0005);
+
+    tick(&mut via, &mut cycle, 5);
+    assert_eq!(via.get_t1(), 0x0000);
+    assert!(!via.irq());
+
+    tick(&mut via, &mut cycle, 1);
+    assert_eq!(via.get_t1(), 0xFFFF);
+    assert!(via.irq());
+    assert_eq!(via.read(0xD), 0xC0);
+
+    assert_eq!(via.read(0x4), 0xFF);
+    assert!(!via.irq());
+
+    tick(&mut via, &mut cycle, 0x10000);
+    assert!(!via.irq());
+}
+
+#[test]
+fn test_t1_free_run_pb7() {
+    let mut cycle = 0;
+    let mut via = Via::new();
+    via.write(0xB, 0xC0);
+    via.write(0x4, 0x03);
+    via.write(0x5, 0x00);
+    assert_eq!(via.get_port_b() & 0x80, 0x00);
+
+    let mut levels = vec![];
+    for _ in 0..10 {
+        tick(&mut via, &mut cycle, 1);
+        levels.push(via.get_port_b() >> 7);
+    }
+
+    assert_eq!(levels, vec![0, 0, 0, 0, 1, 1, 1, 1, 1, 0]);
+}
+
+#[test]
+fn test_t2_one_shot() {
+    let mut cycle = 0;
+    let mut via = Via::new();
+    via.write(0xE, 0xA0);
+    via.write(0x8, 0x02);
+    via.write(0x9, 0x00);
+
+    tick(&mut via, &mut cycle, 3);
+    assert!(!via.irq());
+
+    tick(&mut via, &mut cycle, 1);
+    assert!(via.irq());
+    assert_eq!(via.read(0x8), 0xFF);
+    assert!(!via.irq());
+}
+
+#[test]
+fn test_t2_pulse_counting() {
+    let mut cycle = 0;
+    let mut via = Via::new();
+    via.write(0xB, 0x20);
+    via.write(0xE, 0xA0);
+    via.write(0x8, 0x01);
+    via.write(0x9, 0x00);
+
+    tick(&mut via, &mut cycle, 100);
+    assert_eq!(via.get_t2(), 0x0001);
+
+    for _ in 0..2 {
+        via.set_port_b_input(0x00);
+        via.set_port_b_input(0x40);
+    }
+
+    assert_eq!(via.get_t2(), 0xFFFF);
+    assert!(via.irq());
+}
+
+#[test]
+fn test_ca1_interrupt() {
+    let mut via = Via::new();
+    via.write(0xE, 0x82);
+    assert_eq!(via.read(0xE), 0x82);
+
+    via.set_ca1(false);
+    assert!(via.irq());
+    assert_eq!(via.read(0xD), 0x82);
+
+    // Only a real read of port A clears the flag.
+    via.peek(0xF);
+    assert!(via.irq());
+    via.read(0xF);
+    assert!(!via.irq());
+
+    via.write(0xC, 0x01);
+    via.set_ca1(true);
+    assert!(via.irq());
+
+    via.write(0xD, 0x02);
+    assert!(!via.irq());
+}
+
+#[test]
+fn test_ca2_pulse_output() {
+    let mut cycle = 0;
+    let mut via = Via::new();
+    via.write(0xC, 0x0A);
+    assert!(via.get_ca2());
+
+    via.write(0x1, 0x00);
+    tick(&mut via, &mut cycle, 1);
+    assert!(!via.get_ca2());
+
+    tick(&mut via, &mut cycle, 1);
+    assert!(via.get_ca2());
+}
+
+#[test]
+fn test_cb2_handshake_output() {
+    let mut via = Via::new();
+    via.write(0xC, 0x80);
+    assert!(via.get_cb2());
+
+    via.write(0x0, 0x42);
+    assert!(!via.get_cb2());
+
+    via.set_cb1(false);
+    assert!(via.get_cb2());
+}
+
+#[test]
+fn test_shift_out_phi2() {
+    let mut cycle = 0;
+    let mut via = Via::new();
+    via.write(0xB, 0x18);
+    via.write(0xE, 0x84);
+    via.write(0xA, 0xA5);
+
+    let mut bits = vec![];
+    for _ in 0..8 {
+        tick(&mut via, &mut cycle, 1);
+        bits.push(via.get_cb2() as u8);
+    }
+
+    assert_eq!(bits, vec![1, 0, 1, 0, 0, 1, 0, 1]);
+    assert!(via.irq());
+}
+
+#[test]
+fn test_shift_in_external() {
+    let mut via = Via::new();
+    via.write(0xB, 0x0C);
+    via.read(0xA);
+
+    for bit in [0, 1, 1, 0, 1, 0, 0, 1] {
+        via.set_cb2(bit == 1);
+        via.set_cb1(false);
+        via.set_cb1(true);
+    }
+
+    assert_eq!(via.read(0xD) & 0x04, 0x04);
+    assert_eq!(via.read(0xA), 0x69);
+}
+
+#[test]
+fn test_timer_irq_drives_cpu() {
+    let via = Rc::new(RefCell::new(Via::new()));
+    let mut cpu = CPU::new();
+    cpu.map_device(0x6000..=0x600F, via.clone());
+
+    load(
+        &mut cpu,
+        0x0,
+        &[
+            0xA9, 0xC0, // LDA #$C0
+            0x8D, 0x0E, 0x60, // STA $600E
+            0xA9, 0x40, // LDA #$40
+            0x8D, 0x0B, 0x60, // STA $600B
+            0xA9, 0x30, // LDA #$30
+            0x8D, 0x04, 0x60, // STA $6004
+            0xA9, 0x00, // LDA #$00
+            0x8D, 0x05, 0x60, // STA $6005
+            0x58, // CLI
+            0x4C, 0x15, 0x00, // JMP $0015
+        ],
+    );
+    load(
+        &mut cpu,
+        0x4000,
+        &[
+            0xAD, 0x04, 0x60, // LDA $6004
+            0xE8, // INX
+            0x40, // RTI
+        ],
+    );
+    set_vector(&mut cpu, IRQ_VECTOR, 0x4000);
+
+    cpu.run_until(0x32 * 10);
+
+    assert_eq!(cpu.get_registers().get_x(), 9);
+}